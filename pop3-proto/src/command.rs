@@ -5,15 +5,21 @@ use crate::{
     macros::collection,
 };
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Command {
     Noop,
-    Uidl,
+    /// Without a message number the server lists the unique ids of all messages in a multi-line response.
+    Uidl {
+        listing: bool,
+    },
     Top,
     Dele,
     Rset,
     Retr,
-    List,
+    /// Without a message number the server sends a scan listing of all messages in a multi-line response.
+    List {
+        listing: bool,
+    },
     Stat,
     Apop,
     Auth,
@@ -21,9 +27,20 @@ pub enum Command {
     Pass,
     Quit,
     Capa,
+    Stls,
+    Utf8,
+    /// Without an argument the server lists the languages it supports in a multi-line response.
+    Lang {
+        list: bool,
+    },
     Greet,
+    /// A command that is not part of any of the supported specifications, e.g. `XTND` or `LAST`.
+    Custom {
+        name: String,
+        multiline: bool,
+    },
     #[cfg(feature = "sasl")]
-    Base64(String),
+    Base64(Vec<u8>),
 }
 
 impl Display for Command {
@@ -33,6 +50,18 @@ impl Display for Command {
             Self::Base64(other) => {
                 write!(f, "{}", crate::base64::encode(other))?;
             }
+            Self::Custom { name, .. } => {
+                write!(f, "{}", name.to_ascii_uppercase())?;
+            }
+            Self::Lang { .. } => {
+                write!(f, "LANG")?;
+            }
+            Self::List { .. } => {
+                write!(f, "LIST")?;
+            }
+            Self::Uidl { .. } => {
+                write!(f, "UIDL")?;
+            }
             _ => {
                 for (key, value) in Self::definitions().into_iter() {
                     if &value == self {
@@ -48,23 +77,36 @@ impl Display for Command {
 }
 
 impl Command {
+    /// The name of the command, which unlike its [Display] output never contains the data of a SASL exchange.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Greet => String::from("GREET"),
+            #[cfg(feature = "sasl")]
+            Self::Base64(_) => String::from("AUTH"),
+            command => command.to_string(),
+        }
+    }
+
     fn definitions() -> HashMap<String, Self> {
         use Command::*;
 
         collection!(
             "noop" => Noop,
-            "uidl" => Uidl,
+            "uidl" => Uidl { listing: true },
             "top" => Top,
             "dele" => Dele,
             "rset" => Rset,
             "retr" => Retr,
-            "list" => List,
+            "list" => List { listing: true },
             "stat" => Stat,
             "apop" => Apop,
             "auth" => Auth,
             "user" => User,
             "quit" => Quit,
             "capa" => Capa,
+            "stls" => Stls,
+            "utf8" => Utf8,
+            "lang" => Lang { list: false },
             "pass" => Pass
         )
     }
@@ -0,0 +1,14 @@
+//! The fixed parts of the protocol.
+
+// \n
+pub const LF: u8 = 0x0a;
+// \r
+pub const CR: u8 = 0x0d;
+
+pub const OK: &str = "+OK";
+pub const ERR: &str = "-ERR";
+
+pub const END_OF_LINE: [u8; 2] = [CR, LF];
+
+/// The termination octet of a multi-line response, along with the line endings around it.
+pub const MULTILINE_END: &[u8] = b"\n.\r\n";
@@ -0,0 +1,119 @@
+use std::{
+    error,
+    fmt::{self},
+    num::ParseIntError,
+    result,
+    str::Utf8Error,
+};
+
+macro_rules! err {
+    ($kind:expr, $($arg:tt)*) => {{
+		use crate::error::Error;
+
+        let kind = $kind;
+        let message = format!($($arg)*);
+        return Err(Error::new( kind, message ));
+    }};
+}
+
+/// What went wrong while parsing or serializing protocol data.
+///
+/// New kinds can be added without a major release, so a match on this has to include a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    ParseInt(ParseIntError),
+    ParseString(Utf8Error),
+    #[cfg(feature = "sasl")]
+    DecodeBase64(base64::DecodeError),
+    /// An argument of a request is not valid, so the request can not be sent.
+    InvalidArgument,
+    InvalidResponse,
+    ParseCommand,
+}
+
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new<S>(error_kind: ErrorKind, message: S) -> Self
+    where
+        String: From<S>,
+    {
+        Self {
+            message: message.into(),
+            kind: error_kind,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_parts(self) -> (ErrorKind, String) {
+        (self.kind, self.message)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::ParseInt(err) => Some(err),
+            ErrorKind::ParseString(err) => Some(err),
+            #[cfg(feature = "sasl")]
+            ErrorKind::DecodeBase64(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// A short description of the kind, the details are in the message of the [Error] and its [error::Error::source].
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::ParseInt(_) => f.write_str("invalid number"),
+            ErrorKind::ParseString(_) => f.write_str("invalid utf-8"),
+            #[cfg(feature = "sasl")]
+            ErrorKind::DecodeBase64(_) => f.write_str("invalid base64"),
+            ErrorKind::InvalidArgument => f.write_str("invalid argument"),
+            ErrorKind::InvalidResponse => f.write_str("invalid response"),
+            ErrorKind::ParseCommand => f.write_str("invalid command"),
+        }
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(parse_int_error: ParseIntError) -> Self {
+        Self::new(ErrorKind::ParseInt(parse_int_error), "Failed to parse int")
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(error: Utf8Error) -> Self {
+        Self::new(ErrorKind::ParseString(error), "Failed to parse string")
+    }
+}
+
+#[cfg(feature = "sasl")]
+impl From<base64::DecodeError> for Error {
+    fn from(error: base64::DecodeError) -> Self {
+        Self::new(ErrorKind::DecodeBase64(error), "Failed to decode base64")
+    }
+}
+
+pub(crate) use err;
+
+pub type Result<T> = result::Result<T, Error>;
@@ -0,0 +1,42 @@
+//! # Pop3 protocol
+//!
+//! The types, parsers and serializers for the Pop3 protocol ([RFC 1939](https://www.rfc-editor.org/rfc/rfc1939)) and its extensions, as used by [async-pop](https://crates.io/crates/async-pop).
+//!
+//! This crate purely works on bytes: it does not do any io and does not depend on an async runtime or tls implementation,
+//! so that clients, servers, proxies and analyzers can share the exact same protocol code.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use pop3_proto::{response::{ParseMode, Response}, Command, Request};
+//!
+//! let mut request: Request = Command::Retr.into();
+//!
+//! request.add_arg(1);
+//!
+//! assert_eq!(request.to_string(), "RETR 1");
+//!
+//! let (response, _) = Response::parse(b"+OK\r\nHello\r\n.\r\n", &Command::Retr, ParseMode::Strict)
+//!     .unwrap()
+//!     .unwrap();
+//!
+//! assert!(matches!(response, Response::Bytes(_)));
+//! ```
+//!
+//! ## Features
+//!
+//! - `sasl`: the AUTH command and the challenges sent in response to it ([RFC 5034](https://www.rfc-editor.org/rfc/rfc5034)).
+//! - `serde`: (de)serialization of the response types.
+
+#[cfg(feature = "sasl")]
+pub mod base64;
+pub mod command;
+pub mod constants;
+pub mod error;
+mod macros;
+pub mod request;
+pub mod response;
+
+pub use command::Command;
+pub use request::Request;
+pub use response::types::number::MessageNumber;
@@ -0,0 +1,254 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    command::Command,
+    error::{err, Error, ErrorKind, Result},
+};
+
+#[derive(Debug)]
+pub struct Request {
+    command: Command,
+    args: Vec<String>,
+    /// Whether the argument at the same index skips the validation, see [Request::add_arg_unchecked].
+    unchecked: Vec<bool>,
+}
+
+impl From<Request> for Command {
+    fn from(request: Request) -> Self {
+        request.command
+    }
+}
+
+impl From<Command> for Request {
+    fn from(command: Command) -> Self {
+        Self::new(command, &Vec::<String>::new())
+    }
+}
+
+impl Display for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.command)?;
+
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Request {
+    type Err = Error;
+
+    /// Parses a command line as sent by a client, without its line ending.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split(' ').filter(|part| !part.is_empty());
+
+        let command: Command = parts.next().unwrap_or_default().parse()?;
+
+        let args: Vec<&str> = parts.collect();
+
+        Ok(Self::new(command, &args))
+    }
+}
+
+impl Request {
+    pub fn new<A: Display>(command: Command, args: &[A]) -> Self {
+        let mut request = Self {
+            command,
+            args: Vec::with_capacity(args.len()),
+            unchecked: Vec::with_capacity(args.len()),
+        };
+
+        for arg in args {
+            request.push_arg(arg.to_string(), false);
+        }
+
+        request
+    }
+
+    /// Adds an argument to the request.
+    ///
+    /// Arguments may not contain line breaks or NUL characters, as those would allow other commands to be injected.
+    /// This is checked right before the request is sent, see [Request::validate], or right away using [Request::try_add_arg].
+    pub fn add_arg<A: Display>(&mut self, arg: A) {
+        self.push_arg(arg.to_string(), false)
+    }
+
+    /// Adds an argument to the request, failing with [ErrorKind::InvalidArgument] if it contains a line break or a NUL character.
+    pub fn try_add_arg<A: Display>(&mut self, arg: A) -> Result<()> {
+        let arg = arg.to_string();
+
+        check_arg(&arg)?;
+
+        self.push_arg(arg, false);
+
+        Ok(())
+    }
+
+    /// Adds an argument to the request that is not validated, the other arguments still are.
+    ///
+    /// Only use this for servers that really need control characters in an argument, the argument is sent to the server as is.
+    pub fn add_arg_unchecked<A: Display>(&mut self, arg: A) {
+        self.push_arg(arg.to_string(), true)
+    }
+
+    /// LIST and UIDL only respond with a listing when they are sent without a message number.
+    fn push_arg(&mut self, arg: String, unchecked: bool) {
+        if let Command::List { listing } | Command::Uidl { listing } = &mut self.command {
+            *listing = false;
+        }
+
+        self.args.push(arg);
+        self.unchecked.push(unchecked);
+    }
+
+    /// Makes sure none of the arguments contain a line break or a NUL character, except for the ones added using [Request::add_arg_unchecked].
+    pub fn validate(&self) -> Result<()> {
+        self.args
+            .iter()
+            .zip(&self.unchecked)
+            .filter(|(_, unchecked)| !**unchecked)
+            .try_for_each(|(arg, _)| check_arg(arg))
+    }
+
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// The message number the request refers to, if any.
+    pub fn message_number(&self) -> Option<usize> {
+        match self.command {
+            Command::Retr
+            | Command::Top
+            | Command::Dele
+            | Command::List { .. }
+            | Command::Uidl { .. } => self.args.first()?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The request in a form that is safe to log, with the credentials that are sent using PASS, APOP and AUTH left out.
+    pub fn redacted(&self) -> Redacted<'_> {
+        Redacted { request: self }
+    }
+}
+
+fn check_arg(arg: &str) -> Result<()> {
+    if arg.contains(['\r', '\n', '\0']) {
+        err!(
+            ErrorKind::InvalidArgument,
+            "Request arguments may not contain line breaks or NUL characters"
+        )
+    }
+
+    Ok(())
+}
+
+/// Displays a request without its credentials, see [Request::redacted].
+pub struct Redacted<'a> {
+    request: &'a Request,
+}
+
+impl Display for Redacted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The index of the first argument that contains a secret.
+        let secret_from = match self.request.command {
+            Command::Pass => 0,
+            Command::Apop | Command::Auth => 1,
+            #[cfg(feature = "sasl")]
+            Command::Base64(_) => return write!(f, "<redacted>"),
+            _ => self.request.args.len(),
+        };
+
+        write!(f, "{}", self.request.command)?;
+
+        for (index, arg) in self.request.args.iter().enumerate() {
+            if index < secret_from {
+                write!(f, " {}", arg)?;
+            } else {
+                write!(f, " <redacted>")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::command::Command;
+
+    use super::Request;
+
+    #[test]
+    fn test_redacted() {
+        let request = Request::new(Command::Pass, &["hunter2"]);
+
+        assert_eq!(request.redacted().to_string(), "PASS <redacted>");
+
+        let request = Request::new(
+            Command::Apop,
+            &["mrose", "c4c9334bac560ecc979e58001b3e22fb"],
+        );
+
+        assert_eq!(request.redacted().to_string(), "APOP mrose <redacted>");
+
+        let request = Request::new(Command::Auth, &["PLAIN", "AHVzZXIAaHVudGVyMg=="]);
+
+        assert_eq!(request.redacted().to_string(), "AUTH PLAIN <redacted>");
+
+        let request = Request::new(Command::Retr, &[4]);
+
+        assert_eq!(request.redacted().to_string(), "RETR 4");
+        assert_eq!(request.message_number(), Some(4));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut request: Request = Command::User.into();
+
+        request.add_arg("user\r\nDELE 1");
+
+        assert!(request.validate().is_err());
+        assert!(request.try_add_arg("pass\0").is_err());
+
+        let mut request: Request = Command::User.into();
+
+        assert!(request.try_add_arg("user@example.com").is_ok());
+        assert!(request.validate().is_ok());
+
+        request.add_arg_unchecked("\r\n");
+
+        assert!(request.validate().is_ok());
+
+        let mut request: Request = Command::Apop.into();
+
+        request.add_arg("user\r\nDELE 1");
+        request.add_arg_unchecked("\0");
+
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let request: Request = "TOP 1  10".parse().unwrap();
+
+        assert_eq!(request.command(), &Command::Top);
+        assert_eq!(request.args(), ["1", "10"]);
+
+        let request: Request = "list".parse().unwrap();
+
+        assert_eq!(request.command(), &Command::List { listing: true });
+
+        let request: Request = "LIST 2".parse().unwrap();
+
+        assert_eq!(request.command(), &Command::List { listing: false });
+
+        assert!("XYZZY 1".parse::<Request>().is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    time,
+};
+
+use bytes::Bytes;
+
+use super::types::{message::Text, number::Duration, DataType};
+
+#[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expiration {
+    #[default]
+    Never,
+    Time(Duration),
+}
+
+#[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Capability {
+    /// Whether the TOP command is supported.
+    Top,
+    /// Whether the USER and PASS commands (login) are supported.
+    User,
+    /// Whether the use of a SASL based login is supported and if so what kinds. See https://www.rfc-editor.org/rfc/rfc1734
+    Sasl(#[cfg_attr(feature = "serde", serde(with = "mechanisms"))] Vec<Bytes>),
+    /// Whether the server uses extends response codes. See https://www.rfc-editor.org/rfc/rfc2449#section-8
+    RespCodes,
+    /// Whether there is a delay between each login and how long it is.
+    LoginDelay(Duration),
+    /// Whether the server supports pipelining. See https://www.rfc-editor.org/rfc/rfc2197
+    Pipelining,
+    /// The amount of time the server will store messsages for.
+    Expire(Expiration),
+    /// Whether the UIDL command is supported.
+    Uidl,
+    /// The type of authentication method the server prefers/uses.
+    Implementation(Text),
+    Stls,
+    /// Whether the UTF8 command is supported and, if `user` is set, whether usernames and passwords may contain UTF-8 once it is enabled. See https://www.rfc-editor.org/rfc/rfc6856#section-2
+    Utf8 {
+        user: bool,
+    },
+    /// Whether the LANG command is supported. See https://www.rfc-editor.org/rfc/rfc6856#section-3
+    Lang,
+    Other(Text),
+}
+
+impl Capability {
+    /// The capability as a line of a CAPA response, without the line ending.
+    ///
+    /// Parsing the line results in the same capability, except for [Capability::Other], which does not keep any parameters.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        match self {
+            Self::Top => bytes.extend_from_slice(b"TOP"),
+            Self::User => bytes.extend_from_slice(b"USER"),
+            Self::Sasl(mechanisms) => {
+                bytes.extend_from_slice(b"SASL");
+
+                for mechanism in mechanisms {
+                    bytes.push(b' ');
+                    bytes.extend_from_slice(mechanism);
+                }
+            }
+            Self::RespCodes => bytes.extend_from_slice(b"RESP-CODES"),
+            Self::LoginDelay(delay) => {
+                bytes.extend_from_slice(b"LOGIN-DELAY ");
+                bytes.extend_from_slice(delay.raw());
+            }
+            Self::Pipelining => bytes.extend_from_slice(b"PIPELINING"),
+            Self::Expire(Expiration::Never) => bytes.extend_from_slice(b"EXPIRE NEVER"),
+            Self::Expire(Expiration::Time(days)) => {
+                bytes.extend_from_slice(b"EXPIRE ");
+                bytes.extend_from_slice(days.raw());
+            }
+            Self::Uidl => bytes.extend_from_slice(b"UIDL"),
+            Self::Implementation(text) => {
+                bytes.extend_from_slice(b"IMPLEMENTATION");
+
+                if !text.raw().is_empty() {
+                    bytes.push(b' ');
+                    bytes.extend_from_slice(text.raw());
+                }
+            }
+            Self::Stls => bytes.extend_from_slice(b"STLS"),
+            Self::Utf8 { user } => {
+                bytes.extend_from_slice(b"UTF8");
+
+                if *user {
+                    bytes.extend_from_slice(b" USER");
+                }
+            }
+            Self::Lang => bytes.extend_from_slice(b"LANG"),
+            Self::Other(name) => bytes.extend_from_slice(name.raw()),
+        }
+
+        bytes
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+/// Whether commands that depend on an optional capability, such as TOP, UIDL and USER, are only sent when the server advertised it.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum CapabilityCheck {
+    /// Refuse to send the command with a `FeatureUnsupported` error if the capability was not advertised.
+    ///
+    /// USER is only refused when the capabilities are known, as lazy capabilities are not fetched just to log in, and servers that do not support CAPA can not advertise it.
+    #[default]
+    Strict,
+    /// Send the command anyway and let the server decide, as plenty of servers support TOP, UIDL and USER without listing them.
+    TrustServer,
+}
+
+/// The capabilities a server advertised in response to the CAPA command.
+///
+/// Keeps the order the server sent them in, while allowing fast lookups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<Capability>", into = "Vec<Capability>")
+)]
+pub struct Capabilities {
+    list: Vec<Capability>,
+    set: HashSet<Capability>,
+}
+
+impl Capabilities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the server advertised exactly this capability, including any parameters.
+    pub fn contains(&self, capability: &Capability) -> bool {
+        self.set.contains(capability)
+    }
+
+    pub fn supports_top(&self) -> bool {
+        self.contains(&Capability::Top)
+    }
+
+    pub fn supports_user(&self) -> bool {
+        self.contains(&Capability::User)
+    }
+
+    pub fn supports_uidl(&self) -> bool {
+        self.contains(&Capability::Uidl)
+    }
+
+    pub fn supports_pipelining(&self) -> bool {
+        self.contains(&Capability::Pipelining)
+    }
+
+    pub fn supports_stls(&self) -> bool {
+        self.contains(&Capability::Stls)
+    }
+
+    pub fn supports_resp_codes(&self) -> bool {
+        self.contains(&Capability::RespCodes)
+    }
+
+    pub fn supports_lang(&self) -> bool {
+        self.contains(&Capability::Lang)
+    }
+
+    /// Whether the server supports the UTF8 command ([RFC 6856](https://www.rfc-editor.org/rfc/rfc6856#section-2)).
+    pub fn supports_utf8(&self) -> bool {
+        self.utf8_capability().is_some()
+    }
+
+    /// Whether the server accepts UTF-8 usernames and passwords once UTF8 is enabled.
+    pub fn supports_utf8_user(&self) -> bool {
+        self.utf8_capability() == Some(true)
+    }
+
+    fn utf8_capability(&self) -> Option<bool> {
+        self.list.iter().find_map(|capability| match capability {
+            Capability::Utf8 { user } => Some(*user),
+            _ => None,
+        })
+    }
+
+    /// The SASL mechanisms the server supports, empty if it does not support SASL at all.
+    pub fn sasl_mechanisms(&self) -> &[Bytes] {
+        self.list
+            .iter()
+            .find_map(|capability| match capability {
+                Capability::Sasl(mechanisms) => Some(mechanisms.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether the server supports the given SASL mechanism, compared case insensitively.
+    pub fn supports_sasl_mechanism<M: AsRef<[u8]>>(&self, mechanism: M) -> bool {
+        self.sasl_mechanisms()
+            .iter()
+            .any(|supported| supported.eq_ignore_ascii_case(mechanism.as_ref()))
+    }
+
+    /// How long the server keeps messages, if it advertised it.
+    pub fn expire(&self) -> Option<&Expiration> {
+        self.list.iter().find_map(|capability| match capability {
+            Capability::Expire(expiration) => Some(expiration),
+            _ => None,
+        })
+    }
+
+    /// The minimum amount of time between logins, if the server advertised one.
+    pub fn login_delay(&self) -> Option<time::Duration> {
+        self.list.iter().find_map(|capability| match capability {
+            Capability::LoginDelay(delay) => delay.value().ok(),
+            _ => None,
+        })
+    }
+
+    /// The capabilities as the lines of a CAPA response, each ending in CRLF, without the status line and the terminating dot.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for capability in &self.list {
+            bytes.extend(capability.to_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+
+        bytes
+    }
+
+    /// The capabilities in the order the server sent them.
+    pub fn iter(&self) -> std::slice::Iter<'_, Capability> {
+        self.list.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// The capabilities that were added and removed when going from this set to the given one.
+    pub fn changes_to(&self, new: &Capabilities) -> CapabilityChanges {
+        CapabilityChanges {
+            added: new
+                .iter()
+                .filter(|capability| !self.contains(capability))
+                .cloned()
+                .collect(),
+            removed: self
+                .iter()
+                .filter(|capability| !new.contains(capability))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// How the capabilities of a server changed, e.g. after STLS or logging in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityChanges {
+    pub added: Vec<Capability>,
+    pub removed: Vec<Capability>,
+}
+
+impl CapabilityChanges {
+    /// Whether the capabilities stayed the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl From<Capabilities> for Vec<Capability> {
+    fn from(capabilities: Capabilities) -> Self {
+        capabilities.list
+    }
+}
+
+/// Serializes the SASL mechanisms the same way as [Text], as they are plain ascii names.
+#[cfg(feature = "serde")]
+mod mechanisms {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::response::types::{message::Text, DataType};
+
+    pub fn serialize<S: Serializer>(
+        mechanisms: &[Bytes],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(mechanisms.iter().cloned().map(Text::from))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Bytes>, D::Error> {
+        let mechanisms = Vec::<Text>::deserialize(deserializer)?;
+
+        Ok(mechanisms
+            .iter()
+            .map(|mechanism| Bytes::copy_from_slice(mechanism.raw()))
+            .collect())
+    }
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        let list: Vec<Capability> = iter.into_iter().collect();
+        let set = list.iter().cloned().collect();
+
+        Self { list, set }
+    }
+}
+
+impl From<Vec<Capability>> for Capabilities {
+    fn from(list: Vec<Capability>) -> Self {
+        list.into_iter().collect()
+    }
+}
+
+impl IntoIterator for Capabilities {
+    type Item = Capability;
+    type IntoIter = std::vec::IntoIter<Capability>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Capabilities {
+    type Item = &'a Capability;
+    type IntoIter = std::slice::Iter<'a, Capability>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.iter()
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::types::{message::Text, DataType};
+
+/// An extended response code, sent in square brackets at the start of a negative response.
+///
+/// See [RFC 2449](https://www.rfc-editor.org/rfc/rfc2449#section-8) and [RFC 3206](https://www.rfc-editor.org/rfc/rfc3206).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResponseCode {
+    /// The maildrop is already locked by another session.
+    InUse,
+    /// The user tried to log in again before the login delay passed.
+    LoginDelay,
+    /// A temporary problem on the server, the command may succeed when it is tried again later.
+    SysTemp,
+    /// A permanent problem on the server, trying again will not help.
+    SysPerm,
+    /// The credentials were wrong.
+    Auth,
+    /// Any other response code, including its hierarchy, e.g. `IN-USE/SUBCODE`.
+    Other(Text),
+}
+
+impl ResponseCode {
+    pub(crate) fn from_bytes(code: &[u8]) -> Self {
+        match code.to_ascii_uppercase().as_slice() {
+            b"IN-USE" => Self::InUse,
+            b"LOGIN-DELAY" => Self::LoginDelay,
+            b"SYS/TEMP" => Self::SysTemp,
+            b"SYS/PERM" => Self::SysPerm,
+            b"AUTH" => Self::Auth,
+            _ => Self::Other(code.into()),
+        }
+    }
+}
+
+impl Display for ResponseCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InUse => write!(f, "IN-USE"),
+            Self::LoginDelay => write!(f, "LOGIN-DELAY"),
+            Self::SysTemp => write!(f, "SYS/TEMP"),
+            Self::SysPerm => write!(f, "SYS/PERM"),
+            Self::Auth => write!(f, "AUTH"),
+            Self::Other(code) => write!(f, "{}", code.as_str_lossy()),
+        }
+    }
+}
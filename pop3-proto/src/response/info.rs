@@ -0,0 +1,86 @@
+use super::types::{message::Text, number::MessageNumber};
+
+/// What is known about a single message in the maildrop, combining the LIST and UIDL responses.
+///
+/// Returned by `Client::message_infos` in async-pop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageInfo {
+    number: MessageNumber,
+    size: u64,
+    uid: Option<Text>,
+}
+
+impl MessageInfo {
+    pub fn new(number: MessageNumber, size: u64, uid: Option<Text>) -> Self {
+        Self { number, size, uid }
+    }
+
+    /// The message number, which is only valid for the current session.
+    pub fn number(&self) -> MessageNumber {
+        self.number
+    }
+
+    /// The size of the message in octets.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The unique id of the message, which stays the same across sessions.
+    ///
+    /// This is `None` if the server does not support UIDL.
+    pub fn uid(&self) -> Option<&Text> {
+        self.uid.as_ref()
+    }
+}
+
+/// The state of the whole maildrop at the start of a session, combining the STAT, LIST and UIDL responses.
+///
+/// Returned by `Client::snapshot` in async-pop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MailboxSnapshot {
+    message_count: u64,
+    size: u64,
+    messages: Vec<MessageInfo>,
+}
+
+impl MailboxSnapshot {
+    pub fn new(message_count: u64, size: u64, messages: Vec<MessageInfo>) -> Self {
+        Self {
+            message_count,
+            size,
+            messages,
+        }
+    }
+
+    /// The amount of messages that are not marked as deleted, according to STAT.
+    ///
+    /// When no message is marked as deleted, this is also the number of the last message.
+    pub fn message_count(&self) -> u64 {
+        self.message_count
+    }
+
+    /// The size of the maildrop in octets, according to STAT.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Every message that is not marked as deleted, in the order the server listed them.
+    pub fn messages(&self) -> &[MessageInfo] {
+        &self.messages
+    }
+
+    pub fn into_messages(self) -> Vec<MessageInfo> {
+        self.messages
+    }
+
+    /// The message with the given number, if it was listed.
+    pub fn get<N: Into<MessageNumber>>(&self, msg_number: N) -> Option<&MessageInfo> {
+        let msg_number = msg_number.into();
+
+        self.messages
+            .iter()
+            .find(|info| info.number() == msg_number)
+    }
+}
@@ -0,0 +1,28 @@
+use super::types::message::Text;
+
+/// A language the server can use for its response text, as listed in response to the LANG command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Language {
+    tag: Text,
+    description: Text,
+}
+
+impl Language {
+    pub fn new<T: Into<Text>, D: Into<Text>>(tag: T, description: D) -> Self {
+        Self {
+            tag: tag.into(),
+            description: description.into(),
+        }
+    }
+
+    /// The language tag, e.g. `en` or `en-boont`, see [RFC 5646](https://www.rfc-editor.org/rfc/rfc5646).
+    pub fn tag(&self) -> &Text {
+        &self.tag
+    }
+
+    /// A description of the language, in the language itself or the current response language.
+    pub fn description(&self) -> &Text {
+        &self.description
+    }
+}
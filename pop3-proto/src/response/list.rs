@@ -0,0 +1,82 @@
+use super::{stat::Stat, types::number::MessageNumber};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ListResponse {
+    Multiple(List),
+    Single(Stat),
+}
+
+impl ListResponse {
+    /// The scan listings in the response, a single one if LIST was sent with a message number.
+    ///
+    /// The counter of every item is the message number and its size is the size of that message.
+    pub fn iter(&self) -> std::slice::Iter<'_, Stat> {
+        match self {
+            Self::Multiple(list) => list.items.iter(),
+            Self::Single(stat) => std::slice::from_ref(stat).iter(),
+        }
+    }
+
+    /// The scan listing of the message with the given number, if it is in the response.
+    pub fn get<N: Into<MessageNumber>>(&self, msg_number: N) -> Option<&Stat> {
+        let msg_number = msg_number.into();
+
+        self.iter()
+            .find(|stat| stat.message_number().ok() == Some(msg_number))
+    }
+}
+
+impl IntoIterator for ListResponse {
+    type Item = Stat;
+    type IntoIter = std::vec::IntoIter<Stat>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Self::Multiple(list) => list.items.into_iter(),
+            Self::Single(stat) => vec![stat].into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a ListResponse {
+    type Item = &'a Stat;
+    type IntoIter = std::slice::Iter<'a, Stat>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<List> for ListResponse {
+    fn from(list: List) -> Self {
+        Self::Multiple(list)
+    }
+}
+
+impl From<Stat> for ListResponse {
+    fn from(item: Stat) -> Self {
+        Self::Single(item)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct List {
+    stats: Option<Stat>,
+    items: Vec<Stat>,
+}
+
+impl List {
+    pub fn new(stats: Option<Stat>, items: Vec<Stat>) -> Self {
+        Self { stats, items }
+    }
+
+    pub fn items(&self) -> &[Stat] {
+        self.items.as_ref()
+    }
+
+    pub fn stats(&self) -> Option<&Stat> {
+        self.stats.as_ref()
+    }
+}
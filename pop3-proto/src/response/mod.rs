@@ -0,0 +1,386 @@
+//! Types and parsers for the responses a Pop3 server can send.
+
+pub mod capability;
+pub mod code;
+pub mod info;
+pub mod lang;
+pub mod list;
+pub mod parser;
+pub mod quit;
+pub mod stat;
+pub mod types;
+pub mod uidl;
+
+use std::fmt::{self, Display, Formatter};
+
+use bytes::Bytes;
+use nom::IResult;
+
+use crate::{
+    command::Command,
+    error::{Error, ErrorKind, Result},
+};
+
+use self::{
+    capability::Capabilities, code::ResponseCode, lang::Language, list::List, stat::Stat,
+    types::message::Text, uidl::UidlResponse,
+};
+
+/// How strictly the responses from the server should be parsed.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Only accept responses that follow the specification.
+    #[default]
+    Strict,
+    /// Work around common quirks of misbehaving servers, such as blank lines inside of multi-line responses and lines that end in a bare line feed or carriage return.
+    Lenient,
+}
+
+impl ParseMode {
+    pub fn is_lenient(&self) -> bool {
+        *self == Self::Lenient
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    success: bool,
+}
+
+impl Status {
+    pub fn new(success: bool) -> Self {
+        Self { success }
+    }
+
+    pub fn success(&self) -> bool {
+        self.success
+    }
+}
+
+/// A negative response sent by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorResponse {
+    code: Option<ResponseCode>,
+    message: Text,
+}
+
+impl ErrorResponse {
+    pub fn new(code: Option<ResponseCode>, message: Text) -> Self {
+        Self { code, message }
+    }
+
+    /// The extended response code, if the server sent one.
+    pub fn code(&self) -> Option<&ResponseCode> {
+        self.code.as_ref()
+    }
+
+    /// The human readable message, excluding the response code.
+    pub fn message(&self) -> &Text {
+        &self.message
+    }
+}
+
+impl Display for ErrorResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "[{}] {}", code, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    Stat(Stat),
+    List(List),
+    Bytes(Bytes),
+    Uidl(UidlResponse),
+    Capability(Capabilities),
+    Languages(Vec<Language>),
+    Message(Text),
+    #[cfg(feature = "sasl")]
+    Challenge(Text),
+    Err(ErrorResponse),
+}
+
+impl Response {
+    /// Parses the response to the given command from the start of `input`.
+    ///
+    /// Returns the response along with the amount of bytes it spans, any bytes after that belong to the next response.
+    /// If `input` holds only part of a response, [None] is returned and the call should be repeated once more data has arrived.
+    /// Nothing is consumed in that case, so `input` must always start at the beginning of the response.
+    ///
+    /// Input that can never become a valid response fails with [ErrorKind::InvalidResponse]. This function never panics, whatever the input.
+    ///
+    /// The command decides how the response is interpreted, e.g. `+OK 2 320` is a [Response::Stat] for STAT but the start of a listing for LIST without a message number.
+    /// A [ParseMode::Lenient] parser tolerates blank lines in listings and lines ending in a bare line feed, but bare carriage returns are only handled by the connection itself.
+    /// # Examples
+    /// ```rust
+    /// use pop3_proto::{response::{ParseMode, Response}, Command};
+    ///
+    /// let input = b"+OK 2 320\r\n+OK bye\r\n";
+    ///
+    /// let (response, consumed) = Response::parse(input, &Command::Stat, ParseMode::Strict)
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// assert!(matches!(response, Response::Stat(_)));
+    /// assert_eq!(consumed, 11);
+    ///
+    /// assert!(Response::parse(&input[..5], &Command::Stat, ParseMode::Strict).unwrap().is_none());
+    /// ```
+    pub fn parse(
+        input: &[u8],
+        command: &Command,
+        mode: ParseMode,
+    ) -> Result<Option<(Self, usize)>> {
+        match parser::parse(input, command, mode) {
+            Ok((remaining, response)) => Ok(Some((response, input.len() - remaining.len()))),
+            Err(nom::Err::Incomplete(_)) => Ok(None),
+            Err(err) => Err(Error::new(
+                ErrorKind::InvalidResponse,
+                format!("The server gave an invalid response: '{}'", err),
+            )),
+        }
+    }
+
+    /// Parses a response in [ParseMode::Strict], returning the unparsed input.
+    ///
+    /// This exposes the underlying nom parser, prefer [Response::parse] which does not depend on the nom version used by this crate.
+    pub fn from_bytes<'a>(input: &'a [u8], command: &Command) -> IResult<&'a [u8], Self> {
+        parser::parse(input, command, ParseMode::Strict)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{command::Command, MessageNumber};
+
+    use super::{list::ListResponse, types::DataType, ParseMode, Response};
+
+    fn commands() -> Vec<Command> {
+        vec![
+            Command::Stat,
+            Command::List { listing: true },
+            Command::List { listing: false },
+            Command::Uidl { listing: true },
+            Command::Uidl { listing: false },
+            Command::Retr,
+            Command::Capa,
+            Command::Lang { list: true },
+            Command::Noop,
+        ]
+    }
+
+    #[test]
+    fn test_list_iter() {
+        let input = b"+OK 2 messages\r\n1 120\r\n3 200\r\n.\r\n";
+
+        let list: ListResponse =
+            match Response::parse(input, &Command::List { listing: true }, ParseMode::Strict) {
+                Ok(Some((Response::List(list), _))) => list.into(),
+                _ => unreachable!(),
+            };
+
+        let sizes: Vec<u64> = list.iter().map(|stat| stat.size_bytes()).collect();
+
+        assert_eq!(sizes, [120, 200]);
+        assert_eq!(list.get(3).unwrap().size_bytes(), 200);
+        assert!(list.get(2).is_none());
+
+        let single: ListResponse = match Response::parse(
+            b"+OK 3 200\r\n",
+            &Command::List { listing: false },
+            ParseMode::Strict,
+        ) {
+            Ok(Some((Response::Stat(stat), _))) => stat.into(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(single.get(3), list.get(3));
+        assert_eq!(single.get(MessageNumber::new(3)), list.get(3));
+        assert_eq!(list.iter().last().unwrap().message_number().unwrap(), 3);
+        assert_eq!(single.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_uidl_lookup() {
+        let input = b"+OK\r\n1 whqtswO00WBw418f9t5JxYwZ\r\n2 QhdPYR:00WBw1Ph7x7\r\n.\r\n";
+
+        let uidl = match Response::parse(input, &Command::Uidl { listing: true }, ParseMode::Strict)
+        {
+            Ok(Some((Response::Uidl(uidl), _))) => uidl,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(uidl.iter().count(), 2);
+        assert_eq!(
+            uidl.get(2).unwrap().id().value().unwrap(),
+            "QhdPYR:00WBw1Ph7x7"
+        );
+        assert_eq!(
+            uidl.find_by_id("whqtswO00WBw418f9t5JxYwZ")
+                .unwrap()
+                .index()
+                .value()
+                .unwrap(),
+            1
+        );
+        assert!(uidl.find_by_id("missing").is_none());
+        assert_eq!(
+            uidl.find_by_id("QhdPYR:00WBw1Ph7x7")
+                .unwrap()
+                .message_number()
+                .unwrap(),
+            MessageNumber::new(2)
+        );
+
+        let map = uidl.into_map();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&1], "whqtswO00WBw418f9t5JxYwZ");
+    }
+
+    #[test]
+    fn test_number_comparisons() {
+        use super::types::number::{Duration, Number};
+
+        let number = Number::try_from(b"320".as_slice()).unwrap();
+
+        assert_eq!(number, 320);
+        assert_eq!(320, number);
+        assert!(number > 100 && number < 1000);
+        assert!(100 < number);
+        assert_eq!(u64::from(&number) + 1, 321);
+        assert_eq!(usize::try_from(&number).unwrap(), 320);
+        assert_eq!(u32::try_from(&number).unwrap(), 320);
+
+        assert!(Number::try_from(b"12ab".as_slice()).is_err());
+        assert!(Number::try_from(b"".as_slice()).is_err());
+        assert!(Number::try_from(b"+12".as_slice()).is_err());
+        assert!(Number::try_from(b"99999999999999999999999".as_slice()).is_err());
+
+        let large = Number::from(u64::MAX);
+
+        assert_eq!(large.as_u64(), u64::MAX);
+        assert!(u32::try_from(&large).is_err());
+
+        let expire = Duration::new(Number::from(999_999_999_999_999_999), 24 * 60 * 60);
+
+        assert!(expire.value().is_err());
+    }
+
+    #[test]
+    fn test_stat_values() {
+        let input = b"+OK 2 messages (18446744073709551615 octets)\r\n";
+
+        let (response, _) = Response::parse(input, &Command::Stat, ParseMode::Strict)
+            .unwrap()
+            .unwrap();
+
+        match response {
+            Response::Stat(stat) => {
+                assert_eq!(stat.message_count(), 2);
+                assert_eq!(stat.size_bytes(), u64::MAX);
+            }
+            _ => unreachable!(),
+        }
+
+        // A number that does not fit is rejected instead of being capped.
+        let input = b"+OK 2 messages (99999999999999999999999 octets)\r\n";
+
+        assert!(Response::parse(input, &Command::Stat, ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        let input = b"+OK 2 messages\r\n1 120\r\n2 200\r\n.\r\n+OK\r\n";
+
+        let command = Command::List { listing: true };
+
+        for end in 0..33 {
+            let result = Response::parse(&input[..end], &command, ParseMode::Strict);
+
+            assert!(matches!(result, Ok(None)), "{}", end);
+        }
+
+        let (response, consumed) = Response::parse(input, &command, ParseMode::Strict)
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(response, Response::List(_)));
+        assert_eq!(consumed, 33);
+
+        assert!(Response::parse(b"* OK\r\n", &command, ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn test_parse_garbage() {
+        let mut seed: u32 = 0x2545_f491;
+
+        let alphabet = b"+-OKER .\r\n0123456789 []/<>abc\x00\xff";
+
+        for round in 0..2000 {
+            // Half of the inputs get past the status line, so the parsers of the individual responses are exercised too.
+            let status: &[u8] = if round % 2 == 0 { b"+OK " } else { b"" };
+
+            let input: Vec<u8> = status
+                .iter()
+                .copied()
+                .chain((0..64).map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+
+                    alphabet[seed as usize % alphabet.len()]
+                }))
+                .collect();
+
+            for command in commands() {
+                for mode in [ParseMode::Strict, ParseMode::Lenient] {
+                    if let Ok(Some((_, consumed))) = Response::parse(&input, &command, mode) {
+                        assert!(consumed <= input.len());
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use super::{capability::Capabilities, uidl::UidlResponse};
+
+        let input = b"+OK\r\n1 whqtswO00WBw418f9t5JxYwZ\r\n2 QhdPYR:00WBw1Ph7x7\r\n.\r\n";
+
+        let uidl = match Response::parse(input, &Command::Uidl { listing: true }, ParseMode::Strict)
+        {
+            Ok(Some((Response::Uidl(uidl), _))) => uidl,
+            _ => unreachable!(),
+        };
+
+        let json = serde_json::to_string(&uidl).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"Multiple":{"message":"","items":[{"index":1,"id":"whqtswO00WBw418f9t5JxYwZ"},{"index":2,"id":"QhdPYR:00WBw1Ph7x7"}]}}"#
+        );
+        assert_eq!(serde_json::from_str::<UidlResponse>(&json).unwrap(), uidl);
+
+        let input = b"+OK\r\nSASL PLAIN XOAUTH2\r\nLOGIN-DELAY 900\r\nUIDL\r\n.\r\n";
+
+        let capabilities = match Response::parse(input, &Command::Capa, ParseMode::Strict) {
+            Ok(Some((Response::Capability(capabilities), _))) => capabilities,
+            _ => unreachable!(),
+        };
+
+        let json = serde_json::to_string(&capabilities).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Capabilities>(&json).unwrap(),
+            capabilities
+        );
+        assert!(json.contains(r#"{"Sasl":["PLAIN","XOAUTH2"]}"#), "{}", json);
+    }
+}
@@ -0,0 +1,140 @@
+use bytes::{Bytes, BytesMut};
+use nom::{
+    bytes::streaming::{tag, take_until},
+    character::streaming::{digit1, line_ending, not_line_ending, space0},
+    combinator::{map_res, opt},
+    multi::many0,
+    sequence::{pair, terminated},
+    IResult,
+};
+
+use crate::response::{types::number::Number, ParseMode};
+
+/// Turns a part of the input into [Bytes], either by copying it or by referencing the shared buffer the input was read into.
+pub type Share<'s> = &'s dyn Fn(&[u8]) -> Bytes;
+
+/// Shares a part of the input by copying it, for input that is not backed by a shared buffer.
+pub fn copy(slice: &[u8]) -> Bytes {
+    Bytes::copy_from_slice(slice)
+}
+
+/// A number made up of digits, which fails if it does not fit in a [u64].
+pub fn number(input: &[u8]) -> IResult<&[u8], Number> {
+    map_res(digit1, |digits: &[u8]| Number::try_from(digits))(input)
+}
+
+pub fn eol(input: &[u8]) -> IResult<&[u8], ()> {
+    let (input, _) = pair(space0, line_ending)(input)?;
+
+    Ok((input, ()))
+}
+
+/// The termination octet of a multi-line response, which may only end with a bare line feed when lenient.
+pub fn end_of_multiline(input: &[u8], mode: ParseMode) -> IResult<&[u8], ()> {
+    let (input, _) = if mode.is_lenient() {
+        pair(tag(b"."), line_ending)(input)?
+    } else {
+        pair(tag(b"."), tag(b"\r\n"))(input)?
+    };
+
+    Ok((input, ()))
+}
+
+pub fn message_parser(input: &[u8]) -> IResult<&[u8], Option<&[u8]>> {
+    terminated(opt(not_line_ending), eol)(input)
+}
+
+/// Skips any empty lines and returns how many were found.
+pub fn blank_lines(input: &[u8]) -> IResult<&[u8], usize> {
+    let (input, lines) = many0(line_ending)(input)?;
+
+    Ok((input, lines.len()))
+}
+
+/// Parses every line of a multi-line response with the given parser, up until the termination octet.
+///
+/// When lenient, blank lines in between the items are skipped instead of causing an error.
+pub fn multiline<'a, O>(
+    mut item: impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+    mode: ParseMode,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>> {
+    move |mut input| {
+        let mut items = Vec::new();
+
+        loop {
+            if mode.is_lenient() {
+                input = blank_lines(input)?.0;
+            }
+
+            match end_of_multiline(input, mode) {
+                Ok((input, _)) => return Ok((input, items)),
+                Err(nom::Err::Error(_)) => {}
+                Err(err) => return Err(err),
+            }
+
+            let (remaining, value) = item(input)?;
+
+            items.push(value);
+
+            input = remaining;
+        }
+    }
+}
+
+/// Parses the body of a multi-line response up until the termination octet, removing the byte-stuffing from lines that start with a period.
+///
+/// The line ending of the last line is not part of the body.
+pub fn multiline_body<'a>(
+    input: &'a [u8],
+    mode: ParseMode,
+    share: Share,
+) -> IResult<&'a [u8], Bytes> {
+    let start = input;
+    // The body is a slice of the input up until the first line that needs its byte-stuffing removed.
+    let mut body: Option<BytesMut> = None;
+    let mut body_end = 0;
+    let mut pending_line_ending: &[u8] = b"";
+    let mut input = input;
+
+    loop {
+        match end_of_multiline(input, mode) {
+            Ok((input, _)) => {
+                let body = match body {
+                    Some(body) => body.freeze(),
+                    None => share(&start[..body_end]),
+                };
+
+                return Ok((input, body));
+            }
+            Err(nom::Err::Error(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        let (remaining, line) = take_until("\n")(input)?;
+
+        let (content, line_ending) = match line.strip_suffix(b"\r") {
+            Some(content) => (content, &input[content.len()..line.len() + 1]),
+            None => (line, &input[line.len()..line.len() + 1]),
+        };
+
+        match (&mut body, content.strip_prefix(b".")) {
+            (None, None) => body_end = start.len() - input.len() + content.len(),
+            (None, Some(unstuffed)) => {
+                let mut copied = BytesMut::from(&start[..body_end]);
+
+                copied.extend_from_slice(pending_line_ending);
+                copied.extend_from_slice(unstuffed);
+
+                body = Some(copied);
+            }
+            (Some(body), unstuffed) => {
+                body.extend_from_slice(pending_line_ending);
+                body.extend_from_slice(unstuffed.unwrap_or(content));
+            }
+        }
+
+        pending_line_ending = line_ending;
+
+        input = &remaining[1..];
+    }
+}
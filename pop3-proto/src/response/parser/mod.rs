@@ -0,0 +1,477 @@
+//! The nom parsers behind [Response::parse], for codecs that manage their own buffers, e.g. to decode responses without copying message bodies.
+//!
+//! These expose the nom version used by this crate, prefer [Response::parse] where possible.
+mod core;
+#[cfg(feature = "sasl")]
+mod rfc1734;
+mod rfc1939;
+mod rfc2449;
+mod rfc6856;
+
+use nom::IResult;
+
+use crate::command::Command;
+
+pub use self::{
+    core::{copy, Share},
+    rfc1939::{scan_listing_line, unique_id_line},
+};
+
+use self::{
+    rfc1939::{
+        error_response, list_response, rfc822_response, stat_response, status, string_response,
+        uidl_list_response, uidl_response,
+    },
+    rfc2449::capability_response,
+    rfc6856::lang_list_response,
+};
+
+use super::{ParseMode, Response};
+
+/// Parses the response to the given command, copying message bodies out of the input.
+pub fn parse<'a>(
+    input: &'a [u8],
+    request: &Command,
+    mode: ParseMode,
+) -> IResult<&'a [u8], Response> {
+    parse_shared(input, request, mode, &copy)
+}
+
+/// Parses a response like [parse], but lets message bodies reference the buffer the input was read into through `share` instead of copying them.
+pub fn parse_shared<'a>(
+    input: &'a [u8],
+    request: &Command,
+    mode: ParseMode,
+    share: Share,
+) -> IResult<&'a [u8], Response> {
+    if input.is_empty() {
+        return Err(nom::Err::Incomplete(nom::Needed::Unknown));
+    }
+
+    #[cfg(feature = "sasl")]
+    match request {
+        Command::Base64(_) | Command::Auth => match rfc1734::auth(input) {
+            Ok((input, base64_challenge)) => {
+                if let Ok(challenge) = crate::base64::decode(base64_challenge) {
+                    return Ok((input, Response::Challenge(challenge.into())));
+                }
+            }
+            Err(nom::Err::Incomplete(needed)) => return Err(nom::Err::Incomplete(needed)),
+            Err(_) => {}
+        },
+        _ => {}
+    }
+
+    let (input, status) = status(input)?;
+
+    if status.success() {
+        match request {
+            Command::Stat => stat_response(input),
+            Command::Uidl { listing: false } => uidl_response(input),
+            Command::Uidl { listing: true } => uidl_list_response(input, mode),
+            Command::List { listing: false } => stat_response(input),
+            Command::List { listing: true } => list_response(input, mode),
+            Command::Retr | Command::Top => rfc822_response(input, mode, share),
+            Command::Custom {
+                multiline: true, ..
+            } => rfc822_response(input, mode, share),
+            Command::Capa => capability_response(input, mode),
+            Command::Lang { list: true } => lang_list_response(input, mode),
+            _ => string_response(input),
+        }
+    } else {
+        error_response(input)
+    }
+}
+
+/// Parses only the status line of a response, leaving the rest of the response untouched.
+pub fn status_line(input: &[u8]) -> IResult<&[u8], Response> {
+    let (input, status) = status(input)?;
+
+    if status.success() {
+        string_response(input)
+    } else {
+        error_response(input)
+    }
+}
+
+/// Counts the empty lines inside of a multi-line listing response.
+///
+/// The first line is always a status line, so any blank line in the response shows up as two consecutive line endings.
+pub fn count_blank_lines(input: &[u8]) -> usize {
+    input
+        .windows(4)
+        .filter(|window| window == b"\r\n\r\n")
+        .count()
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use crate::response::{
+        capability::Capability, code::ResponseCode, types::DataType, uidl::UidlResponse,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let listing = Command::List { listing: true };
+        let single = Command::List { listing: false };
+
+        let data = b"+OK 2 messages (320 bytes)\r\n1 120 more info\r\n2 200 info info\r\n.\r\n";
+
+        let (output, response) = parse(data, &listing, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::List(list) => {
+                assert!(list.items().len() == 2);
+                // assert!(list.message().as_ref() == b"scan listing follows")
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+
+        let data = b"+OK 2 messages (320 bytes)\r\n1 120\r\n2 200\r\n";
+
+        let result = parse(data, &listing, ParseMode::Strict);
+
+        assert!(result.is_err());
+
+        let data = b"+OK 1 120\r\n";
+
+        let (output, response) = parse(data, &single, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Stat(stat) => {
+                assert!(stat.counter().value().unwrap() == 1 && stat.size().value().unwrap() == 120)
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+
+        let data = b"+OK 1 120 test\r\n";
+
+        let (output, response) = parse(data, &single, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Stat(stat) => {
+                assert!(stat.counter().value().unwrap() == 1 && stat.size().value().unwrap() == 120)
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+
+        let data = b"+OK 1 \r\n";
+
+        let result = parse(data, &single, ParseMode::Strict);
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn test_list_header() {
+        let listing = Command::List { listing: true };
+
+        let data = b"+OK scan listing follows\r\n1 120\r\n2 200\r\n.\r\n";
+
+        let (output, response) = parse(data, &listing, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::List(list) => {
+                assert_eq!(list.items().len(), 2);
+                assert!(list.stats().is_none());
+            }
+            _ => unreachable!(),
+        }
+
+        // Looks exactly like the response to LIST with a message number.
+        let data = b"+OK 2 320\r\n1 120\r\n2 200\r\n.\r\n";
+
+        let (output, response) = parse(data, &listing, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::List(list) => {
+                assert_eq!(list.items().len(), 2);
+
+                let stats = list.stats().unwrap();
+
+                assert_eq!(stats.counter().value().unwrap(), 2);
+                assert_eq!(stats.size().value().unwrap(), 320);
+            }
+            _ => unreachable!(),
+        }
+
+        let data = b"+OK\r\n.\r\n";
+
+        let (output, response) = parse(data, &listing, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::List(list) => assert!(list.items().is_empty()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_stat() {
+        let data = b"+OK 2 messages (320 octets)\r\n";
+
+        let (output, response) = parse(data, &Command::Stat, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Stat(stat) => {
+                assert_eq!(stat.counter().value().unwrap(), 2);
+                assert_eq!(stat.size().value().unwrap(), 320);
+            }
+            _ => unreachable!(),
+        }
+
+        let data = b"+OK 20 600\r\n";
+
+        let (output, response) = parse(data, &Command::Stat, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Stat(stat) => {
+                assert!(stat.counter().value().unwrap() == 20);
+                assert!(stat.size().value().unwrap() == 600);
+            }
+            _ => {
+                println!("{:?}", response);
+                unreachable!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_uidl() {
+        let data = b"+OK unique-id listing follows\r\n1 whqtswO00WBw418f9t5JxYwZ\r\n2 QhdPYR:00WBw1Ph7x7\r\n.\r\n";
+
+        let (output, response) =
+            parse(data, &Command::Uidl { listing: true }, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Uidl(UidlResponse::Multiple(list)) => {
+                println!("{:?}", list);
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_string() {
+        let data = b"+OK maildrop has 2 messages (320 octets)\r\n";
+
+        let (output, response) = parse(data, &Command::Greet, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Message(msg) => {
+                assert!(msg.as_ref() == b"maildrop has 2 messages (320 octets)")
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_capa() {
+        let data = b"+OK\r\nUSER\r\nRESP-CODES\r\nEXPIRE 30\r\nSASL GSSAPI SKEY\r\nGOOGLE-TEST-CAPA\r\n.\r\n";
+
+        let (output, response) = parse(data, &Command::Capa, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Capability(capas) => {
+                assert!(capas.len() == 5);
+
+                assert!(capas.supports_user());
+                assert!(capas.supports_resp_codes());
+                assert!(!capas.supports_top());
+                assert!(capas.contains(&Capability::User));
+                assert_eq!(capas.sasl_mechanisms().len(), 2);
+                assert!(capas.expire().is_some());
+                assert_eq!(capas.login_delay(), None);
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_lang_list() {
+        let data = "+OK Language listing follows\r\nen English\r\nen-boont Boontling\r\n.\r\n";
+
+        let (output, response) = parse(
+            data.as_bytes(),
+            &Command::Lang { list: true },
+            ParseMode::Strict,
+        )
+        .unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Languages(languages) => {
+                assert_eq!(languages.len(), 2);
+                assert_eq!(languages[1].tag().as_ref(), b"en-boont");
+                assert_eq!(languages[1].description().as_ref(), b"Boontling");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_error_response_code() {
+        let data = b"-ERR [IN-USE] mailbox locked\r\n";
+
+        let (_, response) = parse(data, &Command::Stat, ParseMode::Strict).unwrap();
+
+        match response {
+            Response::Err(err) => {
+                assert_eq!(err.code(), Some(&ResponseCode::InUse));
+                assert_eq!(err.message().as_str().unwrap(), "mailbox locked");
+            }
+            _ => unreachable!(),
+        }
+
+        let data = b"-ERR [SYS/TEMP/DISK] try again\r\n";
+
+        let (_, response) = parse(data, &Command::Stat, ParseMode::Strict).unwrap();
+
+        match response {
+            Response::Err(err) => {
+                assert_eq!(
+                    err.code(),
+                    Some(&ResponseCode::Other("SYS/TEMP/DISK".into()))
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        let data = b"-ERR [no code here] oops\r\n";
+
+        let (_, response) = parse(data, &Command::Stat, ParseMode::Strict).unwrap();
+
+        match response {
+            Response::Err(err) => {
+                assert_eq!(err.code(), None);
+                assert_eq!(err.message().as_str().unwrap(), "[no code here] oops");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_capa_sasl() {
+        let data = b"+OK\r\nSASL PLAIN NTLM EXTERNAL X-CUSTOM_1\r\n.\r\n";
+
+        let (_, response) = parse(data, &Command::Capa, ParseMode::Strict).unwrap();
+
+        match response {
+            Response::Capability(capas) => {
+                assert_eq!(
+                    capas.sasl_mechanisms(),
+                    &[
+                        Bytes::from_static(b"PLAIN"),
+                        Bytes::from_static(b"NTLM"),
+                        Bytes::from_static(b"EXTERNAL"),
+                        Bytes::from_static(b"X-CUSTOM_1"),
+                    ]
+                );
+
+                assert!(capas.supports_sasl_mechanism("ntlm"));
+                assert!(!capas.supports_sasl_mechanism("GSSAPI"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_lenient_blank_lines() {
+        let data = b"+OK\r\nUSER\r\n\r\nTOP\r\n\r\n.\r\n";
+
+        assert!(parse(data, &Command::Capa, ParseMode::Strict).is_err());
+
+        let (output, response) = parse(data, &Command::Capa, ParseMode::Lenient).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Capability(capas) => assert_eq!(capas.len(), 2),
+            _ => unreachable!(),
+        }
+
+        let data = b"+OK\r\n1 120\r\n\r\n2 200\r\n.\r\n";
+
+        let (output, response) =
+            parse(data, &Command::List { listing: true }, ParseMode::Lenient).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::List(list) => assert_eq!(list.items().len(), 2),
+            _ => unreachable!(),
+        }
+
+        let data = b"+OK\r\n\r\n1 whqtswO00WBw418f9t5JxYwZ\r\n.\r\n";
+
+        let (output, response) =
+            parse(data, &Command::Uidl { listing: true }, ParseMode::Lenient).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Uidl(UidlResponse::Multiple(list)) => assert_eq!(list.items().len(), 1),
+            _ => unreachable!(),
+        }
+
+        assert_eq!(count_blank_lines(data), 1);
+    }
+
+    #[test]
+    fn test_custom() {
+        let command = Command::Custom {
+            name: "XTND".into(),
+            multiline: true,
+        };
+
+        let data = b"+OK extended listing\r\nfirst\r\nsecond\r\n.\r\n";
+
+        let (output, response) = parse(data, &command, ParseMode::Strict).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Bytes(bytes) => assert_eq!(bytes.as_ref(), b"first\r\nsecond"),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use nom::{
+    branch::alt,
+    bytes::streaming::{tag, take_while_m_n},
+    character::streaming::{not_line_ending, space0, space1},
+    combinator::{map, opt, value},
+    sequence::{preceded, terminated},
+    IResult,
+};
+
+use crate::{
+    constants::{END_OF_LINE, ERR, OK},
+    response::{
+        list::List,
+        stat::Stat,
+        types::number::Number,
+        uidl::{Uidl, UniqueId},
+        ErrorResponse, ParseMode, Response, Status,
+    },
+};
+
+use super::{
+    core::{eol, message_parser, multiline, multiline_body, number, Share},
+    rfc2449::response_code,
+};
+
+pub(crate) fn status(input: &[u8]) -> IResult<&[u8], Status> {
+    terminated(
+        map(alt((value(true, tag(OK)), value(false, tag(ERR)))), |val| {
+            Status::new(val)
+        }),
+        space0,
+    )(input)
+}
+
+fn stat(input: &[u8]) -> IResult<&[u8], Stat> {
+    let (input, count) = number(input)?;
+    let (input, _) = space1(input)?;
+    let (input, size) = number(input)?;
+    let (input, _) = opt(not_line_ending)(input)?;
+    let (input, _) = eol(input)?;
+
+    Ok((input, Stat::new(count, size)))
+}
+
+/// Reads the message count and the size of the maildrop from the free-form text after `+OK`.
+///
+/// Servers do not agree on what else goes into the line, e.g. `2 320`, `2 messages (320 octets)` or `2 320 maildrop ready`, so the first two numbers are used.
+fn summary_stats(line: &[u8]) -> Option<Stat> {
+    let mut numbers = line
+        .split(|byte| !byte.is_ascii_digit())
+        .filter(|number| !number.is_empty());
+
+    let count = Number::try_from(numbers.next()?).ok()?;
+    let size = Number::try_from(numbers.next()?).ok()?;
+
+    Some(Stat::new(count, size))
+}
+
+pub(crate) fn stat_response(input: &[u8]) -> IResult<&[u8], Response> {
+    let (remaining, message) = message_parser(input)?;
+
+    match message.and_then(summary_stats) {
+        Some(stats) => Ok((remaining, Response::Stat(stats))),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Digit,
+        ))),
+    }
+}
+
+/// Parses a scan listing, the text after `+OK` is free-form and only used for the totals if it happens to contain them.
+pub(crate) fn list_response(input: &[u8], mode: ParseMode) -> IResult<&[u8], Response> {
+    let (input, message) = message_parser(input)?;
+
+    let (input, items) = multiline(preceded(opt(tag(".")), stat), mode)(input)?;
+
+    let list = List::new(message.and_then(summary_stats), items);
+
+    Ok((input, Response::List(list)))
+}
+
+struct UniqueIdParser;
+
+impl UniqueIdParser {
+    fn is_valid_char(c: u8) -> bool {
+        (0x21..=0x7E).contains(&c)
+    }
+
+    pub fn parse(input: &[u8]) -> IResult<&[u8], &[u8]> {
+        take_while_m_n(1, 70, Self::is_valid_char)(input)
+    }
+}
+
+fn uidl(input: &[u8]) -> IResult<&[u8], UniqueId> {
+    let (input, index) = number(input)?;
+    let (input, _) = space1(input)?;
+    let (input, id) = UniqueIdParser::parse(input)?;
+    let (input, _) = eol(input)?;
+
+    Ok((input, UniqueId::new(index, id)))
+}
+
+pub(crate) fn uidl_list_response(input: &[u8], mode: ParseMode) -> IResult<&[u8], Response> {
+    let (input, message) = message_parser(input)?;
+
+    let (input, list) = multiline(preceded(opt(tag(".")), uidl), mode)(input)?;
+
+    let list = Uidl::new(message, list);
+
+    Ok((input, Response::Uidl(list.into())))
+}
+
+/// Parses a single line of a listing, without its line ending, as read by a codec that streams the listing line by line.
+fn listing_line<T>(line: &[u8], parser: impl Fn(&[u8]) -> IResult<&[u8], T>) -> Option<T> {
+    let mut input = Vec::with_capacity(line.len() + END_OF_LINE.len());
+
+    input.extend_from_slice(line);
+    input.extend_from_slice(&END_OF_LINE);
+
+    match parser(&input) {
+        Ok(([], value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Parses a single line of a scan listing, e.g. `1 120`, without its line ending.
+pub fn scan_listing_line(line: &[u8]) -> Option<Stat> {
+    listing_line(line, stat)
+}
+
+/// Parses a single line of a unique id listing, e.g. `1 whqtswO00WBw418f9t5JxYwZ`, without its line ending.
+pub fn unique_id_line(line: &[u8]) -> Option<UniqueId> {
+    listing_line(line, uidl)
+}
+
+pub(crate) fn uidl_response(input: &[u8]) -> IResult<&[u8], Response> {
+    let (input, unique_id) = uidl(input)?;
+
+    Ok((input, Response::Uidl(unique_id.into())))
+}
+
+pub(crate) fn rfc822_response<'a>(
+    input: &'a [u8],
+    mode: ParseMode,
+    share: Share,
+) -> IResult<&'a [u8], Response> {
+    let (input, _message) = message_parser(input)?;
+
+    let (input, content) = multiline_body(input, mode, share)?;
+
+    Ok((input, Response::Bytes(content)))
+}
+
+pub(crate) fn error_response(input: &[u8]) -> IResult<&[u8], Response> {
+    let (input, code) = opt(response_code)(input)?;
+    let (input, message) = message_parser(input)?;
+
+    let message = message.unwrap_or(b"");
+
+    Ok((
+        input,
+        Response::Err(ErrorResponse::new(code, message.into())),
+    ))
+}
+
+pub(crate) fn string_response(input: &[u8]) -> IResult<&[u8], Response> {
+    let (input, message) = message_parser(input)?;
+
+    let message = message.unwrap_or(b"");
+
+    Ok((input, Response::Message(message.into())))
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use crate::response::{parser::core::copy, types::DataType};
+
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let data = b"+OK\r\n";
+
+        let (output, resp_status) = status(data).unwrap();
+
+        assert!(output == b"\r\n");
+        assert!(resp_status.success());
+
+        let data = b"-ERR\r\n";
+
+        let (output, resp_status) = status(data).unwrap();
+
+        assert!(output == b"\r\n");
+        assert!(!resp_status.success());
+    }
+
+    #[test]
+    fn test_stat() {
+        let data = b"1 120 bla bla\r\n";
+
+        let (output, stats) = stat(data).unwrap();
+
+        assert!(output.is_empty());
+        assert!(stats.counter().value().unwrap() == 1);
+        assert!(stats.size().value().unwrap() == 120);
+
+        let data = b"1 sdf bla bla\r\n";
+
+        let result = stat(data);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_summary_stats() {
+        let stats = summary_stats(b"2 messages (320 bytes)").unwrap();
+
+        assert!(stats.counter().value().unwrap() == 2);
+        assert!(stats.size().value().unwrap() == 320);
+
+        let stats = summary_stats(b"2 320 maildrop ready").unwrap();
+
+        assert!(stats.counter().value().unwrap() == 2);
+        assert!(stats.size().value().unwrap() == 320);
+
+        assert!(summary_stats(b"scan listing follows").is_none());
+        assert!(summary_stats(b"1 ").is_none());
+    }
+
+    #[test]
+    fn test_rfc822() {
+        let data = b"Date: Thu, 9 Sep 2023 15:30:00 -0400\r\nFrom: John Doe <johndoe@example.com>\r\nTo: Jane Smith <janesmith@example.com>\r\nSubject: Hello, Jane!\r\n\r\nDear Jane,\r\n\r\nI hope this message finds you well. I just wanted to say hello and see how you're doing.\r\n\r\nBest regards,\r\nJohn\r\n.\r\n";
+
+        let (output, response) = rfc822_response(data, ParseMode::Strict, &copy).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Bytes(bytes) => {
+                assert!(bytes.len() == 228)
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_rfc822_byte_stuffing() {
+        let data = b"\r\nSubject: Dots\r\n\r\n..\r\n...and more\r\n.\r\n";
+
+        let (output, response) = rfc822_response(data, ParseMode::Strict, &copy).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Bytes(bytes) => {
+                assert_eq!(bytes.as_ref(), b"Subject: Dots\r\n\r\n.\r\n..and more")
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+
+        let data = b"\r\n.\r\n";
+
+        let (output, response) = rfc822_response(data, ParseMode::Strict, &copy).unwrap();
+
+        assert!(output.is_empty());
+
+        match response {
+            Response::Bytes(bytes) => assert!(bytes.is_empty()),
+            _ => {
+                unreachable!()
+            }
+        }
+
+        let data = b"\r\nSubject: Incomplete\r\n..";
+
+        assert!(matches!(
+            rfc822_response(data, ParseMode::Strict, &copy),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn test_rfc822_bare_line_feeds() {
+        let data = b"\r\nfirst\n.\nsecond\r\n.\r\n";
+
+        let (output, _) = rfc822_response(data, ParseMode::Strict, &copy).unwrap();
+
+        assert!(output.is_empty());
+
+        let (output, response) = rfc822_response(data, ParseMode::Lenient, &copy).unwrap();
+
+        assert_eq!(output, b"second\r\n.\r\n");
+
+        match response {
+            Response::Bytes(bytes) => assert_eq!(bytes.as_ref(), b"first"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_rfc822_shared() {
+        let data = Bytes::from_static(b"\r\nSubject: Shared\r\n\r\nBody\r\n.\r\n");
+
+        let share = |slice: &[u8]| data.slice_ref(slice);
+
+        let (_, response) = rfc822_response(&data, ParseMode::Strict, &share).unwrap();
+
+        match response {
+            Response::Bytes(bytes) => {
+                assert_eq!(bytes.as_ref(), b"Subject: Shared\r\n\r\nBody");
+                assert_eq!(bytes.as_ptr(), data[2..].as_ptr());
+            }
+            _ => unreachable!(),
+        }
+
+        let data = Bytes::from_static(b"\r\nfirst\r\n..second\r\n.\r\n");
+
+        let share = |slice: &[u8]| data.slice_ref(slice);
+
+        match rfc822_response(&data, ParseMode::Strict, &share).unwrap().1 {
+            Response::Bytes(bytes) => assert_eq!(bytes.as_ref(), b"first\r\n.second"),
+            _ => unreachable!(),
+        }
+    }
+}
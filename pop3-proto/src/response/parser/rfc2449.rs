@@ -1,32 +1,43 @@
 use bytes::Bytes;
 use nom::{
     branch::alt,
-    bytes::streaming::tag_no_case,
-    character::streaming::{digit1, one_of, space0, space1},
+    bytes::streaming::{tag, tag_no_case, take_while1, take_while_m_n},
+    character::streaming::{one_of, space0, space1},
     combinator::{map, opt, value},
-    multi::{many1, many_till, separated_list0},
-    sequence::{preceded, terminated},
+    multi::{many1, separated_list0},
+    sequence::{delimited, preceded, terminated},
     IResult,
 };
 
 use crate::response::{
     capability::{Capability, Expiration},
+    code::ResponseCode,
     types::number::Duration,
-    Response,
+    ParseMode, Response,
 };
 
-use super::core::{end_of_multiline, eol, message_parser};
+use super::{
+    core::{eol, message_parser, multiline, number},
+    rfc6856::utf8,
+};
 
+/// The name of a SASL mechanism, see https://www.rfc-editor.org/rfc/rfc4422#section-3.1
 fn sasl_mechanism(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    alt((
-        tag_no_case("KERBEROS_V4"),
-        tag_no_case("GSSAPI"),
-        tag_no_case("SKEY"),
-        tag_no_case("CRAM-MD5"),
-        tag_no_case("PLAIN"),
-        tag_no_case("XOAUTH2"),
-        tag_no_case("OAUTHBEARER"),
-    ))(input)
+    take_while_m_n(1, 20, |byte: u8| {
+        byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'
+    })(input)
+}
+
+/// The extended response code at the start of a negative response, e.g. `[IN-USE]`.
+pub(crate) fn response_code(input: &[u8]) -> IResult<&[u8], ResponseCode> {
+    let (input, code) = delimited(
+        tag("["),
+        take_while1(|byte: u8| !matches!(byte, b'[' | b']' | b' ' | b'\r' | b'\n')),
+        tag("]"),
+    )(input)?;
+    let (input, _) = space0(input)?;
+
+    Ok((input, ResponseCode::from_bytes(code)))
 }
 
 fn sasl(input: &[u8]) -> IResult<&[u8], Capability> {
@@ -35,12 +46,7 @@ fn sasl(input: &[u8]) -> IResult<&[u8], Capability> {
     let (input, mechanisms) = separated_list0(space1, sasl_mechanism)(input)?;
     let (input, _) = eol(input)?;
 
-    let capa = Capability::Sasl(
-        mechanisms
-            .into_iter()
-            .map(|slice| Bytes::copy_from_slice(slice))
-            .collect(),
-    );
+    let capa = Capability::Sasl(mechanisms.into_iter().map(Bytes::copy_from_slice).collect());
 
     Ok((input, capa))
 }
@@ -48,7 +54,7 @@ fn sasl(input: &[u8]) -> IResult<&[u8], Capability> {
 fn login_delay(input: &[u8]) -> IResult<&[u8], Capability> {
     let (input, _) = tag_no_case("LOGIN-DELAY")(input)?;
     let (input, _) = space1(input)?;
-    let (input, time) = digit1(input)?;
+    let (input, time) = number(input)?;
     let (input, _) = eol(input)?;
 
     let capa = Capability::LoginDelay(Duration::new(time, 1));
@@ -61,7 +67,7 @@ fn expire(input: &[u8]) -> IResult<&[u8], Capability> {
     let (input, expiration) = opt(preceded(
         space1,
         alt((
-            map(digit1, |time: &[u8]| {
+            map(number, |time| {
                 Expiration::Time(Duration::new(time, 24 * 60 * 60))
             }),
             value(Expiration::Never, tag_no_case("NEVER")),
@@ -76,6 +82,7 @@ fn expire(input: &[u8]) -> IResult<&[u8], Capability> {
 
 fn implementation(input: &[u8]) -> IResult<&[u8], Capability> {
     let (input, _) = tag_no_case("IMPLEMENTATION")(input)?;
+    let (input, _) = space0(input)?;
     let (input, message) = message_parser(input)?;
 
     let message = message.unwrap_or(b"");
@@ -111,6 +118,7 @@ fn capability(input: &[u8]) -> IResult<&[u8], Capability> {
     );
     let uidl = terminated(value(Capability::Uidl, tag_no_case(b"UIDL")), eol);
     let stls = terminated(value(Capability::Stls, tag_no_case(b"STLS")), eol);
+    let lang = terminated(value(Capability::Lang, tag_no_case(b"LANG")), eol);
 
     let (input, capability) = alt((
         top,
@@ -123,18 +131,20 @@ fn capability(input: &[u8]) -> IResult<&[u8], Capability> {
         uidl,
         implementation,
         stls,
+        utf8,
+        lang,
         unknown_capability,
     ))(input)?;
 
     Ok((input, capability))
 }
 
-pub(crate) fn capability_response(input: &[u8]) -> IResult<&[u8], Response> {
+pub(crate) fn capability_response(input: &[u8], mode: ParseMode) -> IResult<&[u8], Response> {
     let (input, _message) = message_parser(input)?;
 
-    let (input, (capabilities, _end)) = many_till(capability, end_of_multiline)(input)?;
+    let (input, capabilities) = multiline(capability, mode)(input)?;
 
-    Ok((input, Response::Capability(capabilities)))
+    Ok((input, Response::Capability(capabilities.into())))
 }
 
 #[cfg(test)]
@@ -158,4 +168,27 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_capability_round_trip() {
+        let data = b"TOP\r\nUSER\r\nSASL PLAIN XOAUTH2\r\nSASL\r\nRESP-CODES\r\nLOGIN-DELAY 900\r\nPIPELINING\r\nEXPIRE 60\r\nEXPIRE NEVER\r\nUIDL\r\nIMPLEMENTATION Shlemazle-Plotz-v302\r\nIMPLEMENTATION\r\nSTLS\r\nUTF8\r\nUTF8 USER\r\nLANG\r\nX-FOO\r\n";
+
+        let mut input = &data[..];
+
+        let mut rendered = Vec::new();
+
+        while !input.is_empty() {
+            let (rest, capa) = capability(input).unwrap();
+
+            let line = [capa.to_bytes(), b"\r\n".to_vec()].concat();
+
+            assert_eq!(capability(&line).unwrap(), (&b""[..], capa));
+
+            rendered.extend(line);
+
+            input = rest;
+        }
+
+        assert_eq!(rendered, data);
+    }
 }
@@ -0,0 +1,78 @@
+use nom::{
+    bytes::streaming::{tag_no_case, take_while1},
+    character::streaming::space1,
+    combinator::opt,
+    multi::many0,
+    sequence::preceded,
+    IResult,
+};
+
+use crate::response::{capability::Capability, lang::Language, ParseMode, Response};
+
+use super::core::{eol, message_parser, multiline};
+
+/// The UTF8 capability, optionally followed by the USER argument, see https://www.rfc-editor.org/rfc/rfc6856#section-2
+pub(crate) fn utf8(input: &[u8]) -> IResult<&[u8], Capability> {
+    let (input, _) = tag_no_case("UTF8")(input)?;
+    let (input, args) = many0(preceded(
+        space1,
+        take_while1(|byte: u8| byte.is_ascii_graphic()),
+    ))(input)?;
+    let (input, _) = eol(input)?;
+
+    let user = args.iter().any(|arg| arg.eq_ignore_ascii_case(b"USER"));
+
+    Ok((input, Capability::Utf8 { user }))
+}
+
+/// A single language in a LANG listing, e.g. `en-boont Boontling`.
+fn language(input: &[u8]) -> IResult<&[u8], Language> {
+    let (input, tag) = take_while1(|byte: u8| byte.is_ascii_graphic())(input)?;
+    let (input, description) = opt(preceded(space1, message_parser))(input)?;
+
+    let input = match description {
+        Some(_) => input,
+        None => eol(input)?.0,
+    };
+
+    let description = description.flatten().unwrap_or(b"");
+
+    Ok((input, Language::new(tag, description)))
+}
+
+pub(crate) fn lang_list_response(input: &[u8], mode: ParseMode) -> IResult<&[u8], Response> {
+    let (input, _message) = message_parser(input)?;
+
+    let (input, languages) = multiline(language, mode)(input)?;
+
+    Ok((input, Response::Languages(languages)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_utf8() {
+        let (input, capa) = utf8(b"UTF8 USER\r\n").unwrap();
+
+        assert!(input.is_empty());
+        assert_eq!(capa, Capability::Utf8 { user: true });
+
+        let (_, capa) = utf8(b"UTF8\r\n").unwrap();
+
+        assert_eq!(capa, Capability::Utf8 { user: false });
+    }
+
+    #[test]
+    fn test_language() {
+        let (input, lang) = language(b"en-boont Boontling\r\n").unwrap();
+
+        assert!(input.is_empty());
+        assert_eq!(lang, Language::new("en-boont", "Boontling"));
+
+        let (_, lang) = language(b"de\r\n").unwrap();
+
+        assert_eq!(lang, Language::new("de", ""));
+    }
+}
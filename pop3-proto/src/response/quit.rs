@@ -0,0 +1,37 @@
+use super::types::message::Text;
+
+/// The outcome of ending a session using QUIT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuitSummary {
+    success: bool,
+    messages_marked: usize,
+    server_text: Text,
+}
+
+impl QuitSummary {
+    pub fn new<T: Into<Text>>(success: bool, messages_marked: usize, server_text: T) -> Self {
+        Self {
+            success,
+            messages_marked,
+            server_text: server_text.into(),
+        }
+    }
+
+    /// Whether the server responded positively, which means that all of the messages marked as deleted were removed.
+    ///
+    /// When this is false the server failed to remove some of the messages, see [QuitSummary::server_text] for the reason.
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    /// The amount of messages that were marked as deleted when the session ended.
+    pub fn messages_marked(&self) -> usize {
+        self.messages_marked
+    }
+
+    /// The text the server sent along with its final response, e.g. "dewey POP3 server signing off (2 messages left)".
+    pub fn server_text(&self) -> &Text {
+        &self.server_text
+    }
+}
@@ -0,0 +1,44 @@
+use crate::error::Result;
+
+use super::types::number::{MessageNumber, Number};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stat {
+    message_count: Number,
+    size: Number,
+}
+
+impl Stat {
+    pub fn new<C: Into<Number>, S: Into<Number>>(message_count: C, size: S) -> Self {
+        Self {
+            message_count: message_count.into(),
+            size: size.into(),
+        }
+    }
+
+    pub fn counter(&self) -> &Number {
+        &self.message_count
+    }
+
+    pub fn size(&self) -> &Number {
+        &self.size
+    }
+
+    /// The amount of messages as an integer.
+    pub fn message_count(&self) -> u64 {
+        self.message_count.as_u64()
+    }
+
+    /// The size in octets as an integer.
+    pub fn size_bytes(&self) -> u64 {
+        self.size.as_u64()
+    }
+
+    /// The counter as a [MessageNumber], for an item of a LIST response where the counter is the number of the message it describes.
+    ///
+    /// Fails if the number does not fit in a [usize].
+    pub fn message_number(&self) -> Result<MessageNumber> {
+        Ok(usize::try_from(&self.message_count)?.into())
+    }
+}
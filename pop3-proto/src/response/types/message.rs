@@ -0,0 +1,128 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+    result,
+};
+
+use bytes::Bytes;
+
+use crate::error::{Error, Result};
+
+use super::DataType;
+
+#[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Clone)]
+/// Represents a Pop3 string data type.
+///
+/// Get its real value by calling `value()` from the [DataType] trait
+pub struct Text {
+    inner: Bytes,
+}
+
+impl From<&str> for Text {
+    fn from(value: &str) -> Self {
+        Self::from(value.as_bytes())
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let message = self.as_str_lossy();
+
+        write!(f, "{}", message)
+    }
+}
+
+impl TryInto<String> for Text {
+    type Error = Error;
+
+    fn try_into(self) -> result::Result<String, Self::Error> {
+        self.value()
+    }
+}
+
+impl From<&[u8]> for Text {
+    fn from(value: &[u8]) -> Self {
+        Self {
+            inner: Bytes::copy_from_slice(value),
+        }
+    }
+}
+
+impl AsRef<[u8]> for Text {
+    fn as_ref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl From<Bytes> for Text {
+    fn from(value: Bytes) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl DataType<String> for Text {
+    fn raw(&self) -> &[u8] {
+        &self.inner
+    }
+
+    fn as_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.inner)
+    }
+
+    fn as_str(&self) -> Result<&str> {
+        Ok(std::str::from_utf8(&self.inner)?)
+    }
+
+    fn value(&self) -> Result<String> {
+        self.as_str().map(|slice| slice.to_string())
+    }
+}
+
+/// Serialized as a string when it is valid UTF-8, which is nearly always the case, and as bytes otherwise.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Text {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        match std::str::from_utf8(&self.inner) {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => serializer.serialize_bytes(&self.inner),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Text {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        struct TextVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for TextVisitor {
+            type Value = Text;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a string or bytes")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> result::Result<Text, E> {
+                Ok(value.into())
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, value: &[u8]) -> result::Result<Text, E> {
+                Ok(value.into())
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> result::Result<Text, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+
+                Ok(Bytes::from(bytes).into())
+            }
+        }
+
+        deserializer.deserialize_any(TextVisitor)
+    }
+}
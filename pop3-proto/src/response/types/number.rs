@@ -0,0 +1,316 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+    result, time,
+};
+
+use bytes::Bytes;
+
+use crate::error::{err, Error, ErrorKind, Result};
+
+use super::DataType;
+
+#[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Clone)]
+/// Represents a Pop3 number data type.
+///
+/// Get its real value by calling `value()` from the [DataType] trait, or [Number::as_u64] to skip the error handling.
+/// Numbers can be compared with integers directly.
+///
+/// A number always consists of digits only and fits in a [u64], anything else is rejected when it is created.
+pub struct Number {
+    inner: Bytes,
+}
+
+impl TryInto<usize> for Number {
+    type Error = Error;
+
+    fn try_into(self) -> result::Result<usize, Self::Error> {
+        self.value()
+    }
+}
+
+impl TryFrom<&[u8]> for Number {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        Bytes::copy_from_slice(value).try_into()
+    }
+}
+
+impl AsRef<[u8]> for Number {
+    fn as_ref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl TryFrom<Bytes> for Number {
+    type Error = Error;
+
+    fn try_from(value: Bytes) -> Result<Self> {
+        if value.is_empty() || !value.iter().all(u8::is_ascii_digit) {
+            err!(
+                ErrorKind::InvalidArgument,
+                "A number can only consist of digits, got '{}'",
+                String::from_utf8_lossy(&value)
+            )
+        }
+
+        // Fails if the number does not fit.
+        std::str::from_utf8(&value)?.parse::<u64>()?;
+
+        Ok(Self { inner: value })
+    }
+}
+
+impl From<u64> for Number {
+    fn from(value: u64) -> Self {
+        Self {
+            inner: value.to_string().into(),
+        }
+    }
+}
+
+/// Serialized as an integer.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Number {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_u64())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Number {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("an unsigned integer or a string")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> result::Result<Number, E> {
+                Ok(value.into())
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> result::Result<Number, E> {
+                Number::try_from(value.as_bytes()).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let number = self.as_str_lossy();
+
+        write!(f, "{}", number)
+    }
+}
+
+impl Number {
+    /// The value as an integer, without having to handle errors.
+    ///
+    /// This is always exact, as a number that is not made up of digits or does not fit is rejected when it is created.
+    pub fn as_u64(&self) -> u64 {
+        self.inner
+            .iter()
+            .fold(0, |value, digit| value * 10 + u64::from(digit - b'0'))
+    }
+}
+
+impl From<&Number> for u64 {
+    fn from(number: &Number) -> Self {
+        number.as_u64()
+    }
+}
+
+/// Fails on platforms where the value does not fit, use [u64::from] to get the exact value.
+impl TryFrom<&Number> for usize {
+    type Error = Error;
+
+    fn try_from(number: &Number) -> Result<Self> {
+        match usize::try_from(number.as_u64()) {
+            Ok(value) => Ok(value),
+            Err(_) => err!(
+                ErrorKind::InvalidArgument,
+                "The number {} is too large for this platform",
+                number
+            ),
+        }
+    }
+}
+
+/// Fails if the value does not fit, which happens for sizes of more than 4 GiB, use [u64::from] to get the exact value.
+impl TryFrom<&Number> for u32 {
+    type Error = Error;
+
+    fn try_from(number: &Number) -> Result<Self> {
+        match u32::try_from(number.as_u64()) {
+            Ok(value) => Ok(value),
+            Err(_) => err!(
+                ErrorKind::InvalidArgument,
+                "The number {} is too large",
+                number
+            ),
+        }
+    }
+}
+
+impl PartialEq<u64> for Number {
+    fn eq(&self, other: &u64) -> bool {
+        self.as_u64() == *other
+    }
+}
+
+impl PartialEq<Number> for u64 {
+    fn eq(&self, other: &Number) -> bool {
+        other == self
+    }
+}
+
+impl PartialOrd<u64> for Number {
+    fn partial_cmp(&self, other: &u64) -> Option<std::cmp::Ordering> {
+        self.as_u64().partial_cmp(other)
+    }
+}
+
+impl PartialOrd<Number> for u64 {
+    fn partial_cmp(&self, other: &Number) -> Option<std::cmp::Ordering> {
+        other.partial_cmp(self).map(std::cmp::Ordering::reverse)
+    }
+}
+
+impl DataType<usize> for Number {
+    fn raw(&self) -> &[u8] {
+        &self.inner
+    }
+
+    fn as_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.inner)
+    }
+
+    fn as_str(&self) -> Result<&str> {
+        Ok(std::str::from_utf8(&self.inner)?)
+    }
+
+    fn value(&self) -> Result<usize> {
+        let string = self.as_str()?;
+
+        let number: usize = string.parse()?;
+
+        Ok(number)
+    }
+}
+
+/// The number of a message in the maildrop, as used by commands such as RETR, TOP and DELE.
+///
+/// Message numbers start at 1 and only stay the same for the duration of a session, use the unique id from UIDL to refer to a message across sessions.
+/// Having a type of its own makes it harder to mix up a message number with a line count or a size, an integer can be converted using [From].
+#[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct MessageNumber(usize);
+
+impl MessageNumber {
+    pub fn new(number: usize) -> Self {
+        Self(number)
+    }
+
+    /// The message number as an integer.
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for MessageNumber {
+    fn from(number: usize) -> Self {
+        Self(number)
+    }
+}
+
+impl From<MessageNumber> for usize {
+    fn from(number: MessageNumber) -> Self {
+        number.0
+    }
+}
+
+impl PartialEq<usize> for MessageNumber {
+    fn eq(&self, other: &usize) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Display for MessageNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Represents a Pop3 duration data type.
+///
+/// Get its real value by calling `value()` from the [DataType] trait
+pub struct Duration {
+    inner: Number,
+    to_secs_multiplier: u64,
+}
+
+impl Duration {
+    pub fn new(number: Number, to_secs_multiplier: u64) -> Self {
+        Self {
+            inner: number,
+            to_secs_multiplier,
+        }
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let number = self.as_str_lossy();
+
+        write!(f, "{}", number)
+    }
+}
+
+impl TryInto<time::Duration> for Duration {
+    type Error = Error;
+
+    fn try_into(self) -> result::Result<time::Duration, Self::Error> {
+        self.value()
+    }
+}
+
+impl DataType<time::Duration> for Duration {
+    fn raw(&self) -> &[u8] {
+        self.inner.raw()
+    }
+
+    fn as_str_lossy(&self) -> Cow<'_, str> {
+        self.inner.as_str_lossy()
+    }
+
+    fn as_str(&self) -> Result<&str> {
+        self.inner.as_str()
+    }
+
+    fn value(&self) -> Result<time::Duration> {
+        let secs = match self.inner.as_u64().checked_mul(self.to_secs_multiplier) {
+            Some(secs) => secs,
+            None => err!(
+                ErrorKind::InvalidArgument,
+                "The duration {} is too long",
+                self.inner
+            ),
+        };
+
+        Ok(time::Duration::from_secs(secs))
+    }
+}
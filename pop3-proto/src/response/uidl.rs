@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use crate::error::Result;
+
+use super::types::{
+    message::Text,
+    number::{MessageNumber, Number},
+    DataType,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UidlResponse {
+    Multiple(Uidl),
+    Single(UniqueId),
+}
+
+impl UidlResponse {
+    /// The unique ids in the response, a single one if UIDL was sent with a message number.
+    pub fn iter(&self) -> std::slice::Iter<'_, UniqueId> {
+        match self {
+            Self::Multiple(uidl) => uidl.items.iter(),
+            Self::Single(unique_id) => std::slice::from_ref(unique_id).iter(),
+        }
+    }
+
+    /// The unique id of the message with the given number, if it is in the response.
+    pub fn get<N: Into<MessageNumber>>(&self, msg_number: N) -> Option<&UniqueId> {
+        let msg_number = msg_number.into();
+
+        self.iter()
+            .find(|unique_id| unique_id.message_number().ok() == Some(msg_number))
+    }
+
+    /// The entry with the given unique id, which tells the number of the message in this session.
+    pub fn find_by_id<I: AsRef<[u8]>>(&self, id: I) -> Option<&UniqueId> {
+        self.iter()
+            .find(|unique_id| unique_id.id().raw() == id.as_ref())
+    }
+
+    /// The unique ids by message number.
+    ///
+    /// Entries with a message number that does not fit in a [usize] are left out, unique ids that are not valid utf-8 are converted lossily.
+    pub fn into_map(self) -> HashMap<usize, String> {
+        self.into_iter()
+            .filter_map(|unique_id| {
+                let index = unique_id.message_number().ok()?.get();
+
+                Some((index, unique_id.id().as_str_lossy().into_owned()))
+            })
+            .collect()
+    }
+}
+
+impl IntoIterator for UidlResponse {
+    type Item = UniqueId;
+    type IntoIter = std::vec::IntoIter<UniqueId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Self::Multiple(uidl) => uidl.items.into_iter(),
+            Self::Single(unique_id) => vec![unique_id].into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a UidlResponse {
+    type Item = &'a UniqueId;
+    type IntoIter = std::slice::Iter<'a, UniqueId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<Uidl> for UidlResponse {
+    fn from(value: Uidl) -> Self {
+        Self::Multiple(value)
+    }
+}
+
+impl From<UniqueId> for UidlResponse {
+    fn from(value: UniqueId) -> Self {
+        Self::Single(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Uidl {
+    message: Option<Text>,
+    items: Vec<UniqueId>,
+}
+
+impl Uidl {
+    pub fn new<M: Into<Text>>(message: Option<M>, items: Vec<UniqueId>) -> Self {
+        Self {
+            message: message.map(|msg| msg.into()),
+            items,
+        }
+    }
+
+    pub fn items(&self) -> &[UniqueId] {
+        self.items.as_ref()
+    }
+
+    pub fn message(&self) -> Option<&Text> {
+        self.message.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UniqueId {
+    index: Number,
+    id: Text,
+}
+
+impl UniqueId {
+    pub fn new<I: Into<Number>, D: Into<Text>>(index: I, id: D) -> Self {
+        Self {
+            index: index.into(),
+            id: id.into(),
+        }
+    }
+
+    pub fn index(&self) -> &Number {
+        &self.index
+    }
+
+    pub fn id(&self) -> &Text {
+        &self.id
+    }
+
+    /// The index as a [MessageNumber], which refers to the message in commands such as RETR.
+    ///
+    /// Fails if the number does not fit in a [usize].
+    pub fn message_number(&self) -> Result<MessageNumber> {
+        Ok(usize::try_from(&self.index)?.into())
+    }
+}
@@ -0,0 +1,264 @@
+//! Logging in using whichever mechanism the server supports, see [AuthStrategy].
+//!
+//! Servers differ wildly in the ways they allow logging in, so instead of hard coding a single mechanism, the strategy tries the ones the server advertises in order of preference.
+use std::fmt::{self, Display, Formatter};
+
+use bytes::Bytes;
+
+use crate::macros::debug;
+
+use crate::{
+    error::{err, Error, ErrorKind, Result},
+    response::types::message::Text,
+    runtime::io::{Read, Write},
+    Client,
+};
+
+/// Which mechanisms [Client::login] uses, set using [crate::ClientBuilder::login_policy].
+///
+/// Only the capabilities that are already known are taken into account, when fetching them was deferred using [crate::ClientBuilder::lazy_capabilities] the SASL mechanisms are skipped.
+///
+/// SASL PLAIN is only used on a secure connection, so that on a plain connection a rejected attempt is never followed by sending the password again using USER and PASS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoginPolicy {
+    /// Use SASL PLAIN when the server advertises it, and the USER and PASS commands otherwise.
+    Plain,
+    /// Use the strongest mechanism the server supports: CRAM-MD5 or APOP, which never send the password, before SASL PLAIN and finally USER and PASS.
+    ///
+    /// A mechanism the server rejects is followed by the next one, see [AuthStrategy].
+    Strongest,
+    /// Only use the USER and PASS commands.
+    #[default]
+    UserPass,
+}
+
+impl LoginPolicy {
+    /// The mechanisms that are tried on a secure connection, in order of preference.
+    pub fn preference(&self) -> Vec<AuthMechanism> {
+        match self {
+            #[cfg(feature = "sasl")]
+            Self::Plain => vec![AuthMechanism::Plain, AuthMechanism::UserPass],
+            #[cfg(feature = "sasl")]
+            Self::Strongest => vec![
+                AuthMechanism::CramMd5,
+                AuthMechanism::Apop,
+                AuthMechanism::Plain,
+                AuthMechanism::UserPass,
+            ],
+            #[cfg(not(feature = "sasl"))]
+            Self::Plain => vec![AuthMechanism::UserPass],
+            #[cfg(not(feature = "sasl"))]
+            Self::Strongest => vec![AuthMechanism::Apop, AuthMechanism::UserPass],
+            Self::UserPass => vec![AuthMechanism::UserPass],
+        }
+    }
+}
+
+/// A way to log in using a username and password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthMechanism {
+    /// The APOP command, which never sends the password itself, see [Client::apop_login].
+    Apop,
+    /// The SASL CRAM-MD5 mechanism, which never sends the password itself either.
+    #[cfg(feature = "sasl")]
+    CramMd5,
+    /// The SASL NTLM mechanism.
+    #[cfg(feature = "ntlm")]
+    Ntlm,
+    /// The SASL PLAIN mechanism, which is only used on a secure connection.
+    #[cfg(feature = "sasl")]
+    Plain,
+    /// The USER and PASS commands.
+    UserPass,
+}
+
+impl AuthMechanism {
+    /// Whether the server indicated that it supports this mechanism, using its capabilities or the timestamp in its greeting.
+    ///
+    /// USER and PASS are always assumed to be supported, as not every server that supports them advertises the USER capability.
+    pub fn is_supported<S: Read + Write + Unpin + Send>(&self, client: &Client<S>) -> bool {
+        match self {
+            Self::Apop => client.supports_apop(),
+            #[cfg(feature = "sasl")]
+            Self::CramMd5 => client.has_auth_mechanism("CRAM-MD5"),
+            #[cfg(feature = "ntlm")]
+            Self::Ntlm => client.has_auth_mechanism("NTLM"),
+            #[cfg(feature = "sasl")]
+            Self::Plain => client.has_auth_mechanism("PLAIN"),
+            Self::UserPass => true,
+        }
+    }
+}
+
+impl Display for AuthMechanism {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Apop => write!(f, "APOP"),
+            #[cfg(feature = "sasl")]
+            Self::CramMd5 => write!(f, "SASL CRAM-MD5"),
+            #[cfg(feature = "ntlm")]
+            Self::Ntlm => write!(f, "SASL NTLM"),
+            #[cfg(feature = "sasl")]
+            Self::Plain => write!(f, "SASL PLAIN"),
+            Self::UserPass => write!(f, "USER/PASS"),
+        }
+    }
+}
+
+/// Logs in using a username and password, trying the mechanisms the server supports in order of preference until one of them is accepted.
+///
+/// A mechanism that is rejected by the server is followed by the next one, any other error, such as a lost connection, ends the attempt right away.
+/// By default APOP is preferred as it does not send the password, then SASL and finally USER and PASS.
+/// SASL PLAIN is skipped on a plain connection, as a rejected attempt would be followed by sending the password in the clear once more.
+/// # Examples
+/// ```rust,ignore
+/// use async_pop::auth::{AuthMechanism, AuthStrategy};
+///
+/// let strategy = AuthStrategy::new("user", "password");
+///
+/// let mechanism = client.authenticate(&strategy).await?;
+///
+/// println!("Logged in using {}", mechanism);
+///
+/// // Never fall back to sending the password in plain text.
+/// let strategy = AuthStrategy::new("user", "password").with_preference([AuthMechanism::Apop]);
+/// ```
+#[derive(Clone)]
+pub struct AuthStrategy {
+    username: String,
+    password: String,
+    preference: Vec<AuthMechanism>,
+}
+
+impl AuthStrategy {
+    pub fn new<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            preference: vec![
+                AuthMechanism::Apop,
+                #[cfg(feature = "sasl")]
+                AuthMechanism::CramMd5,
+                #[cfg(feature = "ntlm")]
+                AuthMechanism::Ntlm,
+                #[cfg(feature = "sasl")]
+                AuthMechanism::Plain,
+                AuthMechanism::UserPass,
+            ],
+        }
+    }
+
+    /// Only try the given mechanisms, in the given order.
+    pub fn with_preference<I: IntoIterator<Item = AuthMechanism>>(mut self, preference: I) -> Self {
+        self.preference = preference.into_iter().collect();
+
+        self
+    }
+
+    /// The mechanisms that are tried, in order of preference.
+    pub fn preference(&self) -> &[AuthMechanism] {
+        &self.preference
+    }
+
+    /// Returns the mechanism that was accepted, along with the response texts of the server, see [Client::login].
+    pub(crate) async fn authenticate<S: Read + Write + Unpin + Send>(
+        &self,
+        client: &mut Client<S>,
+    ) -> Result<(AuthMechanism, (Text, Text))> {
+        client.ensure_capabilities().await?;
+
+        self.try_supported(client).await
+    }
+
+    /// Same as [AuthStrategy::authenticate], but using the capabilities that are already known, without fetching them when that was deferred.
+    pub(crate) async fn try_supported<S: Read + Write + Unpin + Send>(
+        &self,
+        client: &mut Client<S>,
+    ) -> Result<(AuthMechanism, (Text, Text))> {
+        let mut rejected: Option<Error> = None;
+
+        for mechanism in &self.preference {
+            if !mechanism.is_supported(client) {
+                continue;
+            }
+
+            #[cfg(feature = "sasl")]
+            if *mechanism == AuthMechanism::Plain && !client.is_secure() {
+                debug!("Not using SASL PLAIN on a plain connection");
+
+                continue;
+            }
+
+            match self.try_mechanism(client, mechanism).await {
+                Ok(responses) => return Ok((*mechanism, responses)),
+                Err(err) if matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                    debug!("The server rejected {}: {}", mechanism, err);
+
+                    rejected = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        match rejected {
+            Some(err) => Err(err),
+            None => err!(
+                ErrorKind::FeatureUnsupported,
+                "The server does not support any of the preferred authentication mechanisms"
+            ),
+        }
+    }
+
+    async fn try_mechanism<S: Read + Write + Unpin + Send>(
+        &self,
+        client: &mut Client<S>,
+        mechanism: &AuthMechanism,
+    ) -> Result<(Text, Text)> {
+        let response = match mechanism {
+            AuthMechanism::Apop => client.apop_login(&self.username, &self.password).await?,
+            #[cfg(feature = "sasl")]
+            AuthMechanism::CramMd5 => {
+                client
+                    .auth(crate::sasl::CramMd5Authenticator::new(
+                        &self.username,
+                        &self.password,
+                    ))
+                    .await?
+            }
+            #[cfg(feature = "ntlm")]
+            AuthMechanism::Ntlm => {
+                client
+                    .auth(crate::sasl::NtlmAuthenticator::new(
+                        &self.username,
+                        &self.password,
+                    ))
+                    .await?
+            }
+            #[cfg(feature = "sasl")]
+            AuthMechanism::Plain => {
+                client
+                    .auth(crate::sasl::PlainAuthenticator::new(
+                        &self.username,
+                        &self.password,
+                    ))
+                    .await?
+            }
+            AuthMechanism::UserPass => {
+                return client.user_pass(&self.username, &self.password).await
+            }
+        };
+
+        // Only USER and PASS consist of two commands.
+        Ok((response, Bytes::new().into()))
+    }
+}
+
+/// Leaves out the password.
+impl fmt::Debug for AuthStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthStrategy")
+            .field("username", &self.username)
+            .field("preference", &self.preference)
+            .finish()
+    }
+}
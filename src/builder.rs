@@ -0,0 +1,560 @@
+use std::{sync::Arc, time::Duration};
+
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+use std::net::{IpAddr, SocketAddr};
+
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+use crate::{
+    constants::CONNECT_ATTEMPT_DELAY,
+    happy_eyeballs::{self, AddressFamily},
+    runtime::net::{lookup_host, SocketOptions, TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    auth::LoginPolicy,
+    error::Result,
+    interceptor::Interceptor,
+    login_delay::LoginDelayPolicy,
+    response::{
+        capability::{Capabilities, CapabilityCheck},
+        ParseMode,
+    },
+    runtime::{
+        io::{Read, Write},
+        with_timeout,
+    },
+    security::SecurityPolicy,
+    stream::{BufferGrowth, PopStream, DEFAULT_READ_BUFFER_SIZE},
+    timer::{default_timer, Timer},
+    Client, ClientState,
+};
+
+/// Configures and creates a [Client].
+///
+/// The [crate::connect], [crate::connect_plain] and [crate::new] functions are shorthands for using a builder with the default options.
+/// # Examples
+/// ```rust,ignore
+/// use std::time::Duration;
+///
+/// let tls = async_native_tls::TlsConnector::new();
+///
+/// let mut client = async_pop::ClientBuilder::new()
+///     .connect_timeout(Duration::from_secs(10))
+///     .greeting_timeout(Duration::from_secs(30))
+///     .connect(("pop.gmail.com", 995), "pop.gmail.com", &tls)
+///     .await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ClientBuilder {
+    connect_timeout: Option<Duration>,
+    greeting_timeout: Option<Duration>,
+    parse_mode: ParseMode,
+    max_response_size: Option<usize>,
+    raw_response_limit: usize,
+    read_buffer_size: Option<usize>,
+    buffer_growth: BufferGrowth,
+    read_rate_limit: Option<u64>,
+    write_rate_limit: Option<u64>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    login_delay_policy: LoginDelayPolicy,
+    login_policy: LoginPolicy,
+    security_policy: SecurityPolicy,
+    server_name: Option<String>,
+    lazy_capabilities: bool,
+    capability_check: CapabilityCheck,
+    timer: Option<Arc<dyn Timer>>,
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    connect_attempt_delay: Option<Duration>,
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    address_family: AddressFamily,
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    socket_options: SocketOptions,
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    connect_to: Vec<SocketAddr>,
+    #[cfg(feature = "socks5")]
+    proxy: Option<crate::socks::Socks5Proxy>,
+    #[cfg(feature = "tls")]
+    pinned_certificates: Vec<crate::tls::Fingerprint>,
+    #[cfg(feature = "tls")]
+    tls_options: crate::tls::TlsOptions,
+    #[cfg(feature = "tls")]
+    tls_domain: Option<String>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum amount of time that opening the connection may take, including the tls handshake.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+
+        self
+    }
+
+    /// How long to wait for a connection attempt before also trying the next address of the server, defaults to 250 milliseconds.
+    ///
+    /// When the server resolves to multiple addresses, they are tried one after another until one of them accepts the connection, see [happy_eyeballs].
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn connect_attempt_delay(mut self, delay: Duration) -> Self {
+        self.connect_attempt_delay = Some(delay);
+
+        self
+    }
+
+    /// Which address families to connect to and which one is tried first, see [AddressFamily].
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn address_family(mut self, family: AddressFamily) -> Self {
+        self.address_family = family;
+
+        self
+    }
+
+    /// Connect to the given address instead of the address passed to [ClientBuilder::connect] or [ClientBuilder::connect_plain], which is not resolved at all.
+    ///
+    /// The domain passed to [ClientBuilder::connect] is still used to verify the certificate and to track the login delay,
+    /// which allows reaching a specific server behind a load balancer or a server that has no DNS record for its domain.
+    /// Can be called multiple times, in which case the addresses are tried like the resolved addresses of a server.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn connect_to(mut self, addr: SocketAddr) -> Self {
+        self.connect_to.push(addr);
+
+        self
+    }
+
+    /// Bind the outgoing connection to the given local address, so the server sees it as the source address of the connection.
+    ///
+    /// Only the addresses of the server in the same address family are connected to. This does not apply to the connection to a proxy.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn local_address(mut self, address: IpAddr) -> Self {
+        self.socket_options.local_address = Some(address);
+
+        self
+    }
+
+    /// Bind the outgoing connection to the network interface with the given name, e.g. `eth1`.
+    ///
+    /// This usually requires the `CAP_NET_RAW` capability. This does not apply to the connection to a proxy.
+    #[cfg(all(
+        any(feature = "runtime-async-std", feature = "runtime-tokio"),
+        any(target_os = "android", target_os = "fuchsia", target_os = "linux")
+    ))]
+    pub fn interface<I: Into<String>>(mut self, interface: I) -> Self {
+        self.socket_options.interface = Some(interface.into());
+
+        self
+    }
+
+    /// Whether to set `TCP_NODELAY` on the connection, which sends small writes such as commands right away instead of batching them.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.socket_options.nodelay = Some(nodelay);
+
+        self
+    }
+
+    /// Enable tcp keepalive, sending the first probe once the connection has been idle for the given amount of time.
+    ///
+    /// This keeps long running sessions alive through NATs and firewalls that drop idle connections, and detects connections that died silently.
+    /// Unlike [Client::keep_alive], this does not send any commands to the server, so it does not stop the server from ending the session after its own idle timeout.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn tcp_keepalive(mut self, time: Duration) -> Self {
+        self.socket_options.keepalive_time = Some(time);
+
+        self
+    }
+
+    /// The amount of time between tcp keepalive probes when the server does not respond to them, which enables tcp keepalive.
+    ///
+    /// Ignored on platforms that do not allow changing it.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn tcp_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.socket_options.keepalive_interval = Some(interval);
+
+        self
+    }
+
+    /// The maximum amount of time to wait for the server to send its greeting once connected.
+    pub fn greeting_timeout(mut self, timeout: Duration) -> Self {
+        self.greeting_timeout = Some(timeout);
+
+        self
+    }
+
+    /// How strictly the responses from the server should be parsed, see [ParseMode].
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+
+        self
+    }
+
+    /// The maximum size of a single response that is kept in memory, defaults to 20 MiB.
+    ///
+    /// Responses that are larger, such as messages with big attachments, fail with [crate::error::ErrorKind::ResponseTooLarge].
+    /// Use `usize::MAX` to allow responses of any size, or see [Client::retr_with_limit] to raise the limit for a single message.
+    pub fn max_response_size(mut self, max_size: usize) -> Self {
+        self.max_response_size = Some(max_size);
+
+        self
+    }
+
+    /// Keep a copy of the first `max_size` bytes of the last response exactly as the server sent it, see [Client::last_raw_response].
+    ///
+    /// This is useful when a server sends something the parsers do not understand, as the copy can be attached to a bug report. Disabled by default.
+    pub fn retain_raw_response(mut self, max_size: usize) -> Self {
+        self.raw_response_limit = max_size;
+
+        self
+    }
+
+    /// The size the buffer that responses are read into starts out with, defaults to 2 KiB.
+    ///
+    /// Clients that download a lot of large messages can use a larger buffer, e.g. 64 KiB, to need fewer reads and resizes.
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buffer_size = Some(size);
+
+        self
+    }
+
+    /// How the read buffer grows when a response does not fit in it, see [BufferGrowth].
+    pub fn buffer_growth(mut self, growth: BufferGrowth) -> Self {
+        self.buffer_growth = growth;
+
+        self
+    }
+
+    /// Limit how many bytes per second are read from the server, e.g. for a background fetcher on a metered connection.
+    ///
+    /// Short bursts of up to a second worth of data are allowed.
+    pub fn read_rate_limit(mut self, bytes_per_second: u64) -> Self {
+        self.read_rate_limit = Some(bytes_per_second);
+
+        self
+    }
+
+    /// Limit how many bytes per second are written to the server.
+    pub fn write_rate_limit(mut self, bytes_per_second: u64) -> Self {
+        self.write_rate_limit = Some(bytes_per_second);
+
+        self
+    }
+
+    /// Registers a hook that sees every request before it is sent and every response once it has been received, including the greeting.
+    ///
+    /// See [Interceptor] for more info.
+    pub fn interceptor<I: Interceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+
+        self
+    }
+
+    /// Tunnel the connections opened by [ClientBuilder::connect] and [ClientBuilder::connect_plain] through a SOCKS5 proxy.
+    ///
+    /// The addresses of the server are still resolved locally and filtered by [ClientBuilder::connect_to] and [ClientBuilder::address_family],
+    /// but the proxy is asked to connect to them one after another instead of racing the attempts.
+    #[cfg(feature = "socks5")]
+    pub fn proxy(mut self, proxy: crate::socks::Socks5Proxy) -> Self {
+        self.proxy = Some(proxy);
+
+        self
+    }
+
+    /// Only accept the connection opened by [ClientBuilder::connect] if the server presents a certificate with the given SHA-256 fingerprint.
+    ///
+    /// Can be called multiple times to allow several certificates, e.g. while a certificate is being replaced.
+    /// The fingerprint is checked after the handshake, in addition to the verification done by the tls connector.
+    /// To trust a self-signed certificate or one issued by a private CA, disable certificate verification, e.g. using [ClientBuilder::danger_accept_invalid_certs], and pin the certificate instead.
+    #[cfg(feature = "tls")]
+    pub fn pin_certificate(mut self, fingerprint: crate::tls::Fingerprint) -> Self {
+        self.pinned_certificates.push(fingerprint);
+
+        self
+    }
+
+    /// The domain sent to the server during the tls handshake and that its certificate is verified against, instead of the domain passed to [ClientBuilder::connect].
+    ///
+    /// Useful when the name the server is known by differs from the name on its certificate, e.g. an internal alias. The domain passed to [ClientBuilder::connect] is still used to track the login delay.
+    #[cfg(feature = "tls")]
+    pub fn tls_domain<D: Into<String>>(mut self, domain: D) -> Self {
+        self.tls_domain = Some(domain.into());
+
+        self
+    }
+
+    /// **Dangerous:** accept any certificate the server presents, including expired and self-signed ones.
+    ///
+    /// This makes the connection vulnerable to anyone who can intercept it, so only use it for test setups. A warning is logged for every connection made this way.
+    /// Prefer [ClientBuilder::pin_certificate] to trust a specific self-signed certificate, which still works with this enabled.
+    ///
+    /// Only applies to [crate::tls::TlsConnector::System], connecting with any other connector fails with [crate::error::ErrorKind::InvalidArgument] when this is enabled.
+    #[cfg(feature = "async-native-tls")]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.tls_options.accept_invalid_certs = accept;
+
+        self
+    }
+
+    /// **Dangerous:** accept a valid certificate even when it was issued for a different domain than the one being connected to.
+    ///
+    /// Like [ClientBuilder::danger_accept_invalid_certs], this only applies to [crate::tls::TlsConnector::System] and logs a warning for every connection.
+    #[cfg(feature = "async-native-tls")]
+    pub fn danger_accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.tls_options.accept_invalid_hostnames = accept;
+
+        self
+    }
+
+    /// What to do when logging in to a server before its login delay has passed, see [LoginDelayPolicy].
+    pub fn login_delay_policy(mut self, policy: LoginDelayPolicy) -> Self {
+        self.login_delay_policy = policy;
+
+        self
+    }
+
+    /// Which mechanisms [Client::login] uses to log in, see [LoginPolicy].
+    ///
+    /// Defaults to [LoginPolicy::UserPass], which only uses the USER and PASS commands.
+    pub fn login_policy(mut self, policy: LoginPolicy) -> Self {
+        self.login_policy = policy;
+
+        self
+    }
+
+    /// When credentials may be sent over a plain connection, see [SecurityPolicy].
+    ///
+    /// Defaults to [SecurityPolicy::Opportunistic], which refuses to log in over a plain connection to a server that supports STLS.
+    pub fn security_policy(mut self, policy: SecurityPolicy) -> Self {
+        self.security_policy = policy;
+
+        self
+    }
+
+    /// The name used to keep track of the login delay of the server.
+    ///
+    /// Defaults to the domain when using [ClientBuilder::connect] and the address of the server when using [ClientBuilder::connect_plain].
+    /// When using [ClientBuilder::build] the login delay is only tracked if this is set.
+    pub fn server_name<N: Into<String>>(mut self, name: N) -> Self {
+        self.server_name = Some(name.into());
+
+        self
+    }
+
+    /// Do not fetch the capabilities when connecting and after logging in, but only once a command needs them.
+    ///
+    /// This saves a round trip on every connection, at the cost of [Client::login] never trying SASL PLAIN and the login delay of the server not being tracked.
+    /// [SecurityPolicy::Opportunistic] also can not tell whether the server supports STLS, unless the capabilities were fetched before logging in.
+    pub fn lazy_capabilities(mut self, lazy: bool) -> Self {
+        self.lazy_capabilities = lazy;
+
+        self
+    }
+
+    /// Whether commands such as TOP, UIDL and USER may be sent when the server did not advertise them, see [CapabilityCheck].
+    pub fn capability_check(mut self, check: CapabilityCheck) -> Self {
+        self.capability_check = check;
+
+        self
+    }
+
+    /// The timer used for timeouts, rate limits, keep alives and login delays, see [Timer].
+    ///
+    /// Defaults to the timer of the enabled runtime.
+    pub fn timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.timer = Some(timer);
+
+        self
+    }
+
+    /// Creates a new client from an existing stream.
+    pub async fn build<S: Read + Write + Unpin + Send>(&self, stream: S) -> Result<Client<S>> {
+        self.create_client(PopStream::new(stream), false, None)
+            .await
+    }
+
+    /// Create a new client with a tls connection.
+    ///
+    /// Accepts a reference to either an `async_native_tls::TlsConnector` or an `async_rustls::TlsConnector`, depending on the enabled features,
+    /// or [crate::tls::TlsConnector::System] to let the client create the connector.
+    #[cfg(all(
+        feature = "tls",
+        any(feature = "runtime-async-std", feature = "runtime-tokio")
+    ))]
+    pub async fn connect<
+        'a,
+        A: ToSocketAddrs,
+        D: AsRef<str>,
+        C: Into<crate::tls::TlsConnector<'a>>,
+    >(
+        &self,
+        addr: A,
+        domain: D,
+        tls: C,
+    ) -> Result<Client<impl crate::tls::TlsStream<TcpStream>>> {
+        let tls_connector: crate::tls::TlsConnector<'a> = tls.into();
+
+        let server = domain.as_ref().to_string();
+
+        let tls_stream = with_timeout(
+            &*self.timer_or_default(),
+            self.connect_timeout,
+            None,
+            "Timed out while connecting to the server",
+            async {
+                let (tcp_stream, _) = self.open_tcp(addr).await?;
+
+                let domain = match &self.tls_domain {
+                    Some(tls_domain) => tls_domain.clone(),
+                    None => domain.as_ref().to_string(),
+                };
+
+                let tls_stream = tls_connector
+                    .connect_with(domain, tcp_stream, &self.tls_options)
+                    .await?;
+
+                crate::tls::verify_pins(&tls_stream, &self.pinned_certificates)?;
+
+                Ok(tls_stream)
+            },
+        )
+        .await?;
+
+        self.create_client(PopStream::new(tls_stream), true, Some(server))
+            .await
+    }
+
+    /// Creates a new client using a plain connection.
+    ///
+    /// DO NOT USE in a production environment. Your password will be sent over a plain tcp stream which hackers could intercept.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub async fn connect_plain<A: ToSocketAddrs>(&self, addr: A) -> Result<Client<TcpStream>> {
+        let (tcp_stream, server) = with_timeout(
+            &*self.timer_or_default(),
+            self.connect_timeout,
+            None,
+            "Timed out while connecting to the server",
+            self.open_tcp(addr),
+        )
+        .await?;
+
+        self.create_client(PopStream::new(tcp_stream), false, Some(server.to_string()))
+            .await
+    }
+
+    /// Creates a new client that talks to the server through a message based tunnel, such as a WebSocket, see [crate::tunnel].
+    #[cfg(feature = "tunnel")]
+    pub async fn connect_tunnel<T>(
+        &self,
+        tunnel: crate::tunnel::Tunnel<T>,
+    ) -> Result<Client<crate::tunnel::Tunnel<T>>>
+    where
+        crate::tunnel::Tunnel<T>: Read + Write + Unpin + Send,
+    {
+        let secure = tunnel.is_secure();
+
+        self.create_client(PopStream::new(tunnel), secure, None)
+            .await
+    }
+
+    /// Opens a tcp connection, returning it along with the address of the server.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    async fn open_tcp<A: ToSocketAddrs>(&self, addr: A) -> Result<(TcpStream, SocketAddr)> {
+        let resolved = if self.connect_to.is_empty() {
+            lookup_host(addr).await?.collect()
+        } else {
+            self.connect_to.clone()
+        };
+
+        let mut addrs = happy_eyeballs::sort(resolved, self.address_family);
+
+        #[cfg(feature = "socks5")]
+        if let Some(proxy) = &self.proxy {
+            return proxy.connect_any(addrs).await;
+        }
+
+        if let Some(local_address) = self.socket_options.local_address {
+            addrs.retain(|addr| addr.is_ipv4() == local_address.is_ipv4());
+        }
+
+        happy_eyeballs::connect(
+            addrs,
+            self.connect_attempt_delay.unwrap_or(CONNECT_ATTEMPT_DELAY),
+            &self.socket_options,
+            &*self.timer_or_default(),
+        )
+        .await
+    }
+
+    async fn create_client<S: Read + Write + Unpin + Send>(
+        &self,
+        mut socket: PopStream<S>,
+        secure: bool,
+        server: Option<String>,
+    ) -> Result<Client<S>> {
+        let timer = self.timer_or_default();
+
+        socket.set_timer(timer.clone());
+        socket.set_parse_mode(self.parse_mode);
+
+        if let Some(max_size) = self.max_response_size {
+            socket.set_max_response_size(max_size);
+        }
+
+        socket.set_raw_response_limit(self.raw_response_limit);
+
+        socket.set_read_buffer(
+            self.read_buffer_size.unwrap_or(DEFAULT_READ_BUFFER_SIZE),
+            self.buffer_growth,
+        );
+
+        socket.set_rate_limits(self.read_rate_limit, self.write_rate_limit);
+
+        for interceptor in &self.interceptors {
+            socket.add_interceptor(interceptor.clone());
+        }
+
+        let mut client = Client {
+            marked_as_del: Vec::new(),
+            auth_pending: false,
+            message_count: None,
+            capabilities: Capabilities::new(),
+            capa_supported: true,
+            capabilities_fetched: false,
+            lazy_capabilities: self.lazy_capabilities,
+            capability_check: self.capability_check,
+            utf8: false,
+            uid_cache: None,
+            greeting: None,
+            read_greeting: false,
+            secure,
+            server: self.server_name.clone().or(server),
+            login_delay_policy: self.login_delay_policy,
+            login_policy: self.login_policy,
+            security_policy: self.security_policy,
+            inner: Some(socket),
+            state: ClientState::Authentication,
+            timer: timer.clone(),
+        };
+
+        let greeting = with_timeout(
+            &*timer,
+            self.greeting_timeout,
+            None,
+            "Timed out while waiting for the server to greet us",
+            client.read_greeting(),
+        )
+        .await?;
+
+        client.greeting = Some(greeting);
+
+        if !self.lazy_capabilities {
+            client.update_capabilities().await?;
+        }
+
+        Ok(client)
+    }
+
+    fn timer_or_default(&self) -> Arc<dyn Timer> {
+        self.timer.clone().unwrap_or_else(default_timer)
+    }
+}
@@ -0,0 +1,94 @@
+//! Adapts tokio io types to the `futures::io` traits the client is built on.
+//!
+//! [crate::connect] and [crate::connect_plain] already wrap their tcp stream, wrap a stream in a [Compat] before passing it to [crate::new] when it only implements the tokio traits.
+
+use std::{
+    io::SeekFrom,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::ready;
+
+/// A tokio io type that implements the `futures::io` traits.
+#[derive(Debug)]
+pub struct Compat<T> {
+    inner: T,
+    /// Whether a seek was started, as tokio splits up seeking into starting and completing it.
+    seeking: bool,
+}
+
+impl<T> Compat<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            seeking: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: tokio::io::AsyncRead + Unpin> futures::io::AsyncRead for Compat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+
+        ready!(Pin::new(&mut self.get_mut().inner).poll_read(cx, &mut buf))?;
+
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+impl<T: tokio::io::AsyncWrite + Unpin> futures::io::AsyncWrite for Compat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<T: tokio::io::AsyncSeek + Unpin> futures::io::AsyncSeek for Compat<T> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+
+        if !this.seeking {
+            Pin::new(&mut this.inner).start_seek(pos)?;
+
+            this.seeking = true;
+        }
+
+        let position = ready!(Pin::new(&mut this.inner).poll_complete(cx));
+
+        this.seeking = false;
+
+        Poll::Ready(position)
+    }
+}
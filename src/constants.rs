@@ -1,9 +1,18 @@
-// \n
-pub const LF: u8 = 0x0a;
-// \r
-pub const CR: u8 = 0x0d;
+pub use pop3_proto::constants::{CR, END_OF_LINE, LF, MULTILINE_END, OK};
 
-pub const OK: &str = "+OK";
-pub const ERR: &str = "-ERR";
+/// The maximum amount of commands that are pipelined at once by the bulk helpers, so the server never blocks on writing responses while we are still writing commands.
+pub const PIPELINE_BATCH_SIZE: usize = 32;
 
-pub const END_OF_LINE: [u8; 2] = [CR, LF];
+/// The largest amount of lines that can be requested using TOP, as many servers parse the argument as a signed 32 bit number.
+pub const MAX_TOP_LINES: usize = i32::MAX as usize;
+
+/// How long a connection attempt may take before the next address of the server is tried as well, as recommended by [RFC 8305](https://www.rfc-editor.org/rfc/rfc8305#section-5).
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+pub const CONNECT_ATTEMPT_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// The maximum amount of bytes of a single request or response that is written to the logs.
+pub const MAX_LOGGED_BYTES: usize = 1024;
+
+/// The maximum length of a command line including its line ending, as defined in [RFC 2449](https://www.rfc-editor.org/rfc/rfc2449#section-4).
+#[cfg(feature = "server")]
+pub const MAX_COMMAND_LENGTH: usize = 255;
@@ -1,12 +1,16 @@
 use std::{
     error,
     fmt::{self},
+    io::Error as IoError,
     num::ParseIntError,
     result,
     str::Utf8Error,
 };
 
-use crate::runtime::io::Error as IoError;
+use crate::{
+    command::Command,
+    response::{code::ResponseCode, types::message::Text, ErrorResponse},
+};
 
 macro_rules! err {
     ($kind:expr, $($arg:tt)*) => {{
@@ -18,7 +22,11 @@ macro_rules! err {
     }};
 }
 
+/// What went wrong, along with the details that are needed to handle the error.
+///
+/// New kinds can be added without a major release, so a match on this has to include a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ErrorKind {
     #[cfg(feature = "async-native-tls")]
     Tls(async_native_tls::Error),
@@ -27,13 +35,33 @@ pub enum ErrorKind {
     Io(IoError),
     ParseInt(ParseIntError),
     ParseString(Utf8Error),
-    ServerError(String),
+    /// The server sent a negative response.
+    ServerError {
+        /// The extended response code, if the server sent one.
+        code: Option<ResponseCode>,
+        /// The text of the response as it was received, excluding the response code.
+        text: Text,
+        /// The command the server responded to, if it is known.
+        command: Option<Command>,
+    },
     #[cfg(feature = "sasl")]
     DecodeBase64(base64::DecodeError),
+    /// The server rejected the OAuth2 token, see [crate::sasl::OAuthError].
+    #[cfg(feature = "sasl")]
+    OAuth(crate::sasl::OAuthError),
     NotConnected,
     ShouldNotBeConnected,
     IncorrectStateForCommand,
     MessageIsDeleted,
+    /// An argument of a request is not valid, so the request was not sent.
+    InvalidArgument,
+    /// An [crate::interceptor::Interceptor] refused to send the request.
+    Rejected,
+    /// No message in the maildrop has the given unique id.
+    UnknownUid,
+    /// The message could not be parsed as an email.
+    #[cfg(feature = "mail-parser")]
+    ParseMessage,
     FeatureUnsupported,
     ServerFailedToGreet,
     InvalidResponse,
@@ -42,12 +70,34 @@ pub enum ErrorKind {
     ParseCommand,
     UnexpectedResponse,
     ConnectionClosed,
+    /// An earlier error left the connection in an unknown state, so nothing is sent over it anymore, see [crate::Client::is_usable].
+    Poisoned,
+    /// The operation did not complete in time, contains the command that was being waited on, if any.
+    Timeout {
+        command: Option<Command>,
+    },
+    /// Logging in was refused to respect the login delay of the server, contains the remaining time until logging in is allowed.
+    LoginDelay(std::time::Duration),
+    #[cfg(feature = "socks5")]
+    Proxy,
+    /// Credentials were not sent because the connection is not secure, see [crate::security::SecurityPolicy].
+    Insecure,
+    /// The server advertised STLS but refused to upgrade the connection, which may mean an attacker is trying to keep it in plain text.
+    #[cfg(feature = "tls")]
+    Downgrade,
+    /// The certificate of the server does not match any of the pinned fingerprints, see [crate::ClientBuilder::pin_certificate].
+    #[cfg(feature = "tls")]
+    CertificateMismatch,
+    /// A recorded session could not be read, see [crate::transcript::Transcript::parse].
+    #[cfg(feature = "test-util")]
+    InvalidTranscript,
 }
 
 #[derive(Debug)]
 pub struct Error {
     message: String,
-    kind: ErrorKind,
+    // Boxed to keep results small, as the kinds carry the details of the error.
+    kind: Box<ErrorKind>,
 }
 
 impl Error {
@@ -57,7 +107,7 @@ impl Error {
     {
         Self {
             message: message.into(),
-            kind: error_kind,
+            kind: Box::new(error_kind),
         }
     }
 
@@ -68,6 +118,63 @@ impl Error {
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
+
+    /// The extended response code the server sent along with its negative response, if any.
+    pub fn response_code(&self) -> Option<&ResponseCode> {
+        match &*self.kind {
+            ErrorKind::ServerError {
+                code: Some(code), ..
+            } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The command that the server responded to negatively, if this error was caused by a negative response.
+    pub fn command(&self) -> Option<&Command> {
+        match &*self.kind {
+            ErrorKind::ServerError { command, .. } => command.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Remember which command the negative response this error was created from belongs to.
+    pub(crate) fn for_command(mut self, for_command: Command) -> Self {
+        if let ErrorKind::ServerError { command, .. } = &mut *self.kind {
+            *command = Some(for_command);
+        }
+
+        self
+    }
+
+    /// Whether the operation could succeed when it is tried again later, e.g. because the connection dropped or the server reported a temporary problem.
+    pub fn is_transient(&self) -> bool {
+        match *self.kind {
+            ErrorKind::Io(_)
+            | ErrorKind::ConnectionClosed
+            | ErrorKind::Poisoned
+            | ErrorKind::Timeout { .. }
+            | ErrorKind::LoginDelay(_) => true,
+            _ => matches!(
+                self.response_code(),
+                Some(ResponseCode::SysTemp | ResponseCode::InUse | ResponseCode::LoginDelay)
+            ),
+        }
+    }
+
+    /// Whether the server rejected the credentials that were used to log in.
+    pub fn is_auth_failure(&self) -> bool {
+        #[cfg(feature = "sasl")]
+        if let ErrorKind::OAuth(_) = *self.kind {
+            return true;
+        }
+
+        matches!(self.response_code(), Some(ResponseCode::Auth))
+    }
+
+    /// Whether the maildrop is locked because another session is using it.
+    pub fn is_in_use(&self) -> bool {
+        matches!(self.response_code(), Some(ResponseCode::InUse))
+    }
 }
 
 impl error::Error for Error {
@@ -76,21 +183,87 @@ impl error::Error for Error {
     }
 
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        match self.kind() {
+        match &*self.kind {
+            #[cfg(feature = "async-native-tls")]
+            ErrorKind::Tls(err) => Some(err),
+            ErrorKind::Io(err) => Some(err),
+            ErrorKind::ParseInt(err) => Some(err),
+            ErrorKind::ParseString(err) => Some(err),
+            #[cfg(feature = "sasl")]
+            ErrorKind::DecodeBase64(err) => Some(err),
             _ => None,
         }
     }
 }
 
-impl Into<String> for Error {
-    fn into(self) -> String {
-        self.message
+// Errors are sent between tasks and wrapped by other error types, so this must keep holding for every kind.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    assert_send_sync::<Error>();
+};
+
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.message
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// A short description of the kind, the details are in the message of the [Error] and its [error::Error::source].
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "async-native-tls")]
+            ErrorKind::Tls(_) => f.write_str("tls error"),
+            #[cfg(feature = "async-rustls")]
+            ErrorKind::InvalidDnsName => f.write_str("invalid dns name"),
+            ErrorKind::Io(_) => f.write_str("io error"),
+            ErrorKind::ParseInt(_) => f.write_str("invalid number"),
+            ErrorKind::ParseString(_) => f.write_str("invalid utf-8"),
+            ErrorKind::ServerError { .. } => f.write_str("server error"),
+            #[cfg(feature = "sasl")]
+            ErrorKind::DecodeBase64(_) => f.write_str("invalid base64"),
+            #[cfg(feature = "sasl")]
+            ErrorKind::OAuth(_) => f.write_str("oauth error"),
+            ErrorKind::NotConnected => f.write_str("not connected"),
+            ErrorKind::ShouldNotBeConnected => f.write_str("should not be connected"),
+            ErrorKind::IncorrectStateForCommand => f.write_str("incorrect state for command"),
+            ErrorKind::MessageIsDeleted => f.write_str("message is deleted"),
+            ErrorKind::InvalidArgument => f.write_str("invalid argument"),
+            ErrorKind::Rejected => f.write_str("rejected by interceptor"),
+            ErrorKind::UnknownUid => f.write_str("unknown uid"),
+            #[cfg(feature = "mail-parser")]
+            ErrorKind::ParseMessage => f.write_str("invalid message"),
+            ErrorKind::FeatureUnsupported => f.write_str("unsupported feature"),
+            ErrorKind::ServerFailedToGreet => f.write_str("server failed to greet"),
+            ErrorKind::InvalidResponse => f.write_str("invalid response"),
+            ErrorKind::ResponseTooLarge => f.write_str("response too large"),
+            ErrorKind::MissingRequest => f.write_str("missing request"),
+            ErrorKind::ParseCommand => f.write_str("invalid command"),
+            ErrorKind::UnexpectedResponse => f.write_str("unexpected response"),
+            ErrorKind::ConnectionClosed => f.write_str("connection closed"),
+            ErrorKind::Poisoned => f.write_str("connection poisoned"),
+            ErrorKind::Timeout {
+                command: Some(command),
+            } => write!(f, "timeout during {}", command),
+            ErrorKind::Timeout { command: None } => f.write_str("timeout"),
+            ErrorKind::LoginDelay(_) => f.write_str("login delay"),
+            #[cfg(feature = "socks5")]
+            ErrorKind::Proxy => f.write_str("proxy error"),
+            ErrorKind::Insecure => f.write_str("insecure connection"),
+            #[cfg(feature = "tls")]
+            ErrorKind::Downgrade => f.write_str("tls downgrade"),
+            #[cfg(feature = "tls")]
+            ErrorKind::CertificateMismatch => f.write_str("certificate mismatch"),
+            #[cfg(feature = "test-util")]
+            ErrorKind::InvalidTranscript => f.write_str("invalid transcript"),
+        }
     }
 }
 
@@ -104,6 +277,41 @@ impl From<async_native_tls::Error> for Error {
     }
 }
 
+impl From<ErrorResponse> for Error {
+    fn from(response: ErrorResponse) -> Self {
+        Self {
+            message: response.to_string(),
+            kind: Box::new(ErrorKind::ServerError {
+                code: response.code().cloned(),
+                text: response.message().clone(),
+                command: None,
+            }),
+        }
+    }
+}
+
+/// Keeps the message, the kinds of the protocol layer all have a counterpart here.
+impl From<pop3_proto::error::Error> for Error {
+    fn from(error: pop3_proto::error::Error) -> Self {
+        use pop3_proto::error::ErrorKind as ProtoErrorKind;
+
+        let (kind, message) = error.into_parts();
+
+        let kind = match kind {
+            ProtoErrorKind::ParseInt(err) => ErrorKind::ParseInt(err),
+            ProtoErrorKind::ParseString(err) => ErrorKind::ParseString(err),
+            #[cfg(feature = "sasl")]
+            ProtoErrorKind::DecodeBase64(err) => ErrorKind::DecodeBase64(err),
+            ProtoErrorKind::InvalidArgument => ErrorKind::InvalidArgument,
+            ProtoErrorKind::ParseCommand => ErrorKind::ParseCommand,
+            // Including kinds added by newer versions of the protocol layer.
+            _ => ErrorKind::InvalidResponse,
+        };
+
+        Self::new(kind, message)
+    }
+}
+
 impl From<IoError> for Error {
     fn from(io_error: IoError) -> Self {
         Self::new(ErrorKind::Io(io_error), "Error with connection to server")
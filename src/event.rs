@@ -0,0 +1,18 @@
+/// Something noteworthy that happened on the connection, but did not cause a command to fail.
+///
+/// Events are collected by the client and can be retrieved using [crate::Client::take_events].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Event {
+    /// The server sent blank lines inside of a multi-line response, which were skipped because the client is in lenient mode.
+    SkippedBlankLines {
+        /// The command that the response belonged to.
+        command: String,
+        /// The amount of lines that were skipped.
+        count: usize,
+    },
+    /// Responses to commands that were cancelled before their response was read completely were thrown away, before sending the next command.
+    DiscardedResponses {
+        /// The amount of responses that were thrown away.
+        count: usize,
+    },
+}
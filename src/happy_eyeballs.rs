@@ -0,0 +1,163 @@
+//! Connecting to a server that resolves to multiple addresses, as described in [RFC 8305](https://www.rfc-editor.org/rfc/rfc8305).
+//!
+//! Instead of giving up once the first address turns out to be unreachable, the addresses are tried one after another.
+//! A new attempt is started as soon as the previous one failed, or once the attempt delay passed without it succeeding, while the earlier attempts keep running.
+//! The first connection that is established is used and the other attempts are cancelled.
+use std::{net::SocketAddr, time::Duration};
+
+use futures::{
+    future::{select, Either},
+    stream::FuturesUnordered,
+    StreamExt,
+};
+
+use crate::{
+    error::Result,
+    macros::debug,
+    runtime::net::{self, SocketOptions, TcpStream},
+    timer::Timer,
+};
+
+/// Which address families to connect to and which one is tried first, see [crate::ClientBuilder::address_family].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressFamily {
+    /// Start with an IPv6 address and alternate between both families, as recommended by the RFC.
+    #[default]
+    PreferIpv6,
+    /// Start with an IPv4 address and alternate between both families.
+    PreferIpv4,
+    /// Only connect to IPv6 addresses.
+    Ipv6Only,
+    /// Only connect to IPv4 addresses.
+    Ipv4Only,
+}
+
+/// Orders the resolved addresses according to the address family, keeping the order of the resolver within a family.
+pub(crate) fn sort(addrs: Vec<SocketAddr>, family: AddressFamily) -> Vec<SocketAddr> {
+    let (ipv6, ipv4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+
+    let (first, second) = match family {
+        AddressFamily::PreferIpv6 => (ipv6, ipv4),
+        AddressFamily::PreferIpv4 => (ipv4, ipv6),
+        AddressFamily::Ipv6Only => return ipv6,
+        AddressFamily::Ipv4Only => return ipv4,
+    };
+
+    let mut sorted = Vec::with_capacity(first.len() + second.len());
+
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => sorted.extend(a.into_iter().chain(b)),
+        }
+    }
+
+    sorted
+}
+
+/// Connects to the first of the given addresses that accepts a connection, see the [module docs](self).
+pub(crate) async fn connect(
+    addrs: Vec<SocketAddr>,
+    attempt_delay: Duration,
+    options: &SocketOptions,
+    timer: &dyn Timer,
+) -> Result<(TcpStream, SocketAddr)> {
+    let attempt = |addr: SocketAddr| async move { (addr, net::connect_with(addr, options).await) };
+
+    let mut pending = addrs.into_iter();
+
+    let mut attempts = FuturesUnordered::new();
+
+    let mut last_error = None;
+
+    loop {
+        if attempts.is_empty() {
+            match pending.next() {
+                Some(addr) => attempts.push(attempt(addr)),
+                None => break,
+            }
+        }
+
+        // Once every address is being tried there is nothing left to start, so only wait for the attempts.
+        let next = if pending.as_slice().is_empty() {
+            Either::Left(attempts.next().await)
+        } else {
+            match select(attempts.next(), timer.sleep(attempt_delay)).await {
+                Either::Left((result, _)) => Either::Left(result),
+                Either::Right(_) => Either::Right(()),
+            }
+        };
+
+        match next {
+            Either::Left(Some((addr, Ok(stream)))) => return Ok((stream, addr)),
+            Either::Left(Some((addr, Err(err)))) => {
+                debug!("Failed to connect to {}: {}", addr, err);
+
+                last_error = Some(err);
+
+                if let Some(addr) = pending.next() {
+                    attempts.push(attempt(addr));
+                }
+            }
+            Either::Left(None) => {}
+            Either::Right(()) => {
+                if let Some(addr) = pending.next() {
+                    debug!(
+                        "Still connecting after {:?}, also trying {}",
+                        attempt_delay, addr
+                    );
+
+                    attempts.push(attempt(addr));
+                }
+            }
+        }
+    }
+
+    let err = last_error.unwrap_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::AddrNotAvailable,
+            "The address of the server did not resolve to any usable address",
+        )
+    });
+
+    Err(err.into())
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+
+    use super::{sort, AddressFamily};
+
+    #[test]
+    fn test_sort() {
+        let addrs: Vec<SocketAddr> = ["[::1]:110", "[::2]:110", "127.0.0.1:110", "[::3]:110"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+
+        let order = |family| -> Vec<String> {
+            sort(addrs.clone(), family)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+
+        assert_eq!(
+            order(AddressFamily::PreferIpv6),
+            ["[::1]:110", "127.0.0.1:110", "[::2]:110", "[::3]:110"]
+        );
+        assert_eq!(
+            order(AddressFamily::PreferIpv4),
+            ["127.0.0.1:110", "[::1]:110", "[::2]:110", "[::3]:110"]
+        );
+        assert_eq!(order(AddressFamily::Ipv4Only), ["127.0.0.1:110"]);
+        assert_eq!(
+            order(AddressFamily::Ipv6Only),
+            ["[::1]:110", "[::2]:110", "[::3]:110"]
+        );
+    }
+}
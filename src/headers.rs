@@ -0,0 +1,388 @@
+//! A minimal parser for the header block of a message, so that fields such as the subject can be read from the output of TOP without pulling in a full MIME parser.
+//!
+//! The values are returned as they are sent, encoded words ([RFC 2047](https://www.rfc-editor.org/rfc/rfc2047)) are only decoded by [Headers::get_decoded].
+use bytes::Bytes;
+
+use crate::response::types::{message::Text, DataType};
+
+/// The header fields of a message, in the order they appear in the message.
+///
+/// A field name can occur more than once, e.g. `Received`, and names are compared case insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers {
+    fields: Vec<(String, Text)>,
+}
+
+impl Headers {
+    /// Parses the header block at the start of a message, up until the first blank line.
+    ///
+    /// Folded fields are unfolded ([RFC 5322](https://www.rfc-editor.org/rfc/rfc5322#section-2.2.3)) and lines that are not a valid field are skipped.
+    pub fn parse(message: &[u8]) -> Self {
+        let mut fields: Vec<(String, Vec<u8>)> = Vec::new();
+
+        for line in message.split(|byte| *byte == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            if line.is_empty() {
+                break;
+            }
+
+            if line[0] == b' ' || line[0] == b'\t' {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.extend_from_slice(line);
+                }
+
+                continue;
+            }
+
+            let colon = match line.iter().position(|byte| *byte == b':') {
+                Some(colon) => colon,
+                None => continue,
+            };
+
+            let name = &line[..colon];
+
+            if name.is_empty() || !name.iter().all(|byte| byte.is_ascii_graphic()) {
+                continue;
+            }
+
+            fields.push((
+                String::from_utf8_lossy(name).into_owned(),
+                line[colon + 1..].to_vec(),
+            ));
+        }
+
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (name, Bytes::copy_from_slice(trim(&value)).into()))
+            .collect();
+
+        Self { fields }
+    }
+
+    /// The value of the first field with the given name.
+    pub fn get<N: AsRef<str>>(&self, name: N) -> Option<&Text> {
+        self.get_all(name).next()
+    }
+
+    /// The value of the first field with the given name with its encoded words decoded, see [decode_words].
+    pub fn get_decoded<N: AsRef<str>>(&self, name: N) -> Option<String> {
+        self.get(name).map(|value| decode_words(value.raw()))
+    }
+
+    /// The values of every field with the given name, in the order they appear in the message.
+    pub fn get_all<N: AsRef<str>>(&self, name: N) -> impl Iterator<Item = &Text> {
+        self.fields
+            .iter()
+            .filter(move |(field, _)| field.eq_ignore_ascii_case(name.as_ref()))
+            .map(|(_, value)| value)
+    }
+
+    /// Every field as a name and value pair, in the order they appear in the message.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Text)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// The fields a list of messages is usually rendered with, see [crate::Client::preview_headers].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderPreview {
+    from: Option<String>,
+    subject: Option<String>,
+    date: Option<String>,
+    size: u64,
+}
+
+impl HeaderPreview {
+    /// Takes the fields from the given headers, decoding their encoded words.
+    pub fn new(headers: &Headers, size: u64) -> Self {
+        Self {
+            from: headers.get_decoded("From"),
+            subject: headers.get_decoded("Subject"),
+            date: headers.get_decoded("Date"),
+            size,
+        }
+    }
+
+    /// The sender, as it appears in the From field, e.g. `Alice <alice@example.com>`.
+    pub fn from(&self) -> Option<&str> {
+        self.from.as_deref()
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// The Date field as it was sent, it is not parsed.
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+
+    /// The size of the whole message in octets, according to LIST.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Decodes the encoded words ([RFC 2047](https://www.rfc-editor.org/rfc/rfc2047)) in a header value.
+///
+/// Decoding is lenient: words that are not valid are kept as they are and bytes that are not valid in their character set are replaced.
+/// Only UTF-8, US-ASCII and ISO-8859-1 are supported, other character sets are decoded as UTF-8.
+pub fn decode_words(value: &[u8]) -> String {
+    let mut decoded = String::new();
+    let mut rest = value;
+    // Whitespace between two adjacent encoded words is not part of the value.
+    let mut after_word = false;
+
+    while let Some(start) = find(rest, b"=?") {
+        let before = &rest[..start];
+
+        match encoded_word(&rest[start..]) {
+            Some((word, len)) => {
+                if !after_word || !before.iter().all(u8::is_ascii_whitespace) {
+                    decoded.push_str(&String::from_utf8_lossy(before));
+                }
+
+                decoded.push_str(&word);
+
+                rest = &rest[start + len..];
+                after_word = true;
+            }
+            None => {
+                decoded.push_str(&String::from_utf8_lossy(&rest[..start + 2]));
+
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+
+    decoded.push_str(&String::from_utf8_lossy(rest));
+
+    decoded
+}
+
+/// Decodes the encoded word at the start of the input, returning it along with its length.
+fn encoded_word(input: &[u8]) -> Option<(String, usize)> {
+    let inner = input.strip_prefix(b"=?")?;
+
+    let charset_end = inner.iter().position(|byte| *byte == b'?')?;
+    let charset = &inner[..charset_end];
+
+    let inner = &inner[charset_end + 1..];
+
+    if inner.len() < 2 || inner[1] != b'?' {
+        return None;
+    }
+
+    let text_end = find(&inner[2..], b"?=")?;
+    let text = &inner[2..2 + text_end];
+
+    if text.iter().any(u8::is_ascii_whitespace) {
+        return None;
+    }
+
+    let bytes = match inner[0].to_ascii_uppercase() {
+        b'B' => decode_base64(text)?,
+        b'Q' => decode_q(text),
+        _ => return None,
+    };
+
+    // A language can be appended to the character set ([RFC 2231](https://www.rfc-editor.org/rfc/rfc2231#section-5)).
+    let charset = charset
+        .split(|byte| *byte == b'*')
+        .next()
+        .unwrap_or(charset);
+
+    let word =
+        if charset.eq_ignore_ascii_case(b"iso-8859-1") || charset.eq_ignore_ascii_case(b"latin1") {
+            bytes.iter().map(|byte| char::from(*byte)).collect()
+        } else {
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+
+    Some((word, 2 + charset_end + 1 + 2 + text_end + 2))
+}
+
+fn decode_q(text: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut index = 0;
+
+    while index < text.len() {
+        let hex = text
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (text[index], hex) {
+            (b'=', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+            (b'_', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+
+        index += 1;
+    }
+
+    decoded
+}
+
+/// Decodes standard base64, ignoring missing padding.
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in text.iter().take_while(|byte| **byte != b'=') {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The length of the part of a message that TOP would return for the given amount of lines: the header block, the blank line after it and the first lines of the body.
+///
+/// Returns [None] if the message ends before that, in which case all of it is part of the preview.
+pub(crate) fn preview_len(message: &[u8], lines: usize) -> Option<usize> {
+    // The amount of lines that are left once the end of the header block has been found.
+    let mut remaining: Option<usize> = None;
+
+    let mut start = 0;
+
+    for (index, _) in message
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+    {
+        let line = &message[start..index];
+
+        start = index + 1;
+
+        remaining = match remaining {
+            Some(remaining) => Some(remaining - 1),
+            None if line.strip_suffix(b"\r").unwrap_or(line).is_empty() => Some(lines),
+            None => None,
+        };
+
+        if remaining == Some(0) {
+            return Some(start);
+        }
+    }
+
+    None
+}
+
+fn trim(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|byte| !byte.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+
+    &value[start..end]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let message = b"Received: from a\r\nReceived: from b\r\n\tby c\r\nSubject:  Hello\r\n there \r\nnot a header\r\n\r\nX-Body: not a header\r\n";
+
+        let headers = Headers::parse(message);
+
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.get("subject").unwrap().to_string(), "Hello there");
+
+        let received: Vec<_> = headers
+            .get_all("Received")
+            .map(|value| value.to_string())
+            .collect();
+
+        assert_eq!(received, vec!["from a", "from b\tby c"]);
+        assert!(headers.get("X-Body").is_none());
+    }
+
+    #[test]
+    fn test_decode_words() {
+        assert_eq!(
+            decode_words(b"=?UTF-8?B?SGVsbG8gd8O2cmxk?="),
+            "Hello w\u{f6}rld"
+        );
+        assert_eq!(
+            decode_words(b"=?iso-8859-1?q?caf=E9_au_lait?= is ready"),
+            "caf\u{e9} au lait is ready"
+        );
+        // Whitespace between encoded words is dropped, but not between an encoded word and plain text.
+        assert_eq!(
+            decode_words(b"Re: =?utf-8?Q?a?= =?utf-8?Q?b?= c"),
+            "Re: ab c"
+        );
+        assert_eq!(
+            decode_words(b"=?utf-8?B?QWxpY2U?= <alice@example.com>"),
+            "Alice <alice@example.com>"
+        );
+        assert_eq!(
+            decode_words(b"=?utf-8?X?abc?= =?broken"),
+            "=?utf-8?X?abc?= =?broken"
+        );
+        assert_eq!(decode_words(b"=?utf-8?B?!!!?="), "=?utf-8?B?!!!?=");
+
+        let headers = Headers::parse(b"Subject: =?utf-8?q?Caf=C3=A9?=\r\n\r\n");
+
+        assert_eq!(headers.get_decoded("subject").unwrap(), "Caf\u{e9}");
+        assert_eq!(
+            HeaderPreview::new(&headers, 120).subject(),
+            Some("Caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn test_preview_len() {
+        let message = b"Subject: Hi\r\n\r\nfirst\r\nsecond\r\n";
+
+        assert_eq!(preview_len(message, 0), Some(15));
+        assert_eq!(preview_len(message, 1), Some(22));
+        assert_eq!(preview_len(message, 2), Some(message.len()));
+        assert_eq!(preview_len(message, 3), None);
+        assert_eq!(preview_len(b"Subject: Hi\r\n", 0), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! Checking whether a connection is still alive, see [crate::Client::ping].
+//!
+//! Pools and long-lived sessions can use the outcome to decide whether to keep using a connection, keep an eye on it or replace it.
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// How the server responded to [crate::Client::ping].
+#[derive(Debug)]
+pub enum Health {
+    /// The server answered within half of the timeout, after the given amount of time.
+    Healthy(Duration),
+    /// The server answered, but took more than half of the timeout to do so.
+    Slow(Duration),
+    /// The server did not answer in time or the connection failed, the connection should no longer be used.
+    Dead(Error),
+}
+
+impl Health {
+    /// Whether the server answered in time, even if it was slow.
+    pub fn is_alive(&self) -> bool {
+        !matches!(self, Self::Dead(_))
+    }
+
+    /// How long it took for the server to answer, if it did.
+    pub fn latency(&self) -> Option<Duration> {
+        match self {
+            Self::Healthy(latency) | Self::Slow(latency) => Some(*latency),
+            Self::Dead(_) => None,
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Hooks that see every request before it is sent and every response once it has been received.
+//!
+//! Interceptors are registered using [crate::ClientBuilder::interceptor] or [crate::Client::add_interceptor] and are called in the order they were added.
+//! They can be used for audit logging, collecting metrics or enforcing a policy on which commands may be sent.
+//! # Examples
+//! ```rust,ignore
+//! use async_pop::{error::{Error, ErrorKind, Result}, interceptor::Interceptor, request::Request, Command};
+//!
+//! struct ReadOnly;
+//!
+//! impl Interceptor for ReadOnly {
+//!     fn on_request(&self, request: &Request) -> Result<()> {
+//!         match request.command() {
+//!             Command::Dele => Err(Error::new(ErrorKind::Rejected, "This client may not delete messages")),
+//!             _ => Ok(()),
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::{command::Command, error::Result, request::Request, response::Response};
+
+pub trait Interceptor: Send + Sync {
+    /// Called right before a request is written to the server.
+    ///
+    /// Returning an error stops the request from being sent, the error is then returned to the caller.
+    /// The credentials in a request are only hidden from [Request::redacted], so be careful when logging requests in full.
+    fn on_request(&self, request: &Request) -> Result<()> {
+        let _ = request;
+
+        Ok(())
+    }
+
+    /// Called once the (status line of the) response to a command has been received, including negative responses.
+    fn on_response(&self, command: &Command, response: &Response) {
+        let _ = (command, response);
+    }
+}
+
+impl std::fmt::Debug for dyn Interceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Interceptor")
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use crate::macros::{debug, trace};
+use futures::lock::{Mutex, MutexGuard};
+
+use crate::{
+    runtime::{
+        io::{Read, Write},
+        spawn,
+    },
+    timer::Timer,
+    Client, ClientState,
+};
+
+/// A handle to a [Client] that is kept alive by a background task, created using [Client::keep_alive].
+///
+/// Whenever the connection has been idle for longer than the configured threshold, the task sends a NOOP command, preventing the server from closing the session.
+/// The task stops once this handle is dropped or the session is closed.
+/// # Examples
+/// ```rust,ignore
+/// let client = client.keep_alive(Duration::from_secs(60));
+///
+/// for msg_number in 1..=count {
+///     let message = client.lock().await.retr(msg_number).await?;
+///
+///     // Slowly process the message, the connection will stay alive in the meantime.
+///     process(message).await;
+/// }
+/// ```
+pub struct KeepAlive<S: Read + Write + Unpin + Send> {
+    client: Arc<Mutex<Client<S>>>,
+}
+
+impl<S: Read + Write + Unpin + Send + 'static> KeepAlive<S> {
+    pub(crate) fn new(client: Client<S>, idle: Duration) -> Self {
+        let timer = client.timer.clone();
+
+        let client = Arc::new(Mutex::new(client));
+
+        spawn(Self::run(Arc::downgrade(&client), timer, idle));
+
+        Self { client }
+    }
+
+    async fn run(client: Weak<Mutex<Client<S>>>, timer: Arc<dyn Timer>, idle: Duration) {
+        let mut wait = idle;
+
+        loop {
+            timer.sleep(wait).await;
+
+            let client = match client.upgrade() {
+                Some(client) => client,
+                None => break,
+            };
+
+            let mut client = client.lock().await;
+
+            let idle_for = match client.idle_time() {
+                Some(idle_for) => idle_for,
+                None => break,
+            };
+
+            if idle_for < idle {
+                wait = idle - idle_for;
+
+                continue;
+            }
+
+            wait = idle;
+
+            if client.get_state() != &ClientState::Transaction {
+                continue;
+            }
+
+            trace!("Sending keep alive to server");
+
+            if let Err(err) = client.noop().await {
+                debug!("Stopping keep alive after failed NOOP: {}", err);
+
+                break;
+            }
+        }
+    }
+
+    /// Get exclusive access to the client, waiting for the background task to finish sending a NOOP if needed.
+    pub async fn lock(&self) -> MutexGuard<'_, Client<S>> {
+        self.client.lock().await
+    }
+
+    /// Stop keeping the connection alive and return the client.
+    pub async fn into_inner(self) -> Client<S> {
+        let mut client = self.client;
+
+        loop {
+            match Arc::try_unwrap(client) {
+                Ok(client) => return client.into_inner(),
+                Err(shared) => {
+                    // The background task is currently using the client, wait for it to be done.
+                    drop(shared.lock().await);
+
+                    client = shared;
+                }
+            }
+        }
+    }
+}
@@ -7,10 +7,46 @@
 //!
 //! You can create a new session using the `connect` function or the `connect_plain` function.
 //!
-//! `connect` expects a tls connector from the `async-native-tls` crate. In the future more tls options will be supported.
+//! `connect` expects a tls connector from either the `async-native-tls` or the `async-rustls` crate, depending on which of those features is enabled. See the [tls] module for more info.
 //!
 //! If you already have a connected socket, you can also create a new session using the `new` function.
 //!
+//! A plain connection can be upgraded to a secure one using the `stls` method, if the server supports it.
+//! By default, logging in over a plain connection is refused when the server supports STLS, see the [security] module.
+//!
+//! To configure options such as timeouts, create the session using a `ClientBuilder` instead.
+//!
+//! Commands can be cancelled safely, e.g. using `select!` or a timeout: the rest of the command is sent and its response is thrown away before the next command is sent.
+//! The server may still have carried out the cancelled command.
+//!
+//! Enabling the `mail-parser` feature adds `Client::retr_parsed` and `Client::top_parsed`, which return messages parsed using the [mail-parser](https://crates.io/crates/mail-parser) crate.
+//!
+//! Enabling the `serde` feature implements `Serialize` and `Deserialize` for the response types, such as unique ids, listings and capabilities.
+//!
+//! Enabling the `spill` feature adds `Client::retr_spill`, which writes messages that are larger than a given threshold to a temporary file instead of keeping them in memory.
+//!
+//! Enabling the `socks5` feature allows the connection to be tunneled through a SOCKS5 proxy, such as Tor, using `ClientBuilder::proxy`.
+//!
+//! Enabling the `tracing` feature emits the diagnostics of the client through the [tracing](https://crates.io/crates/tracing) crate instead of `log`, with a span for every command. The credentials sent using PASS, APOP and AUTH are never logged.
+//!
+//! Enabling the `tunnel` feature adds `ClientBuilder::connect_tunnel`, which runs the client over a WebSocket or another message based tunnel, e.g. in a browser. See the [tunnel](crate::tunnel) module for more info.
+//!
+//! Enabling the `server` feature adds the [server](crate::server) module, which implements the server half of the protocol on top of the same request and response types.
+//!
+//! Enabling the `test-util` feature adds the [mock](crate::mock) module, an in-process POP3 server to test code that uses this crate against,
+//! and the [transcript](crate::transcript) module, which records sessions with real servers so they can be replayed in offline tests.
+//!
+//! ## Runtimes
+//!
+//! The client works with any stream that implements the `futures::io` traits, and only needs a [Timer](crate::timer::Timer) from the async runtime.
+//! The `runtime-async-std` (default) and `runtime-tokio` features provide the tcp connections used by `connect`, a default timer and the background task of `Client::keep_alive`.
+//! Both can be enabled at the same time, in which case async-std is used. Streams that only implement the tokio traits can be wrapped in a [Compat](crate::compat::Compat).
+//!
+//! ## Crate layout
+//!
+//! The protocol layer ([request] and [response]) lives in the [pop3-proto](https://crates.io/crates/pop3-proto) crate, which only works on bytes and does not depend on any runtime or tls implementation,
+//! so that servers, proxies and other tools can share the same parsers. Everything that touches a socket lives in this crate, in the client and its stream.
+//!
 //! ## Example
 //!
 //! ```rust,ignore
@@ -25,7 +61,7 @@
 //! async fn main() {
 //!     let tls = TlsConnector::new();
 //!
-//!     let mut client = async_pop::connect(("pop.gmail.com", 995), "pop.gmail.com", &tls, None).await.unwrap();
+//!     let mut client = async_pop::connect(("pop.gmail.com", 995), "pop.gmail.com", &tls).await.unwrap();
 //!
 //!     client.login("example@gmail.com", "password").await.unwrap();
 //!
@@ -40,49 +76,126 @@
 //! }
 //! ```
 
-mod command;
+pub mod auth;
+mod builder;
+#[cfg(feature = "runtime-tokio")]
+pub mod compat;
 mod constants;
 pub mod error;
+pub mod event;
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+pub mod happy_eyeballs;
+pub mod headers;
+pub mod health;
+pub mod interceptor;
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+pub mod keepalive;
+pub mod login_delay;
 mod macros;
-pub mod request;
-pub mod response;
+pub mod metrics;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod pipeline;
+pub mod pool;
+pub mod resilient;
+pub mod retry;
 mod runtime;
+pub mod security;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shared;
+#[cfg(feature = "socks5")]
+pub mod socks;
+#[cfg(feature = "spill")]
+pub mod spill;
 mod stream;
+pub mod sync;
+mod throttle;
+pub mod timer;
+#[cfg(feature = "test-util")]
+pub mod transcript;
+#[cfg(feature = "tunnel")]
+pub mod tunnel;
+pub mod uid;
+mod wire_log;
 
 #[cfg(feature = "tls")]
-mod tls;
+pub mod tls;
 
-#[cfg(feature = "sasl")]
-mod base64;
 #[cfg(feature = "sasl")]
 pub mod sasl;
 
-use std::collections::HashSet;
+// The protocol layer lives in its own crate, so that servers, proxies and analyzers can share it without depending on a runtime.
+#[cfg(feature = "sasl")]
+use pop3_proto::base64;
+use pop3_proto::command;
+pub use pop3_proto::{request, response};
+
+pub use builder::ClientBuilder;
+pub use command::Command;
+#[cfg(feature = "mail-parser")]
+pub use mail_parser;
+pub use response::types::number::MessageNumber;
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::RangeInclusive,
+    sync::Arc,
+    time::Duration,
+};
+pub use stream::BufferGrowth;
 
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use runtime::Instant;
+
+use auth::{AuthMechanism, AuthStrategy, LoginPolicy};
 use bytes::Bytes;
 use command::Command::*;
-use error::{ErrorKind, Result};
+use constants::{MAX_TOP_LINES, PIPELINE_BATCH_SIZE};
+use error::{Error, ErrorKind, Result};
+use event::Event;
+use futures::{Stream, StreamExt, TryStreamExt};
+use headers::{HeaderPreview, Headers};
+use health::Health;
+use interceptor::Interceptor;
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+use keepalive::KeepAlive;
+use login_delay::LoginDelayPolicy;
+use macros::debug;
+use md5::{Digest, Md5};
+use metrics::Metrics;
+use pipeline::Pipeline;
 use request::Request;
 use response::{
-    capability::{Capabilities, Capability},
+    capability::{Capabilities, Capability, CapabilityChanges, CapabilityCheck},
+    code::ResponseCode,
+    info::{MailboxSnapshot, MessageInfo},
+    lang::Language,
     list::ListResponse,
+    parser,
+    quit::QuitSummary,
     stat::Stat,
     types::message::Text,
-    uidl::UidlResponse,
-    Response,
+    uidl::{UidlResponse, UniqueId},
+    ParseMode, Response,
 };
-use sasl::PlainAuthenticator;
+use security::SecurityPolicy;
+use shared::SharedClient;
 use stream::PopStream;
+use uid::UidProvider;
+use wire_log::WireLog;
 
 use crate::{
     error::err,
     runtime::{
-        io::{Read, Write},
-        net::{TcpStream, ToSocketAddrs},
-        Instant,
+        io::{Read, Write, WriteExt},
+        with_timeout, Instant,
     },
+    timer::Timer,
 };
 
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+use runtime::net::{TcpStream, ToSocketAddrs};
+
 #[derive(Eq, PartialEq, Debug)]
 pub enum ClientState {
     Authentication,
@@ -94,30 +207,26 @@ pub enum ClientState {
 pub struct Client<S: Write + Read + Unpin + Send> {
     inner: Option<PopStream<S>>,
     capabilities: Capabilities,
-    marked_as_del: Vec<usize>,
+    capa_supported: bool,
+    capabilities_fetched: bool,
+    lazy_capabilities: bool,
+    capability_check: CapabilityCheck,
+    utf8: bool,
+    uid_cache: Option<HashMap<Text, MessageNumber>>,
+    marked_as_del: Vec<MessageNumber>,
+    /// The amount of messages in the maildrop when the session started, known once STAT has been sent.
+    message_count: Option<usize>,
     greeting: Option<Text>,
     read_greeting: bool,
+    secure: bool,
+    server: Option<String>,
+    login_delay_policy: LoginDelayPolicy,
+    login_policy: LoginPolicy,
+    security_policy: SecurityPolicy,
     state: ClientState,
-}
-
-/// Creates a client from a given socket connection.
-async fn create_client_from_socket<S: Read + Write + Unpin + Send>(
-    socket: PopStream<S>,
-) -> Result<Client<S>> {
-    let mut client = Client {
-        marked_as_del: Vec::new(),
-        capabilities: Vec::new(),
-        greeting: None,
-        read_greeting: false,
-        inner: Some(socket),
-        state: ClientState::Authentication,
-    };
-
-    client.greeting = Some(client.read_greeting().await?);
-
-    client.update_capabilities().await;
-
-    Ok(client)
+    /// A login attempt was started but the server has not given its final answer to it yet, see [Client::reset_auth].
+    auth_pending: bool,
+    timer: Arc<dyn Timer>,
 }
 
 /// Creates a new pop3 client from an existing stream.
@@ -136,38 +245,45 @@ async fn create_client_from_socket<S: Read + Write + Unpin + Send>(
 /// }
 /// ```
 pub async fn new<S: Read + Write + Unpin + Send>(stream: S) -> Result<Client<S>> {
-    let socket = PopStream::new(stream);
-
-    create_client_from_socket(socket).await
+    ClientBuilder::new().build(stream).await
 }
 
 /// Create a new pop3 client with a tls connection.
-#[cfg(feature = "tls")]
+///
+/// Accepts a reference to either an `async_native_tls::TlsConnector` or an `async_rustls::TlsConnector`, depending on the enabled features.
+/// # Examples
+/// ```rust,ignore
+/// use std::sync::Arc;
+///
+/// use async_rustls::{rustls::ClientConfig, TlsConnector};
+///
+/// let config = ClientConfig::builder()
+///     .with_safe_defaults()
+///     .with_root_certificates(root_store)
+///     .with_no_client_auth();
+///
+/// let tls = TlsConnector::from(Arc::new(config));
+///
+/// let client = async_pop::connect(("pop.gmail.com", 995), "pop.gmail.com", &tls).await?;
+/// ```
+#[cfg(all(
+    feature = "tls",
+    any(feature = "runtime-async-std", feature = "runtime-tokio")
+))]
 pub async fn connect<'a, A: ToSocketAddrs, D: AsRef<str>, C: Into<tls::TlsConnector<'a>>>(
     addr: A,
     domain: D,
     tls: C,
 ) -> Result<Client<impl tls::TlsStream<TcpStream>>> {
-    let tcp_stream = TcpStream::connect(addr).await?;
-
-    let tls_connector: tls::TlsConnector<'a> = tls.into();
-
-    let tls_stream = tls_connector.connect(domain, tcp_stream).await?;
-
-    let socket = PopStream::new(tls_stream);
-
-    create_client_from_socket(socket).await
+    ClientBuilder::new().connect(addr, domain, tls).await
 }
 
 /// Creates a new pop3 client using a plain connection.
 ///
 /// DO NOT USE in a production environment. Your password will be sent over a plain tcp stream which hackers could intercept.
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
 pub async fn connect_plain<A: ToSocketAddrs>(addr: A) -> Result<Client<TcpStream>> {
-    let tcp_stream = TcpStream::connect(addr).await?;
-
-    let socket = PopStream::new(tcp_stream);
-
-    create_client_from_socket(socket).await
+    ClientBuilder::new().connect_plain(addr).await
 }
 
 impl<S: Read + Write + Unpin + Send> Client<S> {
@@ -222,6 +338,100 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         &self.state
     }
 
+    /// Whether commands can still be sent over the connection: the session has not ended and no earlier error left the connection in an unknown state.
+    ///
+    /// Responses that can not be parsed, failed reads and failed writes poison the connection, after which every command fails with [ErrorKind::Poisoned].
+    /// The only way out is to end the session using [Client::quit] or [Client::close], which drop the connection, and to open a new one.
+    pub fn is_usable(&self) -> bool {
+        match self.inner.as_ref() {
+            Some(stream) => {
+                !stream.is_poisoned()
+                    && matches!(
+                        self.state,
+                        ClientState::Authentication | ClientState::Transaction
+                    )
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the connection is known to be secured using tls, either because it was opened using [connect] or upgraded using [Client::stls].
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Change how strictly the responses from the server are parsed.
+    ///
+    /// Setting this to [ParseMode::Lenient] allows the client to work with servers that do not fully follow the specification.
+    /// Any quirks that were worked around are recorded as events, see [Client::take_events].
+    pub fn set_parse_mode(&mut self, mode: ParseMode) -> Result<()> {
+        self.inner_mut()?.set_parse_mode(mode);
+
+        Ok(())
+    }
+
+    /// Change the maximum size of a single response that is kept in memory, responses that are larger fail with [ErrorKind::ResponseTooLarge].
+    ///
+    /// Use `usize::MAX` to allow responses of any size, see also [ClientBuilder::max_response_size].
+    pub fn set_max_response_size(&mut self, max_size: usize) -> Result<()> {
+        self.inner_mut()?.set_max_response_size(max_size);
+
+        Ok(())
+    }
+
+    /// The amount of bytes that were transferred and the round-trip latencies of the commands that were sent during this session.
+    ///
+    /// Returns [None] if there is no connection.
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.inner.as_ref().map(PopStream::metrics)
+    }
+
+    /// Registers a hook that sees every request before it is sent and every response once it has been received, see [interceptor::Interceptor].
+    pub fn add_interceptor<I: Interceptor + 'static>(&mut self, interceptor: I) -> Result<()> {
+        self.inner_mut()?.add_interceptor(Arc::new(interceptor));
+
+        Ok(())
+    }
+
+    /// Limit how many bytes per second are read from and written to the server, [None] removes the limit.
+    ///
+    /// See also [ClientBuilder::read_rate_limit] and [ClientBuilder::write_rate_limit].
+    pub fn set_rate_limits(&mut self, read: Option<u64>, write: Option<u64>) -> Result<()> {
+        self.inner_mut()?.set_rate_limits(read, write);
+
+        Ok(())
+    }
+
+    /// The bytes of the last response exactly as the server sent them, including the status line and line endings.
+    ///
+    /// Only available when enabled using [ClientBuilder::retain_raw_response], longer responses are cut off at the configured size.
+    /// The copy is also kept when the response could not be parsed, in which case it contains everything that was received up until the error.
+    pub fn last_raw_response(&self) -> Option<&[u8]> {
+        self.inner.as_ref().and_then(PopStream::last_raw_response)
+    }
+
+    /// Copy every line that is sent to and received from the server from now on to the given writer, or stop doing so when [None] is given.
+    ///
+    /// Each line is written as soon as it is complete, prefixed with `C: ` or `S: ` and escaped the same way as a saved `transcript::Transcript`,
+    /// so it can be loaded as one using the `test-util` feature. The credentials sent using PASS, APOP and AUTH are replaced with `<redacted>`.
+    /// The data is sent and received in the clear, the tls handshake itself is not part of the transcript.
+    ///
+    /// The writer is called from within the client, so it should not block for long, e.g. use a file or an in-memory buffer.
+    /// Once writing fails, a warning is logged and the writer is no longer used.
+    pub fn set_transcript(&mut self, writer: Option<Box<dyn std::io::Write + Send>>) -> Result<()> {
+        self.inner_mut()?.set_wire_log(writer.map(WireLog::new));
+
+        Ok(())
+    }
+
+    /// Returns all of the events that happened on the connection since the last call to this function.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        match self.inner.as_mut() {
+            Some(socket) => socket.take_events(),
+            None => Vec::new(),
+        }
+    }
+
     /// ## NOOP
     /// The POP3 server does nothing, it merely replies with a positive response.
     /// ### Arguments: none
@@ -231,13 +441,239 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     /// - OK
     /// # Examples:
     /// ```rust,ignore
-    /// client.noop()?;
+    /// let text = client.noop().await?;
     /// ```
     /// https://www.rfc-editor.org/rfc/rfc1939#page-9
-    pub async fn noop(&mut self) -> Result<()> {
-        self.send_request(Noop).await?;
+    pub async fn noop(&mut self) -> Result<Text> {
+        match self.send_request(Noop).await? {
+            Response::Message(resp) => Ok(resp),
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected noop response"
+            ),
+        }
+    }
 
-        Ok(())
+    /// Sends a NOOP that has to be answered within the given amount of time, and tells how healthy the connection is based on the answer, see [Health].
+    ///
+    /// A negative response still counts as an answer, as the server is there and the connection is in sync.
+    /// If the server did not answer in time the connection is poisoned, as it can not be told apart from a connection that is gone (see [Client::is_usable]).
+    pub async fn ping(&mut self, timeout: Duration) -> Health {
+        let timer = self.timer.clone();
+
+        let started = Instant::now();
+
+        let result = with_timeout(
+            &*timer,
+            Some(timeout),
+            Some(Noop),
+            "Timed out while waiting for the server to answer the ping",
+            self.noop(),
+        )
+        .await;
+
+        let latency = started.elapsed();
+
+        match result {
+            Err(err) if !matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                if let (ErrorKind::Timeout { .. }, Some(stream)) = (err.kind(), self.inner.as_mut())
+                {
+                    stream.poison();
+                }
+
+                debug!("Ping failed: {}", err);
+
+                Health::Dead(err)
+            }
+            _ if latency > timeout / 2 => Health::Slow(latency),
+            _ => Health::Healthy(latency),
+        }
+    }
+
+    /// Lists every message that is not marked as deleted along with its size and unique id, see [MessageInfo].
+    ///
+    /// LIST and UIDL are pipelined when the server supports it. If the server does not support UIDL, the unique ids are left empty.
+    pub async fn message_infos(&mut self) -> Result<Vec<MessageInfo>> {
+        let uidl_supported = self.check_capability(vec![Capability::Uidl]).await.is_ok();
+
+        let mut pipeline = self.pipeline().list(None::<MessageNumber>);
+
+        if uidl_supported {
+            pipeline = pipeline.uidl(None::<MessageNumber>);
+        }
+
+        let mut responses = pipeline.execute().await?.into_iter();
+
+        Self::read_message_infos(&mut responses)
+    }
+
+    /// Issues STAT, LIST and UIDL at once and combines their responses, see [MailboxSnapshot].
+    ///
+    /// The commands are pipelined when the server supports it. If the server does not support UIDL, the unique ids are left empty, like [Client::message_infos].
+    /// # Examples
+    /// ```rust,ignore
+    /// let snapshot = client.snapshot().await?;
+    ///
+    /// println!("{} messages, {} octets", snapshot.message_count(), snapshot.size());
+    ///
+    /// for info in snapshot.messages() {
+    ///     println!("{}: {:?}", info.number(), info.uid());
+    /// }
+    /// ```
+    pub async fn snapshot(&mut self) -> Result<MailboxSnapshot> {
+        let uidl_supported = self.check_capability(vec![Capability::Uidl]).await.is_ok();
+
+        let mut pipeline = self.pipeline().stat().list(None::<MessageNumber>);
+
+        if uidl_supported {
+            pipeline = pipeline.uidl(None::<MessageNumber>);
+        }
+
+        let mut responses = pipeline.execute().await?.into_iter();
+
+        let stat = match responses.next() {
+            Some(Ok(Response::Stat(stat))) => stat,
+            Some(Err(err)) => return Err(err),
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected stat response"
+            ),
+        };
+
+        let messages = Self::read_message_infos(&mut responses)?;
+
+        Ok(MailboxSnapshot::new(
+            stat.message_count(),
+            stat.size_bytes(),
+            messages,
+        ))
+    }
+
+    /// Combines the responses to LIST and optionally UIDL, in that order.
+    fn read_message_infos<I: Iterator<Item = Result<Response>>>(
+        responses: &mut I,
+    ) -> Result<Vec<MessageInfo>> {
+        let list = match responses.next() {
+            Some(Ok(Response::List(list))) => list,
+            Some(Err(err)) => return Err(err),
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected list response"
+            ),
+        };
+
+        let uids: HashMap<MessageNumber, Text> = match responses.next() {
+            Some(Ok(Response::Uidl(UidlResponse::Multiple(uidl)))) => uidl
+                .items()
+                .iter()
+                .map(|unique_id| Ok((unique_id.message_number()?, unique_id.id().clone())))
+                .collect::<Result<_>>()?,
+            Some(Ok(_)) => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected uidl response"
+            ),
+            Some(Err(err)) => {
+                debug!(
+                    "Server rejected UIDL, listing messages without unique ids: {}",
+                    err
+                );
+
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+
+        list.items()
+            .iter()
+            .map(|item| {
+                let number = item.message_number()?;
+
+                Ok(MessageInfo::new(
+                    number,
+                    item.size_bytes(),
+                    uids.get(&number).cloned(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Same as [Client::message_infos], but the unique ids come from the given provider, e.g. [uid::HeaderHash] for servers that do not support UIDL.
+    pub async fn message_infos_with<P: UidProvider>(
+        &mut self,
+        provider: &P,
+    ) -> Result<Vec<MessageInfo>> {
+        let list = self.list(None::<MessageNumber>).await?;
+
+        let infos: Vec<MessageInfo> = list
+            .iter()
+            .map(|item| {
+                Ok(MessageInfo::new(
+                    item.message_number()?,
+                    item.size_bytes(),
+                    None,
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut uids = provider.unique_ids(self, &infos).await?;
+
+        Ok(infos
+            .into_iter()
+            .map(|info| {
+                let uid = uids.remove(&info.number());
+
+                MessageInfo::new(info.number(), info.size(), uid)
+            })
+            .collect())
+    }
+
+    /// Looks up the message number of the message with the given unique id.
+    ///
+    /// The mapping is built using [Client::uidl] the first time it is needed and kept for the rest of the session, as message numbers only change between sessions.
+    /// If the unique id is not known, the mapping is fetched again once before failing with [ErrorKind::UnknownUid].
+    pub async fn message_number<U: AsRef<str>>(&mut self, uid: U) -> Result<MessageNumber> {
+        let uid = Text::from(uid.as_ref());
+
+        let cached = self
+            .uid_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&uid).copied());
+
+        if let Some(msg_number) = cached {
+            return Ok(msg_number);
+        }
+
+        self.uidl(None::<MessageNumber>).await?;
+
+        match self.uid_cache.as_ref().and_then(|cache| cache.get(&uid)) {
+            Some(msg_number) => Ok(*msg_number),
+            None => err!(
+                ErrorKind::UnknownUid,
+                "There is no message with the unique id '{}'",
+                uid
+            ),
+        }
+    }
+
+    /// Same as [Client::retr], but for the message with the given unique id, see [Client::message_number].
+    pub async fn retr_by_uid<U: AsRef<str>>(&mut self, uid: U) -> Result<Bytes> {
+        let msg_number = self.message_number(uid).await?;
+
+        self.retr(msg_number).await
+    }
+
+    /// Same as [Client::top], but for the message with the given unique id, see [Client::message_number].
+    pub async fn top_by_uid<U: AsRef<str>>(&mut self, uid: U, lines: usize) -> Result<Bytes> {
+        let msg_number = self.message_number(uid).await?;
+
+        self.top(msg_number, lines).await
+    }
+
+    /// Same as [Client::dele], but for the message with the given unique id, see [Client::message_number].
+    pub async fn dele_by_uid<U: AsRef<str>>(&mut self, uid: U) -> Result<Text> {
+        let msg_number = self.message_number(uid).await?;
+
+        self.dele(msg_number).await
     }
 
     /// ## UIDL
@@ -248,7 +684,7 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     /// After the initial +OK, for each message in the maildrop, the POP3 server responds with a line containing information for that message.          This line is called a "unique-id listing" for that message.
     ///
     /// ### Arguments:
-    /// - a message-number (optional), which, if present, may NOT refer to a message marked as deleted.
+    /// - a message-number (optional), which, if present, may NOT refer to a message marked as deleted. Without one the type has to be spelled out, e.g. `None::<MessageNumber>`.
     ///
     /// ### Restrictions:
     /// - May only be given in the TRANSACTION state.
@@ -258,15 +694,19 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     /// - -ERR no such message
     ///
     /// https://www.rfc-editor.org/rfc/rfc1939#page-12
-    pub async fn uidl(&mut self, msg_number: Option<usize>) -> Result<UidlResponse> {
-        self.check_capability(vec![Capability::Uidl])?;
+    pub async fn uidl<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: Option<N>,
+    ) -> Result<UidlResponse> {
+        let msg_number = msg_number.map(Into::into);
 
-        match msg_number.as_ref() {
-            Some(msg_number) => self.check_deleted(msg_number)?,
-            None => {}
-        };
+        self.check_capability(vec![Capability::Uidl]).await?;
+
+        if let Some(msg_number) = msg_number {
+            self.check_msg_number(msg_number)?
+        }
 
-        let mut request: Request = Uidl.into();
+        let mut request: Request = Uidl { listing: true }.into();
 
         if let Some(number) = msg_number {
             request.add_arg(number)
@@ -274,6 +714,17 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
 
         let response = self.send_request(request).await?;
 
+        if let Response::Uidl(UidlResponse::Multiple(uidl)) = &response {
+            self.uid_cache = Some(
+                uidl.items()
+                    .iter()
+                    .filter_map(|unique_id| {
+                        Some((unique_id.id().clone(), unique_id.message_number().ok()?))
+                    })
+                    .collect(),
+            );
+        }
+
         match response {
             Response::Uidl(resp) => Ok(resp),
             _ => {
@@ -285,17 +736,70 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         }
     }
 
-    /// When the last communication with the server happened.
+    /// ## UIDL
+    /// Same as [Client::uidl] without an argument, but yields the unique ids one by one as they arrive from the server.
+    ///
+    /// Only a single line of the listing is kept in memory at a time, so even huge maildrops can be listed with bounded memory.
+    /// Unlike [Client::uidl], this does not fill the cache that is used to look up messages by their unique id.
+    ///
+    /// If the stream is dropped before it ends, the rest of the response is read and thrown away before the next command is sent.
+    /// https://www.rfc-editor.org/rfc/rfc1939#page-12
+    pub async fn uidl_stream(&mut self) -> Result<impl Stream<Item = Result<UniqueId>> + '_> {
+        self.check_capability(vec![Capability::Uidl]).await?;
+
+        let lines = self.read_listing(Uidl { listing: true }.into()).await?;
+
+        Ok(lines.map(|line| match parser::unique_id_line(&line?) {
+            Some(unique_id) => Ok(unique_id),
+            None => err!(
+                ErrorKind::InvalidResponse,
+                "The server sent an invalid unique id listing"
+            ),
+        }))
+    }
+
+    /// Sends a request for a multi-line listing and returns a stream over its lines.
+    async fn read_listing(&mut self, request: Request) -> Result<stream::Lines<'_, S>> {
+        let stream = self.inner_mut()?;
+
+        stream.encode(&request).await?;
+
+        stream.read_status(request).await?;
+
+        Ok(stream.lines())
+    }
+
+    /// When the last command was sent to the server.
     ///
-    /// Returns [None] if there is no connection or the connection is not in the right state.
+    /// Returns [None] if there is no connection. This works in any state and does not touch the connection, so it is cheap to poll.
     pub fn last_activity(&self) -> Option<Instant> {
         Some(self.inner.as_ref()?.last_activity())
     }
 
-    pub async fn top(&mut self, msg_number: usize, lines: usize) -> Result<Bytes> {
-        self.check_deleted(&msg_number)?;
+    /// How long ago the last command was sent to the server, see [Client::last_activity].
+    pub fn idle_time(&self) -> Option<Duration> {
+        self.last_activity()
+            .map(|last_activity| last_activity.elapsed())
+    }
+
+    pub async fn top<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+        lines: usize,
+    ) -> Result<Bytes> {
+        let msg_number = msg_number.into();
+
+        self.check_msg_number(msg_number)?;
+
+        if lines > MAX_TOP_LINES {
+            err!(
+                ErrorKind::InvalidArgument,
+                "Can not request more than {} lines of a message, use RETR to fetch the whole message",
+                MAX_TOP_LINES
+            )
+        }
 
-        self.check_capability(vec![Capability::Top])?;
+        self.check_capability(vec![Capability::Top]).await?;
 
         let mut request: Request = Top.into();
 
@@ -313,67 +817,488 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         }
     }
 
-    /// Check whether a given message is marked as deleted by the server.
-    ///
-    /// If this function returns true then the message may still not exist.
-    /// # Examples:
-    /// ```rust,ignore
-    /// let msg_number: u32 = 8;
-    /// let is_deleted = client.is_deleted(msg_number);
-    /// assert_eq!(is_deleted, false);
-    /// ```
-    pub fn is_deleted(&mut self, msg_number: &usize) -> bool {
-        self.marked_as_del.sort();
-
-        match self.marked_as_del.binary_search(msg_number) {
-            Ok(_) => true,
-            Err(_) => false,
-        }
-    }
+    /// Fetches the header block of a message using `TOP n 0` and parses it, see [Headers].
+    pub async fn top_headers<N: Into<MessageNumber>>(&mut self, msg_number: N) -> Result<Headers> {
+        let header = self.top(msg_number, 0).await?;
 
-    fn check_deleted(&mut self, msg_number: &usize) -> Result<()> {
-        if self.is_deleted(msg_number) {
-            err!(
-                ErrorKind::MessageIsDeleted,
-                "This message has been marked as deleted and cannot be refenced anymore",
-            )
-        } else {
-            Ok(())
-        }
+        Ok(Headers::parse(&header))
     }
 
-    /// ## DELE
-    /// The POP3 server marks the message as deleted.  Any future reference to the message-number associated with the message in a POP3 command generates an error.  The POP3 server does not actually delete the message until the POP3 session enters the UPDATE state.
-    /// ### Arguments:
-    /// - a message-number (required) which may NOT refer to a message marked as deleted.
-    /// ### Restrictions:
-    /// - may only be given in the TRANSACTION state
-    /// ### Possible Responses:
-    /// - OK: message deleted
-    /// - ERR: no such message
+    /// Fetches the headers and the first `lines` lines of the body of every given message, like [Client::top], yielding each one as soon as its response has been read.
+    ///
+    /// The TOP commands are pipelined in batches when the server supports it, so previewing a large maildrop takes a handful of round trips instead of one per message.
+    /// The stream fails if the connection itself had a problem, while the inner results contain the response to the TOP command for that specific message.
+    ///
+    /// If the stream is dropped before it ends, the responses to the commands that were already sent are read and thrown away before the next command is sent.
     /// # Examples
     /// ```rust,ignore
-    /// let msg_number: u32 = 8;
-    /// let is_deleted = client.is_deleted(msg_number);
+    /// use futures::TryStreamExt;
+    ///
+    /// let mut headers = client.top_many(1..=500, 0).await?;
     ///
-    /// println!("{}", is_deleted);
+    /// while let Some((msg_number, header)) = headers.try_next().await? {
+    ///     println!("{}: {:?}", msg_number, Headers::parse(&header?).get("Subject"));
+    /// }
     /// ```
-    pub async fn dele(&mut self, msg_number: usize) -> Result<Text> {
-        self.check_deleted(&msg_number)?;
+    pub async fn top_many<I: IntoIterator<Item = N>, N: Into<MessageNumber>>(
+        &mut self,
+        msg_numbers: I,
+        lines: usize,
+    ) -> Result<impl Stream<Item = Result<(MessageNumber, Result<Bytes>)>> + '_> {
+        if lines > MAX_TOP_LINES {
+            err!(
+                ErrorKind::InvalidArgument,
+                "Can not request more than {} lines of a message, use RETR to fetch the whole message",
+                MAX_TOP_LINES
+            )
+        }
 
-        let mut request: Request = Dele.into();
+        self.check_capability(vec![Capability::Top]).await?;
 
-        request.add_arg(msg_number);
+        let pipelined = self.has_capability([Capability::Pipelining]);
 
-        let response = self.send_request(request).await?;
+        let msg_numbers: Vec<MessageNumber> = msg_numbers.into_iter().map(Into::into).collect();
 
-        match response {
-            Response::Message(resp) => Ok(resp),
-            _ => err!(
+        let top = move |msg_number: MessageNumber| {
+            let mut request: Request = Top.into();
+
+            request.add_arg(msg_number);
+            request.add_arg(lines);
+
+            request
+        };
+
+        // The messages of the current batch that are still waiting for a response, along with the reason a message was not sent.
+        let batch: VecDeque<(MessageNumber, Option<Error>)> = VecDeque::new();
+
+        // Boxed so the stream is Unpin and can be polled using e.g. `try_next`.
+        Ok(Box::pin(futures::stream::try_unfold(
+            (self, msg_numbers.into_iter(), batch),
+            move |(client, mut msg_numbers, mut batch)| async move {
+                if batch.is_empty() {
+                    let mut requests = Vec::with_capacity(PIPELINE_BATCH_SIZE);
+
+                    for msg_number in msg_numbers.by_ref().take(PIPELINE_BATCH_SIZE) {
+                        match client.check_msg_number(msg_number) {
+                            Ok(()) => {
+                                requests.push(top(msg_number));
+                                batch.push_back((msg_number, None));
+                            }
+                            Err(err) => batch.push_back((msg_number, Some(err))),
+                        }
+                    }
+
+                    if pipelined && !requests.is_empty() {
+                        client.inner_mut()?.encode_all(&requests).await?;
+                    }
+                }
+
+                let (msg_number, error) = match batch.pop_front() {
+                    Some(next) => next,
+                    None => return Ok(None),
+                };
+
+                let result = match error {
+                    Some(err) => Err(err),
+                    None => {
+                        let response = if pipelined {
+                            client.read_pipelined(top(msg_number)).await
+                        } else {
+                            client.send_request(top(msg_number)).await
+                        };
+
+                        match response {
+                            Ok(Response::Bytes(header)) => Ok(header),
+                            Ok(_) => Err(Error::new(
+                                ErrorKind::UnexpectedResponse,
+                                "Did not received the expected top response",
+                            )),
+                            Err(err) if matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                                Err(err)
+                            }
+                            Err(err) => return Err(err),
+                        }
+                    }
+                };
+
+                Ok(Some(((msg_number, result), (client, msg_numbers, batch))))
+            },
+        )))
+    }
+
+    /// Fetches the sender, subject, date and size of a message, which is enough to show it in a list, using LIST and the header block of the message.
+    ///
+    /// Only the header block is transferred, unless the server does not support TOP, see [Client::preview]. Encoded words in the fields are decoded.
+    pub async fn preview_headers<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+    ) -> Result<HeaderPreview> {
+        let msg_number = msg_number.into();
+
+        let list = self.list(Some(msg_number)).await?;
+
+        let size = match list.get(msg_number) {
+            Some(stat) => stat.size_bytes(),
+            None => err!(
                 ErrorKind::UnexpectedResponse,
-                "Did not received the expected dele response"
+                "The server listed a different message than {}",
+                msg_number
             ),
-        }
+        };
+
+        let header = self.preview(msg_number, 0).await?;
+
+        Ok(HeaderPreview::new(&Headers::parse(&header), size))
+    }
+
+    /// Fetches the headers and the first `lines` lines of the body of a message, like [Client::top], but also on servers that do not support TOP.
+    ///
+    /// Without TOP the message is retrieved using RETR and cut off locally. Only the preview itself is kept in memory, the rest of the message is read and thrown away,
+    /// and the preview may not be larger than the maximum response size of the client.
+    /// # Examples
+    /// ```rust,ignore
+    /// let headers = Headers::parse(&client.preview(1, 0).await?);
+    /// ```
+    pub async fn preview<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+        lines: usize,
+    ) -> Result<Bytes> {
+        let msg_number = msg_number.into();
+
+        match self.top(msg_number, lines).await {
+            Err(err) if matches!(err.kind(), ErrorKind::FeatureUnsupported) => {}
+            // When the capability is not checked, a server that does not support TOP rejects it.
+            Err(err)
+                if matches!(err.kind(), ErrorKind::ServerError { .. })
+                    && !self.has_capability([Capability::Top]) => {}
+            result => return result,
+        }
+
+        debug!("Falling back to RETR to preview message {}", msg_number);
+
+        let max_size = self.inner_mut()?.max_response_size();
+
+        let mut chunks = self.retr_stream(msg_number).await?;
+
+        let mut preview = Vec::new();
+
+        while let Some(chunk) = chunks.try_next().await? {
+            preview.extend_from_slice(&chunk);
+
+            if let Some(len) = headers::preview_len(&preview, lines) {
+                preview.truncate(len);
+
+                break;
+            }
+
+            if preview.len() > max_size {
+                err!(
+                    ErrorKind::ResponseTooLarge,
+                    "The preview of message {} is larger than {} bytes",
+                    msg_number,
+                    max_size
+                )
+            }
+        }
+
+        Ok(preview.into())
+    }
+
+    /// Same as [Client::retr], but parses the message using the `mail-parser` crate.
+    ///
+    /// The returned message owns its data, so it can outlive the client.
+    #[cfg(feature = "mail-parser")]
+    pub async fn retr_parsed<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+    ) -> Result<mail_parser::Message<'static>> {
+        let message = self.retr(msg_number).await?;
+
+        parse_message(&message)
+    }
+
+    /// Same as [Client::top], but parses the headers and the given amount of lines of the body using the `mail-parser` crate.
+    #[cfg(feature = "mail-parser")]
+    pub async fn top_parsed<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+        lines: usize,
+    ) -> Result<mail_parser::Message<'static>> {
+        let message = self.top(msg_number, lines).await?;
+
+        parse_message(&message)
+    }
+
+    /// Check whether a given message is marked as deleted by the server.
+    ///
+    /// If this function returns true then the message may still not exist.
+    /// # Examples:
+    /// ```rust,ignore
+    /// let is_deleted = client.is_deleted(8);
+    /// assert_eq!(is_deleted, false);
+    /// ```
+    pub fn is_deleted<N: Into<MessageNumber>>(&self, msg_number: N) -> bool {
+        self.marked_as_del.binary_search(&msg_number.into()).is_ok()
+    }
+
+    /// The message numbers that were marked as deleted in this session, in ascending order.
+    ///
+    /// The marks are cleared by [Client::rset] and the messages are only removed once the session ends using [Client::quit].
+    pub fn deleted_messages(&self) -> &[MessageNumber] {
+        &self.marked_as_del
+    }
+
+    /// Removes the local deletion mark of a message, returning whether it was marked.
+    ///
+    /// This does not tell the server anything, it only allows the message number to be used again, e.g. when the client is known to be out of sync with the server.
+    pub fn unmark_deleted<N: Into<MessageNumber>>(&mut self, msg_number: N) -> bool {
+        let msg_number = msg_number.into();
+
+        match self.marked_as_del.binary_search(&msg_number) {
+            Ok(index) => {
+                self.marked_as_del.remove(index);
+
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn mark_deleted(&mut self, msg_number: MessageNumber) {
+        if let Err(index) = self.marked_as_del.binary_search(&msg_number) {
+            self.marked_as_del.insert(index, msg_number);
+        }
+    }
+
+    /// Makes sure the message number refers to a message that exists and has not been marked as deleted, before it is sent to the server.
+    fn check_msg_number(&self, msg_number: MessageNumber) -> Result<()> {
+        if msg_number == 0 {
+            err!(
+                ErrorKind::InvalidArgument,
+                "Message numbers start at 1, 0 does not refer to a message"
+            )
+        }
+
+        if let Some(count) = self.message_count {
+            if msg_number.get() > count {
+                err!(
+                    ErrorKind::InvalidArgument,
+                    "Message {} does not exist, the maildrop only has {} messages",
+                    msg_number,
+                    count
+                )
+            }
+        }
+
+        if self.is_deleted(msg_number) {
+            err!(
+                ErrorKind::MessageIsDeleted,
+                "This message has been marked as deleted and cannot be refenced anymore",
+            )
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fetches every message in the given range of message numbers, returning the message numbers along with their content in order.
+    ///
+    /// The RETR commands are pipelined when the server supports it, otherwise they are sent one by one.
+    pub async fn retr_range(
+        &mut self,
+        range: RangeInclusive<MessageNumber>,
+    ) -> Result<Vec<(MessageNumber, Bytes)>> {
+        let msg_numbers: Vec<MessageNumber> = (range.start().get()..=range.end().get())
+            .map(MessageNumber::from)
+            .collect();
+
+        for msg_number in &msg_numbers {
+            self.check_msg_number(*msg_number)?;
+        }
+
+        self.retr_many(msg_numbers).await
+    }
+
+    /// Fetches every message in the maildrop that is not marked as deleted, see [Client::retr_range].
+    pub async fn retr_all(&mut self) -> Result<Vec<(MessageNumber, Bytes)>> {
+        let msg_numbers = self.listed_msg_numbers().await?;
+
+        self.retr_many(msg_numbers).await
+    }
+
+    /// Returns a stream of every message in the maildrop that is not marked as deleted, along with its message number.
+    ///
+    /// The messages are listed when the stream is first polled and every message is only fetched once the stream gets to it, so the stream can be stopped early without downloading the rest of the maildrop.
+    /// # Examples
+    /// ```rust,ignore
+    /// use futures::TryStreamExt;
+    ///
+    /// let mut messages = client.messages();
+    ///
+    /// while let Some((msg_number, message)) = messages.try_next().await? {
+    ///     println!("{}: {} bytes", msg_number, message.len());
+    /// }
+    /// ```
+    pub fn messages(&mut self) -> impl Stream<Item = Result<(MessageNumber, Bytes)>> + '_ {
+        futures::stream::try_unfold(
+            (self, None::<std::vec::IntoIter<MessageNumber>>),
+            |(client, msg_numbers)| async move {
+                let mut msg_numbers = match msg_numbers {
+                    Some(msg_numbers) => msg_numbers,
+                    None => client.listed_msg_numbers().await?.into_iter(),
+                };
+
+                match msg_numbers.next() {
+                    Some(msg_number) => {
+                        let message = client.retr(msg_number).await?;
+
+                        Ok(Some(((msg_number, message), (client, Some(msg_numbers)))))
+                    }
+                    None => Ok(None),
+                }
+            },
+        )
+    }
+
+    /// The message numbers of every message that is not marked as deleted, according to LIST.
+    async fn listed_msg_numbers(&mut self) -> Result<Vec<MessageNumber>> {
+        let list = self.list(None::<MessageNumber>).await?;
+
+        list.iter().map(|stat| Ok(stat.message_number()?)).collect()
+    }
+
+    async fn retr_many(
+        &mut self,
+        msg_numbers: Vec<MessageNumber>,
+    ) -> Result<Vec<(MessageNumber, Bytes)>> {
+        let mut messages = Vec::with_capacity(msg_numbers.len());
+
+        for batch in msg_numbers.chunks(PIPELINE_BATCH_SIZE) {
+            let pipeline = batch.iter().fold(self.pipeline(), |pipeline, msg_number| {
+                pipeline.retr(*msg_number)
+            });
+
+            for (msg_number, response) in batch.iter().zip(pipeline.execute().await?) {
+                match response? {
+                    Response::Bytes(message) => messages.push((*msg_number, message)),
+                    _ => err!(
+                        ErrorKind::UnexpectedResponse,
+                        "Did not received the expected retr response"
+                    ),
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// ## DELE
+    /// The POP3 server marks the message as deleted.  Any future reference to the message-number associated with the message in a POP3 command generates an error.  The POP3 server does not actually delete the message until the POP3 session enters the UPDATE state.
+    /// ### Arguments:
+    /// - a message-number (required) which may NOT refer to a message marked as deleted.
+    /// ### Restrictions:
+    /// - may only be given in the TRANSACTION state
+    /// ### Possible Responses:
+    /// - OK: message deleted
+    /// - ERR: no such message
+    /// # Examples
+    /// ```rust,ignore
+    /// client.dele(8).await?;
+    ///
+    /// assert!(client.is_deleted(8));
+    /// ```
+    pub async fn dele<N: Into<MessageNumber>>(&mut self, msg_number: N) -> Result<Text> {
+        let msg_number = msg_number.into();
+
+        self.check_msg_number(msg_number)?;
+
+        let mut request: Request = Dele.into();
+
+        request.add_arg(msg_number);
+
+        let response = self.send_request(request).await?;
+
+        self.mark_deleted(msg_number);
+
+        match response {
+            Response::Message(resp) => Ok(resp),
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected dele response"
+            ),
+        }
+    }
+
+    /// Marks all of the given messages as deleted, see [Client::dele].
+    ///
+    /// The DELE commands are pipelined when the server supports it, otherwise they are sent one by one.
+    /// The outer result fails if the connection itself had a problem, while the inner results contain the response to the DELE command for that specific message.
+    /// A message is only marked once the server confirmed its DELE, so after a failed connection [Client::deleted_messages] does not list messages the server never marked.
+    pub async fn dele_many<I: IntoIterator<Item = N>, N: Into<MessageNumber>>(
+        &mut self,
+        msg_numbers: I,
+    ) -> Result<Vec<(MessageNumber, Result<Text>)>> {
+        let msg_numbers: Vec<MessageNumber> = msg_numbers.into_iter().map(Into::into).collect();
+
+        let mut results = Vec::with_capacity(msg_numbers.len());
+
+        for batch in msg_numbers.chunks(PIPELINE_BATCH_SIZE) {
+            let mut to_delete = Vec::with_capacity(batch.len());
+            let mut checked = Vec::with_capacity(batch.len());
+
+            // A message is only marked once the server confirmed it, but one that occurs twice is still only deleted once.
+            for msg_number in batch {
+                let result = if to_delete.contains(msg_number) {
+                    Err(Error::new(
+                        ErrorKind::MessageIsDeleted,
+                        "This message has been marked as deleted and cannot be refenced anymore",
+                    ))
+                } else {
+                    self.check_msg_number(*msg_number)
+                };
+
+                match result {
+                    Ok(()) => {
+                        to_delete.push(*msg_number);
+                        checked.push((*msg_number, None));
+                    }
+                    Err(err) => checked.push((*msg_number, Some(err))),
+                }
+            }
+
+            let pipeline = to_delete
+                .iter()
+                .fold(self.pipeline(), |pipeline, msg_number| {
+                    pipeline.dele(*msg_number)
+                });
+
+            let mut responses = pipeline.execute().await?.into_iter();
+
+            for (msg_number, error) in checked {
+                if let Some(err) = error {
+                    results.push((msg_number, Err(err)));
+
+                    continue;
+                }
+
+                let result = match responses.next() {
+                    Some(Ok(Response::Message(resp))) => {
+                        self.mark_deleted(msg_number);
+
+                        Ok(resp)
+                    }
+                    Some(Err(err)) => Err(err),
+                    _ => Err(Error::new(
+                        ErrorKind::UnexpectedResponse,
+                        "Did not received the expected dele response",
+                    )),
+                };
+
+                results.push((msg_number, result));
+            }
+        }
+
+        Ok(results)
     }
 
     /// ## RSET
@@ -389,7 +1314,7 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     pub async fn rset(&mut self) -> Result<Text> {
         let response = self.send_request(Rset).await?;
 
-        self.marked_as_del = Vec::new();
+        self.marked_as_del.clear();
 
         match response {
             Response::Message(resp) => Ok(resp),
@@ -423,8 +1348,10 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     /// println!("{}", subject);
     /// ```
     /// https://www.rfc-editor.org/rfc/rfc1939#page-8
-    pub async fn retr(&mut self, msg_number: usize) -> Result<Bytes> {
-        self.check_deleted(&msg_number)?;
+    pub async fn retr<N: Into<MessageNumber>>(&mut self, msg_number: N) -> Result<Bytes> {
+        let msg_number = msg_number.into();
+
+        self.check_msg_number(msg_number)?;
 
         let mut request: Request = Retr.into();
 
@@ -441,6 +1368,119 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         }
     }
 
+    /// ## RETR
+    /// Same as [Client::retr], but instead of buffering the entire message in memory, it returns a stream that yields the message in chunks as they arrive from the server.
+    ///
+    /// If the stream is dropped before it ends, the rest of the response is read and thrown away before the next command is sent.
+    /// # Examples
+    /// ```rust,ignore
+    /// use futures::TryStreamExt;
+    ///
+    /// let mut stream = client.retr_stream(1).await?;
+    ///
+    /// while let Some(chunk) = stream.try_next().await? {
+    ///     file.write_all(&chunk).await?;
+    /// }
+    /// ```
+    /// https://www.rfc-editor.org/rfc/rfc1939#page-8
+    pub async fn retr_stream<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + '_> {
+        let msg_number = msg_number.into();
+
+        self.check_msg_number(msg_number)?;
+
+        let mut request: Request = Retr.into();
+
+        request.add_arg(msg_number);
+
+        let stream = self.inner_mut()?;
+
+        stream.encode(&request).await?;
+
+        stream.read_status(request).await?;
+
+        Ok(stream.body_chunks())
+    }
+
+    /// ## RETR
+    /// Same as [Client::retr], but writes the message directly into the given writer as it arrives from the server, instead of buffering it in memory.
+    ///
+    /// Returns the amount of bytes that were written.
+    /// # Examples
+    /// ```rust,ignore
+    /// let mut file = async_std::fs::File::create("message.eml").await?;
+    ///
+    /// let size = client.retr_to(1, &mut file).await?;
+    /// ```
+    /// https://www.rfc-editor.org/rfc/rfc1939#page-8
+    pub async fn retr_to<N: Into<MessageNumber>, W: Write + Unpin>(
+        &mut self,
+        msg_number: N,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let mut stream = self.retr_stream(msg_number).await?;
+
+        let mut written = 0;
+
+        while let Some(chunk) = stream.try_next().await? {
+            writer.write_all(&chunk).await?;
+
+            written += chunk.len() as u64;
+        }
+
+        writer.flush().await?;
+
+        Ok(written)
+    }
+
+    /// ## RETR
+    /// Same as [Client::retr], but allows the message to be at most `max_size` bytes instead of the maximum response size of the client.
+    pub async fn retr_with_limit<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+        max_size: usize,
+    ) -> Result<Bytes> {
+        let previous = self.inner_mut()?.max_response_size();
+
+        self.set_max_response_size(max_size)?;
+
+        // Restores the previous limit when dropped, also when this future is cancelled halfway through.
+        let limited = ResponseLimit {
+            client: self,
+            previous,
+        };
+
+        limited.client.retr(msg_number).await
+    }
+
+    /// ## RETR
+    /// Same as [Client::retr], but once the message turns out to be larger than `threshold` bytes, it is written to a temporary file instead of being kept in memory.
+    ///
+    /// This allows the occasional huge message to be retrieved without running out of memory.
+    /// # Examples
+    /// ```rust,ignore
+    /// use async_pop::spill::SpilledMessage;
+    ///
+    /// match client.retr_spill(1, 10 * 1024 * 1024).await? {
+    ///     SpilledMessage::InMemory(bytes) => println!("{} bytes", bytes.len()),
+    ///     SpilledMessage::OnDisk(file) => println!("{:?}", file.metadata().await?.len()),
+    /// }
+    /// ```
+    #[cfg(feature = "spill")]
+    pub async fn retr_spill<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+        threshold: usize,
+    ) -> Result<spill::SpilledMessage> {
+        let chunks = self.retr_stream(msg_number).await?;
+
+        futures::pin_mut!(chunks);
+
+        spill::collect(chunks, threshold).await
+    }
+
     /// ## LIST
     ///
     /// If an argument was given and the POP3 server issues a positive response with a line containing information for that message.  This line is called a "scan listing" for that message.
@@ -448,17 +1488,20 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     /// If no argument was given and the POP3 server issues a positive response, then the response given is multi-line. After the initial +OK, for each message in the maildrop, the POP3 server responds with a line containing information for that message. This line is also called a "scan listing" for that message.  If there are no messages in the maildrop, then the POP3 server responds with no scan listings--it issues a positive response followed by a line containing a termination octet and a CRLF pair.
     ///
     /// ### Arguments:
-    /// - a message-number (optional), which, if present, may NOT refer to a message marked as deleted
+    /// - a message-number (optional), which, if present, may NOT refer to a message marked as deleted. Without one the type has to be spelled out, e.g. `None::<MessageNumber>`
     /// ### Restrictions:
     /// - may only be given in the TRANSACTION state
     /// ### Possible responses:
     /// - +OK scan listing follows
     /// - -ERR no such message
-    pub async fn list(&mut self, msg_number: Option<usize>) -> Result<ListResponse> {
-        let mut request: Request = List.into();
+    pub async fn list<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: Option<N>,
+    ) -> Result<ListResponse> {
+        let mut request: Request = List { listing: true }.into();
 
-        if let Some(msg_number) = msg_number {
-            self.check_deleted(&msg_number)?;
+        if let Some(msg_number) = msg_number.map(Into::into) {
+            self.check_msg_number(msg_number)?;
             request.add_arg(msg_number)
         }
 
@@ -474,6 +1517,25 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         }
     }
 
+    /// ## LIST
+    /// Same as [Client::list] without an argument, but yields the scan listings one by one as they arrive from the server.
+    ///
+    /// Only a single line of the listing is kept in memory at a time, so even huge maildrops can be listed with bounded memory.
+    ///
+    /// If the stream is dropped before it ends, the rest of the response is read and thrown away before the next command is sent.
+    /// https://www.rfc-editor.org/rfc/rfc1939#page-6
+    pub async fn list_stream(&mut self) -> Result<impl Stream<Item = Result<Stat>> + '_> {
+        let lines = self.read_listing(List { listing: true }.into()).await?;
+
+        Ok(lines.map(|line| match parser::scan_listing_line(&line?) {
+            Some(stat) => Ok(stat),
+            None => err!(
+                ErrorKind::InvalidResponse,
+                "The server sent an invalid scan listing"
+            ),
+        }))
+    }
+
     /// ## STAT
     /// The POP3 server issues a positive response with a line containing information for the maildrop. This line is called a "drop listing" for that maildrop.
     /// ### Arguments: none
@@ -484,8 +1546,15 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     pub async fn stat(&mut self) -> Result<Stat> {
         let response = self.send_request(Stat).await?;
 
-        match response.into() {
-            Response::Stat(resp) => Ok(resp),
+        match response {
+            Response::Stat(resp) => {
+                // Messages that are marked as deleted are left out of the count, but keep their number.
+                if let Ok(count) = usize::try_from(resp.counter()) {
+                    self.message_count = Some(count + self.marked_as_del.len());
+                }
+
+                Ok(resp)
+            }
             _ => err!(
                 ErrorKind::UnexpectedResponse,
                 "Did not received the expected stat response"
@@ -518,217 +1587,716 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     pub async fn apop<N: AsRef<str>, D: AsRef<str>>(&mut self, name: N, digest: D) -> Result<Text> {
         self.check_client_state(ClientState::Authentication)?;
 
-        self.has_read_greeting()?;
+        self.has_read_greeting()?;
+
+        self.reset_auth().await?;
+
+        self.negotiate_utf8(name.as_ref()).await?;
+
+        let mut request: Request = Apop.into();
+
+        request.add_arg(name.as_ref());
+        request.add_arg(digest.as_ref());
+
+        self.check_login_delay().await?;
+
+        self.auth_pending = true;
+
+        let response = self.send_request(request).await;
+
+        let response = self.track_auth(response, true)?;
+
+        self.logged_in().await?;
+
+        match response {
+            Response::Message(resp) => Ok(resp),
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected apop response"
+            ),
+        }
+    }
+
+    /// Logs in using [APOP](Client::apop), computing the digest from the timestamp in the greeting of the server and the given password.
+    ///
+    /// Fails with [ErrorKind::FeatureUnsupported] if the greeting does not contain a timestamp, as the server does not support APOP in that case.
+    /// # Examples:
+    /// ```rust,ignore
+    /// client.apop_login("mrose", "tanstaaf").await?;
+    /// ```
+    pub async fn apop_login<U: AsRef<str>, P: AsRef<str>>(
+        &mut self,
+        user: U,
+        password: P,
+    ) -> Result<Text> {
+        let timestamp = match self.greeting.as_ref().and_then(apop_timestamp) {
+            Some(timestamp) => timestamp,
+            None => err!(
+                ErrorKind::FeatureUnsupported,
+                "The greeting of the server does not contain a timestamp, so APOP is not supported"
+            ),
+        };
+
+        let mut hasher = Md5::new();
+
+        hasher.update(timestamp);
+        hasher.update(password.as_ref());
+
+        let digest: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        self.apop(user, digest).await
+    }
+
+    /// Whether the greeting of the server contains the timestamp that is needed to log in using [Client::apop].
+    pub fn supports_apop(&self) -> bool {
+        self.greeting.as_ref().and_then(apop_timestamp).is_some()
+    }
+
+    /// Logs in using the first mechanism of the strategy that the server supports and accepts, returning the mechanism that was used, see [AuthStrategy].
+    pub async fn authenticate(&mut self, strategy: &AuthStrategy) -> Result<AuthMechanism> {
+        self.check_client_state(ClientState::Authentication)?;
+
+        let (mechanism, _) = strategy.authenticate(self).await?;
+
+        Ok(mechanism)
+    }
+
+    pub fn has_auth_mechanism<M: AsRef<[u8]>>(&self, mechanism: M) -> bool {
+        self.capabilities.supports_sasl_mechanism(mechanism)
+    }
+
+    /// ### AUTH
+    ///
+    /// Requires an [sasl::Authenticator] to work. One could implement this themeselves for any given mechanism, look at the documentation for this trait.
+    ///
+    /// If a common mechanism is needed, it can probably be found in the [sasl] module.
+    ///
+    /// The AUTH command indicates an authentication mechanism to the server.  If the server supports the requested authentication mechanism, it performs an authentication protocol exchange to authenticate and identify the user. Optionally, it also negotiates a protection mechanism for subsequent protocol interactions.  If the requested authentication mechanism is not supported, the server should reject the AUTH command by sending a negative response.
+    ///
+    /// The authentication protocol exchange consists of a series of server challenges and client answers that are specific to the authentication mechanism.  A server challenge, otherwise known as a ready response, is a line consisting of a "+" character followed by a single space and a BASE64 encoded string.  The client answer consists of a line containing a BASE64 encoded string.  If the client wishes to cancel an authentication exchange, it should issue a line with a single "*".  If the server receives such an answer, it must reject the AUTH command by sending a negative response.
+    ///
+    /// A protection mechanism provides integrity and privacy protection to the protocol session.  If a protection mechanism is negotiated, it is applied to all subsequent data sent over the connection.  The protection mechanism takes effect immediately following the CRLF that concludes the authentication exchange for the client, and the CRLF of the positive response for the server.  Once the protection mechanism is in effect, the stream of command and response octets is processed into buffers of ciphertext.  Each buffer is transferred over the connection as a stream of octets prepended with a four octet field in network byte order that represents the length of the following data. The maximum ciphertext buffer length is defined by the protection mechanism.
+    ///
+    /// The server is not required to support any particular authentication mechanism, nor are authentication mechanisms required to support any protection mechanisms.  If an AUTH command fails with a negative response, the session remains in the AUTHORIZATION state and client may try another authentication mechanism by issuing another AUTH command, or may attempt to authenticate by using the USER/PASS or APOP commands.  In other words, the client may request authentication types in decreasing order of preference, with the USER/PASS or APOP command as a last resort.
+    #[cfg(feature = "sasl")]
+    pub async fn auth<A: sasl::Authenticator>(&mut self, mut authenticator: A) -> Result<Text> {
+        self.check_client_state(ClientState::Authentication)?;
+
+        self.has_read_greeting()?;
+
+        if authenticator.requires_tls() && !self.secure {
+            err!(
+                ErrorKind::FeatureUnsupported,
+                "The {} mechanism can only be used on a secure connection",
+                authenticator.mechanism()
+            )
+        }
+
+        self.check_security()?;
+
+        self.reset_auth().await?;
+
+        self.check_login_delay().await?;
+
+        let mut request: Request = Auth.into();
+
+        let mechanism = authenticator.mechanism();
+
+        request.add_arg(mechanism);
+
+        if let Some(arg) = authenticator.auth() {
+            // An empty initial response is sent as a single "=", see https://www.rfc-editor.org/rfc/rfc5034#section-4
+            if arg.is_empty() {
+                request.add_arg("=")
+            } else {
+                request.add_arg(crate::base64::encode(arg))
+            }
+        }
+
+        self.auth_pending = true;
+
+        let result = self.sasl_exchange(&mut authenticator, &request).await;
+
+        let message = self.track_auth(result, true)?;
+
+        self.logged_in().await?;
+
+        Ok(message)
+    }
+
+    #[cfg(feature = "sasl")]
+    async fn sasl_exchange<A: sasl::Authenticator>(
+        &mut self,
+        authenticator: &mut A,
+        request: &Request,
+    ) -> Result<Text> {
+        let stream = self.inner_mut()?;
+
+        stream.encode(request).await?;
+
+        // The server keeps sending challenges until it has decided whether the authentication succeeded.
+        loop {
+            match stream.read_response(Auth).await? {
+                Response::Challenge(challenge) => match authenticator.process(challenge.as_ref()) {
+                    Ok(answer) => {
+                        let answer: Request = Base64(answer).into();
+
+                        stream.encode(&answer).await?;
+                    }
+                    Err(err) => {
+                        // Cancel the exchange, to which the server has to respond with an error.
+                        stream.send_bytes("*").await?;
+
+                        match stream.read_response(Auth).await {
+                            Err(cancelled)
+                                if matches!(cancelled.kind(), ErrorKind::ServerError { .. }) => {}
+                            Err(cancelled) => return Err(cancelled),
+                            Ok(_) => err!(
+                                ErrorKind::UnexpectedResponse,
+                                "The server did not reject the cancelled authentication"
+                            ),
+                        }
+
+                        self.auth_pending = false;
+
+                        return Err(err);
+                    }
+                },
+                Response::Message(message) => return Ok(message),
+                _ => err!(
+                    ErrorKind::UnexpectedResponse,
+                    "Did not received the expected auth response"
+                ),
+            }
+        }
+    }
+
+    /// ## USER & PASS
+    ///
+    /// To authenticate using the USER and PASS command combination, the client must first issue the USER command. If the POP3 server responds with a positive status indicator ("+OK"), then the client may issue either the PASS command to complete the authentication, or the QUIT command to terminate the POP3 session.  If the POP3 server responds with a negative status indicator ("-ERR") to the USER command, then the client may either issue a new authentication command or may issue the QUIT command.
+    ///
+    /// The server may return a positive response even though no such mailbox exists. The server may return a negative response if mailbox exists, but does not permit plaintext password authentication.
+    ///
+    /// When the client issues the PASS command, the POP3 server uses the argument pair from the USER and PASS commands to determine if the client should be given access to the appropriate maildrop.
+    ///
+    /// Since the PASS command has exactly one argument, a POP3 server may treat spaces in the argument as part of the password, instead of as argument separators.
+    ///
+    /// ### Arguments:
+    /// -  a string identifying a mailbox (required), which is of significance ONLY to the server
+    /// -  a server/mailbox-specific password (required)
+    ///
+    /// ### Restrictions:
+    /// may only be given in the AUTHORIZATION state after the POP3 greeting or after an unsuccessful USER or PASS command
+    ///
+    /// ### Possible responses:
+    /// - +OK maildrop locked and ready
+    /// - -ERR invalid password
+    /// - -ERR unable to lock maildrop
+    /// - -ERR never heard of mailbox name
+    ///
+    /// Depending on the [LoginPolicy] of the client, a mechanism the server advertises is used instead of USER and PASS, such as SASL PLAIN on a secure connection.
+    /// The second text is empty in that case, as only USER and PASS take two commands. Use [Client::authenticate] to find out which mechanism was used.
+    pub async fn login<U: AsRef<str>, P: AsRef<str>>(
+        &mut self,
+        user: U,
+        password: P,
+    ) -> Result<(Text, Text)> {
+        self.check_client_state(ClientState::Authentication)?;
+
+        // Every mechanism checks the security policy and the login delay by itself.
+        let strategy = AuthStrategy::new(user.as_ref(), password.as_ref())
+            .with_preference(self.login_policy.preference());
+
+        let (mechanism, responses) = strategy.try_supported(self).await?;
+
+        debug!("Logged in using {}", mechanism);
+
+        Ok(responses)
+    }
+
+    /// Same as [Client::login], but only using the USER and PASS commands, even if the server supports SASL PLAIN.
+    pub(crate) async fn user_pass(&mut self, user: &str, password: &str) -> Result<(Text, Text)> {
+        self.check_client_state(ClientState::Authentication)?;
+
+        self.check_security()?;
+
+        self.reset_auth().await?;
+
+        self.check_login_delay().await?;
+
+        self.has_read_greeting()?;
+
+        let advertised = self.check_user_capability()?;
+
+        self.negotiate_utf8(user).await?;
+        self.negotiate_utf8(password).await?;
+
+        let mut request: Request = User.into();
+
+        request.add_arg(user);
+
+        self.auth_pending = true;
+
+        let user_response = match self.send_request(request).await {
+            // Without a response code, a server that never advertised USER most likely rejected the command itself rather than the user.
+            Err(err)
+                if !advertised
+                    && err.response_code().is_none()
+                    && matches!(err.kind(), ErrorKind::ServerError { .. }) =>
+            {
+                self.auth_pending = false;
+
+                err!(
+                    ErrorKind::FeatureUnsupported,
+                    "The server does not advertise the USER capability and rejected the USER command: {}",
+                    err.message()
+                )
+            }
+            result => result,
+        };
+
+        let user_response = self.track_auth(user_response, false)?;
+
+        let mut request: Request = Pass.into();
+
+        request.add_arg(password);
+
+        let pass_response = self.send_request(request).await;
+
+        let pass_response = self.track_auth(pass_response, true)?;
+
+        self.logged_in().await?;
+
+        let user_response_str = match user_response {
+            Response::Message(resp) => resp,
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected user response"
+            ),
+        };
+
+        let pass_response_str = match pass_response {
+            Response::Message(resp) => resp,
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected pass response"
+            ),
+        };
+
+        Ok((user_response_str, pass_response_str))
+    }
+
+    /// Returns to a clean Authentication state after a login attempt that never got its final answer, so that another attempt can be made, e.g. using different credentials.
+    ///
+    /// A login that was rejected by the server needs no cleaning up, but one whose future was dropped halfway may leave the server waiting for the rest of it,
+    /// such as the answer to a SASL challenge or the PASS that should follow USER. The unfinished exchange is cancelled and the server is asked which state it is in.
+    /// In the unlikely case that the attempt succeeded after all, the client moves to the Transaction state, see [Client::get_state].
+    ///
+    /// [Client::login], [Client::apop] and [Client::auth] do this by themselves before every attempt, so they can simply be called again after a failure.
+    /// Each attempt respects the LOGIN-DELAY of the server, including a delay it asked for when rejecting an earlier attempt, see [LoginDelayPolicy].
+    ///
+    /// POP3 has no way to log out without ending the session, so once logged in this fails with [ErrorKind::IncorrectStateForCommand].
+    /// To log in as someone else, end the session using [Client::quit] and connect again.
+    /// # Examples
+    /// ```rust,ignore
+    /// if client.login("user", "wrong password").await.is_err() {
+    ///     client.login("user", "right password").await?;
+    /// }
+    /// ```
+    pub async fn reset_auth(&mut self) -> Result<()> {
+        self.check_client_state(ClientState::Authentication)?;
+
+        if !self.auth_pending {
+            return Ok(());
+        }
+
+        debug!("Cancelling an unfinished login attempt");
 
-        let mut request: Request = Apop.into();
+        let stream = self.inner_mut()?;
 
-        request.add_arg(name.as_ref());
-        request.add_arg(digest.as_ref());
+        // A server that is waiting for the answer to a SASL challenge cancels the exchange, in any other state the line is rejected.
+        stream.send_bytes("*").await?;
 
-        let response = self.send_request(request).await?;
+        match stream.read_response(Auth).await {
+            Ok(_) => {}
+            Err(err) if matches!(err.kind(), ErrorKind::ServerError { .. }) => {}
+            Err(err) => return Err(err),
+        }
 
-        self.update_capabilities().await;
+        // NOOP is only allowed in the Transaction state, so its response tells whether the attempt went through.
+        match self.send_request(Noop).await {
+            Ok(_) => {
+                self.auth_pending = false;
 
-        self.state = ClientState::Transaction;
+                self.logged_in().await
+            }
+            Err(err) if matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                self.auth_pending = false;
 
-        match response {
-            Response::Message(resp) => Ok(resp),
-            _ => err!(
-                ErrorKind::UnexpectedResponse,
-                "Did not received the expected apop response"
-            ),
+                Ok(())
+            }
+            Err(err) => Err(err),
         }
     }
 
-    pub fn has_auth_mechanism<M: AsRef<[u8]>>(&self, mechanism: M) -> bool {
-        for capa in &self.capabilities {
-            match capa {
-                Capability::Sasl(supported_mechanisms) => {
-                    for supported_mechanism in supported_mechanisms {
-                        if supported_mechanism.to_ascii_lowercase()
-                            == mechanism.as_ref().to_ascii_lowercase()
-                        {
-                            return true;
-                        }
+    /// Makes sure logging in using USER and PASS is allowed by the [CapabilityCheck], returning whether the server advertised the USER capability.
+    ///
+    /// Lazy capabilities are not fetched just for this, and a server that does not support CAPA can not advertise USER, so both are given the benefit of the doubt.
+    fn check_user_capability(&self) -> Result<bool> {
+        if self.has_capability([Capability::User]) {
+            return Ok(true);
+        }
+
+        let known = self.capabilities_fetched && self.capa_supported;
+
+        if known && self.capability_check == CapabilityCheck::Strict {
+            err!(
+                ErrorKind::FeatureUnsupported,
+                "The server does not advertise the USER capability, use CapabilityCheck::TrustServer to log in using USER and PASS anyway"
+            )
+        }
+
+        Ok(false)
+    }
+
+    /// Keeps track of whether the server is done with the current login attempt, given the result of one of its steps.
+    fn track_auth<T>(&mut self, result: Result<T>, last_step: bool) -> Result<T> {
+        match &result {
+            Ok(_) => {
+                if last_step {
+                    self.auth_pending = false;
+                }
+            }
+            Err(err) if matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                self.auth_pending = false;
+
+                // The server wants some time to pass before the next attempt, which is only known if it advertised its login delay.
+                if err.response_code() == Some(&ResponseCode::LoginDelay) {
+                    if let Some(delay) = self.capabilities.login_delay() {
+                        login_delay::record(self.server.as_deref(), Some(delay));
                     }
                 }
-                _ => {}
             }
+            Err(_) => {}
         }
 
-        false
+        result
     }
 
-    /// ### AUTH
+    /// ## STLS
     ///
-    /// Requires an [sasl::Authenticator] to work. One could implement this themeselves for any given mechanism, look at the documentation for this trait.
+    /// Upgrades the current plain connection to a secure one, as specified in [RFC 2595](https://www.rfc-editor.org/rfc/rfc2595#section-4).
     ///
-    /// If a common mechanism is needed, it can probably be found in the [sasl] module.
+    /// After the server responds positively, a tls handshake is performed using the given connector and the capabilities are fetched again, as they may differ on the secure connection.
     ///
-    /// The AUTH command indicates an authentication mechanism to the server.  If the server supports the requested authentication mechanism, it performs an authentication protocol exchange to authenticate and identify the user. Optionally, it also negotiates a protection mechanism for subsequent protocol interactions.  If the requested authentication mechanism is not supported, the server should reject the AUTH command by sending a negative response.
+    /// ### Arguments: none
     ///
-    /// The authentication protocol exchange consists of a series of server challenges and client answers that are specific to the authentication mechanism.  A server challenge, otherwise known as a ready response, is a line consisting of a "+" character followed by a single space and a BASE64 encoded string.  The client answer consists of a line containing a BASE64 encoded string.  If the client wishes to cancel an authentication exchange, it should issue a line with a single "*".  If the server receives such an answer, it must reject the AUTH command by sending a negative response.
+    /// ### Restrictions:
+    /// - Only permitted in AUTHORIZATION state
     ///
-    /// A protection mechanism provides integrity and privacy protection to the protocol session.  If a protection mechanism is negotiated, it is applied to all subsequent data sent over the connection.  The protection mechanism takes effect immediately following the CRLF that concludes the authentication exchange for the client, and the CRLF of the positive response for the server.  Once the protection mechanism is in effect, the stream of command and response octets is processed into buffers of ciphertext.  Each buffer is transferred over the connection as a stream of octets prepended with a four octet field in network byte order that represents the length of the following data. The maximum ciphertext buffer length is defined by the protection mechanism.
+    /// ### Possible Responses:
+    /// - +OK
+    /// - -ERR
+    /// # Examples:
+    /// ```rust,ignore
+    /// let tls = async_native_tls::TlsConnector::new();
     ///
-    /// The server is not required to support any particular authentication mechanism, nor are authentication mechanisms required to support any protection mechanisms.  If an AUTH command fails with a negative response, the session remains in the AUTHORIZATION state and client may try another authentication mechanism by issuing another AUTH command, or may attempt to authenticate by using the USER/PASS or APOP commands.  In other words, the client may request authentication types in decreasing order of preference, with the USER/PASS or APOP command as a last resort.
-    #[cfg(feature = "sasl")]
-    pub async fn auth<A: sasl::Authenticator + Sync>(&mut self, authenticator: A) -> Result<Text> {
+    /// let client = async_pop::connect_plain(("pop.gmail.com", 110)).await?;
+    ///
+    /// let mut client = client.stls("pop.gmail.com", &tls).await?;
+    ///
+    /// client.login("example@gmail.com", "password").await?;
+    /// ```
+    #[cfg(feature = "tls")]
+    pub async fn stls<'a, D: AsRef<str>, C: Into<tls::TlsConnector<'a>>>(
+        mut self,
+        domain: D,
+        tls: C,
+    ) -> Result<Client<impl tls::TlsStream<S>>> {
         self.check_client_state(ClientState::Authentication)?;
 
-        self.has_read_greeting()?;
+        self.check_capability(vec![Capability::Stls]).await?;
 
-        let mut request: Request = Auth.into();
+        if let Err(err) = self.send_request(Stls).await {
+            // A server that advertised STLS has no reason to refuse it, so someone may be trying to keep the connection in plain text.
+            if self.security_policy != SecurityPolicy::None
+                && self.has_capability([Capability::Stls])
+                && matches!(err.kind(), ErrorKind::ServerError { .. })
+            {
+                err!(
+                    ErrorKind::Downgrade,
+                    "The server advertised STLS but refused to upgrade the connection: {}",
+                    err.message()
+                )
+            }
 
-        let mechanism = authenticator.mechanism();
+            return Err(err);
+        }
 
-        request.add_arg(mechanism);
+        let mut socket = match self.inner.take() {
+            Some(socket) => socket,
+            None => err!(ErrorKind::NotConnected, "Not connected to any server"),
+        };
 
-        if let Some(arg) = authenticator.auth() {
-            request.add_arg(crate::base64::encode(arg))
-        }
+        let mode = socket.parse_mode();
+        let max_response_size = socket.max_response_size();
+        let raw_response_limit = socket.raw_response_limit();
+        let wire_log = socket.take_wire_log();
+        let (read_buffer_size, buffer_growth) = socket.read_buffer();
+        let (read_limit, write_limit) = socket.rate_limits();
+        let metrics = socket.metrics().clone();
+        let interceptors = socket.interceptors().to_vec();
 
-        let stream = self.inner_mut()?;
+        let tls_connector: tls::TlsConnector<'a> = tls.into();
 
-        stream.encode(&request).await?;
+        let tls_stream = tls_connector.connect(domain, socket.into_inner()?).await?;
 
-        let communicator = sasl::Communicator::new(stream);
+        let mut socket = PopStream::new(tls_stream);
 
-        authenticator.handle(communicator).await?;
+        socket.set_parse_mode(mode);
+        socket.set_max_response_size(max_response_size);
+        socket.set_raw_response_limit(raw_response_limit);
+        socket.set_wire_log(wire_log);
+        socket.set_read_buffer(read_buffer_size, buffer_growth);
+        socket.set_timer(self.timer.clone());
+        socket.set_rate_limits(read_limit, write_limit);
+        socket.set_metrics(metrics);
 
-        let message = match stream.read_response(request).await? {
-            Response::Message(message) => message,
-            _ => err!(
-                ErrorKind::UnexpectedResponse,
-                "Did not received the expected auith response"
-            ),
-        };
+        for interceptor in interceptors {
+            socket.add_interceptor(interceptor);
+        }
 
-        self.update_capabilities().await;
+        let mut client = Client {
+            marked_as_del: Vec::new(),
+            message_count: None,
+            capabilities: Capabilities::new(),
+            capa_supported: true,
+            capabilities_fetched: false,
+            lazy_capabilities: self.lazy_capabilities,
+            capability_check: self.capability_check,
+            utf8: self.utf8,
+            uid_cache: None,
+            greeting: self.greeting,
+            read_greeting: self.read_greeting,
+            secure: true,
+            server: self.server,
+            login_delay_policy: self.login_delay_policy,
+            login_policy: self.login_policy,
+            security_policy: self.security_policy,
+            inner: Some(socket),
+            state: ClientState::Authentication,
+            auth_pending: false,
+            timer: self.timer,
+        };
 
-        self.state = ClientState::Transaction;
+        if !client.lazy_capabilities {
+            client.update_capabilities().await?;
+        }
 
-        Ok(message)
+        Ok(client)
     }
 
-    /// ## USER & PASS
-    ///
-    /// To authenticate using the USER and PASS command combination, the client must first issue the USER command. If the POP3 server responds with a positive status indicator ("+OK"), then the client may issue either the PASS command to complete the authentication, or the QUIT command to terminate the POP3 session.  If the POP3 server responds with a negative status indicator ("-ERR") to the USER command, then the client may either issue a new authentication command or may issue the QUIT command.
-    ///
-    /// The server may return a positive response even though no such mailbox exists. The server may return a negative response if mailbox exists, but does not permit plaintext password authentication.
-    ///
-    /// When the client issues the PASS command, the POP3 server uses the argument pair from the USER and PASS commands to determine if the client should be given access to the appropriate maildrop.
-    ///
-    /// Since the PASS command has exactly one argument, a POP3 server may treat spaces in the argument as part of the password, instead of as argument separators.
-    ///
-    /// ### Arguments:
-    /// -  a string identifying a mailbox (required), which is of significance ONLY to the server
-    /// -  a server/mailbox-specific password (required)
-    ///
+    /// ## UTF8
+    /// Enables UTF-8 mode, after which the server may send UTF-8 in its responses and, if it advertised `UTF8 USER`, accepts UTF-8 usernames and passwords.
+    /// ### Arguments: none
     /// ### Restrictions:
-    /// may only be given in the AUTHORIZATION state after the POP3 greeting or after an unsuccessful USER or PASS command
-    ///
+    /// May only be given in the AUTHORIZATION state.
     /// ### Possible responses:
-    /// - +OK maildrop locked and ready
-    /// - -ERR invalid password
-    /// - -ERR unable to lock maildrop
-    /// - -ERR never heard of mailbox name
-    pub async fn login<U: AsRef<str>, P: AsRef<str>>(
-        &mut self,
-        user: U,
-        password: P,
-    ) -> Result<(Text, Text)> {
+    /// - +OK UTF8 enabled
+    /// - -ERR UTF8 not supported
+    ///
+    /// See [RFC 6856](https://www.rfc-editor.org/rfc/rfc6856#section-2) for more info.
+    pub async fn utf8(&mut self) -> Result<Text> {
         self.check_client_state(ClientState::Authentication)?;
 
-        if self.has_auth_mechanism("PLAIN") {
-            let plain_auth = PlainAuthenticator::new(user.as_ref(), password.as_ref());
-
-            if let Ok(text) = self.auth(plain_auth).await {
-                return Ok((text, Bytes::new().into()));
-            }
-        }
-
-        self.has_read_greeting()?;
-
-        let mut request: Request = User.into();
+        self.check_supported(Capabilities::supports_utf8).await?;
 
-        request.add_arg(user.as_ref());
+        let response = self.send_request(Utf8).await?;
 
-        let user_response = self.send_request(request).await?;
+        self.utf8 = true;
 
-        let mut request: Request = Pass.into();
+        match response {
+            Response::Message(resp) => Ok(resp),
+            _ => err!(
+                ErrorKind::UnexpectedResponse,
+                "Did not received the expected utf8 response"
+            ),
+        }
+    }
 
-        request.add_arg(password.as_ref());
+    /// ## LANG
+    /// Changes the language the server uses for the text in its responses, `*` selects the default language of the server, or one based on the preferences of the user.
+    /// ### Arguments:
+    /// - a language tag, e.g. `en` or `de`
+    /// ### Restrictions:
+    /// None, may be given in any state.
+    /// ### Possible responses:
+    /// - +OK language changed
+    /// - -ERR invalid language
+    ///
+    /// See [RFC 6856](https://www.rfc-editor.org/rfc/rfc6856#section-3) for more info.
+    pub async fn lang<T: AsRef<str>>(&mut self, tag: T) -> Result<Text> {
+        self.check_supported(Capabilities::supports_lang).await?;
 
-        let pass_response = self.send_request(request).await?;
+        let mut request: Request = Lang { list: false }.into();
 
-        self.update_capabilities().await;
+        request.add_arg(tag.as_ref());
 
-        self.state = ClientState::Transaction;
+        let response = self.send_request(request).await?;
 
-        let user_response_str = match user_response {
-            Response::Message(resp) => resp,
+        match response {
+            Response::Message(resp) => Ok(resp),
             _ => err!(
                 ErrorKind::UnexpectedResponse,
-                "Did not received the expected user response"
+                "Did not received the expected lang response"
             ),
-        };
+        }
+    }
 
-        let pass_response_str = match pass_response {
-            Response::Message(resp) => resp,
+    /// ## LANG
+    /// Lists the languages the server supports for the text in its responses, see [Client::lang].
+    pub async fn lang_list(&mut self) -> Result<Vec<Language>> {
+        self.check_supported(Capabilities::supports_lang).await?;
+
+        let response = self.send_request(Lang { list: true }).await?;
+
+        match response {
+            Response::Languages(languages) => Ok(languages),
             _ => err!(
                 ErrorKind::UnexpectedResponse,
-                "Did not received the expected pass response"
+                "Did not received the expected lang response"
             ),
-        };
+        }
+    }
 
-        Ok((user_response_str, pass_response_str))
+    /// Whether UTF-8 mode was enabled using [Client::utf8].
+    pub fn is_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    /// Enable UTF-8 mode before sending a credential that is not plain ASCII, if the server allows UTF-8 credentials.
+    async fn negotiate_utf8(&mut self, credential: &str) -> Result<()> {
+        if !self.utf8 && !credential.is_ascii() && self.capabilities.supports_utf8_user() {
+            self.utf8().await?;
+        }
+
+        Ok(())
     }
 
     /// ## QUIT
     /// Quits the session
     ///
+    /// When logged in, the server enters the UPDATE state and removes all of the messages that were marked as deleted.
+    /// A negative response means the server failed to remove some of them, which is reported in the [QuitSummary] instead of as an error, as the session ends either way.
+    ///
     /// ### Arguments: none
     ///
     /// ### Restrictions: none
     ///
     /// ### Possible Responses:
     /// - +OK
+    /// - -ERR some deleted messages not removed
     ///
     /// https://www.rfc-editor.org/rfc/rfc1939#page-5
-    pub async fn quit(&mut self) -> Result<Text> {
-        let response = self.send_request(Quit).await?;
+    pub async fn quit(&mut self) -> Result<QuitSummary> {
+        let messages_marked = self.marked_as_del.len();
 
-        self.state = ClientState::Update;
-        self.inner = None;
-        self.state = ClientState::None;
-        self.read_greeting = false;
+        let response = self.send_request(Quit).await;
 
-        self.marked_as_del.clear();
-        self.capabilities.clear();
+        if self.state == ClientState::Transaction {
+            self.state = ClientState::Update;
+        }
+
+        self.disconnect();
 
         match response {
-            Response::Message(resp) => Ok(resp),
-            _ => err!(
+            Ok(Response::Message(resp)) => Ok(QuitSummary::new(true, messages_marked, resp)),
+            Ok(_) => err!(
                 ErrorKind::UnexpectedResponse,
                 "Did not received the expected quit response"
             ),
+            Err(err) => match err.kind() {
+                ErrorKind::ServerError { .. } => {
+                    Ok(QuitSummary::new(false, messages_marked, err.message()))
+                }
+                _ => Err(err),
+            },
         }
     }
 
-    /// Check whether the server supports one of the given capabilities.
-    pub fn has_capability<C: AsRef<[Capability]>>(&mut self, capabilities: C) -> bool {
-        let to_find: HashSet<_> = capabilities.as_ref().iter().collect();
-        let server_has: HashSet<_> = self.capabilities.iter().collect();
+    /// Ends the session using [Client::quit], but gives up once the given amount of time has passed or if quitting failed, in which case the connection is closed without waiting for the server and `None` is returned.
+    ///
+    /// Use this on shutdown paths that must not hang on a misbehaving server.
+    /// Note that the server may not remove the messages that were marked as deleted when the connection is closed forcefully.
+    pub async fn close(&mut self, timeout: Duration) -> Option<QuitSummary> {
+        let timer = self.timer.clone();
+
+        let result = with_timeout(
+            &*timer,
+            Some(timeout),
+            Some(Quit),
+            "Timed out while waiting for the server to end the session",
+            self.quit(),
+        )
+        .await;
+
+        match result {
+            Ok(summary) => Some(summary),
+            Err(err) => {
+                debug!("Closing the connection forcefully: {}", err);
+
+                self.disconnect();
+
+                None
+            }
+        }
+    }
+
+    /// Drop the connection and forget everything about the session.
+    fn disconnect(&mut self) {
+        self.inner = None;
+        self.state = ClientState::None;
+        self.read_greeting = false;
+
+        self.marked_as_del.clear();
+        self.capabilities = Capabilities::new();
+        self.uid_cache = None;
+    }
 
-        let intersect: Vec<_> = server_has.intersection(&to_find).collect();
+    /// Check whether the server supports all of the given capabilities.
+    pub fn has_capability<C: AsRef<[Capability]>>(&self, capabilities: C) -> bool {
+        capabilities
+            .as_ref()
+            .iter()
+            .all(|capability| self.capabilities.contains(capability))
+    }
 
-        intersect.len() == capabilities.as_ref().len()
+    /// Make sure the given capabilities are present, fetching the capabilities first if that was deferred.
+    ///
+    /// Always succeeds when the [CapabilityCheck] is [CapabilityCheck::TrustServer], in which case a server without the capability responds with [ErrorKind::ServerError].
+    async fn check_capability<C: AsRef<[Capability]>>(&mut self, capability: C) -> Result<()> {
+        self.check_supported(|capabilities| {
+            capability
+                .as_ref()
+                .iter()
+                .all(|capability| capabilities.contains(capability))
+        })
+        .await
     }
 
-    /// Make sure the given capabilities are present
-    fn check_capability<C: AsRef<[Capability]>>(&mut self, capability: C) -> Result<()> {
-        if !self.has_capability(capability) {
+    /// Same as [Client::check_capability], but for capabilities that can have any parameters.
+    async fn check_supported<F: Fn(&Capabilities) -> bool>(&mut self, supported: F) -> Result<()> {
+        if self.capability_check == CapabilityCheck::TrustServer {
+            return Ok(());
+        }
+
+        self.ensure_capabilities().await?;
+
+        if !supported(&self.capabilities) {
             err!(
                 ErrorKind::FeatureUnsupported,
                 "The remote pop server does not support this command/function",
@@ -739,6 +2307,8 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     }
 
     /// Returns the current list of capabilities given by the server.
+    ///
+    /// This is empty until the capabilities are fetched when [crate::ClientBuilder::lazy_capabilities] is enabled.
     pub fn capabilities(&self) -> &Capabilities {
         &self.capabilities
     }
@@ -747,7 +2317,7 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
     pub async fn capa(&mut self) -> Result<Capabilities> {
         let response = self.send_request(Capa).await?;
 
-        match response.into() {
+        match response {
             Response::Capability(resp) => Ok(resp),
             _ => err!(
                 ErrorKind::UnexpectedResponse,
@@ -756,23 +2326,148 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         }
     }
 
-    async fn update_capabilities(&mut self) {
-        if let Ok(capabilities) = self.capa().await {
-            self.capabilities = capabilities
+    /// Fetches the capabilities again and returns how they changed compared to the ones that were known.
+    ///
+    /// The capabilities of a server may change after STLS and after logging in, e.g. some servers only advertise PIPELINING in the transaction state.
+    pub async fn refresh_capabilities(&mut self) -> Result<CapabilityChanges> {
+        let capabilities = self.capa().await?;
+
+        let changes = self.capabilities.changes_to(&capabilities);
+
+        self.capabilities = capabilities;
+        self.capa_supported = true;
+        self.capabilities_fetched = true;
+
+        Ok(changes)
+    }
+
+    /// Whether the server supports the CAPA command, servers that do not implement [RFC 2449](https://www.rfc-editor.org/rfc/rfc2449) will not have any capabilities.
+    pub fn supports_capa(&self) -> bool {
+        self.capa_supported
+    }
+
+    /// Fetch the capabilities if that did not happen yet.
+    pub(crate) async fn ensure_capabilities(&mut self) -> Result<()> {
+        if !self.capabilities_fetched {
+            self.update_capabilities().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the capabilities, treating a negative response as an empty set of capabilities as not every server supports CAPA.
+    async fn update_capabilities(&mut self) -> Result<()> {
+        match self.capa().await {
+            Ok(capabilities) => {
+                self.capabilities = capabilities;
+                self.capa_supported = true;
+            }
+            Err(err) if matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                debug!("Server does not support CAPA: {}", err);
+
+                self.capabilities = Capabilities::new();
+                self.capa_supported = false;
+            }
+            Err(err) => return Err(err),
+        }
+
+        self.capabilities_fetched = true;
+
+        Ok(())
+    }
+
+    /// Sends a command that is not natively supported by this crate, such as `XTND` or `LAST`, and returns the raw response.
+    ///
+    /// If `multiline` is true, the response is read as a multi-line response and returned as [Response::Bytes], otherwise the status text is returned as [Response::Message].
+    /// # Examples
+    /// ```rust,ignore
+    /// let response = client.send_custom("LAST", &Vec::<String>::new(), false).await?;
+    ///
+    /// if let Response::Message(text) = response {
+    ///     println!("{}", text);
+    /// }
+    /// ```
+    pub async fn send_custom<C: AsRef<str>, A: std::fmt::Display>(
+        &mut self,
+        command: C,
+        args: &[A],
+        multiline: bool,
+    ) -> Result<Response> {
+        let name = command.as_ref();
+
+        if name.is_empty() || !name.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+            err!(
+                ErrorKind::ParseCommand,
+                "'{}' is not a valid name for a POP command",
+                name
+            )
         }
+
+        let command = Custom {
+            name: name.to_string(),
+            multiline,
+        };
+
+        self.send_request(Request::new(command, args)).await
+    }
+
+    /// Hand the client over to a background task that sends a NOOP whenever the connection has been idle for the given amount of time.
+    ///
+    /// See [KeepAlive] for more info.
+    #[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
+    pub fn keep_alive(self, idle: Duration) -> KeepAlive<S>
+    where
+        S: 'static,
+    {
+        KeepAlive::new(self, idle)
+    }
+
+    /// Turn the client into a handle that can be cloned and used from multiple tasks at the same time.
+    ///
+    /// See [SharedClient] for more info.
+    pub fn shared(self) -> SharedClient<S> {
+        SharedClient::new(self)
+    }
+
+    /// Creates a [Pipeline] to send multiple commands at once.
+    pub fn pipeline(&mut self) -> Pipeline<'_, S> {
+        Pipeline::new(self)
     }
 
     /// Sends a valid Pop3 command and returns the response sent by the server.
+    ///
+    /// With the `tracing` feature enabled, every request runs inside of a span carrying the command name and message number.
     pub async fn send_request<R: Into<Request>>(&mut self, request: R) -> Result<Response> {
-        let request = request.into();
+        self.exchange(request.into(), true).await
+    }
+
+    /// Reads the response to a request that was already written as part of a pipeline, in the same span as [Client::send_request].
+    pub(crate) async fn read_pipelined(&mut self, request: Request) -> Result<Response> {
+        self.exchange(request, false).await
+    }
+
+    async fn exchange(&mut self, request: Request, send: bool) -> Result<Response> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "pop3_command",
+            command = %request.command().name(),
+            message = request.message_number(),
+        );
 
         let stream = self.inner_mut()?;
 
-        stream.encode(&request).await?;
+        let exchange = async {
+            if send {
+                stream.encode(&request).await?;
+            }
+
+            stream.read_response(request).await
+        };
 
-        let response = stream.read_response(request).await?;
+        #[cfg(feature = "tracing")]
+        let exchange = tracing::Instrument::instrument(exchange, span);
 
-        Ok(response)
+        exchange.await
     }
 
     fn has_read_greeting(&self) -> Result<()> {
@@ -807,11 +2502,94 @@ impl<S: Read + Write + Unpin + Send> Client<S> {
         }
     }
 
+    /// Make sure credentials may be sent over the connection, according to the [SecurityPolicy].
+    fn check_security(&self) -> Result<()> {
+        if self.secure {
+            return Ok(());
+        }
+
+        match self.security_policy {
+            SecurityPolicy::None => Ok(()),
+            SecurityPolicy::Required => err!(
+                ErrorKind::Insecure,
+                "Refusing to send credentials over a plain connection, upgrade it using STLS first"
+            ),
+            // Lazy capabilities are not fetched just for this, so only the capabilities that are already known count.
+            SecurityPolicy::Opportunistic => {
+                if self.has_capability([Capability::Stls]) {
+                    err!(
+                        ErrorKind::Insecure,
+                        "The server supports STLS, upgrade the connection before sending credentials"
+                    )
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    async fn check_login_delay(&self) -> Result<()> {
+        login_delay::check(
+            &*self.timer,
+            self.server.as_deref(),
+            self.login_delay_policy,
+        )
+        .await
+    }
+
+    /// Move to the transaction state after successfully logging in, refreshing the capabilities as they may have changed.
+    async fn logged_in(&mut self) -> Result<()> {
+        if self.lazy_capabilities {
+            self.capabilities_fetched = false;
+        } else {
+            self.update_capabilities().await?;
+        }
+
+        self.state = ClientState::Transaction;
+
+        login_delay::record(self.server.as_deref(), self.capabilities.login_delay());
+
+        Ok(())
+    }
+
     /// The greeting that the POP server sent when the connection opened.
     pub fn greeting(&self) -> Option<&Text> {
         self.greeting.as_ref()
     }
 }
 
-#[cfg(test)]
+/// Restores the maximum response size of the client when dropped, see [Client::retr_with_limit].
+struct ResponseLimit<'a, S: Read + Write + Unpin + Send> {
+    client: &'a mut Client<S>,
+    previous: usize,
+}
+
+impl<'a, S: Read + Write + Unpin + Send> Drop for ResponseLimit<'a, S> {
+    fn drop(&mut self) {
+        if let Some(socket) = self.client.inner.as_mut() {
+            socket.set_max_response_size(self.previous);
+        }
+    }
+}
+
+/// Parses a message that was fetched from the server, taking ownership of its data.
+#[cfg(feature = "mail-parser")]
+fn parse_message(message: &[u8]) -> Result<mail_parser::Message<'static>> {
+    match mail_parser::MessageParser::default().parse(message) {
+        Some(message) => Ok(message.into_owned()),
+        None => err!(ErrorKind::ParseMessage, "Failed to parse the message"),
+    }
+}
+
+/// Finds the timestamp (including the angle brackets) that a server supporting APOP puts in its greeting.
+fn apop_timestamp(greeting: &Text) -> Option<Vec<u8>> {
+    let greeting = greeting.as_ref();
+
+    let start = greeting.iter().position(|byte| *byte == b'<')?;
+    let end = start + greeting[start..].iter().position(|byte| *byte == b'>')?;
+
+    Some(greeting[start..=end].to_vec())
+}
+
+#[cfg(all(test, any(feature = "runtime-async-std", feature = "runtime-tokio")))]
 mod test;
@@ -0,0 +1,82 @@
+//! Keeps track of when the next login to a server is allowed, based on the LOGIN-DELAY capability ([RFC 2449](https://www.rfc-editor.org/rfc/rfc2449#section-6.5)).
+//!
+//! Servers that advertise a login delay may lock out clients that log in more often than that. The time of the last login is tracked per server for the whole process, so the delay is respected across clients.
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use crate::macros::debug;
+
+use crate::{
+    error::{err, ErrorKind, Result},
+    runtime::Instant,
+    timer::Timer,
+};
+
+/// What to do when logging in before the login delay of the server has passed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoginDelayPolicy {
+    /// Fail with [ErrorKind::LoginDelay], which contains the remaining time.
+    #[default]
+    Error,
+    /// Wait until the delay has passed and then log in.
+    Wait,
+    /// Log in anyway, risking a lockout.
+    Ignore,
+}
+
+static NEXT_LOGIN: Mutex<Option<HashMap<String, Instant>>> = Mutex::new(None);
+
+/// The amount of time that has to pass before the given server may be logged in to again.
+fn remaining(server: &str) -> Option<Duration> {
+    let next_logins = NEXT_LOGIN.lock().unwrap();
+
+    let next_login = next_logins.as_ref()?.get(server)?;
+
+    next_login
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+}
+
+/// Make sure it is allowed to log in to the given server, according to the policy.
+pub(crate) async fn check(
+    timer: &dyn Timer,
+    server: Option<&str>,
+    policy: LoginDelayPolicy,
+) -> Result<()> {
+    let remaining = match server.and_then(remaining) {
+        Some(remaining) => remaining,
+        None => return Ok(()),
+    };
+
+    match policy {
+        LoginDelayPolicy::Error => err!(
+            ErrorKind::LoginDelay(remaining),
+            "The server only allows logging in again after {} seconds",
+            remaining.as_secs()
+        ),
+        LoginDelayPolicy::Wait => {
+            debug!("Waiting {:?} for the login delay to pass", remaining);
+
+            timer.sleep(remaining).await;
+
+            Ok(())
+        }
+        LoginDelayPolicy::Ignore => Ok(()),
+    }
+}
+
+/// Record a successful login to the given server, which advertised the given login delay.
+pub(crate) fn record(server: Option<&str>, delay: Option<Duration>) {
+    let server = match server {
+        Some(server) => server,
+        None => return,
+    };
+
+    let mut next_logins = NEXT_LOGIN.lock().unwrap();
+
+    let next_logins = next_logins.get_or_insert_with(HashMap::new);
+
+    match delay {
+        Some(delay) => next_logins.insert(server.to_string(), Instant::now() + delay),
+        None => next_logins.remove(server),
+    };
+}
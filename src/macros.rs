@@ -1,20 +1,84 @@
-macro_rules! collection {
-    // map-like
-    ($($k:expr => $v:expr),* $(,)?) => {{
-        core::convert::From::from([$(($k.into(), $v),)*])
-    }};
-    // set-like
-    ($($v:expr),* $(,)?) => {{
-        core::convert::From::from([$($v,)*])
-    }};
+use crate::constants::MAX_LOGGED_BYTES;
+
+/// Displays raw protocol data in a form that is safe to log, see [escaped].
+pub(crate) struct Escaped<'a> {
+    bytes: &'a [u8],
+    limit: usize,
+}
+
+/// Formats bytes sent to or received from the server for the logs.
+///
+/// Line breaks, control characters and anything outside of printable ASCII are escaped, so binary or non UTF-8 data can be logged without panicking or garbling the output.
+/// Only the first [MAX_LOGGED_BYTES] bytes are shown, as a message can be many megabytes large.
+/// The formatting only happens once the value is actually logged.
+pub(crate) fn escaped(bytes: &[u8]) -> Escaped<'_> {
+    Escaped {
+        bytes,
+        limit: MAX_LOGGED_BYTES,
+    }
+}
+
+/// Same as [escaped], but without a limit on the length.
+pub(crate) fn escaped_all(bytes: &[u8]) -> Escaped<'_> {
+    Escaped {
+        bytes,
+        limit: usize::MAX,
+    }
 }
 
-pub(crate) use collection;
+impl std::fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        let shown = &self.bytes[..self.bytes.len().min(self.limit)];
+
+        for byte in shown {
+            match byte {
+                b'\r' => f.write_str("\\r")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\\' => f.write_str("\\\\")?,
+                0x20..=0x7e => f.write_char(*byte as char)?,
+                other => write!(f, "\\x{:02x}", other)?,
+            }
+        }
+
+        if shown.len() < self.bytes.len() {
+            write!(f, "... ({} more bytes)", self.bytes.len() - shown.len())?;
+        }
 
-macro_rules! escape_newlines {
-    ($input:expr) => {
-        $input.replace("\n", "\\n").replace("\r", "\\r")
-    };
+        Ok(())
+    }
 }
 
-pub(crate) use escape_newlines;
+/// The diagnostics go through `tracing` when the feature is enabled, and through `log` otherwise.
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_imports)]
+pub(crate) use log::{debug, trace, warn};
+#[cfg(feature = "tracing")]
+#[allow(unused_imports)]
+pub(crate) use tracing::{debug, trace, warn};
+
+#[cfg(test)]
+mod test {
+    use crate::constants::MAX_LOGGED_BYTES;
+
+    use super::escaped;
+
+    #[test]
+    fn test_escaped() {
+        assert_eq!(escaped(b"+OK\r\n").to_string(), "+OK\\r\\n");
+
+        assert_eq!(
+            escaped(b"Subject: \xe9t\xe9\0\\\r\n").to_string(),
+            "Subject: \\xe9t\\xe9\\x00\\\\\\r\\n"
+        );
+
+        let large = vec![b'a'; MAX_LOGGED_BYTES + 10];
+
+        let logged = escaped(&large).to_string();
+
+        assert!(logged.starts_with(&"a".repeat(MAX_LOGGED_BYTES)));
+        assert!(logged.ends_with("... (10 more bytes)"));
+    }
+}
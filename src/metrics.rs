@@ -0,0 +1,45 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::command::Command;
+
+/// Statistics about the traffic of a session, see [crate::Client::metrics].
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// The amount of bytes that were written to the server.
+    pub bytes_sent: u64,
+    /// The amount of bytes that were read from the server.
+    pub bytes_received: u64,
+    /// The round-trip latencies of the commands that were sent, by command name, e.g. `RETR`.
+    pub commands: HashMap<String, CommandMetrics>,
+}
+
+impl Metrics {
+    /// Records that a response to the given command arrived `latency` after the command was sent.
+    pub(crate) fn record(&mut self, command: &Command, latency: Duration) {
+        let metrics = self.commands.entry(command.name()).or_default();
+
+        metrics.count += 1;
+        metrics.total_latency += latency;
+        metrics.max_latency = metrics.max_latency.max(latency);
+    }
+}
+
+/// The round-trip latencies of a single kind of command.
+#[derive(Debug, Clone, Default)]
+pub struct CommandMetrics {
+    /// How many responses to this command were received.
+    pub count: u64,
+    /// The time between sending the command and receiving the complete response, summed over all of the responses.
+    pub total_latency: Duration,
+    /// The longest time it took to receive a complete response.
+    pub max_latency: Duration,
+}
+
+impl CommandMetrics {
+    pub fn average_latency(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => self.total_latency / count as u32,
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! Test doubles for code that uses this crate, without needing a real mail account.
+//!
+//! A [MockSession] is a scripted connection that is handed to [crate::new] directly, which makes it easy to test how code handles malformed or hostile responses.
+//!
+//! A [MockServer] is an in-process POP3 server that listens on an ephemeral port on the loopback interface and serves a fixed set of messages.
+//! It supports USER/PASS login and the STAT, LIST, UIDL, RETR, TOP, DELE, RSET, NOOP, CAPA and QUIT commands.
+//! Every command it receives is recorded, so tests can assert on what was sent.
+//! # Examples
+//! ```rust,ignore
+//! use async_pop::mock::MockServer;
+//!
+//! let server = MockServer::builder()
+//!     .credentials("user", "password")
+//!     .message("Subject: Hello\r\n\r\nHi!\r\n")
+//!     .start()
+//!     .await?;
+//!
+//! let mut client = async_pop::connect_plain(server.addr()).await?;
+//!
+//! client.login("user", "password").await?;
+//!
+//! assert_eq!(client.stat().await?.counter().value()?, 1);
+//!
+//! server.assert_received(&["CAPA", "USER user", "PASS password", "CAPA", "STAT"]);
+//! ```
+
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+
+mod session;
+
+pub use session::MockSession;
+
+use crate::{
+    error::Result,
+    runtime::{
+        io::{BufReadExt, BufReader, Read, Write, WriteExt},
+        net::{TcpListener, TcpStream},
+        spawn,
+    },
+    server::{multiline, top},
+};
+
+const CAPABILITIES: &str = "USER\r\nTOP\r\nUIDL\r\n";
+
+/// A message in the maildrop of a [MockServer].
+#[derive(Debug, Clone)]
+struct MockMessage {
+    uid: String,
+    body: Bytes,
+}
+
+/// The state that is shared between the server task and its handle.
+#[derive(Debug, Default)]
+struct Shared {
+    messages: Vec<MockMessage>,
+    received: Vec<String>,
+}
+
+/// Configures and starts a [MockServer].
+#[derive(Debug, Default)]
+pub struct MockServerBuilder {
+    credentials: Option<(String, String)>,
+    messages: Vec<MockMessage>,
+}
+
+impl MockServerBuilder {
+    /// The username and password the server accepts, any login is accepted if this is not set.
+    pub fn credentials<U: Into<String>, P: Into<String>>(mut self, user: U, password: P) -> Self {
+        self.credentials = Some((user.into(), password.into()));
+        self
+    }
+
+    /// Adds a message to the maildrop, its unique id is `uid-<number>`.
+    ///
+    /// Lines that end in a bare line feed are sent with a CRLF line ending, like a real server would.
+    pub fn message<B: AsRef<[u8]>>(self, body: B) -> Self {
+        let uid = format!("uid-{}", self.messages.len() + 1);
+
+        self.message_with_uid(uid, body)
+    }
+
+    /// Adds a message to the maildrop with the given unique id.
+    pub fn message_with_uid<U: Into<String>, B: AsRef<[u8]>>(mut self, uid: U, body: B) -> Self {
+        self.messages.push(MockMessage {
+            uid: uid.into(),
+            body: normalize_line_endings(body.as_ref()),
+        });
+        self
+    }
+
+    /// Binds the server to an ephemeral port and starts accepting connections in the background.
+    pub async fn start(self) -> Result<MockServer> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+
+        let addr = listener.local_addr()?;
+
+        let shared = Arc::new(Mutex::new(Shared {
+            messages: self.messages,
+            received: Vec::new(),
+        }));
+
+        spawn(MockServer::run(
+            listener,
+            Arc::clone(&shared),
+            self.credentials,
+        ));
+
+        Ok(MockServer { addr, shared })
+    }
+}
+
+/// A POP3 server running in the background, created using [MockServer::builder].
+///
+/// Connections are handled one at a time. Messages that are deleted in a session are removed once that session ends with QUIT.
+/// The server keeps running until the process exits.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// The address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Every command line the server received so far, over all connections and without the line ending.
+    pub fn received(&self) -> Vec<String> {
+        self.shared.lock().unwrap().received.clone()
+    }
+
+    /// The amount of messages that are left in the maildrop.
+    pub fn message_count(&self) -> usize {
+        self.shared.lock().unwrap().messages.len()
+    }
+
+    /// Panics if the commands the server received are not exactly the given ones, in the same order.
+    pub fn assert_received<C: AsRef<str>>(&self, expected: &[C]) {
+        let received = self.received();
+
+        let expected: Vec<&str> = expected.iter().map(|command| command.as_ref()).collect();
+
+        assert_eq!(
+            received, expected,
+            "The mock server did not receive the expected commands"
+        );
+    }
+
+    async fn run(
+        listener: TcpListener,
+        shared: Arc<Mutex<Shared>>,
+        credentials: Option<(String, String)>,
+    ) {
+        while let Ok((stream, _)) = listener.accept().await {
+            let session = Session::new(Arc::clone(&shared), credentials.clone());
+
+            // A misbehaving client only ends its own session.
+            let _ = session.serve(stream).await;
+        }
+    }
+}
+
+/// A single connection to a [MockServer].
+struct Session {
+    shared: Arc<Mutex<Shared>>,
+    credentials: Option<(String, String)>,
+    user: Option<String>,
+    authenticated: bool,
+    messages: Vec<MockMessage>,
+    deleted: HashSet<usize>,
+}
+
+impl Session {
+    fn new(shared: Arc<Mutex<Shared>>, credentials: Option<(String, String)>) -> Self {
+        let messages = shared.lock().unwrap().messages.clone();
+
+        Self {
+            shared,
+            credentials,
+            user: None,
+            authenticated: false,
+            messages,
+            deleted: HashSet::new(),
+        }
+    }
+
+    async fn serve(mut self, stream: TcpStream) -> std::io::Result<()> {
+        let mut stream = BufReader::new(stream);
+
+        write_response(&mut stream, b"+OK POP3 mock server ready\r\n").await?;
+
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if stream.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            self.shared.lock().unwrap().received.push(line.to_string());
+
+            let (response, quit) = self.respond(line);
+
+            write_response(&mut stream, &response).await?;
+
+            if quit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Creates the response to a single command line, along with whether the session should end.
+    fn respond(&mut self, line: &str) -> (Vec<u8>, bool) {
+        let mut parts = line.split(' ');
+
+        let command = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let args: Vec<&str> = parts.collect();
+
+        match (command.as_str(), self.authenticated) {
+            ("CAPA", _) => (format!("+OK\r\n{}.\r\n", CAPABILITIES).into_bytes(), false),
+            ("NOOP", _) => ok("", false),
+            ("QUIT", _) => {
+                if self.authenticated {
+                    self.commit();
+                }
+
+                ok("bye", true)
+            }
+            ("USER", false) => match args.first() {
+                Some(user) => {
+                    self.user = Some(user.to_string());
+
+                    ok("", false)
+                }
+                None => error("missing username"),
+            },
+            ("PASS", false) => {
+                let password = args.join(" ");
+
+                let valid = match (&self.credentials, &self.user) {
+                    (_, None) => false,
+                    (None, Some(_)) => true,
+                    (Some(credentials), Some(user)) => {
+                        &credentials.0 == user && credentials.1 == password
+                    }
+                };
+
+                if valid {
+                    self.authenticated = true;
+
+                    ok("maildrop ready", false)
+                } else {
+                    self.user = None;
+
+                    error("[AUTH] invalid username or password")
+                }
+            }
+            (_, false) => error("not authenticated"),
+            ("STAT", true) => {
+                let (count, size) = self
+                    .available()
+                    .fold((0, 0), |(count, size), (_, message)| {
+                        (count + 1, size + message.body.len())
+                    });
+
+                ok(&format!("{} {}", count, size), false)
+            }
+            ("LIST", true) => self.listing(&args, |message| message.body.len().to_string()),
+            ("UIDL", true) => self.listing(&args, |message| message.uid.clone()),
+            ("RETR", true) => match self.message(args.first()) {
+                Some(message) => (multiline(&message.body), false),
+                None => error("no such message"),
+            },
+            ("TOP", true) => {
+                let lines = args.get(1).and_then(|lines| lines.parse::<usize>().ok());
+
+                match (self.message(args.first()), lines) {
+                    (Some(message), Some(lines)) => (multiline(&top(&message.body, lines)), false),
+                    _ => error("no such message"),
+                }
+            }
+            ("DELE", true) => match self.number(args.first()) {
+                Some(number) => {
+                    self.deleted.insert(number);
+
+                    ok("message deleted", false)
+                }
+                None => error("no such message"),
+            },
+            ("RSET", true) => {
+                self.deleted.clear();
+
+                ok("", false)
+            }
+            _ => error("unknown command"),
+        }
+    }
+
+    /// The messages that are not marked as deleted, along with their message number.
+    fn available(&self) -> impl Iterator<Item = (usize, &MockMessage)> {
+        self.messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| (index + 1, message))
+            .filter(|(number, _)| !self.deleted.contains(number))
+    }
+
+    /// Parses a message number that refers to a message that exists and is not deleted.
+    fn number(&self, arg: Option<&&str>) -> Option<usize> {
+        let number: usize = arg?.parse().ok()?;
+
+        if number == 0 || number > self.messages.len() || self.deleted.contains(&number) {
+            return None;
+        }
+
+        Some(number)
+    }
+
+    fn message(&self, arg: Option<&&str>) -> Option<&MockMessage> {
+        let number = self.number(arg)?;
+
+        self.messages.get(number - 1)
+    }
+
+    fn listing(&self, args: &[&str], value: impl Fn(&MockMessage) -> String) -> (Vec<u8>, bool) {
+        if args.is_empty() {
+            let lines: String = self
+                .available()
+                .map(|(number, message)| format!("{} {}\r\n", number, value(message)))
+                .collect();
+
+            return (format!("+OK\r\n{}.\r\n", lines).into_bytes(), false);
+        }
+
+        match self.number(args.first()) {
+            Some(number) => ok(
+                &format!("{} {}", number, value(&self.messages[number - 1])),
+                false,
+            ),
+            None => error("no such message"),
+        }
+    }
+
+    /// Removes the deleted messages from the maildrop, which happens when a session ends with QUIT.
+    fn commit(&mut self) {
+        let remaining = self
+            .available()
+            .map(|(_, message)| message.clone())
+            .collect();
+
+        self.shared.lock().unwrap().messages = remaining;
+    }
+}
+
+async fn write_response<S: Read + Write + Unpin>(
+    stream: &mut BufReader<S>,
+    response: &[u8],
+) -> std::io::Result<()> {
+    let stream = stream.get_mut();
+
+    stream.write_all(response).await?;
+    stream.flush().await
+}
+
+fn ok(message: &str, quit: bool) -> (Vec<u8>, bool) {
+    match message {
+        "" => (b"+OK\r\n".to_vec(), quit),
+        message => (format!("+OK {}\r\n", message).into_bytes(), quit),
+    }
+}
+
+fn error(message: &str) -> (Vec<u8>, bool) {
+    (format!("-ERR {}\r\n", message).into_bytes(), false)
+}
+
+fn normalize_line_endings(body: &[u8]) -> Bytes {
+    let mut normalized = Vec::with_capacity(body.len());
+
+    for (index, byte) in body.iter().enumerate() {
+        if *byte == b'\n' && (index == 0 || body[index - 1] != b'\r') {
+            normalized.push(b'\r');
+        }
+
+        normalized.push(*byte);
+    }
+
+    normalized.into()
+}
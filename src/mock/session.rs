@@ -0,0 +1,168 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use crate::macros::escaped;
+
+#[derive(Debug)]
+enum Step {
+    Expect(String),
+    Respond(Vec<u8>),
+    Close,
+}
+
+/// A scripted connection that can be passed to [crate::new], to test how code reacts to specific responses without a socket.
+///
+/// The script is a list of the lines the client is expected to send and the responses the server sends, played back in order.
+/// A line that does not match the next expectation fails the write with an [std::io::ErrorKind::InvalidData] error.
+/// Once the script runs out, the connection is closed.
+///
+/// Keep in mind that the client sends `CAPA` right after the greeting.
+/// # Examples
+/// ```rust,ignore
+/// use async_pop::mock::MockSession;
+///
+/// let session = MockSession::new()
+///     .respond("+OK ready\r\n")
+///     .expect("CAPA")
+///     .respond("+OK\r\nUIDL\r\n.\r\n")
+///     .expect("UIDL")
+///     .respond("+OK\r\n1 abc\r\n");
+///
+/// let mut client = async_pop::new(session).await?;
+///
+/// // The listing is never terminated before the connection closes.
+/// assert!(client.uidl(None::<MessageNumber>).await.is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct MockSession {
+    steps: VecDeque<Step>,
+    /// What the client wrote that does not make up a complete line yet.
+    line: Vec<u8>,
+    waker: Option<Waker>,
+}
+
+impl MockSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects the client to send the given line, without its line ending.
+    pub fn expect<L: Into<String>>(mut self, line: L) -> Self {
+        self.steps.push_back(Step::Expect(line.into()));
+        self
+    }
+
+    /// Sends the given bytes to the client, as is.
+    pub fn respond<R: AsRef<[u8]>>(mut self, response: R) -> Self {
+        self.steps
+            .push_back(Step::Respond(response.as_ref().to_vec()));
+        self
+    }
+
+    /// Closes the connection, even if there are more steps after this one.
+    pub fn close(mut self) -> Self {
+        self.steps.push_back(Step::Close);
+        self
+    }
+
+    /// Whether every step of the script has been played.
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    fn poll_read_into(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<usize> {
+        match self.steps.front_mut() {
+            None | Some(Step::Close) => Poll::Ready(0),
+            Some(Step::Expect(_)) => {
+                self.waker = Some(cx.waker().clone());
+
+                Poll::Pending
+            }
+            Some(Step::Respond(response)) => {
+                let amount = response.len().min(buf.len());
+
+                buf[..amount].copy_from_slice(&response[..amount]);
+
+                response.drain(..amount);
+
+                if response.is_empty() {
+                    self.steps.pop_front();
+                }
+
+                Poll::Ready(amount)
+            }
+        }
+    }
+
+    fn write_from(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if matches!(self.steps.front(), Some(Step::Close)) {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+
+        self.line.extend_from_slice(buf);
+
+        while let Some(end) = self.line.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+
+            let sent = line.strip_suffix(b"\r\n").unwrap_or(&line);
+
+            match self.steps.front() {
+                Some(Step::Expect(expected)) if expected.as_bytes() == sent => {
+                    self.steps.pop_front();
+                }
+                expected => {
+                    let expected = match expected {
+                        Some(Step::Expect(expected)) => expected.as_str(),
+                        _ => "nothing",
+                    };
+
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "The client sent '{}', but the script expected '{}'",
+                            escaped(sent),
+                            expected
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+
+        Ok(buf.len())
+    }
+}
+
+impl futures::io::AsyncRead for MockSession {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().poll_read_into(cx, buf).map(Ok)
+    }
+}
+
+impl futures::io::AsyncWrite for MockSession {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(self.get_mut().write_from(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    command::Command::*,
+    error::{ErrorKind, Result},
+    request::Request,
+    response::{capability::Capability, Response},
+    runtime::io::{Read, Write},
+    Client, MessageNumber,
+};
+
+/// A batch of commands that is sent to the server at once, created using [Client::pipeline].
+///
+/// If the server advertises the PIPELINING capability ([RFC 2449](https://www.rfc-editor.org/rfc/rfc2449#section-6.6)), all of the commands are written in a single go and the responses are read afterwards, saving a round trip per command.
+/// Otherwise the commands are sent one by one.
+/// # Examples
+/// ```rust,ignore
+/// let responses = client.pipeline().uidl(None::<MessageNumber>).list(None::<MessageNumber>).stat().execute().await?;
+///
+/// for response in responses {
+///     println!("{:?}", response?);
+/// }
+/// ```
+pub struct Pipeline<'a, S: Read + Write + Unpin + Send> {
+    client: &'a mut Client<S>,
+    requests: Vec<Request>,
+}
+
+impl<'a, S: Read + Write + Unpin + Send> Pipeline<'a, S> {
+    pub(crate) fn new(client: &'a mut Client<S>) -> Self {
+        Self {
+            client,
+            requests: Vec::new(),
+        }
+    }
+
+    /// Add an arbitrary request to the pipeline.
+    pub fn request<R: Into<Request>>(mut self, request: R) -> Self {
+        self.requests.push(request.into());
+
+        self
+    }
+
+    fn request_with_arg<A: Into<Option<MessageNumber>>>(self, request: Request, arg: A) -> Self {
+        let mut request = request;
+
+        if let Some(arg) = arg.into() {
+            request.add_arg(arg);
+        }
+
+        self.request(request)
+    }
+
+    pub fn noop(self) -> Self {
+        self.request(Noop)
+    }
+
+    pub fn stat(self) -> Self {
+        self.request(Stat)
+    }
+
+    pub fn list<N: Into<MessageNumber>>(self, msg_number: Option<N>) -> Self {
+        self.request_with_arg(List { listing: true }.into(), msg_number.map(Into::into))
+    }
+
+    pub fn uidl<N: Into<MessageNumber>>(self, msg_number: Option<N>) -> Self {
+        self.request_with_arg(Uidl { listing: true }.into(), msg_number.map(Into::into))
+    }
+
+    pub fn retr<N: Into<MessageNumber>>(self, msg_number: N) -> Self {
+        self.request_with_arg(Retr.into(), msg_number.into())
+    }
+
+    pub fn top<N: Into<MessageNumber>>(self, msg_number: N, lines: usize) -> Self {
+        let mut request: Request = Top.into();
+
+        request.add_arg(msg_number.into());
+        request.add_arg(lines);
+
+        self.request(request)
+    }
+
+    pub fn dele<N: Into<MessageNumber>>(self, msg_number: N) -> Self {
+        self.request_with_arg(Dele.into(), msg_number.into())
+    }
+
+    pub fn rset(self) -> Self {
+        self.request(Rset)
+    }
+
+    /// The amount of commands in the pipeline.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Send all of the commands and read their responses, in the order the commands were added.
+    ///
+    /// The outer result fails if the connection itself had a problem, while each of the inner results indicate whether the server accepted that specific command.
+    pub async fn execute(self) -> Result<Vec<Result<Response>>> {
+        let mut responses = Vec::with_capacity(self.requests.len());
+
+        self.client.ensure_capabilities().await?;
+
+        let pipelined = self.client.has_capability([Capability::Pipelining]);
+
+        if pipelined {
+            self.client.inner_mut()?.encode_all(&self.requests).await?;
+        }
+
+        for request in self.requests {
+            let response = if pipelined {
+                self.client.read_pipelined(request).await
+            } else {
+                self.client.send_request(request).await
+            };
+
+            match response {
+                Err(err) if !matches!(err.kind(), ErrorKind::ServerError { .. }) => {
+                    return Err(err)
+                }
+                response => responses.push(response),
+            }
+        }
+
+        Ok(responses)
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::macros::debug;
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    lock::Mutex as AsyncMutex,
+    StreamExt,
+};
+
+use crate::{
+    error::Result,
+    resilient::Credentials,
+    runtime::{
+        io::{Read, Write},
+        Instant,
+    },
+    timer::{default_timer, Timer},
+    Client, ClientState,
+};
+
+struct Idle<S: Read + Write + Unpin + Send> {
+    client: Client<S>,
+    since: Instant,
+}
+
+/// A bounded set of authenticated connections to a single account.
+///
+/// Most servers lock the maildrop for the duration of a session, so the amount of connections defaults to one. In that case the pool mostly serves to share a single session between tasks and to keep it alive in between uses.
+///
+/// Connections are checked when they are handed out: connections that have been idle for longer than the configured maximum are closed, and the others are sent a NOOP (unless disabled) to make sure they are still usable.
+/// If the server advertises a LOGIN-DELAY, the pool waits for that amount of time in between logins.
+/// # Examples
+/// ```rust,ignore
+/// use async_pop::{pool::Pool, resilient::Credentials};
+///
+/// let pool = Pool::new(
+///     || async_pop::connect_plain(("localhost", 110)),
+///     Credentials::login("user", "password"),
+/// )
+/// .max_size(2);
+///
+/// let mut client = pool.get().await?;
+///
+/// let stat = client.stat().await?;
+/// ```
+pub struct Pool<S: Read + Write + Unpin + Send, C> {
+    connect: C,
+    credentials: Credentials,
+    max_size: usize,
+    max_idle: Option<Duration>,
+    health_check: bool,
+    idle: Mutex<Vec<Idle<S>>>,
+    opened: AtomicUsize,
+    permits: AsyncMutex<UnboundedReceiver<()>>,
+    returned: UnboundedSender<()>,
+    next_login: AsyncMutex<Option<Instant>>,
+    timer: Arc<dyn Timer>,
+}
+
+impl<S, C, F> Pool<S, C>
+where
+    S: Read + Write + Unpin + Send,
+    C: Fn() -> F,
+    F: Future<Output = Result<Client<S>>>,
+{
+    /// Creates a new pool, using the given function to connect to the server and the given credentials to authenticate.
+    pub fn new(connect: C, credentials: Credentials) -> Self {
+        let (returned, permits) = mpsc::unbounded();
+
+        Self {
+            connect,
+            credentials,
+            max_size: 1,
+            max_idle: None,
+            health_check: true,
+            idle: Mutex::new(Vec::new()),
+            opened: AtomicUsize::new(0),
+            permits: AsyncMutex::new(permits),
+            returned,
+            next_login: AsyncMutex::new(None),
+            timer: default_timer(),
+        }
+    }
+
+    /// The maximum amount of connections that may be handed out at the same time, defaults to 1.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size.max(1);
+
+        self
+    }
+
+    /// The maximum amount of time a connection may sit unused in the pool before it is closed.
+    pub fn max_idle(mut self, max_idle: Duration) -> Self {
+        self.max_idle = Some(max_idle);
+
+        self
+    }
+
+    /// Whether to send a NOOP to idle connections before handing them out, defaults to true.
+    pub fn health_check(mut self, health_check: bool) -> Self {
+        self.health_check = health_check;
+
+        self
+    }
+
+    /// The timer used to wait for the login delay to pass, which defaults to the timer of the enabled runtime.
+    ///
+    /// Without a runtime feature this has to be set, usually to the timer passed to [crate::ClientBuilder::timer], as the default timer never fires.
+    pub fn timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.timer = timer;
+
+        self
+    }
+
+    /// The amount of connections that are currently unused.
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Get an authenticated connection from the pool, opening a new one if there are no usable idle connections.
+    ///
+    /// Waits until a connection is returned to the pool if the maximum amount of connections are already in use.
+    pub async fn get(&self) -> Result<PooledClient<'_, S>> {
+        self.acquire().await;
+
+        // The permit is given back when this is dropped, also when we fail to connect.
+        let mut pooled = PooledClient {
+            client: None,
+            idle: &self.idle,
+            returned: &self.returned,
+        };
+
+        loop {
+            let idle = match self.idle.lock().unwrap().pop() {
+                Some(idle) => idle,
+                None => break,
+            };
+
+            let mut client = idle.client;
+
+            if let Some(max_idle) = self.max_idle {
+                if idle.since.elapsed() > max_idle {
+                    debug!("Closing connection that has been idle for too long");
+
+                    client.quit().await.ok();
+
+                    continue;
+                }
+            }
+
+            if self.health_check && client.noop().await.is_err() {
+                debug!("Discarding connection that failed its health check");
+
+                continue;
+            }
+
+            pooled.client = Some(client);
+
+            return Ok(pooled);
+        }
+
+        pooled.client = Some(self.open().await?);
+
+        Ok(pooled)
+    }
+
+    async fn acquire(&self) {
+        let mut permits = self.permits.lock().await;
+
+        if let Ok(Some(())) = permits.try_next() {
+            return;
+        }
+
+        if self.opened.load(Ordering::SeqCst) < self.max_size {
+            self.opened.fetch_add(1, Ordering::SeqCst);
+
+            return;
+        }
+
+        permits.next().await;
+    }
+
+    async fn open(&self) -> Result<Client<S>> {
+        // Holding this lock makes sure only one login happens at a time.
+        let mut next_login = self.next_login.lock().await;
+
+        if let Some(next_login) = *next_login {
+            let now = Instant::now();
+
+            if next_login > now {
+                debug!("Waiting for the login delay to pass");
+
+                self.timer.sleep(next_login - now).await;
+            }
+        }
+
+        let mut client = (self.connect)().await?;
+
+        self.credentials.authenticate(&mut client).await?;
+
+        let login_delay = client.capabilities().login_delay();
+
+        *next_login = login_delay.map(|delay| Instant::now() + delay);
+
+        Ok(client)
+    }
+}
+
+/// A connection that was handed out by a [Pool].
+///
+/// The connection is given back to the pool when this is dropped, unless the session was closed or the connection became unusable in the meantime.
+pub struct PooledClient<'a, S: Read + Write + Unpin + Send> {
+    client: Option<Client<S>>,
+    idle: &'a Mutex<Vec<Idle<S>>>,
+    returned: &'a UnboundedSender<()>,
+}
+
+impl<'a, S: Read + Write + Unpin + Send> PooledClient<'a, S> {
+    /// Drop the connection without giving it back to the pool, for example after an error left it in an unknown state.
+    pub fn discard(mut self) {
+        self.client.take();
+    }
+}
+
+impl<'a, S: Read + Write + Unpin + Send> Deref for PooledClient<'a, S> {
+    type Target = Client<S>;
+
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl<'a, S: Read + Write + Unpin + Send> DerefMut for PooledClient<'a, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl<'a, S: Read + Write + Unpin + Send> Drop for PooledClient<'a, S> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            if client.get_state() == &ClientState::Transaction && client.is_usable() {
+                self.idle.lock().unwrap().push(Idle {
+                    client,
+                    since: Instant::now(),
+                });
+            }
+        }
+
+        self.returned.unbounded_send(()).ok();
+    }
+}
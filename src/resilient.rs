@@ -0,0 +1,259 @@
+use std::{future::Future, sync::Arc};
+
+use crate::macros::debug;
+use bytes::Bytes;
+
+use crate::{
+    error::{Error, ErrorKind, Result},
+    response::{
+        list::ListResponse, quit::QuitSummary, stat::Stat, types::message::Text, uidl::UidlResponse,
+    },
+    retry::{ExponentialBackoff, RetryPolicy},
+    runtime::io::{Read, Write},
+    timer::{default_timer, Timer},
+    Client, MessageNumber,
+};
+
+/// The credentials a [ResilientClient] uses to authenticate after (re)connecting.
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    /// Authenticate using the USER and PASS commands, see [Client::login].
+    Login { username: String, password: String },
+    /// Authenticate using the APOP command, see [Client::apop_login].
+    Apop { username: String, password: String },
+    /// Authenticate using the SASL PLAIN mechanism.
+    #[cfg(feature = "sasl")]
+    Plain { username: String, password: String },
+    /// Authenticate using the SASL XOAUTH2 mechanism.
+    #[cfg(feature = "sasl")]
+    OAuth2 { user: String, access_token: String },
+}
+
+impl Credentials {
+    pub fn login<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self::Login {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    pub(crate) async fn authenticate<S: Read + Write + Unpin + Send>(
+        &self,
+        client: &mut Client<S>,
+    ) -> Result<()> {
+        match self {
+            Self::Login { username, password } => {
+                client.login(username, password).await?;
+            }
+            Self::Apop { username, password } => {
+                client.apop_login(username, password).await?;
+            }
+            #[cfg(feature = "sasl")]
+            Self::Plain { username, password } => {
+                client
+                    .auth(crate::sasl::PlainAuthenticator::new(username, password))
+                    .await?;
+            }
+            #[cfg(feature = "sasl")]
+            Self::OAuth2 { user, access_token } => {
+                client
+                    .auth(crate::sasl::OAuth2Authenticator::new(user, access_token))
+                    .await?;
+            }
+        };
+
+        Ok(())
+    }
+}
+
+/// Runs a client method, trying again according to the retry policy if it failed with a transient error.
+///
+/// If the connection itself failed, a new connection is opened before trying again.
+/// Commands that refer to a message number pass `reconnect: false`, as the number may refer to a different message in the new session.
+macro_rules! with_retry {
+    ($self:ident, $client:ident => $call:expr) => {
+        with_retry!($self, reconnect: true, $client => $call)
+    };
+    ($self:ident, reconnect: $reconnect:expr, $client:ident => $call:expr) => {{
+        let mut attempt = 0;
+
+        loop {
+            let result = match $self.client().await {
+                Ok($client) => $call.await,
+                Err(err) => Err(err),
+            };
+
+            match result {
+                Err(err) if err.is_transient() => {
+                    if is_connection_error(&err) {
+                        // The next command opens a new connection either way.
+                        $self.client = None;
+
+                        if !$reconnect {
+                            debug!("Lost connection to the server, not retrying in a new session: {}", err);
+
+                            break Err(err);
+                        }
+                    }
+
+                    let delay = match $self.retry_policy.retry_after(attempt, &err) {
+                        Some(delay) => delay,
+                        None => break Err(err),
+                    };
+
+                    if $self.client.is_none() {
+                        debug!("Lost connection to the server, reconnecting: {}", err);
+                    }
+
+                    attempt += 1;
+
+                    $self.timer.sleep(delay).await;
+                }
+                result => break result,
+            }
+        }
+    }};
+}
+
+fn is_connection_error(error: &Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::Io(_)
+            | ErrorKind::ConnectionClosed
+            | ErrorKind::Poisoned
+            | ErrorKind::NotConnected
+            | ErrorKind::Timeout { .. }
+    )
+}
+
+/// A wrapper around a [Client] that transparently reconnects and re-authenticates whenever the connection is lost, and then retries the command that failed.
+///
+/// How often and how quickly commands are retried is decided by a [RetryPolicy], which defaults to [ExponentialBackoff].
+/// Temporary errors reported by the server (see [Error::is_transient]) are retried as well, without reconnecting.
+///
+/// Note that deletion marks do not survive a reconnect, as the server only removes marked messages when the session is closed properly.
+/// That is why DELE and RSET are not retried automatically, those have to be sent using [ResilientClient::client].
+///
+/// Message numbers are only valid within a session as well: if another session expunged messages in the meantime, the same number refers to a different message after reconnecting.
+/// That is why RETR, TOP and LIST or UIDL for a single message are only retried on the same connection. When the connection is lost, the error is returned and the next command reconnects,
+/// after which the message numbers have to be looked up again, e.g. using [ResilientClient::uidl] and [Client::message_number].
+/// # Examples
+/// ```rust,ignore
+/// use async_pop::resilient::{Credentials, ResilientClient};
+///
+/// let mut client = ResilientClient::new(
+///     || async_pop::connect_plain(("localhost", 110)),
+///     Credentials::login("user", "password"),
+/// );
+///
+/// let stat = client.stat().await?;
+/// ```
+pub struct ResilientClient<S, C>
+where
+    S: Read + Write + Unpin + Send,
+{
+    connect: C,
+    credentials: Credentials,
+    client: Option<Client<S>>,
+    retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+    timer: Arc<dyn Timer>,
+}
+
+impl<S, C, F> ResilientClient<S, C>
+where
+    S: Read + Write + Unpin + Send,
+    C: Fn() -> F,
+    F: Future<Output = Result<Client<S>>>,
+{
+    /// Creates a new client, using the given function to connect to the server and the given credentials to authenticate.
+    ///
+    /// No connection is made until the first command is sent.
+    pub fn new(connect: C, credentials: Credentials) -> Self {
+        Self {
+            connect,
+            credentials,
+            client: None,
+            retry_policy: Box::new(ExponentialBackoff::default()),
+            timer: default_timer(),
+        }
+    }
+
+    /// Change the policy that decides when failed commands are retried.
+    pub fn set_retry_policy<P: RetryPolicy + Send + Sync + 'static>(&mut self, policy: P) {
+        self.retry_policy = Box::new(policy)
+    }
+
+    /// Change the timer used to wait in between retries, which defaults to the timer of the enabled runtime.
+    ///
+    /// Without a runtime feature this has to be set, usually to the timer passed to [crate::ClientBuilder::timer], as the default timer never fires.
+    pub fn set_timer(&mut self, timer: Arc<dyn Timer>) {
+        self.timer = timer
+    }
+
+    /// Returns the currently connected and authenticated client, (re)connecting if needed.
+    pub async fn client(&mut self) -> Result<&mut Client<S>> {
+        // A connection that an earlier error left in an unknown state is replaced as well.
+        if !self.client.as_ref().map_or(false, Client::is_usable) {
+            let mut client = (self.connect)().await?;
+
+            self.credentials.authenticate(&mut client).await?;
+
+            self.client = Some(client);
+        }
+
+        match self.client.as_mut() {
+            Some(client) => Ok(client),
+            None => unreachable!(),
+        }
+    }
+
+    pub async fn noop(&mut self) -> Result<Text> {
+        with_retry!(self, client => client.noop())
+    }
+
+    pub async fn stat(&mut self) -> Result<Stat> {
+        with_retry!(self, client => client.stat())
+    }
+
+    pub async fn list<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: Option<N>,
+    ) -> Result<ListResponse> {
+        let msg_number = msg_number.map(Into::into);
+
+        with_retry!(self, reconnect: msg_number.is_none(), client => client.list(msg_number))
+    }
+
+    pub async fn uidl<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: Option<N>,
+    ) -> Result<UidlResponse> {
+        let msg_number = msg_number.map(Into::into);
+
+        with_retry!(self, reconnect: msg_number.is_none(), client => client.uidl(msg_number))
+    }
+
+    pub async fn retr<N: Into<MessageNumber>>(&mut self, msg_number: N) -> Result<Bytes> {
+        let msg_number = msg_number.into();
+
+        with_retry!(self, reconnect: false, client => client.retr(msg_number))
+    }
+
+    pub async fn top<N: Into<MessageNumber>>(
+        &mut self,
+        msg_number: N,
+        lines: usize,
+    ) -> Result<Bytes> {
+        let msg_number = msg_number.into();
+
+        with_retry!(self, reconnect: false, client => client.top(msg_number, lines))
+    }
+
+    /// Close the session, after which the next command will open a new one.
+    pub async fn quit(&mut self) -> Result<Option<QuitSummary>> {
+        match self.client.take() {
+            Some(mut client) => Ok(Some(client.quit().await?)),
+            None => Ok(None),
+        }
+    }
+}
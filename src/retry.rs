@@ -0,0 +1,118 @@
+//! Policies that decide whether and when a failed command should be tried again.
+//!
+//! Only commands that do not change the state of the maildrop are ever retried: STAT, LIST, UIDL, RETR, TOP and NOOP.
+//! See [crate::resilient::ResilientClient] for a client that uses these policies.
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// Decides whether a command that failed with a transient error (see [Error::is_transient]) should be tried again.
+pub trait RetryPolicy {
+    /// Returns how long to wait before trying again, or `None` to give up and return the error.
+    ///
+    /// The attempt starts at 0 for the first retry of a command.
+    fn retry_after(&self, attempt: usize, error: &Error) -> Option<Duration>;
+}
+
+/// Never retry anything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoRetry;
+
+impl RetryPolicy for NoRetry {
+    fn retry_after(&self, _attempt: usize, _error: &Error) -> Option<Duration> {
+        None
+    }
+}
+
+/// Retry a limited amount of times, doubling the delay after every attempt.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    max_retries: usize,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ExponentialBackoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum amount of times a single command is retried, defaults to 3.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+
+        self
+    }
+
+    /// The delay before the first retry, defaults to 100 milliseconds.
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+
+        self
+    }
+
+    /// The delay will never grow beyond this, defaults to 10 seconds.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+
+        self
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn retry_after(&self, attempt: usize, _error: &Error) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let factor = 2_u32.saturating_pow(attempt.min(u32::MAX as usize) as u32);
+
+        Some(
+            self.initial_delay
+                .saturating_mul(factor)
+                .min(self.max_delay),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = ExponentialBackoff::new()
+            .max_retries(4)
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5));
+
+        let error = Error::new(ErrorKind::Timeout { command: None }, "Timed out");
+
+        let delays: Vec<_> = (0..5)
+            .map(|attempt| policy.retry_after(attempt, &error))
+            .collect();
+
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(5)),
+                None
+            ]
+        );
+    }
+}
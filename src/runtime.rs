@@ -1,27 +1,310 @@
-pub mod io {
-    #[cfg(feature = "runtime-async-std")]
-    pub use async_std::io::{prelude::BufReadExt, BufRead, Error, Read, ReadExt, Write, WriteExt};
+#![allow(unused_imports)]
+
+//! The io traits the client is built on, along with thin adapters for the runtimes that can be enabled using feature flags.
+//!
+//! Everything that talks to a connection only depends on the `futures::io` traits and a [Timer], only opening connections,
+//! spawning tasks and temporary files need a specific runtime. When both runtimes are enabled, async-std is used.
 
-    #[cfg(feature = "runtime-tokio")]
-    pub use tokio::io::{
+pub mod io {
+    pub use futures::io::{
         AsyncBufRead as BufRead, AsyncBufReadExt as BufReadExt, AsyncRead as Read,
-        AsyncReadExt as ReadExt, AsyncWrite as Write, AsyncWriteExt as WriteExt, Error,
+        AsyncReadExt as ReadExt, AsyncWrite as Write, AsyncWriteExt as WriteExt, BufReader, Error,
     };
 }
 
+#[cfg(any(feature = "runtime-async-std", feature = "runtime-tokio"))]
 pub mod net {
     #[cfg(feature = "runtime-async-std")]
-    pub use async_std::net::{TcpStream, ToSocketAddrs};
+    pub use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+    #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+    pub use tokio::net::ToSocketAddrs;
+
+    /// A tokio tcp stream, adapted to the `futures::io` traits.
+    #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+    pub type TcpStream = crate::compat::Compat<tokio::net::TcpStream>;
+
+    /// Opens a tcp connection to the given address.
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> std::io::Result<TcpStream> {
+        #[cfg(feature = "runtime-async-std")]
+        return TcpStream::connect(addr).await;
+
+        #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+        return Ok(TcpStream::new(tokio::net::TcpStream::connect(addr).await?));
+    }
+
+    /// Options that are applied to the socket before it is connected.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct SocketOptions {
+        pub local_address: Option<std::net::IpAddr>,
+        pub nodelay: Option<bool>,
+        pub keepalive_time: Option<std::time::Duration>,
+        pub keepalive_interval: Option<std::time::Duration>,
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        pub interface: Option<String>,
+    }
+
+    impl SocketOptions {
+        fn is_empty(&self) -> bool {
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            if self.interface.is_some() {
+                return false;
+            }
+
+            self.local_address.is_none()
+                && self.nodelay.is_none()
+                && self.keepalive_time.is_none()
+                && self.keepalive_interval.is_none()
+        }
+
+        fn apply(&self, socket: &socket2::Socket) -> std::io::Result<()> {
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            if let Some(interface) = &self.interface {
+                socket.bind_device(Some(interface.as_bytes()))?;
+            }
+
+            if let Some(ip) = self.local_address {
+                socket.bind(&std::net::SocketAddr::new(ip, 0).into())?;
+            }
+
+            if let Some(nodelay) = self.nodelay {
+                socket.set_nodelay(nodelay)?;
+            }
+
+            if self.keepalive_time.is_some() || self.keepalive_interval.is_some() {
+                let mut keepalive = socket2::TcpKeepalive::new();
+
+                if let Some(time) = self.keepalive_time {
+                    keepalive = keepalive.with_time(time);
+                }
+
+                // Not every platform allows changing the interval, in which case the default of the system is used.
+                #[cfg(any(
+                    target_os = "android",
+                    target_os = "dragonfly",
+                    target_os = "freebsd",
+                    target_os = "fuchsia",
+                    target_os = "illumos",
+                    target_os = "ios",
+                    target_os = "linux",
+                    target_os = "macos",
+                    target_os = "netbsd",
+                    target_os = "windows",
+                ))]
+                if let Some(interval) = self.keepalive_interval {
+                    keepalive = keepalive.with_interval(interval);
+                }
+
+                socket.set_tcp_keepalive(&keepalive)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Opens a tcp connection to the given address, applying the given options to the socket first.
+    pub(crate) async fn connect_with(
+        addr: std::net::SocketAddr,
+        options: &SocketOptions,
+    ) -> std::io::Result<TcpStream> {
+        use socket2::{Domain, Protocol, Socket, Type};
+
+        if options.is_empty() {
+            return connect(addr).await;
+        }
+
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+        options.apply(&socket)?;
+
+        socket.set_nonblocking(true)?;
+
+        #[cfg(feature = "runtime-async-std")]
+        {
+            match socket.connect(&addr.into()) {
+                Ok(()) => {}
+                #[cfg(unix)]
+                Err(err) if err.raw_os_error() == Some(libc::EINPROGRESS) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(err) => return Err(err),
+            }
+
+            let stream = async_io::Async::new(std::net::TcpStream::from(socket))?;
+
+            // The socket becomes writable once the connection is established or has failed.
+            stream.writable().await?;
+
+            if let Some(err) = stream.get_ref().take_error()? {
+                return Err(err);
+            }
+
+            Ok(TcpStream::from(stream.into_inner()?))
+        }
+
+        #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+        {
+            let socket = tokio::net::TcpSocket::from_std_stream(socket.into());
+
+            Ok(TcpStream::new(socket.connect(addr).await?))
+        }
+    }
+
+    /// A tokio tcp listener that accepts streams adapted to the `futures::io` traits.
+    #[cfg(all(
+        feature = "runtime-tokio",
+        not(feature = "runtime-async-std"),
+        any(test, feature = "server")
+    ))]
+    pub struct TcpListener {
+        inner: tokio::net::TcpListener,
+    }
+
+    #[cfg(all(
+        feature = "runtime-tokio",
+        not(feature = "runtime-async-std"),
+        any(test, feature = "server")
+    ))]
+    impl TcpListener {
+        pub async fn bind<A: ToSocketAddrs>(addr: A) -> std::io::Result<Self> {
+            Ok(Self {
+                inner: tokio::net::TcpListener::bind(addr).await?,
+            })
+        }
+
+        pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+            self.inner.local_addr()
+        }
+
+        pub async fn accept(&self) -> std::io::Result<(TcpStream, std::net::SocketAddr)> {
+            let (stream, addr) = self.inner.accept().await?;
+
+            Ok((TcpStream::new(stream), addr))
+        }
+    }
+
+    /// Resolves the given address to all of the socket addresses it points to.
+    pub async fn lookup_host<A: ToSocketAddrs>(
+        addr: A,
+    ) -> std::io::Result<impl Iterator<Item = std::net::SocketAddr>> {
+        #[cfg(feature = "runtime-async-std")]
+        return addr.to_socket_addrs().await;
+
+        #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+        return tokio::net::lookup_host(addr).await;
+    }
+}
+
+#[cfg(feature = "spill")]
+pub mod fs {
+    pub use futures::io::AsyncSeekExt as SeekExt;
+
+    #[cfg(feature = "runtime-async-std")]
+    pub use async_std::fs::File;
 
-    #[cfg(feature = "runtime-tokio")]
-    pub use tokio::net::{TcpStream, ToSocketAddrs};
+    /// A tokio file, adapted to the `futures::io` traits.
+    #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+    pub type File = crate::compat::Compat<tokio::fs::File>;
+
+    /// Creates an anonymous temporary file, which is removed once it is closed.
+    pub fn temp_file() -> std::io::Result<File> {
+        let file = tempfile::tempfile()?;
+
+        #[cfg(feature = "runtime-async-std")]
+        return Ok(File::from(file));
+
+        #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+        return Ok(File::new(tokio::fs::File::from_std(file)));
+    }
 }
 
-#[cfg(feature = "runtime-async-std")]
-pub use async_std::future::timeout;
+pub use std::time::Duration;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use wasm::Instant;
+
+#[cfg(all(
+    target_arch = "wasm32",
+    any(feature = "runtime-async-std", feature = "runtime-tokio")
+))]
+compile_error!("The runtime features do not support wasm32, disable the default features and connect using the tunnel feature instead");
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod wasm {
+    use std::time::Duration;
+
+    /// Stands in for [std::time::Instant], which panics on wasm32-unknown-unknown as there is no clock without javascript bindings.
+    ///
+    /// Time never passes for this clock: idle times and latencies are always zero, login delays are not tracked and rate limited transfers stall once their budget is used up.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant;
+
+    impl Instant {
+        pub fn now() -> Self {
+            Self
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        pub fn duration_since(&self, _earlier: Instant) -> Duration {
+            Duration::ZERO
+        }
+
+        pub fn checked_duration_since(&self, _earlier: Instant) -> Option<Duration> {
+            Some(Duration::ZERO)
+        }
+    }
+
+    impl std::ops::Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, _duration: Duration) -> Instant {
+            self
+        }
+    }
+
+    impl std::ops::Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, _earlier: Instant) -> Duration {
+            Duration::ZERO
+        }
+    }
+}
 
 #[cfg(feature = "runtime-async-std")]
-pub use std::time::{Duration, Instant};
+pub use async_std::task::spawn;
+
+#[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+pub use tokio::spawn;
+
+use crate::timer::Timer;
 
-#[cfg(feature = "runtime-tokio")]
-pub use tokio::time::{timeout, Duration, Instant};
+/// Runs the given future, failing with [crate::error::ErrorKind::Timeout] for the given command if it does not complete within the given duration.
+///
+/// When no duration is given, the future is allowed to run forever.
+pub async fn with_timeout<T, F: std::future::Future<Output = crate::error::Result<T>>>(
+    timer: &dyn Timer,
+    duration: Option<Duration>,
+    command: Option<crate::command::Command>,
+    message: &str,
+    future: F,
+) -> crate::error::Result<T> {
+    let duration = match duration {
+        Some(duration) => duration,
+        None => return future.await,
+    };
+
+    futures::pin_mut!(future);
+
+    match futures::future::select(future, timer.sleep(duration)).await {
+        futures::future::Either::Left((result, _)) => result,
+        futures::future::Either::Right(_) => {
+            crate::error::err!(crate::error::ErrorKind::Timeout { command }, "{}", message)
+        }
+    }
+}
@@ -5,7 +5,7 @@ This module provides support for SASL (Simple Authentication and Security Layer)
 
 It allows one to use these mechanisms to authenticate with a Pop3 compatible server and implement more mechanisms if they are needed.
 
-The mechanisms for PLAIN and XOAUTH2 are already present as they are commonly used.
+The mechanisms for PLAIN, CRAM-MD5, XOAUTH2 and EXTERNAL are already present as they are commonly used, NTLM is available when the `ntlm` feature is enabled.
 
 Implementing a mechanism is simple:
 
@@ -25,34 +25,25 @@ impl Authenticator for MyAuthenticator {
         Some(format!("\x00{}\x00{}", self.username, self.secret_token))
     }
 
-    async fn handle<'a, S: Read + Write + Unpin + Send>(
-        &self,
-        communicator: Communicator<'a, S>,
-    ) -> Result<()> {
-        let challenge = communicator.next_challenge().await?;
-
+    fn process(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
         let response = mechanism_lib::handle_challenge(challenge)?;
 
-        communicator.send(response).await?;
-
-        Ok(())
+        Ok(response)
     }
 }
 ```
 */
 
-use std::collections::VecDeque;
+#[cfg(feature = "ntlm")]
+mod ntlm;
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
 
-use async_trait::async_trait;
+use crate::error::{err, Error, ErrorKind, Result};
 
-use crate::{
-    command::Command,
-    error::{err, ErrorKind, Result},
-    request::Request,
-    response::{types::message::Text, Response},
-    runtime::io::{Read, Write},
-    stream::PopStream,
-};
+#[cfg(feature = "ntlm")]
+pub use ntlm::NtlmAuthenticator;
 
 /// A simple mechanism to authenticate via PLAIN
 pub struct PlainAuthenticator {
@@ -79,6 +70,47 @@ impl PlainAuthenticator {
     }
 }
 
+/// A mechanism to authenticate via CRAM-MD5, which proves knowledge of the password without sending it, see [RFC 2195](https://www.rfc-editor.org/rfc/rfc2195).
+pub struct CramMd5Authenticator {
+    username: String,
+    password: String,
+}
+
+impl CramMd5Authenticator {
+    pub fn new<U: Into<String>, P: Into<String>>(username: U, password: P) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+impl Authenticator for CramMd5Authenticator {
+    fn mechanism(&self) -> &str {
+        "CRAM-MD5"
+    }
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        let digest: String = hmac_md5(self.password.as_bytes(), &[challenge])
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(format!("{} {}", self.username, digest).into_bytes())
+    }
+}
+
+/// HMAC-MD5 as described in [RFC 2104](https://www.rfc-editor.org/rfc/rfc2104) over the concatenation of the given parts, used by CRAM-MD5 and NTLM.
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC accepts keys of any length");
+
+    for part in parts {
+        mac.update(part);
+    }
+
+    mac.finalize().into_bytes().into()
+}
+
 /// A simple mechanism to authenticate via OAuth2
 pub struct OAuth2Authenticator {
     user: String,
@@ -94,7 +126,6 @@ impl OAuth2Authenticator {
     }
 }
 
-#[async_trait]
 impl Authenticator for OAuth2Authenticator {
     fn mechanism(&self) -> &str {
         "XOAUTH2"
@@ -108,9 +139,100 @@ impl Authenticator for OAuth2Authenticator {
 
         Some(secret)
     }
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        // The only challenge the server sends is the reason the token was rejected.
+        let error = OAuthError::parse(challenge);
+
+        Err(Error::new(
+            ErrorKind::OAuth(error),
+            "The server rejected the OAuth2 access token",
+        ))
+    }
 }
 
-#[async_trait]
+/// The reason an OAuth2 access token was rejected, as sent by the server in a JSON encoded challenge.
+///
+/// See the [Gmail documentation](https://developers.google.com/gmail/imap/xoauth2-protocol#error_response) for an example.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OAuthError {
+    /// The HTTP-like status code, e.g. "401" when the token is invalid or expired, or "400" for a bad request.
+    pub status: Option<String>,
+    /// The authentication schemes the server accepts, e.g. "Bearer".
+    pub schemes: Option<String>,
+    /// The scope that the token needs to have.
+    pub scope: Option<String>,
+}
+
+impl OAuthError {
+    fn parse(challenge: &[u8]) -> Self {
+        let value: serde_json::Value = match serde_json::from_slice(challenge) {
+            Ok(value) => value,
+            Err(_) => return Self::default(),
+        };
+
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(|field| field.as_str())
+                .map(String::from)
+        };
+
+        Self {
+            status: field("status"),
+            schemes: field("schemes"),
+            scope: field("scope"),
+        }
+    }
+
+    /// Whether the token is invalid or has expired, in which case it should be refreshed.
+    pub fn is_invalid_token(&self) -> bool {
+        self.status.as_deref() == Some("401")
+    }
+}
+
+/// A mechanism to authenticate using credentials that were established outside of the POP session, which in practice means the tls client certificate, see [RFC 4422](https://www.rfc-editor.org/rfc/rfc4422#appendix-A).
+///
+/// It can only be used on a secure connection, the client certificate itself has to be configured on the tls connector.
+pub struct ExternalAuthenticator {
+    authzid: Option<String>,
+}
+
+impl ExternalAuthenticator {
+    /// Authenticate as the identity that belongs to the client certificate.
+    pub fn new() -> Self {
+        Self { authzid: None }
+    }
+
+    /// Request to act as the given identity, instead of the identity that belongs to the client certificate.
+    pub fn with_authzid<A: Into<String>>(authzid: A) -> Self {
+        Self {
+            authzid: Some(authzid.into()),
+        }
+    }
+}
+
+impl Default for ExternalAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Authenticator for ExternalAuthenticator {
+    fn mechanism(&self) -> &str {
+        "EXTERNAL"
+    }
+
+    fn auth(&self) -> Option<String> {
+        Some(self.authzid.clone().unwrap_or_default())
+    }
+
+    fn requires_tls(&self) -> bool {
+        true
+    }
+}
+
+/// A SASL mechanism, see the [module documentation](self) on how to implement one.
 pub trait Authenticator {
     /// The name of the mechanism, e.g: "XOAUTH2" or "KERBEROS_4".
     fn mechanism(&self) -> &str;
@@ -122,58 +244,51 @@ pub trait Authenticator {
         None
     }
 
-    /// Handle a handshake conversation between the server and the client.
-    ///
-    /// The [Communicator] allows you to send and receive data needed for authentication
-    async fn handle<'a, S: Read + Write + Unpin + Send>(
-        &self,
-        _communicator: Communicator<'a, S>,
-    ) -> Result<()> {
-        Ok(())
+    /// Whether the mechanism may only be used on a connection that is secured using tls, such as EXTERNAL which relies on the tls client certificate.
+    fn requires_tls(&self) -> bool {
+        false
     }
-}
-
-pub struct Communicator<'a, S: Read + Write + Unpin + Send> {
-    stream: &'a mut PopStream<S>,
-    requests: VecDeque<Request>,
-}
 
-impl<'a, S: Read + Write + Unpin + Send> Communicator<'a, S> {
-    pub fn new(stream: &'a mut PopStream<S>) -> Self {
-        Self {
-            stream,
-            requests: VecDeque::new(),
-        }
+    /// Answer a challenge sent by the server during the authentication exchange.
+    ///
+    /// The challenge is already base64 decoded and the returned answer is base64 encoded automatically.
+    /// Returning an error cancels the exchange, as described in [RFC 5034](https://www.rfc-editor.org/rfc/rfc5034#section-4), after which the error is returned from [crate::Client::auth].
+    fn process(&mut self, _challenge: &[u8]) -> Result<Vec<u8>> {
+        err!(
+            ErrorKind::UnexpectedResponse,
+            "The server sent a challenge that the {} mechanism did not expect",
+            self.mechanism()
+        )
     }
+}
 
-    pub async fn send<A: Into<String>>(&mut self, secret: A) -> Result<()> {
-        let request: Request = Command::Base64(secret.into()).into();
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        self.stream.encode(&request).await?;
+    #[test]
+    fn test_oauth_error() {
+        let error = OAuthError::parse(
+            br#"{"status":"401","schemes":"bearer mac","scope":"https://mail.google.com/"}"#,
+        );
 
-        self.requests.push_back(request);
+        assert_eq!(error.status.as_deref(), Some("401"));
+        assert_eq!(error.schemes.as_deref(), Some("bearer mac"));
+        assert_eq!(error.scope.as_deref(), Some("https://mail.google.com/"));
+        assert!(error.is_invalid_token());
 
-        Ok(())
+        assert_eq!(OAuthError::parse(b"not json"), OAuthError::default());
     }
 
-    pub async fn next_challenge(&mut self) -> Result<Text> {
-        let command: Command = match self.requests.pop_front() {
-            Some(request) => request.into(),
-            None => Command::Base64(String::new()),
-        };
-
-        let response = self.stream.read_response(command).await?;
+    #[test]
+    fn test_cram_md5() {
+        // The example from https://www.rfc-editor.org/rfc/rfc2195#section-2
+        let mut authenticator = CramMd5Authenticator::new("tim", "tanstaaftanstaaf");
 
-        match response {
-            Response::Challenge(challenge) => Ok(challenge),
-            _ => err!(
-                ErrorKind::UnexpectedResponse,
-                "Did not get a challenge as a response"
-            ),
-        }
-    }
+        let answer = authenticator
+            .process(b"<1896.697170952@postoffice.reston.mci.net>")
+            .unwrap();
 
-    pub async fn stop(&mut self) -> Result<()> {
-        self.stream.send_bytes("*").await
+        assert_eq!(answer, b"tim b913a602c7eda7a495b4e6e7334d3890");
     }
 }
@@ -0,0 +1,371 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use md4::{Digest, Md4};
+
+use crate::error::{err, ErrorKind, Result};
+
+use super::{hmac_md5, Authenticator};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSION_SECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_TARGET_INFO: u32 = 0x0080_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSION_SECURITY
+    | NEGOTIATE_TARGET_INFO
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+/// The id of the timestamp entry in the target info sent by the server.
+const AV_TIMESTAMP: u16 = 0x0007;
+
+/// The amount of 100 nanosecond intervals between 1601-01-01 and the unix epoch.
+const FILETIME_UNIX_OFFSET: u64 = 116_444_736_000_000_000;
+
+/// A mechanism to authenticate via NTLM (version 2), as used by Microsoft Exchange and other legacy corporate servers.
+///
+/// See [MS-NLMP](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-nlmp/b38c36ed-2804-4868-a9ff-8dd3182128e4).
+pub struct NtlmAuthenticator {
+    domain: String,
+    username: String,
+    password: String,
+    workstation: String,
+}
+
+impl NtlmAuthenticator {
+    /// The username may be prefixed with the domain, as in `DOMAIN\user`.
+    pub fn new<U: AsRef<str>, P: Into<String>>(username: U, password: P) -> Self {
+        let (domain, username) = match username.as_ref().split_once('\\') {
+            Some((domain, username)) => (domain.to_string(), username.to_string()),
+            None => (String::new(), username.as_ref().to_string()),
+        };
+
+        Self {
+            domain,
+            username,
+            password: password.into(),
+            workstation: String::new(),
+        }
+    }
+
+    /// The domain the account belongs to.
+    pub fn domain<D: Into<String>>(mut self, domain: D) -> Self {
+        self.domain = domain.into();
+
+        self
+    }
+
+    /// The name of the machine that is authenticating, empty by default.
+    pub fn workstation<W: Into<String>>(mut self, workstation: W) -> Self {
+        self.workstation = workstation.into();
+
+        self
+    }
+
+    fn negotiate_message() -> Vec<u8> {
+        let mut message = Vec::with_capacity(32);
+
+        message.extend_from_slice(SIGNATURE);
+        message.extend_from_slice(&1_u32.to_le_bytes());
+        message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        // Empty domain and workstation fields.
+        message.extend_from_slice(&[0; 16]);
+
+        message
+    }
+
+    fn authenticate_message(&self, challenge: &Challenge, client_challenge: [u8; 8]) -> Vec<u8> {
+        let timestamp = challenge.timestamp().unwrap_or_else(now_as_filetime);
+
+        let hash = ntowfv2(&self.username, &self.password, &self.domain);
+
+        let lm_response = lmv2_response(&hash, &challenge.server_challenge, &client_challenge);
+        let nt_response = ntv2_response(
+            &hash,
+            &challenge.server_challenge,
+            &client_challenge,
+            timestamp,
+            &challenge.target_info,
+        );
+
+        let fields = [
+            lm_response,
+            nt_response,
+            utf16(&self.domain),
+            utf16(&self.username),
+            utf16(&self.workstation),
+            // No session key, as we do not sign or seal messages.
+            Vec::new(),
+        ];
+
+        let mut message = Vec::new();
+
+        message.extend_from_slice(SIGNATURE);
+        message.extend_from_slice(&3_u32.to_le_bytes());
+
+        let mut offset = 12 + fields.len() * 8 + 4;
+
+        for field in &fields {
+            message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            message.extend_from_slice(&(offset as u32).to_le_bytes());
+
+            offset += field.len();
+        }
+
+        message.extend_from_slice(&(challenge.flags & NEGOTIATE_FLAGS).to_le_bytes());
+
+        for field in fields {
+            message.extend_from_slice(&field);
+        }
+
+        message
+    }
+}
+
+impl Authenticator for NtlmAuthenticator {
+    fn mechanism(&self) -> &str {
+        "NTLM"
+    }
+
+    fn process(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        // The server starts with an empty challenge, to which we answer with the negotiate message.
+        if challenge.is_empty() {
+            return Ok(Self::negotiate_message());
+        }
+
+        let challenge = Challenge::parse(challenge)?;
+
+        let mut client_challenge = [0; 8];
+
+        if getrandom::getrandom(&mut client_challenge).is_err() {
+            err!(
+                ErrorKind::FeatureUnsupported,
+                "Could not generate a random client challenge for NTLM"
+            )
+        }
+
+        Ok(self.authenticate_message(&challenge, client_challenge))
+    }
+}
+
+/// The parts of the challenge message sent by the server that we need.
+struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl Challenge {
+    fn parse(message: &[u8]) -> Result<Self> {
+        if message.len() < 48 || &message[..8] != SIGNATURE || read_u32(message, 8) != 2 {
+            err!(
+                ErrorKind::InvalidResponse,
+                "The server did not send a valid NTLM challenge"
+            )
+        }
+
+        let flags = read_u32(message, 20);
+
+        let mut server_challenge = [0; 8];
+
+        server_challenge.copy_from_slice(&message[24..32]);
+
+        let length = read_u16(message, 40) as usize;
+        let offset = read_u32(message, 44) as usize;
+
+        let target_info = match offset
+            .checked_add(length)
+            .and_then(|end| message.get(offset..end))
+        {
+            Some(target_info) => target_info.to_vec(),
+            None => err!(
+                ErrorKind::InvalidResponse,
+                "The NTLM challenge contains an invalid target info field"
+            ),
+        };
+
+        Ok(Self {
+            flags,
+            server_challenge,
+            target_info,
+        })
+    }
+
+    /// The server time, if it was included in the target info.
+    fn timestamp(&self) -> Option<u64> {
+        let mut position = 0;
+
+        while position + 4 <= self.target_info.len() {
+            let id = read_u16(&self.target_info, position);
+            let length = read_u16(&self.target_info, position + 2) as usize;
+
+            position += 4;
+
+            if id == AV_TIMESTAMP && length == 8 {
+                let value = self.target_info.get(position..position + 8)?;
+
+                return Some(u64::from_le_bytes(value.try_into().ok()?));
+            }
+
+            position += length;
+        }
+
+        None
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn utf16(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn now_as_filetime() -> u64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    FILETIME_UNIX_OFFSET + since_epoch.as_nanos() as u64 / 100
+}
+
+fn ntowfv2(username: &str, password: &str, domain: &str) -> [u8; 16] {
+    let password_hash = Md4::digest(utf16(password));
+
+    let identity = utf16(&(username.to_uppercase() + domain));
+
+    hmac_md5(&password_hash, &[&identity])
+}
+
+fn lmv2_response(
+    hash: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+) -> Vec<u8> {
+    let mut response = hmac_md5(hash, &[server_challenge, client_challenge]).to_vec();
+
+    response.extend_from_slice(client_challenge);
+
+    response
+}
+
+fn ntv2_response(
+    hash: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+    timestamp: u64,
+    target_info: &[u8],
+) -> Vec<u8> {
+    let mut blob = vec![0x01, 0x01, 0, 0, 0, 0, 0, 0];
+
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let mut response = hmac_md5(hash, &[server_challenge, &blob]).to_vec();
+
+    response.extend_from_slice(&blob);
+
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Test vectors from section 4.2.4 of MS-NLMP.
+    const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const CLIENT_CHALLENGE: [u8; 8] = [0xaa; 8];
+
+    #[test]
+    fn test_ntlmv2() {
+        let hash = ntowfv2("User", "Password", "Domain");
+
+        assert_eq!(
+            hash,
+            [
+                0x0c, 0x86, 0x8a, 0x40, 0x3b, 0xfd, 0x7a, 0x93, 0xa3, 0x00, 0x1e, 0xf2, 0x2e, 0xf0,
+                0x2e, 0x3f
+            ]
+        );
+
+        let lm_response = lmv2_response(&hash, &SERVER_CHALLENGE, &CLIENT_CHALLENGE);
+
+        assert_eq!(
+            lm_response[..16],
+            [
+                0x86, 0xc3, 0x50, 0x97, 0xac, 0x9c, 0xec, 0x10, 0x25, 0x54, 0x76, 0x4a, 0x57, 0xcc,
+                0xcc, 0x19
+            ]
+        );
+
+        let target_info = [
+            0x02, 0x00, 0x0c, 0x00, 0x44, 0x00, 0x6f, 0x00, 0x6d, 0x00, 0x61, 0x00, 0x69, 0x00,
+            0x6e, 0x00, 0x01, 0x00, 0x0c, 0x00, 0x53, 0x00, 0x65, 0x00, 0x72, 0x00, 0x76, 0x00,
+            0x65, 0x00, 0x72, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let nt_response =
+            ntv2_response(&hash, &SERVER_CHALLENGE, &CLIENT_CHALLENGE, 0, &target_info);
+
+        assert_eq!(
+            nt_response[..16],
+            [
+                0x68, 0xcd, 0x0a, 0xb8, 0x51, 0xe5, 0x1c, 0x96, 0xaa, 0xbc, 0x92, 0x7b, 0xeb, 0xef,
+                0x6a, 0x1c
+            ]
+        );
+    }
+
+    #[test]
+    fn test_challenge() {
+        let mut message = SIGNATURE.to_vec();
+
+        message.extend_from_slice(&2_u32.to_le_bytes());
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        message.extend_from_slice(&SERVER_CHALLENGE);
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&12_u16.to_le_bytes());
+        message.extend_from_slice(&12_u16.to_le_bytes());
+        message.extend_from_slice(&48_u32.to_le_bytes());
+        message.extend_from_slice(&[0x07, 0x00, 0x08, 0x00, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let challenge = Challenge::parse(&message).unwrap();
+
+        assert_eq!(challenge.server_challenge, SERVER_CHALLENGE);
+        assert_eq!(
+            challenge.timestamp(),
+            Some(u64::from_le_bytes([1, 2, 3, 4, 5, 6, 7, 8]))
+        );
+
+        assert!(Challenge::parse(&message[..40]).is_err());
+
+        message[44..48].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert!(Challenge::parse(&message).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+//! Deciding whether credentials may be sent over a connection that is not secured using tls.
+//!
+//! Anyone who can intercept a plain connection can read the password sent by USER/PASS and by most SASL mechanisms.
+//! An attacker can also remove STLS from the capabilities of the server, or make the upgrade fail, to keep the client from securing the connection.
+
+/// When the client is allowed to send credentials over a plain connection, see [crate::ClientBuilder::security_policy].
+///
+/// Connections opened using [crate::connect], or upgraded using [crate::Client::stls], are always allowed to send credentials.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SecurityPolicy {
+    /// Never send credentials over a plain connection, logging in fails with [crate::error::ErrorKind::Insecure].
+    Required,
+    /// Only send credentials over a plain connection if the server does not support STLS.
+    ///
+    /// When the server does support it, the connection has to be upgraded first. This does not protect against an attacker that removes STLS from the capabilities.
+    #[default]
+    Opportunistic,
+    /// Always allow sending credentials, even over a plain connection.
+    None,
+}
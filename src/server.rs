@@ -0,0 +1,405 @@
+//! The server half of the protocol, for building POP3 gateways and test fixtures on top of the same request and response types as the client.
+//!
+//! A [Server] drives a single connection through the AUTHORIZATION, TRANSACTION and UPDATE states described in [RFC 1939](https://www.rfc-editor.org/rfc/rfc1939).
+//! Logging in is delegated to a closure, which returns the [Maildrop] of the user that logged in.
+//! # Examples
+//! ```rust,ignore
+//! use async_pop::{response::ErrorResponse, server::{Maildrop, Server}};
+//!
+//! let listener = TcpListener::bind(("0.0.0.0", 110)).await?;
+//!
+//! loop {
+//!     let (stream, _) = listener.accept().await?;
+//!
+//!     let mut server = Server::new(|user: &str, password: &str| match store.open(user, password) {
+//!         Some(maildrop) => Ok(maildrop),
+//!         None => Err(ErrorResponse::new(None, "Invalid credentials".into()).into()),
+//!     });
+//!
+//!     spawn(async move { server.serve(stream).await });
+//! }
+//! ```
+
+use std::collections::BTreeSet;
+
+use bytes::Bytes;
+
+use crate::{
+    command::Command,
+    constants::MAX_COMMAND_LENGTH,
+    error::{Error, ErrorKind, Result},
+    request::Request,
+    response::{
+        capability::{Capabilities, Capability},
+        code::ResponseCode,
+        types::DataType,
+        ErrorResponse,
+    },
+    runtime::io::{BufReadExt, BufReader, Read, ReadExt, Write, WriteExt},
+};
+
+/// The capabilities the server advertises in response to CAPA.
+fn capabilities() -> Capabilities {
+    vec![Capability::User, Capability::Top, Capability::Uidl].into()
+}
+
+/// The messages of a single user, as served by a [Server].
+///
+/// Messages are numbered starting at 1, in the order returned by [Maildrop::list].
+/// The numbers that are passed to the other methods always refer to an existing message that is not marked as deleted.
+/// An error is sent to the client as a negative response, including its [Error::response_code].
+/// To control exactly what the client receives, return an error created from an [ErrorResponse].
+pub trait Maildrop {
+    /// The size in octets of every message, called once when the session enters the TRANSACTION state.
+    fn list(&mut self) -> Result<Vec<usize>>;
+
+    /// The unique id of a message, which may only consist of the printable ASCII characters.
+    fn uidl(&mut self, msg_number: usize) -> Result<String>;
+
+    /// The full content of a message, using CRLF line endings and without any dot-stuffing.
+    fn retr(&mut self, msg_number: usize) -> Result<Bytes>;
+
+    /// The headers of a message along with the given amount of lines of its body.
+    ///
+    /// By default this is taken from the result of [Maildrop::retr].
+    fn top(&mut self, msg_number: usize, lines: usize) -> Result<Bytes> {
+        let message = self.retr(msg_number)?;
+
+        Ok(top(&message, lines).into())
+    }
+
+    /// Removes the given messages, called in the UPDATE state when the client ends the session with QUIT.
+    fn dele(&mut self, msg_numbers: &[usize]) -> Result<()>;
+}
+
+enum State<M> {
+    Authorization {
+        user: Option<String>,
+    },
+    Transaction {
+        maildrop: M,
+        sizes: Vec<usize>,
+        deleted: BTreeSet<usize>,
+    },
+}
+
+/// Serves a single POP3 connection, see the [module docs](self).
+pub struct Server<F> {
+    login: F,
+    greeting: String,
+}
+
+impl<F, M> Server<F>
+where
+    F: FnMut(&str, &str) -> Result<M>,
+    M: Maildrop,
+{
+    /// Creates a server that logs users in using the given closure, which receives the username and password sent using USER and PASS.
+    ///
+    /// An error is sent to the client with the `[AUTH]` response code, unless the error already has a response code.
+    pub fn new(login: F) -> Self {
+        Self {
+            login,
+            greeting: String::from("POP3 server ready"),
+        }
+    }
+
+    /// The text of the positive response that is sent when a client connects.
+    pub fn greeting<G: Into<String>>(mut self, greeting: G) -> Self {
+        self.greeting = greeting.into();
+        self
+    }
+
+    /// Handles the connection until the client sends QUIT or disconnects.
+    pub async fn serve<S: Read + Write + Unpin>(&mut self, stream: S) -> Result<()> {
+        let mut stream = BufReader::new(stream);
+
+        let greeting = format!("+OK {}\r\n", single_line(&self.greeting));
+
+        write_response(&mut stream, greeting.as_bytes()).await?;
+
+        let mut state = State::Authorization { user: None };
+
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+
+            let read = (&mut stream)
+                .take(MAX_COMMAND_LENGTH as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .await?;
+
+            if read == 0 {
+                return Ok(());
+            }
+
+            if !line.ends_with(b"\n") {
+                // Either the client disconnected halfway through a line, or the line is way too long to be a command.
+                write_response(&mut stream, b"-ERR Command line too long\r\n").await?;
+
+                return Ok(());
+            }
+
+            let text = String::from_utf8_lossy(&line);
+
+            let (response, quit) = match text.trim_end().parse::<Request>() {
+                Ok(request) => self.respond(&mut state, &request),
+                Err(_) => (negative("Unknown command"), false),
+            };
+
+            write_response(&mut stream, &response).await?;
+
+            if quit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Creates the response to a request, along with whether the session ends afterwards.
+    fn respond(&mut self, state: &mut State<M>, request: &Request) -> (Vec<u8>, bool) {
+        let command = request.command();
+
+        let response = match (command, &mut *state) {
+            (Command::Capa, _) => [
+                b"+OK\r\n".to_vec(),
+                capabilities().to_bytes(),
+                b".\r\n".to_vec(),
+            ]
+            .concat(),
+            (Command::Quit, State::Authorization { .. }) => positive("Bye"),
+            (
+                Command::Quit,
+                State::Transaction {
+                    maildrop, deleted, ..
+                },
+            ) => {
+                let deleted: Vec<usize> = deleted.iter().copied().collect();
+
+                let response = match maildrop.dele(&deleted) {
+                    Ok(()) => positive("Bye"),
+                    Err(err) => error_response(&err),
+                };
+
+                return (response, true);
+            }
+            (Command::User, State::Authorization { user }) => match request.args().first() {
+                Some(name) => {
+                    *user = Some(name.clone());
+
+                    positive("")
+                }
+                None => negative("Missing username"),
+            },
+            (Command::Pass, State::Authorization { user }) => {
+                let name = match user.take() {
+                    Some(name) => name,
+                    None => return (negative("Send USER first"), false),
+                };
+
+                // The password is the rest of the line, which may contain spaces.
+                let password = request.args().join(" ");
+
+                match self.login(&name, &password) {
+                    Ok((maildrop, sizes)) => {
+                        *state = State::Transaction {
+                            maildrop,
+                            sizes,
+                            deleted: BTreeSet::new(),
+                        };
+
+                        positive("Maildrop ready")
+                    }
+                    Err(err) => error_response(&err),
+                }
+            }
+            (_, State::Authorization { .. }) => negative("Not authenticated"),
+            (Command::User | Command::Pass, State::Transaction { .. }) => {
+                negative("Already authenticated")
+            }
+            (
+                _,
+                State::Transaction {
+                    maildrop,
+                    sizes,
+                    deleted,
+                },
+            ) => Self::transaction(request, maildrop, sizes, deleted),
+        };
+
+        (response, false)
+    }
+
+    fn login(&mut self, user: &str, password: &str) -> Result<(M, Vec<usize>)> {
+        let mut maildrop = match (self.login)(user, password) {
+            Ok(maildrop) => maildrop,
+            Err(err) if err.response_code().is_some() => return Err(err),
+            Err(err) => {
+                let response = ErrorResponse::new(Some(ResponseCode::Auth), err.message().into());
+
+                return Err(response.into());
+            }
+        };
+
+        let sizes = maildrop.list()?;
+
+        Ok((maildrop, sizes))
+    }
+
+    fn transaction(
+        request: &Request,
+        maildrop: &mut M,
+        sizes: &[usize],
+        deleted: &mut BTreeSet<usize>,
+    ) -> Vec<u8> {
+        let available = |number: &usize| !deleted.contains(number);
+
+        let msg_number = match request.args().first() {
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= sizes.len() && available(&number) => {
+                    Some(number)
+                }
+                _ => return negative("No such message"),
+            },
+            None => None,
+        };
+
+        let result = match (request.command(), msg_number) {
+            (Command::Noop, _) => Ok(positive("")),
+            (Command::Stat, _) => {
+                let (count, size) = (1..=sizes.len())
+                    .filter(available)
+                    .fold((0, 0), |(count, size), number| {
+                        (count + 1, size + sizes[number - 1])
+                    });
+
+                Ok(positive(&format!("{} {}", count, size)))
+            }
+            (Command::List { .. }, Some(number)) => {
+                Ok(positive(&format!("{} {}", number, sizes[number - 1])))
+            }
+            (Command::List { .. }, None) => {
+                let listing: String = (1..=sizes.len())
+                    .filter(available)
+                    .map(|number| format!("{} {}\r\n", number, sizes[number - 1]))
+                    .collect();
+
+                Ok(format!("+OK\r\n{}.\r\n", listing).into_bytes())
+            }
+            (Command::Uidl { .. }, Some(number)) => maildrop
+                .uidl(number)
+                .map(|uid| positive(&format!("{} {}", number, single_line(&uid)))),
+            (Command::Uidl { .. }, None) => (1..=sizes.len())
+                .filter(available)
+                .map(|number| {
+                    let uid = maildrop.uidl(number)?;
+
+                    Ok(format!("{} {}\r\n", number, single_line(&uid)))
+                })
+                .collect::<Result<String>>()
+                .map(|listing| format!("+OK\r\n{}.\r\n", listing).into_bytes()),
+            (Command::Retr, Some(number)) => maildrop.retr(number).map(|body| multiline(&body)),
+            (Command::Top, Some(number)) => {
+                match request.args().get(1).map(|lines| lines.parse()) {
+                    Some(Ok(lines)) => maildrop.top(number, lines).map(|body| multiline(&body)),
+                    _ => Ok(negative("Invalid number of lines")),
+                }
+            }
+            (Command::Dele, Some(number)) => {
+                deleted.insert(number);
+
+                Ok(positive("Message deleted"))
+            }
+            (Command::Rset, _) => {
+                deleted.clear();
+
+                Ok(positive(""))
+            }
+            (Command::Retr | Command::Top | Command::Dele, None) => {
+                Ok(negative("Missing message number"))
+            }
+            _ => Ok(negative("Unsupported command")),
+        };
+
+        result.unwrap_or_else(|err| error_response(&err))
+    }
+}
+
+async fn write_response<S: Read + Write + Unpin>(
+    stream: &mut BufReader<S>,
+    response: &[u8],
+) -> Result<()> {
+    let stream = stream.get_mut();
+
+    stream.write_all(response).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+/// Makes sure text that ends up in a response can not break out of its line.
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+fn positive(message: &str) -> Vec<u8> {
+    match message {
+        "" => b"+OK\r\n".to_vec(),
+        message => format!("+OK {}\r\n", single_line(message)).into_bytes(),
+    }
+}
+
+fn negative(message: &str) -> Vec<u8> {
+    format!("-ERR {}\r\n", message).into_bytes()
+}
+
+fn error_response(err: &Error) -> Vec<u8> {
+    let message = match err.kind() {
+        ErrorKind::ServerError { text, .. } if !text.raw().is_empty() => text.as_str_lossy(),
+        _ => err.message().into(),
+    };
+
+    let response = ErrorResponse::new(
+        err.response_code().cloned(),
+        single_line(&message).as_str().into(),
+    );
+
+    format!("-ERR {}\r\n", response).into_bytes()
+}
+
+/// Wraps a message in a multi-line response, dot-stuffing any line that starts with a dot.
+pub(crate) fn multiline(body: &[u8]) -> Vec<u8> {
+    let mut response = b"+OK\r\n".to_vec();
+
+    for line in body.split_inclusive(|byte| *byte == b'\n') {
+        if line.starts_with(b".") {
+            response.push(b'.');
+        }
+
+        response.extend_from_slice(line);
+    }
+
+    if !response.ends_with(b"\r\n") {
+        response.extend_from_slice(b"\r\n");
+    }
+
+    response.extend_from_slice(b".\r\n");
+
+    response
+}
+
+/// The headers of a message, along with the given amount of lines of its body.
+pub(crate) fn top(body: &[u8], lines: usize) -> Vec<u8> {
+    let (headers, rest) = match body.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(index) => body.split_at(index + 4),
+        None => (body, &[][..]),
+    };
+
+    let mut top = headers.to_vec();
+
+    for line in rest.split_inclusive(|byte| *byte == b'\n').take(lines) {
+        top.extend_from_slice(line);
+    }
+
+    top
+}
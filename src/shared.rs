@@ -0,0 +1,240 @@
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use futures::{
+    channel::oneshot,
+    lock::{Mutex as AsyncMutex, MutexGuard},
+};
+
+use crate::{
+    error::Result,
+    response::{list::ListResponse, stat::Stat, types::message::Text, uidl::UidlResponse},
+    runtime::io::{Read, Write},
+    Client, MessageNumber,
+};
+
+/// The tasks that are waiting for their turn to use the client, in the order they asked for it.
+#[derive(Default)]
+struct Queue {
+    busy: bool,
+    waiting: VecDeque<oneshot::Sender<()>>,
+}
+
+struct Shared<S: Read + Write + Unpin + Send> {
+    queue: Mutex<Queue>,
+    client: AsyncMutex<Client<S>>,
+}
+
+/// A cloneable handle to a single [Client], created using [Client::shared].
+///
+/// POP3 only allows one command at a time, so every handle takes turns using the session. Turns are handed out in the order they were asked for,
+/// meaning a busy task can not starve the others. A single command can be sent using the methods on the handle,
+/// a sequence of commands that should not be interleaved with those of other tasks using [SharedClient::lock].
+/// # Examples
+/// ```rust,ignore
+/// let client = client.shared();
+///
+/// // In every request handler, using its own clone of the handle:
+/// let stat = client.stat().await?;
+///
+/// // Commands that belong together are sent while holding the lock.
+/// let mut session = client.lock().await;
+///
+/// let uid = session.uidl(Some(1)).await?;
+/// let message = session.retr(1).await?;
+/// ```
+pub struct SharedClient<S: Read + Write + Unpin + Send> {
+    shared: Arc<Shared<S>>,
+}
+
+impl<S: Read + Write + Unpin + Send> Clone for SharedClient<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<S: Read + Write + Unpin + Send> SharedClient<S> {
+    pub(crate) fn new(client: Client<S>) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue::default()),
+                client: AsyncMutex::new(client),
+            }),
+        }
+    }
+
+    /// Wait for our turn and get exclusive access to the client, until the returned guard is dropped.
+    pub async fn lock(&self) -> SharedGuard<'_, S> {
+        let turn = self.turn().await;
+
+        // Only the task whose turn it is gets here, so this never has to wait.
+        let client = self.shared.client.lock().await;
+
+        SharedGuard {
+            client,
+            _turn: turn,
+        }
+    }
+
+    async fn turn(&self) -> Turn<'_> {
+        let queue = &self.shared.queue;
+
+        let receiver = {
+            let mut state = queue.lock().unwrap();
+
+            if !state.busy {
+                state.busy = true;
+
+                return Turn { queue };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+
+            state.waiting.push_back(sender);
+
+            receiver
+        };
+
+        let mut waiting = Waiting {
+            receiver: Some(receiver),
+            queue,
+        };
+
+        if let Some(receiver) = waiting.receiver.as_mut() {
+            // The sender is only dropped without sending when we stopped waiting, which can not happen while we are still waiting here.
+            receiver.await.ok();
+        }
+
+        waiting.receiver.take();
+
+        Turn { queue }
+    }
+
+    /// The amount of tasks that are waiting for their turn to use the client.
+    pub fn waiting(&self) -> usize {
+        self.shared
+            .queue
+            .lock()
+            .unwrap()
+            .waiting
+            .iter()
+            .filter(|sender| !sender.is_canceled())
+            .count()
+    }
+
+    /// Returns the client if this is the only handle left, or the handle itself otherwise.
+    pub fn into_inner(self) -> std::result::Result<Client<S>, Self> {
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => Ok(shared.client.into_inner()),
+            Err(shared) => Err(Self { shared }),
+        }
+    }
+
+    /// See [Client::noop].
+    pub async fn noop(&self) -> Result<Text> {
+        self.lock().await.noop().await
+    }
+
+    /// See [Client::stat].
+    pub async fn stat(&self) -> Result<Stat> {
+        self.lock().await.stat().await
+    }
+
+    /// See [Client::list].
+    pub async fn list<N: Into<MessageNumber>>(
+        &self,
+        msg_number: Option<N>,
+    ) -> Result<ListResponse> {
+        self.lock().await.list(msg_number).await
+    }
+
+    /// See [Client::uidl].
+    pub async fn uidl<N: Into<MessageNumber>>(
+        &self,
+        msg_number: Option<N>,
+    ) -> Result<UidlResponse> {
+        self.lock().await.uidl(msg_number).await
+    }
+
+    /// See [Client::retr].
+    pub async fn retr<N: Into<MessageNumber>>(&self, msg_number: N) -> Result<Bytes> {
+        self.lock().await.retr(msg_number).await
+    }
+
+    /// See [Client::top].
+    pub async fn top<N: Into<MessageNumber>>(&self, msg_number: N, lines: usize) -> Result<Bytes> {
+        self.lock().await.top(msg_number, lines).await
+    }
+
+    /// See [Client::dele].
+    pub async fn dele<N: Into<MessageNumber>>(&self, msg_number: N) -> Result<Text> {
+        self.lock().await.dele(msg_number).await
+    }
+}
+
+/// Gives the turn to the next task in line when dropped.
+struct Turn<'a> {
+    queue: &'a Mutex<Queue>,
+}
+
+impl<'a> Drop for Turn<'a> {
+    fn drop(&mut self) {
+        pass_on(self.queue);
+    }
+}
+
+/// Makes sure a turn is not lost when a task stops waiting right after it was handed the turn.
+struct Waiting<'a> {
+    receiver: Option<oneshot::Receiver<()>>,
+    queue: &'a Mutex<Queue>,
+}
+
+impl<'a> Drop for Waiting<'a> {
+    fn drop(&mut self) {
+        if let Some(mut receiver) = self.receiver.take() {
+            receiver.close();
+
+            if let Ok(Some(())) = receiver.try_recv() {
+                pass_on(self.queue);
+            }
+        }
+    }
+}
+
+fn pass_on(queue: &Mutex<Queue>) {
+    let mut queue = queue.lock().unwrap();
+
+    while let Some(next) = queue.waiting.pop_front() {
+        if next.send(()).is_ok() {
+            return;
+        }
+    }
+
+    queue.busy = false;
+}
+
+/// Exclusive access to the client of a [SharedClient], the next task gets its turn once this is dropped.
+pub struct SharedGuard<'a, S: Read + Write + Unpin + Send> {
+    client: MutexGuard<'a, Client<S>>,
+    _turn: Turn<'a>,
+}
+
+impl<'a, S: Read + Write + Unpin + Send> Deref for SharedGuard<'a, S> {
+    type Target = Client<S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl<'a, S: Read + Write + Unpin + Send> DerefMut for SharedGuard<'a, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
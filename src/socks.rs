@@ -0,0 +1,420 @@
+//! A minimal SOCKS5 client ([RFC 1928](https://www.rfc-editor.org/rfc/rfc1928)), used to tunnel the connection to the POP server through a proxy.
+//!
+//! Only the CONNECT command is supported, optionally authenticating with a username and password ([RFC 1929](https://www.rfc-editor.org/rfc/rfc1929)).
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    error::{err, ErrorKind, Result},
+    runtime::{
+        io::{ReadExt, WriteExt},
+        net::{connect, TcpStream},
+    },
+};
+
+const VERSION: u8 = 0x05;
+
+const NO_AUTHENTICATION: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+
+const CONNECT: u8 = 0x01;
+
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+
+/// The address of the server that the proxy should connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    Ip(SocketAddr),
+    /// A host name that is resolved by the proxy, so that no DNS requests leak from this machine.
+    Domain(String, u16),
+}
+
+impl From<SocketAddr> for Destination {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Ip(addr)
+    }
+}
+
+impl<H: Into<String>> From<(H, u16)> for Destination {
+    fn from((host, port): (H, u16)) -> Self {
+        Self::Domain(host.into(), port)
+    }
+}
+
+/// A SOCKS5 proxy server.
+///
+/// Pass it to [crate::ClientBuilder::proxy] to make [crate::ClientBuilder::connect] and [crate::ClientBuilder::connect_plain] tunnel through it.
+/// Those resolve the address of the POP server locally, use [Socks5Proxy::connect] together with [crate::ClientBuilder::build] to have the proxy resolve it instead (needed for .onion addresses).
+/// # Examples
+/// ```rust,ignore
+/// use async_pop::{socks::Socks5Proxy, ClientBuilder};
+///
+/// let proxy = Socks5Proxy::new("127.0.0.1:9050").credentials("user", "password");
+///
+/// let stream = proxy.connect(("pop.example.onion", 110)).await?;
+///
+/// let mut client = ClientBuilder::new().build(stream).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Socks5Proxy {
+    addr: String,
+    credentials: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    /// A proxy listening on the given address, e.g. `127.0.0.1:9050`.
+    pub fn new<A: Into<String>>(addr: A) -> Self {
+        Self {
+            addr: addr.into(),
+            credentials: None,
+        }
+    }
+
+    /// Authenticate with the proxy using a username and password.
+    pub fn credentials<U: Into<String>, P: Into<String>>(
+        mut self,
+        username: U,
+        password: P,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+
+        self
+    }
+
+    /// Opens a tunnel to the first of the given addresses that the proxy manages to connect to, trying them one after another.
+    pub(crate) async fn connect_any(
+        &self,
+        addrs: Vec<SocketAddr>,
+    ) -> Result<(TcpStream, SocketAddr)> {
+        let mut last_error = None;
+
+        for addr in addrs {
+            match self.connect(addr).await {
+                Ok(stream) => return Ok((stream, addr)),
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        match last_error {
+            Some(error) => Err(error),
+            None => err!(
+                ErrorKind::Proxy,
+                "The address of the server did not resolve to anything"
+            ),
+        }
+    }
+
+    /// Opens a tunnel through the proxy to the given destination.
+    pub async fn connect<D: Into<Destination>>(&self, destination: D) -> Result<TcpStream> {
+        let mut stream = connect(self.addr.as_str()).await?;
+
+        self.negotiate(&mut stream).await?;
+
+        let mut request = vec![VERSION, CONNECT, 0x00];
+
+        let port = match destination.into() {
+            Destination::Ip(addr) => {
+                match addr.ip() {
+                    IpAddr::V4(ip) => {
+                        request.push(ADDRESS_IPV4);
+                        request.extend_from_slice(&ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        request.push(ADDRESS_IPV6);
+                        request.extend_from_slice(&ip.octets());
+                    }
+                }
+
+                addr.port()
+            }
+            Destination::Domain(host, port) => {
+                if host.is_empty() || host.len() > u8::MAX as usize {
+                    err!(
+                        ErrorKind::Proxy,
+                        "The host name '{}' cannot be sent to the proxy",
+                        host
+                    )
+                }
+
+                request.push(ADDRESS_DOMAIN);
+                request.push(host.len() as u8);
+                request.extend_from_slice(host.as_bytes());
+
+                port
+            }
+        };
+
+        request.extend_from_slice(&port.to_be_bytes());
+
+        stream.write_all(&request).await?;
+
+        let mut reply = [0; 4];
+
+        stream.read_exact(&mut reply).await?;
+
+        if reply[1] != 0x00 {
+            err!(
+                ErrorKind::Proxy,
+                "The proxy failed to connect to the server: {}",
+                reply_message(reply[1])
+            )
+        }
+
+        // The address the proxy bound to is of no use to us, but it still has to be read.
+        let address_length = match reply[3] {
+            ADDRESS_IPV4 => 4,
+            ADDRESS_IPV6 => 16,
+            ADDRESS_DOMAIN => {
+                let mut length = [0; 1];
+
+                stream.read_exact(&mut length).await?;
+
+                length[0] as usize
+            }
+            _ => err!(ErrorKind::Proxy, "The proxy sent an invalid reply"),
+        };
+
+        let mut bound = vec![0; address_length + 2];
+
+        stream.read_exact(&mut bound).await?;
+
+        Ok(stream)
+    }
+
+    async fn negotiate(&self, stream: &mut TcpStream) -> Result<()> {
+        let method = match self.credentials {
+            Some(_) => USERNAME_PASSWORD,
+            None => NO_AUTHENTICATION,
+        };
+
+        stream.write_all(&[VERSION, 1, method]).await?;
+
+        let mut reply = [0; 2];
+
+        stream.read_exact(&mut reply).await?;
+
+        if reply[0] != VERSION {
+            err!(ErrorKind::Proxy, "The proxy is not a SOCKS5 proxy")
+        }
+
+        if reply[1] != method {
+            err!(
+                ErrorKind::Proxy,
+                "The proxy does not accept the requested authentication method"
+            )
+        }
+
+        if let Some((username, password)) = &self.credentials {
+            if username.len() > u8::MAX as usize || password.len() > u8::MAX as usize {
+                err!(ErrorKind::Proxy, "The proxy credentials are too long")
+            }
+
+            let mut request = vec![0x01, username.len() as u8];
+
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+
+            stream.write_all(&request).await?;
+
+            stream.read_exact(&mut reply).await?;
+
+            if reply[1] != 0x00 {
+                err!(ErrorKind::Proxy, "The proxy rejected the credentials")
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+
+    use crate::{
+        error::ErrorKind,
+        runtime::{
+            io::{ReadExt, WriteExt},
+            net::TcpListener,
+            spawn,
+        },
+    };
+
+    use super::Socks5Proxy;
+
+    /// A step of the proxy script: the bytes the proxy expects to receive and the bytes it replies with.
+    type Step = (&'static [u8], &'static [u8]);
+
+    /// Starts a proxy that accepts one connection per script and plays it back.
+    async fn scripted_proxy(scripts: Vec<Vec<Step>>) -> SocketAddr {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        spawn(async move {
+            for script in scripts {
+                let (mut stream, _) = listener.accept().await.unwrap();
+
+                for (expected, reply) in script {
+                    let mut received = vec![0; expected.len()];
+
+                    stream.read_exact(&mut received).await.unwrap();
+
+                    assert_eq!(received, expected);
+
+                    stream.write_all(reply).await.unwrap();
+                }
+            }
+        });
+
+        addr
+    }
+
+    fn destination() -> SocketAddr {
+        "127.0.0.1:110".parse().unwrap()
+    }
+
+    #[cfg_attr(
+        all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+        tokio::test
+    )]
+    #[cfg_attr(feature = "runtime-async-std", async_std::test)]
+    async fn test_connect_no_authentication() {
+        let addr = scripted_proxy(vec![vec![
+            (&[5, 1, 0], &[5, 0]),
+            (
+                &[5, 1, 0, 1, 127, 0, 0, 1, 0, 110],
+                b"\x05\x00\x00\x01\x00\x00\x00\x00\x00\x00+OK\r\n",
+            ),
+        ]])
+        .await;
+
+        let mut stream = Socks5Proxy::new(addr.to_string())
+            .connect(destination())
+            .await
+            .unwrap();
+
+        let mut greeting = [0; 5];
+
+        stream.read_exact(&mut greeting).await.unwrap();
+
+        assert_eq!(&greeting, b"+OK\r\n");
+    }
+
+    #[cfg_attr(
+        all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+        tokio::test
+    )]
+    #[cfg_attr(feature = "runtime-async-std", async_std::test)]
+    async fn test_connect_credentials() {
+        let addr = scripted_proxy(vec![vec![
+            (&[5, 1, 2], &[5, 2]),
+            (b"\x01\x04user\x06secret", &[1, 0]),
+            (
+                b"\x05\x01\x00\x03\x0bexample.org\x00\x6e",
+                b"\x05\x00\x00\x03\x05proxy\x00\x00",
+            ),
+        ]])
+        .await;
+
+        Socks5Proxy::new(addr.to_string())
+            .credentials("user", "secret")
+            .connect(("example.org", 110))
+            .await
+            .unwrap();
+    }
+
+    #[cfg_attr(
+        all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+        tokio::test
+    )]
+    #[cfg_attr(feature = "runtime-async-std", async_std::test)]
+    async fn test_connect_rejected_credentials() {
+        let addr = scripted_proxy(vec![vec![
+            (&[5, 1, 2], &[5, 2]),
+            (b"\x01\x04user\x05wrong", &[1, 1]),
+        ]])
+        .await;
+
+        let error = Socks5Proxy::new(addr.to_string())
+            .credentials("user", "wrong")
+            .connect(destination())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error.kind(), ErrorKind::Proxy));
+    }
+
+    #[cfg_attr(
+        all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+        tokio::test
+    )]
+    #[cfg_attr(feature = "runtime-async-std", async_std::test)]
+    async fn test_connect_error_reply() {
+        let addr = scripted_proxy(vec![vec![
+            (&[5, 1, 0], &[5, 0]),
+            (
+                &[5, 1, 0, 1, 127, 0, 0, 1, 0, 110],
+                &[5, 5, 0, 1, 0, 0, 0, 0, 0, 0],
+            ),
+        ]])
+        .await;
+
+        let error = Socks5Proxy::new(addr.to_string())
+            .connect(destination())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error.kind(), ErrorKind::Proxy));
+        assert!(error.message().contains("connection refused"));
+    }
+
+    #[cfg_attr(
+        all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+        tokio::test
+    )]
+    #[cfg_attr(feature = "runtime-async-std", async_std::test)]
+    async fn test_connect_any() {
+        let addr = scripted_proxy(vec![
+            vec![
+                (&[5, 1, 0], &[5, 0]),
+                (
+                    &[
+                        5, 1, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 110,
+                    ],
+                    &[5, 4, 0, 1, 0, 0, 0, 0, 0, 0],
+                ),
+            ],
+            vec![
+                (&[5, 1, 0], &[5, 0]),
+                (
+                    &[5, 1, 0, 1, 127, 0, 0, 1, 0, 110],
+                    &[5, 0, 0, 1, 0, 0, 0, 0, 0, 0],
+                ),
+            ],
+        ])
+        .await;
+
+        let (_, connected) = Socks5Proxy::new(addr.to_string())
+            .connect_any(vec!["[::1]:110".parse().unwrap(), destination()])
+            .await
+            .unwrap();
+
+        assert_eq!(connected, destination());
+    }
+}
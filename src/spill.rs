@@ -0,0 +1,64 @@
+//! Retrieving messages that may be too large to keep in memory, see [crate::Client::retr_spill].
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, TryStreamExt};
+
+use crate::{
+    error::Result,
+    runtime::{
+        fs::{temp_file, File, SeekExt},
+        io::WriteExt,
+    },
+};
+
+/// A retrieved message, which is written to a temporary file if it turned out to be larger than the threshold.
+#[derive(Debug)]
+pub enum SpilledMessage {
+    InMemory(Bytes),
+    /// An anonymous temporary file containing the message, positioned at the start.
+    ///
+    /// The file is removed from the disk once it is closed.
+    OnDisk(File),
+}
+
+impl SpilledMessage {
+    pub fn is_on_disk(&self) -> bool {
+        matches!(self, Self::OnDisk(_))
+    }
+}
+
+/// Collect the given chunks in memory, moving them to a temporary file once there are more than `threshold` bytes.
+pub(crate) async fn collect<C: Stream<Item = Result<Bytes>> + Unpin>(
+    mut chunks: C,
+    threshold: usize,
+) -> Result<SpilledMessage> {
+    let mut buffer = BytesMut::new();
+    let mut file: Option<File> = None;
+
+    while let Some(chunk) = chunks.try_next().await? {
+        match file.as_mut() {
+            Some(file) => file.write_all(&chunk).await?,
+            None if buffer.len() + chunk.len() > threshold => {
+                let mut spilled = temp_file()?;
+
+                spilled.write_all(&buffer).await?;
+                spilled.write_all(&chunk).await?;
+
+                buffer.clear();
+
+                file = Some(spilled);
+            }
+            None => buffer.extend_from_slice(&chunk),
+        }
+    }
+
+    match file {
+        Some(mut file) => {
+            file.flush().await?;
+
+            file.seek(std::io::SeekFrom::Start(0)).await?;
+
+            Ok(SpilledMessage::OnDisk(file))
+        }
+        None => Ok(SpilledMessage::InMemory(buffer.freeze())),
+    }
+}
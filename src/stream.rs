@@ -1,126 +1,545 @@
-use bytes::BytesMut;
-use futures::{ready, Stream, StreamExt};
-use log::trace;
-use nom::Needed;
+use bytes::{Bytes, BytesMut};
+use futures::{future::poll_fn, ready, Stream, StreamExt};
+use nom::{IResult, Needed};
 use std::{
+    collections::VecDeque,
     pin::Pin,
-    str,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 use crate::{
     command::Command,
-    error::{err, ErrorKind},
-    macros::escape_newlines,
+    error::{err, Error, ErrorKind},
+    event::Event,
+    interceptor::Interceptor,
+    macros::{debug, escaped, trace},
+    metrics::Metrics,
     request::Request,
-    response::Response,
+    response::{parser, types::message::Text, ParseMode, Response},
     runtime::{
         io::{Read, Write, WriteExt},
         Instant,
     },
+    throttle::RateLimit,
+    timer::{default_timer, Timer},
+    wire_log::WireLog,
 };
 
-use crate::{constants::END_OF_LINE, error::Result};
+use crate::{
+    constants::{CR, END_OF_LINE, LF, MULTILINE_END, OK},
+    error::Result,
+};
 
 pub struct PopStream<S: Read + Write + Unpin> {
     last_activity: Instant,
     buffer: Buffer,
     decode_needs: usize,
+    scanned: usize,
     queue: CommandQueue,
+    mode: ParseMode,
+    events: Vec<Event>,
+    body: Option<MultilineBody>,
+    read_limit: Option<RateLimit>,
+    write_limit: Option<RateLimit>,
+    timer: Arc<dyn Timer>,
+    /// The lines that were sent and are still waiting for a response, in the order they were sent.
+    sent: VecDeque<Sent>,
+    /// Data that still has to be written to the server, which is kept when a write is cancelled so the line is not cut off.
+    outgoing: Vec<u8>,
+    metrics: Metrics,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    /// Whether the last byte that was read is a carriage return, used to normalize line endings in lenient mode.
+    after_cr: bool,
+    raw: RawCopy,
+    wire_log: Option<WireLog>,
+    /// Whether an earlier error left the stream in an unknown state, after which it is no longer used.
+    poisoned: bool,
     stream: S,
 }
 
 impl<S: Read + Write + Unpin> PopStream<S> {
     /// Send a command to the server and read the response into a string.
     pub async fn encode(&mut self, request: &Request) -> Result<()> {
-        self.send_bytes(request.to_string()).await?;
+        self.check_usable()?;
+
+        request.validate()?;
+
+        self.intercept_request(request)?;
+
+        self.resync().await?;
+
+        trace!("C: {}", request.redacted());
+
+        self.write_line(request.to_string(), is_multiline(request.command()))
+            .await?;
 
         Ok(())
     }
 
-    /// Send some bytes to the server
+    /// Send multiple commands to the server in a single write, without waiting for a response in between.
+    pub async fn encode_all(&mut self, requests: &[Request]) -> Result<()> {
+        self.check_usable()?;
+
+        for request in requests {
+            request.validate()?;
+
+            self.intercept_request(request)?;
+        }
+
+        self.resync().await?;
+
+        self.last_activity = Instant::now();
+
+        for request in requests {
+            trace!("C: {}", request.redacted());
+
+            self.outgoing
+                .extend_from_slice(request.to_string().as_bytes());
+            self.outgoing.extend_from_slice(&END_OF_LINE);
+
+            self.sent.push_back(Sent {
+                at: self.last_activity,
+                multiline: is_multiline(request.command()),
+            });
+        }
+
+        self.write_outgoing().await
+    }
+
+    fn intercept_request(&self, request: &Request) -> Result<()> {
+        for interceptor in &self.interceptors {
+            interceptor.on_request(request)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send some bytes to the server, to which the server responds with a single line.
     pub async fn send_bytes<B: AsRef<[u8]>>(&mut self, buf: B) -> Result<()> {
-        trace!(
-            "C: {}",
-            escape_newlines!(str::from_utf8(buf.as_ref()).unwrap())
-        );
+        self.check_usable()?;
+
+        self.resync().await?;
+
+        trace!("C: {}", escaped(buf.as_ref()));
 
+        self.write_line(buf, false).await
+    }
+
+    async fn write_line<B: AsRef<[u8]>>(&mut self, buf: B, multiline: bool) -> Result<()> {
         self.last_activity = Instant::now();
 
-        self.stream.write_all(buf.as_ref()).await?;
+        self.outgoing.extend_from_slice(buf.as_ref());
+        self.outgoing.extend_from_slice(&END_OF_LINE);
+
+        // From here on the server owes us a response, even if we are cancelled before the whole line is written.
+        self.sent.push_back(Sent {
+            at: self.last_activity,
+            multiline,
+        });
+
+        self.write_outgoing().await
+    }
+
+    /// Fails if an earlier error left the stream in an unknown state.
+    fn check_usable(&self) -> Result<()> {
+        if self.poisoned {
+            err!(
+                ErrorKind::Poisoned,
+                "An earlier error left the connection in an unknown state, a new connection has to be opened"
+            )
+        }
+
+        Ok(())
+    }
+
+    /// Whether an earlier error left the stream in an unknown state, e.g. a response that could not be parsed or a failed read.
+    ///
+    /// Cancelled commands do not poison the stream, as their responses are thrown away before the next command is sent.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Stop using the stream, e.g. because the server did not answer in time.
+    pub(crate) fn poison(&mut self) {
+        self.poisoned = true;
+    }
+
+    /// Write all of the outgoing data to the stream, respecting the write rate limit.
+    ///
+    /// Data is only removed once it has been written, so whatever is left after a cancelled write is written by the next call.
+    async fn write_outgoing(&mut self) -> Result<()> {
+        let result = self.write_outgoing_unchecked().await;
+
+        if result.is_err() {
+            self.poisoned = true;
+        }
+
+        result
+    }
+
+    async fn write_outgoing_unchecked(&mut self) -> Result<()> {
+        while !self.outgoing.is_empty() {
+            let allowed = match self.write_limit.as_mut() {
+                Some(limit) => poll_fn(|cx| limit.poll_ready(cx)).await,
+                None => self.outgoing.len(),
+            };
+
+            let len = allowed.min(self.outgoing.len());
+
+            let written = self.stream.write(&self.outgoing[..len]).await?;
+
+            if written == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into());
+            }
+
+            let chunk: Vec<u8> = self.outgoing.drain(..written).collect();
 
-        self.stream.write_all(&END_OF_LINE).await?;
+            self.metrics.bytes_sent += chunk.len() as u64;
+
+            if let Some(log) = self.wire_log.as_mut() {
+                if !log.sent(&chunk) {
+                    self.wire_log = None;
+                }
+            }
+
+            if let Some(limit) = self.write_limit.as_mut() {
+                limit.consume(chunk.len());
+            }
+        }
 
         self.stream.flush().await?;
 
         Ok(())
     }
+
+    /// Get back in sync with the server after a command was cancelled, before sending anything new.
+    ///
+    /// The rest of a cancelled write is sent, after which the responses that nobody is waiting for anymore are read and thrown away,
+    /// including the rest of a multi-line response that was not read until the end.
+    async fn resync(&mut self) -> Result<()> {
+        self.write_outgoing().await?;
+
+        let orphaned = self.sent.len() + usize::from(self.body.is_some());
+
+        if orphaned == 0 {
+            return Ok(());
+        }
+
+        debug!("Discarding {} responses to cancelled commands", orphaned);
+
+        // Whatever a cancelled read was in the middle of parsing is discarded as well.
+        self.queue.clear();
+        self.decode_needs = 0;
+        self.scanned = 0;
+
+        poll_fn(|cx| self.poll_discard(cx)).await?;
+
+        self.events
+            .push(Event::DiscardedResponses { count: orphaned });
+
+        Ok(())
+    }
+
+    fn poll_discard(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            if self.body.is_some() {
+                while ready!(self.poll_body_chunk(cx))?.is_some() {}
+
+                continue;
+            }
+
+            let multiline = match self.sent.front() {
+                Some(sent) => sent.multiline,
+                None => return Poll::Ready(Ok(())),
+            };
+
+            let filled = self.buffer.filled();
+
+            match filled.iter().position(|byte| *byte == LF) {
+                Some(end) => {
+                    // Only a positive response to a multi-line command has a body.
+                    let has_body = multiline && filled.starts_with(OK.as_bytes());
+
+                    self.buffer.consume(end + 1);
+                    self.buffer.shrink();
+
+                    self.sent.pop_front();
+
+                    if has_body {
+                        self.body = Some(MultilineBody::new());
+                    }
+                }
+                None => ready!(self.poll_fill_buffer(cx))?,
+            }
+        }
+    }
 }
 
 impl<S: Read + Write + Unpin> PopStream<S> {
-    fn decode(&mut self) -> Result<Option<Response>> {
+    /// Try to parse a value from the data that is currently in the buffer using the given parser.
+    ///
+    /// Returns [None] if more data is needed to parse the value.
+    fn decode_with<T, P>(&mut self, parse: P) -> Result<Option<T>>
+    where
+        P: FnOnce(&[u8]) -> IResult<&[u8], T>,
+    {
         if self.buffer.cursor() < self.decode_needs {
             return Ok(None);
         }
 
-        let used = self.buffer.take();
+        let filled = self.buffer.filled();
 
-        let current_command = self.queue.current();
+        let (consumed, value) = match parse(filled) {
+            Ok((remaining, value)) => {
+                let consumed = filled.len() - remaining.len();
 
-        match current_command {
-            Some(command) => {
-                match Response::from_bytes(&used[..self.buffer.cursor()], command) {
-                    Ok((remaining, response)) => {
-                        trace!(
-                            "S: {}",
-                            escape_newlines!(str::from_utf8(used.as_ref()).unwrap())
-                        );
+                trace!("S: {}", escaped(&filled[..consumed]));
 
-                        self.queue.mark_current_as_done();
+                self.raw.start(&filled[..consumed]);
 
-                        self.buffer.reset_with(remaining);
+                (consumed, value)
+            }
+            Err(nom::Err::Incomplete(Needed::Size(min))) => {
+                self.decode_needs = self.buffer.cursor() + min.get();
+
+                return Ok(None);
+            }
+            Err(nom::Err::Incomplete(_)) => {
+                self.decode_needs = 0;
+
+                return Ok(None);
+            }
+            Err(other) => {
+                // Keep whatever the parser choked on, so it can be inspected afterwards.
+                self.raw.start(filled);
+
+                // We do not know where the response ends, so the next one can not be found either.
+                self.poisoned = true;
+
+                err!(
+                    ErrorKind::InvalidResponse,
+                    "The server gave an invalid response: '{}'",
+                    other
+                )
+            }
+        };
+
+        self.decode_needs = 0;
+        self.scanned = 0;
+
+        self.buffer.consume(consumed);
+        self.buffer.shrink();
+
+        Ok(Some(value))
+    }
+
+    fn decode(&mut self) -> Result<Option<Response>> {
+        let command = match self.queue.current() {
+            Some(command) => command.clone(),
+            None => err!(
+                ErrorKind::MissingRequest,
+                "Trying to read a response without having sent a request"
+            ),
+        };
+
+        let mode = self.mode;
+
+        let decoded = match self.response_frame(&command) {
+            Frame::Incomplete => return Ok(None),
+            Frame::Unknown => self
+                .decode_with(|input| Self::parse_response(input, &command, mode, &parser::copy))?,
+            Frame::Complete(len) => {
+                let frame = self.buffer.split_frame(len);
+
+                self.decode_needs = 0;
+                self.scanned = 0;
+
+                trace!("S: {}", escaped(&frame));
+
+                self.raw.start(&frame);
+
+                let share = |slice: &[u8]| frame.slice_ref(slice);
+
+                match Self::parse_response(&frame, &command, mode, &share) {
+                    Ok((_, decoded)) => Some(decoded),
+                    Err(err) => {
+                        // The end of the response is known, but whatever the server meant by it is not.
+                        self.poisoned = true;
 
-                        return Ok(Some(response));
-                    }
-                    Err(nom::Err::Incomplete(Needed::Size(min))) => {
-                        self.decode_needs = self.buffer.cursor() + min.get()
-                    }
-                    Err(nom::Err::Incomplete(_)) => {
-                        self.decode_needs = 0;
-                    }
-                    Err(other) => {
                         err!(
                             ErrorKind::InvalidResponse,
                             "The server gave an invalid response: '{}'",
-                            other
+                            err
                         )
                     }
-                };
+                }
             }
-            None => {
-                self.buffer.return_to(used);
+        };
 
-                err!(
-                    ErrorKind::MissingRequest,
-                    "Trying to read a response without having sent a request"
-                );
+        match decoded {
+            Some((response, event)) => {
+                if let Some(event) = event {
+                    self.events.push(event);
+                }
+
+                self.complete_current(&response);
+
+                Ok(Some(response))
             }
+            None => Ok(None),
+        }
+    }
+
+    /// Marks the current command as answered, recording how long the response took.
+    fn complete_current(&mut self, response: &Response) {
+        let command = self.queue.mark_current_as_done();
+
+        for interceptor in &self.interceptors {
+            interceptor.on_response(&command, response);
+        }
+
+        // The greeting is not a response to anything that was sent.
+        if command == Command::Greet {
+            return;
+        }
+
+        if let Some(sent) = self.sent.pop_front() {
+            self.metrics.record(&command, sent.at.elapsed());
+        }
+    }
+
+    fn parse_response<'a>(
+        input: &'a [u8],
+        command: &Command,
+        mode: ParseMode,
+        share: parser::Share,
+    ) -> IResult<&'a [u8], (Response, Option<Event>)> {
+        let (remaining, response) = parser::parse_shared(input, command, mode, share)?;
+
+        let event = if mode.is_lenient() {
+            let consumed = &input[..input.len() - remaining.len()];
+
+            Self::skipped_lines_event(command, consumed)
+        } else {
+            None
+        };
+
+        Ok((remaining, (response, event)))
+    }
+
+    /// Finds out how much of the buffer the response to the given command spans.
+    ///
+    /// A multi-line response is only parsed once its termination octet came in, instead of running the parser over the whole buffer after every read.
+    /// The buffer is searched from where the previous call left off, so decoding a large message takes linear time.
+    fn response_frame(&mut self, command: &Command) -> Frame {
+        let multiline = is_multiline(command);
+
+        let filled = self.buffer.filled();
+
+        // Negative responses are a single line, let the parser handle them.
+        if !multiline || !filled.starts_with(OK.as_bytes()) {
+            return Frame::Unknown;
+        }
+
+        let from = self.scanned.saturating_sub(MULTILINE_END.len() - 1);
+
+        if let Some(end) = find_multiline_end(&filled[from..]) {
+            return Frame::Complete(from + end);
         }
 
-        self.buffer.return_to(used);
+        self.scanned = filled.len();
 
-        Ok(None)
+        Frame::Incomplete
+    }
+
+    /// Creates an event if any blank lines were skipped while parsing a multi-line listing.
+    fn skipped_lines_event(command: &Command, consumed: &[u8]) -> Option<Event> {
+        if !matches!(
+            command,
+            Command::Capa | Command::List { .. } | Command::Uidl { .. }
+        ) {
+            return None;
+        }
+
+        match parser::count_blank_lines(consumed) {
+            0 => None,
+            count => Some(Event::SkippedBlankLines {
+                command: command.to_string(),
+                count,
+            }),
+        }
+    }
+
+    /// Read more data from the underlying stream into the buffer.
+    ///
+    /// The stream is poisoned if this fails, as part of a response may have been read already.
+    fn poll_fill_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let result = ready!(self.poll_fill_buffer_unchecked(cx));
+
+        if result.is_err() {
+            self.poisoned = true;
+        }
+
+        Poll::Ready(result)
+    }
+
+    fn poll_fill_buffer_unchecked(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.buffer.ensure_capacity(self.decode_needs)?;
+
+        let allowed = match self.read_limit.as_mut() {
+            Some(limit) => ready!(limit.poll_ready(cx)),
+            None => usize::MAX,
+        };
+
+        let buf = self.buffer.unused();
+        let len = allowed.min(buf.len());
+        let buf = &mut buf[..len];
+
+        let bytes_read = ready!(Pin::new(&mut self.stream).poll_read(cx, buf))?;
+
+        if let Some(log) = self.wire_log.as_mut() {
+            if !log.received(&buf[..bytes_read]) {
+                self.wire_log = None;
+            }
+        }
+
+        if bytes_read == 0 {
+            return Poll::Ready(Err(Error::new(
+                ErrorKind::ConnectionClosed,
+                "The server closed the connection",
+            )));
+        }
+
+        let start = self.buffer.cursor();
+
+        self.buffer.move_cursor(bytes_read);
+
+        self.metrics.bytes_received += bytes_read as u64;
+
+        if self.mode.is_lenient() {
+            self.buffer
+                .normalize_line_endings(start, &mut self.after_cr)?;
+        }
+
+        if let Some(limit) = self.read_limit.as_mut() {
+            limit.consume(bytes_read);
+        }
+
+        Poll::Ready(Ok(()))
     }
 
     pub async fn read_response<C: Into<Command>>(&mut self, command: C) -> Result<Response> {
-        self.queue.add(command);
+        self.check_usable()?;
+
+        let command = command.into();
+
+        self.queue.add(command.clone());
 
         if let Some(resp_result) = self.next().await {
             return match resp_result {
                 Ok(resp) => match resp {
-                    Response::Err(err) => {
-                        err!(ErrorKind::ServerError(err.to_string()), "Server error")
-                    }
+                    Response::Err(err) => Err(Error::from(err).for_command(command)),
                     _ => Ok(resp),
                 },
                 Err(err) => Err(err),
@@ -129,42 +548,174 @@ impl<S: Read + Write + Unpin> PopStream<S> {
 
         unreachable!()
     }
-}
 
-impl<S: Read + Write + Unpin> Stream for PopStream<S> {
-    type Item = Result<Response>;
+    /// Read only the status line of the response to the given command.
+    ///
+    /// The body of the response can then be read in chunks using [PopStream::next_body_chunk].
+    pub async fn read_status<C: Into<Command>>(&mut self, command: C) -> Result<Text> {
+        self.check_usable()?;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if let Some(response) = self.decode()? {
-            return Poll::Ready(Some(Ok(response)));
+        let command = command.into();
+
+        self.queue.add(command.clone());
+
+        let response = poll_fn(|cx| self.poll_status(cx)).await?;
+
+        self.complete_current(&response);
+
+        match response {
+            Response::Message(message) => {
+                self.body = Some(MultilineBody::new());
+
+                Ok(message)
+            }
+            Response::Err(err) => Err(Error::from(err).for_command(command)),
+            _ => unreachable!(),
         }
+    }
 
-        let this = &mut *self;
+    fn poll_status(&mut self, cx: &mut Context<'_>) -> Poll<Result<Response>> {
+        loop {
+            if let Some(response) = self.decode_with(parser::status_line)? {
+                return Poll::Ready(Ok(response));
+            }
 
+            ready!(self.poll_fill_buffer(cx))?;
+        }
+    }
+
+    /// Read the next chunk of the multi-line response body that is currently being received.
+    ///
+    /// Returns [None] once the termination octet has been reached.
+    pub async fn next_body_chunk(&mut self) -> Result<Option<Bytes>> {
+        poll_fn(|cx| self.poll_body_chunk(cx)).await
+    }
+
+    fn poll_body_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Bytes>>> {
         loop {
-            this.buffer.ensure_capacity(this.decode_needs)?;
+            let body = match self.body.as_mut() {
+                Some(body) => body,
+                None => return Poll::Ready(Ok(None)),
+            };
 
-            let buf = this.buffer.unused();
+            let (chunk, consumed, done) = body.scan(self.buffer.filled());
 
-            #[cfg(feature = "runtime-async-std")]
-            let bytes_read = ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+            self.raw.extend(&self.buffer.filled()[..consumed]);
 
-            #[cfg(feature = "runtime-tokio")]
-            let bytes_read = {
-                let buf = &mut tokio::io::ReadBuf::new(buf);
+            self.buffer.consume(consumed);
 
-                let start = buf.filled().len();
+            if done {
+                self.body = None;
+            }
 
-                ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+            if !chunk.is_empty() {
+                return Poll::Ready(Ok(Some(chunk.freeze())));
+            }
 
-                buf.filled().len() - start
-            };
+            if done {
+                return Poll::Ready(Ok(None));
+            }
+
+            ready!(self.poll_fill_buffer(cx))?;
+        }
+    }
+
+    /// A stream of all the remaining chunks of the multi-line response body that is currently being received.
+    pub fn body_chunks(&mut self) -> BodyChunks<'_, S> {
+        BodyChunks { stream: self }
+    }
+
+    /// Read the next line of the multi-line response that is currently being received, without its line ending and byte-stuffing.
+    ///
+    /// Every line is removed from the buffer once it is returned, so only a single line has to fit in memory at a time.
+    /// Returns [None] once the termination octet has been reached.
+    pub async fn next_line(&mut self) -> Result<Option<Bytes>> {
+        poll_fn(|cx| self.poll_line(cx)).await
+    }
+
+    fn poll_line(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Bytes>>> {
+        loop {
+            if self.body.is_none() {
+                return Poll::Ready(Ok(None));
+            }
+
+            let filled = self.buffer.filled();
+
+            if let Some(end) = filled.iter().position(|byte| *byte == LF) {
+                let line = &filled[..end];
+                let line = line.strip_suffix(&[CR]).unwrap_or(line);
+
+                let next = match line {
+                    b"." => None,
+                    b"" if self.mode.is_lenient() => Some(None),
+                    line => Some(Some(Bytes::copy_from_slice(
+                        line.strip_prefix(b".").unwrap_or(line),
+                    ))),
+                };
+
+                self.raw.extend(&filled[..end + 1]);
+
+                self.buffer.consume(end + 1);
+                self.buffer.shrink();
+
+                match next {
+                    Some(Some(line)) => return Poll::Ready(Ok(Some(line))),
+                    // A blank line that is skipped because the parse mode is lenient.
+                    Some(None) => continue,
+                    None => {
+                        self.body = None;
+
+                        return Poll::Ready(Ok(None));
+                    }
+                }
+            }
+
+            ready!(self.poll_fill_buffer(cx))?;
+        }
+    }
+
+    /// A stream of all the remaining lines of the multi-line response that is currently being received, see [PopStream::next_line].
+    pub fn lines(&mut self) -> Lines<'_, S> {
+        Lines { stream: self }
+    }
+}
+
+/// A stream over the lines of a multi-line response, see [PopStream::lines].
+pub struct Lines<'a, S: Read + Write + Unpin> {
+    stream: &'a mut PopStream<S>,
+}
+
+impl<S: Read + Write + Unpin> Stream for Lines<'_, S> {
+    type Item = Result<Bytes>;
 
-            this.buffer.move_cursor(bytes_read);
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.poll_line(cx).map(Result::transpose)
+    }
+}
 
-            if let Some(response) = this.decode()? {
+/// A stream over the chunks of a multi-line response body, see [PopStream::body_chunks].
+pub struct BodyChunks<'a, S: Read + Write + Unpin> {
+    stream: &'a mut PopStream<S>,
+}
+
+impl<S: Read + Write + Unpin> Stream for BodyChunks<'_, S> {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.poll_body_chunk(cx).map(Result::transpose)
+    }
+}
+
+impl<S: Read + Write + Unpin> Stream for PopStream<S> {
+    type Item = Result<Response>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(response) = self.decode()? {
                 return Poll::Ready(Some(Ok(response)));
             }
+
+            ready!(self.poll_fill_buffer(cx))?;
         }
     }
 }
@@ -176,6 +727,21 @@ impl<S: Read + Write + Unpin> PopStream<S> {
             buffer: Buffer::new(),
             queue: CommandQueue::new(),
             decode_needs: 0,
+            scanned: 0,
+            mode: ParseMode::default(),
+            events: Vec::new(),
+            body: None,
+            read_limit: None,
+            write_limit: None,
+            timer: default_timer(),
+            sent: VecDeque::new(),
+            outgoing: Vec::new(),
+            metrics: Metrics::default(),
+            interceptors: Vec::new(),
+            after_cr: false,
+            raw: RawCopy::default(),
+            wire_log: None,
+            poisoned: false,
             stream,
         }
     }
@@ -183,6 +749,161 @@ impl<S: Read + Write + Unpin> PopStream<S> {
     pub fn last_activity(&self) -> Instant {
         self.last_activity
     }
+
+    /// Returns the underlying stream, so that it can be upgraded to a secure connection.
+    ///
+    /// Fails if the server already sent data that has not been read yet, as that data can not be trusted after the upgrade.
+    pub fn into_inner(self) -> Result<S> {
+        if self.buffer.cursor() > 0 {
+            err!(
+                ErrorKind::UnexpectedResponse,
+                "The server sent unexpected data before the connection was upgraded"
+            )
+        }
+
+        Ok(self.stream)
+    }
+
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.mode = mode
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    pub fn set_max_response_size(&mut self, max_size: usize) {
+        self.buffer.max_size = max_size
+    }
+
+    pub fn max_response_size(&self) -> usize {
+        self.buffer.max_size
+    }
+
+    /// Change the size the read buffer starts out with and how it grows, the size is at least 1 byte.
+    pub fn set_read_buffer(&mut self, size: usize, growth: BufferGrowth) {
+        self.buffer.chunk_size = size.max(1);
+        self.buffer.growth = growth;
+
+        if self.buffer.cursor == 0 {
+            self.buffer.inner = BytesMut::zeroed(self.buffer.chunk_size);
+        }
+    }
+
+    /// The size the read buffer starts out with and how it grows.
+    pub fn read_buffer(&self) -> (usize, BufferGrowth) {
+        (self.buffer.chunk_size, self.buffer.growth)
+    }
+
+    /// Limit how many bytes per second are read from and written to the server, [None] removes the limit.
+    pub fn set_rate_limits(&mut self, read: Option<u64>, write: Option<u64>) {
+        self.read_limit = read.map(|limit| RateLimit::new(limit, self.timer.clone()));
+        self.write_limit = write.map(|limit| RateLimit::new(limit, self.timer.clone()));
+    }
+
+    /// Change the timer used for the rate limits, which only applies to limits that are set afterwards.
+    pub fn set_timer(&mut self, timer: Arc<dyn Timer>) {
+        self.timer = timer;
+    }
+
+    pub fn timer(&self) -> &Arc<dyn Timer> {
+        &self.timer
+    }
+
+    /// How many bytes per second may be read from and written to the server.
+    pub fn rate_limits(&self) -> (Option<u64>, Option<u64>) {
+        (
+            self.read_limit.as_ref().map(RateLimit::bytes_per_second),
+            self.write_limit.as_ref().map(RateLimit::bytes_per_second),
+        )
+    }
+
+    /// The statistics about the traffic of this connection so far.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Carries the statistics of a previous connection over to this one, e.g. after upgrading to a secure connection.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
+
+    /// Registers a hook that sees every request and response, see [Interceptor].
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    pub fn interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &self.interceptors
+    }
+
+    /// Removes and returns all of the events that were recorded so far.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Copy every line that is sent or received from now on to the given writer, or stop doing so when [None] is given.
+    ///
+    /// Data that was already received but not read yet is written right away, so it is not missing from the log.
+    pub(crate) fn set_wire_log(&mut self, mut log: Option<WireLog>) {
+        if let Some(pending) = log.as_mut() {
+            if !pending.received(self.buffer.filled()) {
+                log = None;
+            }
+        }
+
+        self.wire_log = log;
+    }
+
+    #[cfg(feature = "tls")]
+    pub(crate) fn take_wire_log(&mut self) -> Option<WireLog> {
+        self.wire_log.take()
+    }
+
+    /// Keep a copy of at most the given amount of bytes of the last response that was read, 0 disables the copy.
+    pub fn set_raw_response_limit(&mut self, limit: usize) {
+        self.raw = RawCopy {
+            limit,
+            data: Vec::new(),
+        };
+    }
+
+    pub fn raw_response_limit(&self) -> usize {
+        self.raw.limit
+    }
+
+    /// The bytes of the last response as they were received, cut off at the limit set using [PopStream::set_raw_response_limit].
+    ///
+    /// Returns [None] if no copy is kept.
+    pub fn last_raw_response(&self) -> Option<&[u8]> {
+        if self.raw.limit == 0 {
+            return None;
+        }
+
+        Some(&self.raw.data)
+    }
+}
+
+/// A copy of the start of the last response, which is useful to report responses the parsers do not understand.
+#[derive(Debug, Default)]
+struct RawCopy {
+    limit: usize,
+    data: Vec<u8>,
+}
+
+impl RawCopy {
+    /// Replaces the copy with the start of a new response.
+    fn start(&mut self, bytes: &[u8]) {
+        self.data.clear();
+        self.extend(bytes);
+    }
+
+    /// Adds more of the current response, as long as it fits within the limit.
+    fn extend(&mut self, bytes: &[u8]) {
+        let room = self.limit.saturating_sub(self.data.len());
+
+        self.data.extend_from_slice(&bytes[..room.min(bytes.len())]);
+    }
 }
 
 struct CommandQueue {
@@ -202,24 +923,174 @@ impl CommandQueue {
         self.list.first()
     }
 
-    fn mark_current_as_done(&mut self) {
-        self.list.remove(0);
+    fn mark_current_as_done(&mut self) -> Command {
+        self.list.remove(0)
+    }
+
+    fn clear(&mut self) {
+        self.list.clear()
+    }
+}
+
+/// A line that was sent to the server and is still waiting for a response.
+struct Sent {
+    at: Instant,
+    /// Whether a positive response is followed by a multi-line body.
+    multiline: bool,
+}
+
+/// Whether a positive response to the given command is a multi-line response.
+fn is_multiline(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Retr
+            | Command::Top
+            | Command::Capa
+            | Command::Lang { list: true }
+            | Command::List { listing: true }
+            | Command::Uidl { listing: true }
+            | Command::Custom {
+                multiline: true,
+                ..
+            }
+    )
+}
+
+/// How much of the buffer a response spans.
+enum Frame {
+    /// The response has to be parsed to find out where it ends.
+    Unknown,
+    /// The end of the response has not been received yet.
+    Incomplete,
+    /// The response is complete and spans the given amount of bytes.
+    Complete(usize),
+}
+
+/// Finds the end of the termination octet of a multi-line response.
+///
+/// Bare line feeds do not need to be handled here, in lenient mode they have already been replaced while filling the buffer.
+fn find_multiline_end(data: &[u8]) -> Option<usize> {
+    data.windows(MULTILINE_END.len())
+        .position(|window| window == MULTILINE_END)
+        .map(|index| index + MULTILINE_END.len())
+}
+
+/// Keeps track of where we are in a multi-line response body that is read in chunks, so that the termination octet can be detected incrementally.
+struct MultilineBody {
+    at_line_start: bool,
+    pending_line_ending: bool,
+}
+
+impl MultilineBody {
+    const TERMINATOR: &'static [u8] = b".\r\n";
+
+    fn new() -> Self {
+        Self {
+            at_line_start: true,
+            pending_line_ending: false,
+        }
+    }
+
+    /// Scan the given data for content, returning the content found, how many bytes were consumed and whether the end of the body was reached.
+    ///
+    /// The line ending before the termination octet is not part of the content, so the last line ending that was seen is only added once more content follows.
+    fn scan(&mut self, data: &[u8]) -> (BytesMut, usize, bool) {
+        let mut chunk = BytesMut::new();
+        let mut position = 0;
+
+        loop {
+            let rest = &data[position..];
+
+            if self.at_line_start {
+                if rest.starts_with(Self::TERMINATOR) {
+                    return (chunk, position + Self::TERMINATOR.len(), true);
+                }
+
+                // We can not know whether this is the end of the body until more data arrives.
+                if Self::TERMINATOR.starts_with(rest) {
+                    break;
+                }
+            }
+
+            if rest.is_empty() {
+                break;
+            }
+
+            let is_line_start = self.at_line_start;
+
+            let line = match rest.iter().position(|byte| *byte == LF) {
+                Some(index) => {
+                    self.at_line_start = true;
+
+                    &rest[..index + 1]
+                }
+                None => {
+                    self.at_line_start = false;
+
+                    rest
+                }
+            };
+
+            if self.pending_line_ending {
+                chunk.extend_from_slice(&END_OF_LINE);
+
+                self.pending_line_ending = false;
+            }
+
+            position += line.len();
+
+            // Lines starting with a period are byte-stuffed by the server.
+            let line = match line.strip_prefix(b".") {
+                Some(unstuffed) if is_line_start => unstuffed,
+                _ => line,
+            };
+
+            match line.strip_suffix(&END_OF_LINE) {
+                Some(content) if self.at_line_start => {
+                    chunk.extend_from_slice(content);
+
+                    self.pending_line_ending = true;
+                }
+                _ => chunk.extend_from_slice(line),
+            }
+        }
+
+        (chunk, position, false)
     }
 }
 
+/// The default size of the read buffer, see [crate::ClientBuilder::read_buffer_size].
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 2048;
+
+/// The default maximum size of a single response, see [crate::ClientBuilder::max_response_size].
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = DEFAULT_READ_BUFFER_SIZE * 1024 * 10;
+
+/// How the read buffer grows when a response does not fit in it, see [crate::ClientBuilder::buffer_growth].
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum BufferGrowth {
+    /// Grow by the initial size of the buffer every time.
+    #[default]
+    Linear,
+    /// Double the size of the buffer every time, which needs far fewer resizes for large responses.
+    Exponential,
+}
+
 struct Buffer {
     inner: BytesMut,
     cursor: usize,
+    chunk_size: usize,
+    growth: BufferGrowth,
+    max_size: usize,
 }
 
 impl Buffer {
-    const CHUNK_SIZE: usize = 2048;
-    const MAX_SIZE: usize = Self::CHUNK_SIZE * 1024 * 10;
-
     fn new() -> Self {
         Self {
             cursor: 0,
-            inner: BytesMut::zeroed(Self::CHUNK_SIZE),
+            inner: BytesMut::zeroed(DEFAULT_READ_BUFFER_SIZE),
+            chunk_size: DEFAULT_READ_BUFFER_SIZE,
+            growth: BufferGrowth::default(),
+            max_size: DEFAULT_MAX_RESPONSE_SIZE,
         }
     }
 
@@ -234,29 +1105,13 @@ impl Buffer {
         }
     }
 
-    fn take(&mut self) -> BytesMut {
-        std::mem::replace(&mut self.inner, BytesMut::zeroed(Self::CHUNK_SIZE))
-    }
-
-    fn return_to(&mut self, inner: BytesMut) {
-        self.inner = inner
-    }
-
-    fn reset_with<B: AsRef<[u8]>>(&mut self, data: B) {
-        let data = data.as_ref();
-
-        self.cursor = data.len();
-        self.inner = BytesMut::zeroed(Self::CHUNK_SIZE);
-        self.inner[..self.cursor].copy_from_slice(data);
-    }
-
     fn ensure_capacity(&mut self, to_ensure: usize) -> Result<()> {
         let free_bytes: usize = self.inner.len() - self.cursor;
 
         let extra_bytes_needed: usize = to_ensure.saturating_sub(self.inner.len());
 
         if free_bytes == 0 || extra_bytes_needed > 0 {
-            let increase = std::cmp::max(Self::CHUNK_SIZE, extra_bytes_needed);
+            let increase = std::cmp::max(self.chunk_size, extra_bytes_needed);
 
             self.grow(increase)?;
         }
@@ -266,15 +1121,20 @@ impl Buffer {
 
     fn grow(&mut self, amount: usize) -> Result<()> {
         let min_size = self.inner.len() + amount;
-        let new_size = match min_size % Self::CHUNK_SIZE {
-            0 => min_size,
-            n => min_size + (Self::CHUNK_SIZE - n),
+        let new_size = match self.growth {
+            BufferGrowth::Linear => match min_size % self.chunk_size {
+                0 => min_size,
+                n => min_size + (self.chunk_size - n),
+            },
+            BufferGrowth::Exponential => std::cmp::max(min_size, self.inner.len() * 2)
+                .min(std::cmp::max(min_size, self.max_size)),
         };
 
-        if new_size > Self::MAX_SIZE {
+        if new_size > self.max_size {
             err!(
                 ErrorKind::ResponseTooLarge,
-                "The servers response is larger than the maximum allowed size"
+                "The servers response is larger than the maximum allowed size of {} bytes",
+                self.max_size
             );
         } else {
             self.inner.resize(new_size, 0);
@@ -283,7 +1143,189 @@ impl Buffer {
         }
     }
 
+    /// The part of the buffer that contains data that has been read.
+    fn filled(&self) -> &[u8] {
+        &self.inner[..self.cursor]
+    }
+
+    /// Remove the given amount of bytes from the start of the buffer.
+    fn consume(&mut self, amount: usize) {
+        self.inner.copy_within(amount..self.cursor, 0);
+
+        self.cursor -= amount;
+    }
+
+    /// Rewrites the bare line feeds and carriage returns in the data from `start` onwards into CRLF line endings.
+    ///
+    /// `after_cr` keeps track of a carriage return at the end of the previous read, so a line feed that follows it is not doubled.
+    fn normalize_line_endings(&mut self, start: usize, after_cr: &mut bool) -> Result<()> {
+        let data = &self.inner[start..self.cursor];
+
+        let mut normalized = Vec::with_capacity(data.len());
+
+        for byte in data {
+            match *byte {
+                LF if *after_cr => {}
+                LF | CR => normalized.extend_from_slice(&END_OF_LINE),
+                other => normalized.push(other),
+            }
+
+            *after_cr = *byte == CR;
+        }
+
+        if normalized == data {
+            return Ok(());
+        }
+
+        let end = start + normalized.len();
+
+        if end > self.inner.len() {
+            self.grow(end - self.inner.len())?;
+        }
+
+        self.inner[start..end].copy_from_slice(&normalized);
+        self.cursor = end;
+
+        Ok(())
+    }
+
+    /// Splits off the first `len` bytes of the buffer, so that responses can reference them without copying.
+    ///
+    /// The rest of the data moves to a new allocation, so the buffer does not keep the split off part alive.
+    fn split_frame(&mut self, len: usize) -> Bytes {
+        let rest = &self.inner[len..self.cursor];
+
+        let mut inner = BytesMut::zeroed(std::cmp::max(self.chunk_size, rest.len()));
+        inner[..rest.len()].copy_from_slice(rest);
+
+        self.cursor = rest.len();
+
+        let mut frame = std::mem::replace(&mut self.inner, inner);
+        frame.truncate(len);
+
+        frame.freeze()
+    }
+
+    /// Give back the memory of a buffer that grew to fit a large response, once the data that is left fits in a single chunk again.
+    ///
+    /// The buffer is otherwise reused as is, so decoding does not allocate.
+    fn shrink(&mut self) {
+        if self.inner.len() <= self.chunk_size || self.cursor > self.chunk_size {
+            return;
+        }
+
+        let mut inner = BytesMut::zeroed(self.chunk_size);
+        inner[..self.cursor].copy_from_slice(self.filled());
+
+        self.inner = inner;
+    }
+
     fn cursor(&self) -> usize {
         self.cursor
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{find_multiline_end, Buffer, MultilineBody};
+
+    #[test]
+    fn test_multiline_body() {
+        let mut body = MultilineBody::new();
+
+        let (chunk, consumed, done) = body.scan(b"Subject: Hi\r\n\r\nHello\r\n.");
+
+        assert_eq!(chunk.as_ref(), b"Subject: Hi\r\n\r\nHello");
+        assert_eq!(consumed, 22);
+        assert!(!done);
+
+        let (chunk, consumed, done) = body.scan(b".\r\n");
+
+        assert!(chunk.is_empty());
+        assert_eq!(consumed, 3);
+        assert!(done);
+
+        let mut body = MultilineBody::new();
+
+        let (chunk, _, done) = body.scan(b"Hello\r\n");
+
+        assert_eq!(chunk.as_ref(), b"Hello");
+        assert!(!done);
+
+        let (chunk, _, done) = body.scan(b"World\r\n.\r\n");
+
+        assert_eq!(chunk.as_ref(), b"\r\nWorld");
+        assert!(done);
+
+        let mut body = MultilineBody::new();
+
+        let (chunk, consumed, done) = body.scan(b".\r\n+OK\r\n");
+
+        assert!(chunk.is_empty());
+        assert_eq!(consumed, 3);
+        assert!(done);
+
+        let mut body = MultilineBody::new();
+
+        let (chunk, _, done) = body.scan(b"..\r\n..");
+
+        assert_eq!(chunk.as_ref(), b".\r\n.");
+        assert!(!done);
+
+        let (chunk, _, done) = body.scan(b"more\r\n.\r\n");
+
+        assert_eq!(chunk.as_ref(), b"more");
+        assert!(done);
+    }
+
+    #[test]
+    fn test_buffer_reuse() {
+        let mut buffer = Buffer::new();
+
+        buffer.unused()[..5].copy_from_slice(b"+OK\r\n");
+        buffer.move_cursor(5);
+
+        let ptr = buffer.inner.as_ptr();
+
+        buffer.consume(3);
+        buffer.shrink();
+
+        assert_eq!(buffer.filled(), b"\r\n");
+        assert_eq!(buffer.inner.as_ptr(), ptr);
+
+        buffer.grow(buffer.chunk_size * 4).unwrap();
+        buffer.consume(2);
+        buffer.shrink();
+
+        assert_eq!(buffer.inner.len(), buffer.chunk_size);
+        assert_eq!(buffer.cursor(), 0);
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let mut buffer = Buffer::new();
+        let mut after_cr = false;
+
+        let mut read = |buffer: &mut Buffer, data: &[u8]| {
+            let start = buffer.cursor();
+
+            buffer.unused()[..data.len()].copy_from_slice(data);
+            buffer.move_cursor(data.len());
+            buffer.normalize_line_endings(start, &mut after_cr).unwrap();
+        };
+
+        read(&mut buffer, b"+OK\nbody\r");
+        read(&mut buffer, b"\nmore\r.\r");
+
+        assert_eq!(buffer.filled(), b"+OK\r\nbody\r\nmore\r\n.\r\n");
+    }
+
+    #[test]
+    fn test_find_multiline_end() {
+        assert_eq!(find_multiline_end(b"+OK\r\n.\r\n+OK"), Some(8));
+        assert_eq!(find_multiline_end(b"+OK\nbody\n.\n"), None);
+        assert_eq!(find_multiline_end(b"+OK\nbody\n.\nmore\r\n.\r\n"), Some(20));
+        assert_eq!(find_multiline_end(b"+OK\r\n..\r\nbody\r\n."), None);
+        assert_eq!(find_multiline_end(b"+OK\r\nbody\r\n.\r"), None);
+    }
+}
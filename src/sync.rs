@@ -0,0 +1,338 @@
+//! Incremental synchronisation of a maildrop, based on the unique ids returned by UIDL.
+//!
+//! Message numbers change between sessions, so tools that download new mail keep a [Snapshot] of the unique ids they have seen.
+//! On the next session [run] compares it with the messages on the server, hands every new message to a callback and returns the snapshot to save for next time.
+//! For servers that do not support UIDL, [run_with] takes another way to find the unique ids, see [crate::uid].
+//!
+//! Tools that need to survive being interrupted halfway can use [fetch_unseen] with a persistent [UidStore] instead.
+//! On metered connections [fetch_if] only downloads the headers of the messages that are not worth the bandwidth.
+//! # Examples
+//! ```rust,ignore
+//! let previous: Snapshot = load_saved_uids().into_iter().collect();
+//!
+//! let outcome = async_pop::sync::run(
+//!     &mut client,
+//!     &previous,
+//!     |info, message| save_message(info.uid(), message),
+//!     |_info| false,
+//! )
+//! .await?;
+//!
+//! save_uids(outcome.snapshot().iter());
+//! ```
+use std::collections::HashSet;
+
+use bytes::Bytes;
+
+use crate::{
+    error::{err, ErrorKind, Result},
+    response::info::MessageInfo,
+    runtime::io::{Read, Write},
+    uid::UidProvider,
+    Client, MessageNumber,
+};
+
+/// The unique ids of the messages that were on the server after the last synchronisation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    uids: HashSet<String>,
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains<U: AsRef<str>>(&self, uid: U) -> bool {
+        self.uids.contains(uid.as_ref())
+    }
+
+    /// The unique ids in the snapshot, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.uids.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.uids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uids.is_empty()
+    }
+}
+
+impl<U: Into<String>> FromIterator<U> for Snapshot {
+    fn from_iter<I: IntoIterator<Item = U>>(iter: I) -> Self {
+        Self {
+            uids: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Keeps track of the unique ids of the messages that were fetched, see [fetch_unseen].
+///
+/// Unlike a [Snapshot], which is only saved once a synchronisation has finished, the store is updated right after every message is handled.
+/// Backing it with something persistent means a crash or lost connection halfway does not cause the messages that were handled to be fetched again.
+pub trait UidStore {
+    fn contains(&self, uid: &str) -> bool;
+
+    /// Called once the message with the given unique id has been handled, returning an error stops fetching.
+    fn insert(&mut self, uid: &str) -> Result<()>;
+}
+
+impl UidStore for Snapshot {
+    fn contains(&self, uid: &str) -> bool {
+        self.uids.contains(uid)
+    }
+
+    fn insert(&mut self, uid: &str) -> Result<()> {
+        self.uids.insert(uid.to_string());
+
+        Ok(())
+    }
+}
+
+/// How the messages on the server differ from a [Snapshot].
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    /// Messages that are not in the snapshot.
+    pub new: Vec<MessageInfo>,
+    /// Messages that are in the snapshot and still on the server.
+    pub retained: Vec<MessageInfo>,
+    /// Unique ids in the snapshot that are no longer on the server.
+    pub vanished: Vec<String>,
+}
+
+impl Changes {
+    /// Compares the messages on the server with the snapshot, every message needs to have a unique id.
+    pub fn compute(previous: &Snapshot, current: &[MessageInfo]) -> Result<Self> {
+        let mut changes = Self::default();
+        let mut seen = HashSet::with_capacity(current.len());
+
+        for info in current {
+            let uid = match info.uid() {
+                Some(uid) => uid.to_string(),
+                None => err!(
+                    ErrorKind::FeatureUnsupported,
+                    "Message {} does not have a unique id, the server needs to support UIDL to synchronise, see sync::run_with otherwise",
+                    info.number()
+                ),
+            };
+
+            if previous.contains(&uid) {
+                changes.retained.push(info.clone());
+            } else {
+                changes.new.push(info.clone());
+            }
+
+            seen.insert(uid);
+        }
+
+        changes.vanished = previous
+            .iter()
+            .filter(|uid| !seen.contains(*uid))
+            .map(String::from)
+            .collect();
+
+        Ok(changes)
+    }
+}
+
+/// The result of [run].
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    snapshot: Snapshot,
+    changes: Changes,
+    deleted: Vec<MessageInfo>,
+}
+
+impl Outcome {
+    /// The snapshot to pass to the next synchronisation, which excludes the messages that were deleted.
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    pub fn into_snapshot(self) -> Snapshot {
+        self.snapshot
+    }
+
+    /// How the server differed from the previous snapshot.
+    pub fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    /// The messages that were marked as deleted, they are only removed once the session is ended using [Client::quit].
+    pub fn deleted(&self) -> &[MessageInfo] {
+        &self.deleted
+    }
+}
+
+/// Synchronises the maildrop with the given snapshot.
+///
+/// Every new message is fetched and passed to `on_message`, returning an error from it stops the synchronisation.
+/// Afterwards every message on the server, new or retained, for which `should_delete` returns true is marked as deleted.
+pub async fn run<S, F, D>(
+    client: &mut Client<S>,
+    previous: &Snapshot,
+    on_message: F,
+    should_delete: D,
+) -> Result<Outcome>
+where
+    S: Read + Write + Unpin + Send,
+    F: FnMut(&MessageInfo, Bytes) -> Result<()>,
+    D: FnMut(&MessageInfo) -> bool,
+{
+    let current = client.message_infos().await?;
+
+    apply(client, current, previous, on_message, should_delete).await
+}
+
+/// Same as [run], but the unique ids come from the given provider, e.g. [crate::uid::HeaderHash] for servers that do not support UIDL.
+pub async fn run_with<S, P, F, D>(
+    client: &mut Client<S>,
+    provider: &P,
+    previous: &Snapshot,
+    on_message: F,
+    should_delete: D,
+) -> Result<Outcome>
+where
+    S: Read + Write + Unpin + Send,
+    P: UidProvider,
+    F: FnMut(&MessageInfo, Bytes) -> Result<()>,
+    D: FnMut(&MessageInfo) -> bool,
+{
+    let current = client.message_infos_with(provider).await?;
+
+    apply(client, current, previous, on_message, should_delete).await
+}
+
+async fn apply<S, F, D>(
+    client: &mut Client<S>,
+    current: Vec<MessageInfo>,
+    previous: &Snapshot,
+    mut on_message: F,
+    mut should_delete: D,
+) -> Result<Outcome>
+where
+    S: Read + Write + Unpin + Send,
+    F: FnMut(&MessageInfo, Bytes) -> Result<()>,
+    D: FnMut(&MessageInfo) -> bool,
+{
+    let changes = Changes::compute(previous, &current)?;
+
+    for info in &changes.new {
+        let message = client.retr(info.number()).await?;
+
+        on_message(info, message)?;
+    }
+
+    let to_delete: Vec<&MessageInfo> = current.iter().filter(|info| should_delete(info)).collect();
+
+    let results = client
+        .dele_many(to_delete.iter().map(|info| info.number()))
+        .await?;
+
+    let deleted: Vec<MessageInfo> = to_delete
+        .into_iter()
+        .zip(results)
+        .filter(|(_, (_, result))| result.is_ok())
+        .map(|(info, _)| info.clone())
+        .collect();
+
+    let deleted_numbers: HashSet<MessageNumber> = deleted.iter().map(MessageInfo::number).collect();
+
+    let snapshot = current
+        .iter()
+        .filter(|info| !deleted_numbers.contains(&info.number()))
+        .filter_map(|info| info.uid().map(|uid| uid.to_string()))
+        .collect();
+
+    Ok(Outcome {
+        snapshot,
+        changes,
+        deleted,
+    })
+}
+
+/// Fetches every message whose unique id is not in the store and passes it to `on_message`, returning the messages that were fetched.
+///
+/// The unique id is added to the store as soon as `on_message` returns, so when fetching is interrupted the next attempt picks up where this one left off.
+/// Messages are matched by their unique id only, so servers that number the messages differently in every session are handled as well.
+/// The messages have to be listed in the current session, e.g. using [Client::message_infos], and every one of them needs a unique id.
+/// Multiple messages with the same unique id are only fetched once.
+pub async fn fetch_unseen<S, T, F>(
+    client: &mut Client<S>,
+    messages: &[MessageInfo],
+    store: &mut T,
+    mut on_message: F,
+) -> Result<Vec<MessageInfo>>
+where
+    S: Read + Write + Unpin + Send,
+    T: UidStore,
+    F: FnMut(&MessageInfo, Bytes) -> Result<()>,
+{
+    let mut fetched = Vec::new();
+
+    for info in messages {
+        let uid = match info.uid() {
+            Some(uid) => uid.to_string(),
+            None => err!(
+                ErrorKind::FeatureUnsupported,
+                "Message {} does not have a unique id, so it can not be told apart from the messages that were fetched before",
+                info.number()
+            ),
+        };
+
+        if store.contains(&uid) {
+            continue;
+        }
+
+        let message = client.retr(info.number()).await?;
+
+        on_message(info, message)?;
+
+        store.insert(&uid)?;
+
+        fetched.push(info.clone());
+    }
+
+    Ok(fetched)
+}
+
+/// What [fetch_if] fetched for a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fetched {
+    /// The whole message.
+    Message(Bytes),
+    /// Only the header block of the message.
+    Headers(Bytes),
+}
+
+/// Fetches the messages for which `predicate` returns true in full and only the headers of the others, passing each to `on_message`.
+///
+/// The predicate gets the size from LIST, so e.g. `|info| info.size() < 1_000_000` skips the body of every message of a megabyte or more.
+/// The headers are fetched using [Client::preview], note that servers that do not support TOP still send the whole message in that case.
+/// Returning an error from `on_message` stops fetching.
+pub async fn fetch_if<S, P, F>(
+    client: &mut Client<S>,
+    messages: &[MessageInfo],
+    mut predicate: P,
+    mut on_message: F,
+) -> Result<()>
+where
+    S: Read + Write + Unpin + Send,
+    P: FnMut(&MessageInfo) -> bool,
+    F: FnMut(&MessageInfo, Fetched) -> Result<()>,
+{
+    for info in messages {
+        let fetched = if predicate(info) {
+            Fetched::Message(client.retr(info.number()).await?)
+        } else {
+            Fetched::Headers(client.preview(info.number(), 0).await?)
+        };
+
+        on_message(info, fetched)?;
+    }
+
+    Ok(())
+}
@@ -1,68 +1,2533 @@
-use std::env;
+use std::{
+    env,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use crate::runtime::net::TcpStream;
+use bytes::Bytes;
+use dotenv::dotenv;
+use futures::{StreamExt, TryStreamExt};
+use log::info;
+
+use crate::{
+    auth::{AuthMechanism, AuthStrategy, LoginPolicy},
+    command::Command,
+    error::{Error, ErrorKind},
+    event::Event,
+    health::Health,
+    interceptor::Interceptor,
+    pool::Pool,
+    request::Request,
+    resilient::{Credentials, ResilientClient},
+    response::{
+        capability::{Capability, CapabilityCheck},
+        code::ResponseCode,
+        list::ListResponse,
+        types::DataType,
+        uidl::UidlResponse,
+        ParseMode, Response,
+    },
+    retry::{ExponentialBackoff, NoRetry},
+    security::SecurityPolicy,
+    sync::{self, Snapshot},
+    BufferGrowth, ClientBuilder, ClientState, MessageNumber,
+};
+
+use super::Client;
+
+#[derive(Debug)]
+struct ClientInfo {
+    server: String,
+    port: u16,
+    username: String,
+    password: String,
+}
+
+fn create_client_info() -> ClientInfo {
+    dotenv().ok();
+
+    ClientInfo {
+        server: env::var("SERVER").unwrap().to_owned(),
+        port: env::var("PORT").unwrap().parse().unwrap(),
+        username: env::var("USERNAME").unwrap().to_owned(),
+        password: env::var("PASSWORD").unwrap().to_owned(),
+    }
+}
+
+async fn create_logged_in_client() -> Client<TcpStream> {
+    let client_info = create_client_info();
+    let server = client_info.server.as_ref();
+    let port = client_info.port;
+
+    let username = client_info.username;
+    let password = client_info.password;
+
+    let mut client = super::connect_plain((server, port)).await.unwrap();
+
+    client.login(username, password).await.unwrap();
+
+    client
+}
+
+// async fn create_logged_in_client_tls() -> Client<impl crate::tls::TlsStream<TcpStream>> {
+//     let client_info = create_client_info();
+//     let server = client_info.server.as_ref();
+//     let port = client_info.port;
+
+//     let username = client_info.username;
+//     let password = client_info.password;
+
+//     let tls = async_native_tls::TlsConnector::new();
+
+//     let mut client = super::connect((server, port), server, &tls).await.unwrap();
+
+//     client.login(username, password).await.unwrap();
+
+//     client
+// }
+
+/// An in memory stream that replays a scripted server conversation and records everything the client sends.
+struct MockStream {
+    input: Vec<u8>,
+    position: usize,
+    output: Vec<u8>,
+    hang_when_done: bool,
+    stutter: bool,
+    paused: bool,
+}
+
+impl MockStream {
+    fn new<I: AsRef<[u8]>>(input: I) -> Self {
+        Self {
+            input: input.as_ref().to_vec(),
+            position: 0,
+            output: Vec::new(),
+            hang_when_done: false,
+            stutter: false,
+            paused: false,
+        }
+    }
+
+    /// A stream that is not ready on every other read, so commands can be cancelled halfway through their response.
+    fn stuttering<I: AsRef<[u8]>>(input: I) -> Self {
+        Self {
+            stutter: true,
+            ..Self::new(input)
+        }
+    }
+
+    /// A stream that never responds once the scripted input runs out, instead of closing the connection.
+    fn hanging<I: AsRef<[u8]>>(input: I) -> Self {
+        Self {
+            hang_when_done: true,
+            ..Self::new(input)
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.hang_when_done && self.position == self.input.len()
+    }
+
+    fn read_into(&mut self, buf: &mut [u8]) -> usize {
+        let remaining = &self.input[self.position..];
+
+        // Only hand out a few bytes at a time, to make sure responses get split up.
+        let amount = remaining.len().min(buf.len()).min(7);
+
+        buf[..amount].copy_from_slice(&remaining[..amount]);
+
+        self.position += amount;
+
+        amount
+    }
+}
+
+impl futures::io::AsyncRead for MockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if self.is_done() {
+            return Poll::Pending;
+        }
+
+        let this = self.get_mut();
+
+        if this.stutter {
+            this.paused = !this.paused;
+
+            if this.paused {
+                cx.waker().wake_by_ref();
+
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(Ok(this.read_into(buf)))
+    }
+}
+
+impl futures::io::AsyncWrite for MockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().output.extend_from_slice(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+const MOCK_CAPABILITIES: &str = "USER\r\nTOP\r\nUIDL\r\n";
+
+/// Creates a client connected to a [MockStream] that already went through the greeting and capability negotiation.
+async fn create_mock_client<R: AsRef<str>>(responses: R) -> Client<MockStream> {
+    create_mock_client_with(MOCK_CAPABILITIES, responses).await
+}
+
+/// Same as [create_mock_client], but with a custom list of capabilities advertised by the server.
+async fn create_mock_client_with<C: AsRef<str>, R: AsRef<str>>(
+    capabilities: C,
+    responses: R,
+) -> Client<MockStream> {
+    let input = format!(
+        "+OK POP3 server ready <1896.697170952@dbc.mtview.ca.us>\r\n+OK\r\n{}.\r\n{}",
+        capabilities.as_ref(),
+        responses.as_ref()
+    );
+
+    super::new(MockStream::new(input)).await.unwrap()
+}
+
+/// Same as [create_mock_client], but already logged in using USER and PASS.
+async fn create_logged_in_mock_client<R: AsRef<str>>(responses: R) -> Client<MockStream> {
+    let responses = format!(
+        "+OK\r\n+OK\r\n+OK\r\n{}.\r\n{}",
+        MOCK_CAPABILITIES,
+        responses.as_ref()
+    );
+
+    let mut client = create_mock_client(responses).await;
+
+    client.login("user", "password").await.unwrap();
+
+    client
+}
+
+/// Everything the client sent to the [MockStream], excluding the initial capability request.
+fn sent_commands(client: Client<MockStream>) -> String {
+    let stream = client.into_inner().unwrap().into_inner().unwrap();
+
+    String::from_utf8(stream.output).unwrap()["CAPA\r\n".len()..].to_string()
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_stream() {
+    let mut client =
+        create_mock_client("+OK 24 octets\r\nSubject: Hi\r\n\r\nHello there\r\n.\r\n+OK\r\n").await;
+
+    let chunks: Vec<_> = client
+        .retr_stream(1)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(chunks.concat(), b"Subject: Hi\r\n\r\nHello there");
+
+    client.noop().await.unwrap();
+
+    assert_eq!(sent_commands(client), "RETR 1\r\nNOOP\r\n");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_cancelled_commands() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK\r\n+OK\r\n{capa}.\r\n{}",
+        [
+            "+OK 2 320\r\n",
+            "+OK 24 octets\r\nSubject: Hi\r\n\r\nHello there\r\n.\r\n",
+            "+OK 24 octets\r\nSubject: Hi\r\n\r\n..Hello there\r\n.\r\n",
+            "+OK 1 120\r\n",
+        ]
+        .concat(),
+        capa = MOCK_CAPABILITIES
+    );
+
+    let mut client = super::new(MockStream::stuttering(input)).await.unwrap();
+
+    client.login("user", "password").await.unwrap();
+
+    // Cancelled while waiting for the response.
+    {
+        let mut stat = Box::pin(client.stat());
+
+        assert!(futures::poll!(&mut stat).is_pending());
+    }
+
+    // Cancelled halfway through the response, after the response to STAT was thrown away.
+    {
+        let mut retr = Box::pin(client.retr(1));
+
+        for _ in 0..4 {
+            assert!(futures::poll!(&mut retr).is_pending());
+        }
+    }
+
+    // Dropped before the end of the message was reached.
+    {
+        let mut stream = client.retr_stream(1).await.unwrap();
+
+        assert!(stream.try_next().await.unwrap().is_some());
+    }
+
+    match client.list(Some(1)).await.unwrap() {
+        ListResponse::Single(stat) => assert_eq!(stat.size().value().unwrap(), 120),
+        ListResponse::Multiple(_) => unreachable!(),
+    }
+
+    assert_eq!(
+        client.take_events(),
+        [
+            Event::DiscardedResponses { count: 1 },
+            Event::DiscardedResponses { count: 1 },
+            Event::DiscardedResponses { count: 1 }
+        ]
+    );
+
+    let stream = client.into_inner().unwrap().into_inner().unwrap();
+
+    assert!(String::from_utf8(stream.output)
+        .unwrap()
+        .ends_with("STAT\r\nRETR 1\r\nRETR 1\r\nLIST 1\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_poisoned_connection() {
+    let mut client =
+        create_logged_in_mock_client("-ERR [SYS/TEMP] busy\r\n+OK two\r\n+OK\r\n").await;
+
+    // A negative response leaves the connection in sync.
+    assert!(client.stat().await.is_err());
+    assert!(client.is_usable());
+
+    assert!(matches!(
+        client.stat().await.unwrap_err().kind(),
+        ErrorKind::InvalidResponse
+    ));
+    assert!(!client.is_usable());
+
+    let err = client.noop().await.unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::Poisoned));
+    assert!(err.is_transient());
+
+    assert!(client.close(Duration::from_secs(1)).await.is_none());
+    assert_eq!(client.get_state(), &ClientState::None);
+    assert!(!client.is_usable());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_to() {
+    let mut client = create_mock_client("+OK\r\nSubject: Hi\r\n\r\nHello there\r\n.\r\n").await;
+
+    let mut output = Vec::new();
+
+    let written = client.retr_to(1, &mut output).await.unwrap();
+
+    assert_eq!(written, 26);
+    assert_eq!(output, b"Subject: Hi\r\n\r\nHello there");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_pipeline() {
+    let mut client = create_mock_client_with(
+        "PIPELINING\r\nUIDL\r\n",
+        "+OK\r\n1 abc\r\n.\r\n-ERR no such message\r\n+OK 1 120\r\n",
+    )
+    .await;
+
+    let responses = client
+        .pipeline()
+        .uidl(None::<MessageNumber>)
+        .list(Some(2))
+        .stat()
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(responses.len(), 3);
+    assert!(matches!(responses[0], Ok(Response::Uidl(_))));
+    assert!(responses[1].is_err());
+    assert!(matches!(responses[2], Ok(Response::Stat(_))));
+
+    assert_eq!(sent_commands(client), "UIDL\r\nLIST 2\r\nSTAT\r\n");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_refresh_capabilities() {
+    let mut client =
+        create_mock_client_with("USER\r\nSTLS\r\n", "+OK\r\nUSER\r\nPIPELINING\r\n.\r\n").await;
+
+    let changes = client.refresh_capabilities().await.unwrap();
+
+    assert_eq!(changes.added, vec![Capability::Pipelining]);
+    assert_eq!(changes.removed, vec![Capability::Stls]);
+    assert!(client.capabilities().supports_pipelining());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_security_policy() {
+    let mut client = create_mock_client_with("USER\r\nSTLS\r\n", "").await;
+
+    match client.login("user", "password").await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::Insecure)),
+        Ok(_) => unreachable!(),
+    }
+
+    let stream = client.into_inner().unwrap().into_inner().unwrap();
+
+    assert_eq!(String::from_utf8(stream.output).unwrap(), "CAPA\r\n");
+
+    let input = "+OK POP3 server ready\r\n+OK\r\nUSER\r\n.\r\n";
+
+    let mut client = ClientBuilder::new()
+        .security_policy(SecurityPolicy::Required)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    match client.login("user", "password").await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::Insecure)),
+        Ok(_) => unreachable!(),
+    }
+
+    let input =
+        "+OK POP3 server ready\r\n+OK\r\nUSER\r\nSTLS\r\n.\r\n+OK\r\n+OK\r\n+OK\r\nUSER\r\n.\r\n";
+
+    let mut client = ClientBuilder::new()
+        .security_policy(SecurityPolicy::None)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    client.login("user", "password").await.unwrap();
+}
+
+#[cfg(feature = "async-native-tls")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_stls_downgrade() {
+    let client = create_mock_client_with("STLS\r\n", "-ERR not now\r\n").await;
+
+    match client
+        .stls("pop.example.com", crate::tls::TlsConnector::System)
+        .await
+    {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::Downgrade)),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_response_equality() {
+    use crate::response::{stat::Stat, uidl::UniqueId};
+
+    let mut client =
+        create_logged_in_mock_client("+OK 2 320\r\n+OK\r\n1 abc\r\n2 def\r\n.\r\n").await;
+
+    assert_eq!(client.stat().await.unwrap(), Stat::new(2, 320));
+
+    match client.uidl(None::<MessageNumber>).await.unwrap() {
+        UidlResponse::Multiple(uidl) => assert_eq!(
+            uidl.items(),
+            [UniqueId::new(1, "abc"), UniqueId::new(2, "def")]
+        ),
+        UidlResponse::Single(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_capa_unsupported() {
+    let input = "+OK POP3 server ready\r\n-ERR unknown command\r\n";
+
+    let mut client = super::new(MockStream::new(input)).await.unwrap();
+
+    assert!(!client.supports_capa());
+    assert!(client.capabilities().is_empty());
+
+    match client.top(1, 0).await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::FeatureUnsupported)),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_lazy_capabilities() {
+    let input =
+        "+OK POP3 server ready\r\n+OK\r\n+OK\r\n+OK\r\nTOP\r\n.\r\n+OK\r\nSubject: Hi\r\n.\r\n";
+
+    let mut client = ClientBuilder::new()
+        .lazy_capabilities(true)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    assert!(client.capabilities().is_empty());
+
+    client.login("user", "password").await.unwrap();
+
+    client.top(1, 0).await.unwrap();
+
+    assert!(client.capabilities().supports_top());
+
+    let stream = client.into_inner().unwrap().into_inner().unwrap();
+
+    let sent = String::from_utf8(stream.output).unwrap();
+
+    assert_eq!(sent, "USER user\r\nPASS password\r\nCAPA\r\nTOP 1 0\r\n");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_login_without_user_capability() {
+    async fn connect(
+        check: CapabilityCheck,
+        responses: &str,
+    ) -> crate::error::Result<Client<MockStream>> {
+        let input = format!("+OK POP3 server ready\r\n+OK\r\nTOP\r\n.\r\n{}", responses);
+
+        ClientBuilder::new()
+            .capability_check(check)
+            .build(MockStream::new(input))
+            .await
+    }
+
+    let mut client = connect(CapabilityCheck::Strict, "").await.unwrap();
+
+    match client.login("user", "password").await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::FeatureUnsupported)),
+        Ok(_) => unreachable!(),
+    }
+
+    assert_eq!(sent_commands(client), "");
+
+    let mut client = connect(CapabilityCheck::TrustServer, "-ERR unknown command\r\n")
+        .await
+        .unwrap();
+
+    match client.login("user", "password").await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::FeatureUnsupported)),
+        Ok(_) => unreachable!(),
+    }
+
+    // A response code means the server understood the command.
+    let mut client = connect(CapabilityCheck::TrustServer, "-ERR [AUTH] who?\r\n")
+        .await
+        .unwrap();
+
+    match client.login("user", "password").await {
+        Err(err) => assert_eq!(err.response_code(), Some(&ResponseCode::Auth)),
+        Ok(_) => unreachable!(),
+    }
+
+    let mut client = connect(
+        CapabilityCheck::TrustServer,
+        "+OK\r\n+OK\r\n+OK\r\nTOP\r\n.\r\n",
+    )
+    .await
+    .unwrap();
+
+    client.login("user", "password").await.unwrap();
+
+    assert_eq!(client.get_state(), &ClientState::Transaction);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_capability_check() {
+    let input = "+OK POP3 server ready\r\n+OK\r\nUSER\r\n.\r\n+OK\r\n+OK\r\n+OK\r\nUSER\r\n.\r\n+OK\r\nSubject: Hi\r\n.\r\n-ERR command not implemented\r\n";
+
+    let mut client = ClientBuilder::new()
+        .capability_check(CapabilityCheck::TrustServer)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    client.login("user", "password").await.unwrap();
+
+    client.top(1, 0).await.unwrap();
+
+    match client.uidl(None::<MessageNumber>).await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::ServerError { .. })),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_utf8_login() {
+    let mut client = create_mock_client_with(
+        "USER\r\nUTF8 USER\r\n",
+        "+OK UTF8 enabled\r\n+OK\r\n+OK\r\n+OK\r\nUSER\r\nUTF8 USER\r\n.\r\n",
+    )
+    .await;
+
+    client.login("jürgen", "password").await.unwrap();
+
+    assert!(client.is_utf8());
+
+    let stream = client.into_inner().unwrap().into_inner().unwrap();
+
+    let sent = String::from_utf8(stream.output).unwrap();
+
+    assert_eq!(
+        sent,
+        "CAPA\r\nUTF8\r\nUSER jürgen\r\nPASS password\r\nCAPA\r\n"
+    );
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_lang() {
+    let mut client = create_mock_client_with(
+        "USER\r\nLANG\r\n",
+        "+OK Language listing follows\r\nen English\r\nde Deutsch\r\n.\r\n+OK Es wird Deutsch gesprochen\r\n",
+    )
+    .await;
+
+    let languages = client.lang_list().await.unwrap();
+
+    assert_eq!(languages.len(), 2);
+    assert_eq!(languages[1].tag().to_string(), "de");
+
+    client.lang("de").await.unwrap();
+
+    assert_eq!(sent_commands(client), "LANG\r\nLANG de\r\n");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_uid_operations() {
+    let mut client = create_logged_in_mock_client(
+        "+OK\r\n1 abc\r\n2 def\r\n.\r\n+OK\r\nHello\r\n.\r\n+OK deleted\r\n+OK\r\n1 abc\r\n2 def\r\n.\r\n",
+    )
+    .await;
+
+    assert_eq!(client.retr_by_uid("def").await.unwrap().as_ref(), b"Hello");
+
+    client.dele_by_uid("abc").await.unwrap();
+
+    match client.retr_by_uid("ghi").await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::UnknownUid)),
+        Ok(_) => unreachable!(),
+    }
+
+    let sent = sent_commands(client);
+
+    assert!(sent.ends_with("UIDL\r\nRETR 2\r\nDELE 1\r\nUIDL\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_range() {
+    let mut client = create_mock_client_with(
+        "PIPELINING\r\n",
+        "+OK\r\nFirst\r\n.\r\n+OK\r\nSecond\r\n.\r\n+OK 2 messages\r\n1 7\r\n3 7\r\n.\r\n+OK\r\nFirst\r\n.\r\n+OK\r\nThird\r\n.\r\n",
+    )
+    .await;
+
+    let messages = client.retr_range(1.into()..=2.into()).await.unwrap();
+
+    assert_eq!(
+        messages,
+        vec![
+            (MessageNumber::new(1), Bytes::from("First")),
+            (MessageNumber::new(2), Bytes::from("Second"))
+        ]
+    );
+
+    let messages = client.retr_all().await.unwrap();
+
+    assert_eq!(
+        messages,
+        vec![
+            (MessageNumber::new(1), Bytes::from("First")),
+            (MessageNumber::new(3), Bytes::from("Third"))
+        ]
+    );
+
+    assert_eq!(
+        sent_commands(client),
+        "RETR 1\r\nRETR 2\r\nLIST\r\nRETR 1\r\nRETR 3\r\n"
+    );
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_messages_stream() {
+    let mut client = create_logged_in_mock_client(
+        "+OK 3 messages\r\n1 5\r\n2 6\r\n3 5\r\n.\r\n+OK\r\nFirst\r\n.\r\n+OK\r\nSecond\r\n.\r\n",
+    )
+    .await;
+
+    let messages: Vec<_> = client.messages().take(2).try_collect().await.unwrap();
+
+    assert_eq!(
+        messages,
+        vec![
+            (MessageNumber::new(1), Bytes::from("First")),
+            (MessageNumber::new(2), Bytes::from("Second"))
+        ]
+    );
+
+    assert!(sent_commands(client).ends_with("LIST\r\nRETR 1\r\nRETR 2\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_top_many() {
+    let mut client = create_mock_client_with(
+        "PIPELINING\r\nTOP\r\n",
+        "+OK\r\nSubject: One\r\n.\r\n-ERR no such message\r\n+OK\r\nSubject: Three\r\n.\r\n+OK\r\nSubject: One\r\n.\r\n+OK\r\nSubject: Two\r\n.\r\n+OK 2 320\r\n",
+    )
+    .await;
+
+    let results: Vec<(MessageNumber, Result<Bytes, crate::error::Error>)> = client
+        .top_many([1, 2, 3], 0)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].1.as_ref().unwrap(), "Subject: One");
+    assert!(matches!(
+        results[1].1.as_ref().unwrap_err().kind(),
+        ErrorKind::ServerError { .. }
+    ));
+    assert_eq!(results[2].0, 3);
+
+    // Stopping early throws away the responses that were already sent for.
+    {
+        let mut headers = client.top_many([1, 2], 0).await.unwrap();
+
+        let (msg_number, _) = headers.try_next().await.unwrap().unwrap();
+
+        assert_eq!(msg_number, 1);
+    }
+
+    assert_eq!(client.stat().await.unwrap().message_count(), 2);
+
+    assert!(matches!(
+        client
+            .top_many([1], crate::constants::MAX_TOP_LINES + 1)
+            .await
+            .err()
+            .unwrap()
+            .kind(),
+        ErrorKind::InvalidArgument
+    ));
+
+    assert_eq!(
+        sent_commands(client),
+        "TOP 1 0\r\nTOP 2 0\r\nTOP 3 0\r\nTOP 1 0\r\nTOP 2 0\r\nSTAT\r\n"
+    );
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_dele_many() {
+    let mut client = create_mock_client_with(
+        "PIPELINING\r\n",
+        "+OK message 1 deleted\r\n-ERR no such message\r\n+OK message 3 deleted\r\n",
+    )
+    .await;
+
+    let results = client.dele_many([1, 1, 2, 3]).await.unwrap();
+
+    assert_eq!(results.len(), 4);
+    assert!(results[0].1.is_ok());
+    assert!(matches!(
+        results[1].1.as_ref().unwrap_err().kind(),
+        ErrorKind::MessageIsDeleted
+    ));
+    assert!(matches!(
+        results[2].1.as_ref().unwrap_err().kind(),
+        ErrorKind::ServerError { .. }
+    ));
+    assert!(results[3].1.is_ok());
+
+    assert!(client.is_deleted(1));
+    assert!(!client.is_deleted(2));
+
+    assert_eq!(sent_commands(client), "DELE 1\r\nDELE 2\r\nDELE 3\r\n");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_dele_many_connection_error() {
+    let mut client = create_mock_client_with("PIPELINING\r\n", "+OK message 1 deleted\r\n").await;
+
+    assert!(client.dele_many([1, 2]).await.is_err());
+
+    assert!(client.deleted_messages().is_empty());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_deleted_marks() {
+    let mut client =
+        create_logged_in_mock_client("+OK deleted\r\n+OK deleted\r\n+OK\r\n+OK deleted\r\n").await;
+
+    client.dele(3).await.unwrap();
+    client.dele(1).await.unwrap();
+
+    assert_eq!(client.deleted_messages(), &[1, 3]);
+
+    client.rset().await.unwrap();
+
+    assert!(client.deleted_messages().is_empty());
+
+    client.dele(2).await.unwrap();
+
+    assert!(client.unmark_deleted(2));
+    assert!(!client.unmark_deleted(2));
+    assert!(!client.is_deleted(2));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_quit_summary() {
+    let mut client = create_logged_in_mock_client(
+        "+OK deleted\r\n+OK deleted\r\n-ERR some deleted messages not removed\r\n",
+    )
+    .await;
+
+    client.dele_many([1, 2]).await.unwrap();
+
+    let summary = client.quit().await.unwrap();
+
+    assert!(!summary.success());
+    assert_eq!(summary.messages_marked(), 2);
+    assert_eq!(
+        summary.server_text().to_string(),
+        "some deleted messages not removed"
+    );
+    assert_eq!(client.get_state(), &ClientState::None);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_close() {
+    let mut client = create_logged_in_mock_client("+OK bye\r\n").await;
+
+    let summary = client.close(Duration::from_secs(1)).await.unwrap();
+
+    assert!(summary.success());
+
+    let input = format!("+OK POP3 server ready\r\n+OK\r\n{}.\r\n", MOCK_CAPABILITIES);
+
+    let mut client = super::new(MockStream::hanging(input)).await.unwrap();
+
+    assert!(client.close(Duration::from_millis(50)).await.is_none());
+    assert_eq!(client.get_state(), &ClientState::None);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_top_headers() {
+    let mut client =
+        create_logged_in_mock_client("+OK\r\nFrom: alice@example.com\r\nSubject: Hi\r\n\r\n.\r\n")
+            .await;
+
+    let headers = client.top_headers(1).await.unwrap();
+
+    assert_eq!(
+        headers.get("From").unwrap().to_string(),
+        "alice@example.com"
+    );
+    assert_eq!(headers.get("Subject").unwrap().to_string(), "Hi");
+
+    assert!(sent_commands(client).ends_with("TOP 1 0\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_preview_without_top() {
+    let capabilities = "USER\r\n";
+
+    let mut client = create_mock_client_with(
+        capabilities,
+        format!(
+            "+OK\r\n+OK\r\n+OK\r\n{}.\r\n{}",
+            capabilities,
+            [
+                "+OK\r\nSubject: Hi\r\n\r\nfirst\r\nsecond\r\nthird\r\n.\r\n",
+                "+OK 1 320\r\n",
+            ]
+            .concat()
+        ),
+    )
+    .await;
+
+    client.login("user", "password").await.unwrap();
+
+    let preview = client.preview(1, 1).await.unwrap();
+
+    assert_eq!(preview, "Subject: Hi\r\n\r\nfirst\r\n");
+
+    // The rest of the message is thrown away before the next command.
+    assert_eq!(client.stat().await.unwrap().message_count(), 1);
+
+    assert!(sent_commands(client).ends_with("RETR 1\r\nSTAT\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_preview_headers() {
+    let mut client = create_logged_in_mock_client(
+        "+OK 2 3200\r\n+OK\r\nFrom: =?utf-8?q?Ren=C3=A9?= <rene@example.com>\r\nSubject: Hi\r\nDate: Fri, 16 Oct 2026 10:00:00 +0000\r\n\r\n.\r\n",
+    )
+    .await;
+
+    let preview = client.preview_headers(2).await.unwrap();
+
+    assert_eq!(preview.from(), Some("Ren\u{e9} <rene@example.com>"));
+    assert_eq!(preview.subject(), Some("Hi"));
+    assert_eq!(preview.date(), Some("Fri, 16 Oct 2026 10:00:00 +0000"));
+    assert_eq!(preview.size(), 3200);
+
+    assert!(sent_commands(client).ends_with("LIST 2\r\nTOP 2 0\r\n"));
+}
+
+#[cfg(feature = "mail-parser")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_parsed() {
+    let mut client = create_logged_in_mock_client(
+        "+OK\r\nFrom: alice@example.com\r\nSubject: Hi\r\n\r\nHello there\r\n.\r\n",
+    )
+    .await;
+
+    let message = client.retr_parsed(1).await.unwrap();
+
+    drop(client);
+
+    assert_eq!(message.subject(), Some("Hi"));
+    assert_eq!(message.body_text(0).as_deref(), Some("Hello there"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_message_infos() {
+    let mut client = create_mock_client_with(
+        "PIPELINING\r\nUIDL\r\n",
+        "+OK 2 messages\r\n1 120\r\n3 200\r\n.\r\n+OK\r\n1 abc\r\n3 def\r\n.\r\n",
+    )
+    .await;
+
+    let infos = client.message_infos().await.unwrap();
+
+    assert_eq!(infos.len(), 2);
+    assert_eq!(infos[1].number(), 3);
+    assert_eq!(infos[1].size(), 200);
+    assert_eq!(infos[1].uid().unwrap().to_string(), "def");
+
+    assert_eq!(sent_commands(client), "LIST\r\nUIDL\r\n");
+
+    let mut client = create_mock_client_with("USER\r\n", "+OK 1 messages\r\n1 120\r\n.\r\n").await;
+
+    let infos = client.message_infos().await.unwrap();
+
+    assert_eq!(infos[0].uid(), None);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_snapshot() {
+    let mut client = create_mock_client_with(
+        "PIPELINING\r\nUIDL\r\n",
+        "+OK 2 320\r\n+OK 2 messages\r\n1 120\r\n3 200\r\n.\r\n+OK\r\n1 abc\r\n3 def\r\n.\r\n",
+    )
+    .await;
+
+    let snapshot = client.snapshot().await.unwrap();
+
+    assert_eq!(snapshot.message_count(), 2);
+    assert_eq!(snapshot.size(), 320);
+    assert_eq!(snapshot.messages().len(), 2);
+    assert_eq!(snapshot.get(3).unwrap().size(), 200);
+    assert_eq!(snapshot.get(3).unwrap().uid().unwrap().to_string(), "def");
+    assert!(snapshot.get(2).is_none());
+
+    assert_eq!(sent_commands(client), "STAT\r\nLIST\r\nUIDL\r\n");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_sync() {
+    let mut client = create_logged_in_mock_client(
+        "+OK 2 messages\r\n1 120\r\n2 200\r\n.\r\n+OK\r\n1 abc\r\n2 def\r\n.\r\n+OK\r\nNew\r\n.\r\n+OK deleted\r\n",
+    )
+    .await;
+
+    let previous: Snapshot = ["abc", "gone"].into_iter().collect();
+
+    let mut fetched = Vec::new();
+
+    let outcome = sync::run(
+        &mut client,
+        &previous,
+        |info, message| {
+            fetched.push((info.number(), message));
+
+            Ok(())
+        },
+        |info| info.number() == 1,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(fetched, vec![(MessageNumber::new(2), Bytes::from("New"))]);
+    assert_eq!(outcome.changes().vanished, vec!["gone".to_string()]);
+    assert_eq!(outcome.changes().retained.len(), 1);
+    assert_eq!(outcome.deleted().len(), 1);
+    assert_eq!(outcome.snapshot(), &["def"].into_iter().collect());
+
+    assert!(sent_commands(client).ends_with("LIST\r\nUIDL\r\nRETR 2\r\nDELE 1\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_sync_without_uidl() {
+    use crate::{headers::Headers, uid::HeaderHash};
+
+    let capabilities = "USER\r\nTOP\r\n";
+
+    let first = "Message-ID: <1@example.com>\r\nFrom: alice@example.com\r\n\r\n";
+    let second = "Message-ID: <2@example.com>\r\nFrom: bob@example.com\r\n\r\n";
+
+    let mut client = create_mock_client_with(
+        capabilities,
+        format!(
+            "+OK\r\n+OK\r\n+OK\r\n{}.\r\n+OK 2 messages\r\n1 120\r\n2 200\r\n.\r\n+OK\r\n{}.\r\n+OK\r\n{}.\r\n+OK\r\nNew\r\n.\r\n",
+            capabilities, first, second
+        ),
+    )
+    .await;
+
+    client.login("user", "password").await.unwrap();
+
+    let provider = HeaderHash::new();
+
+    let first_id = provider.unique_id(&Headers::parse(first.as_bytes()), 120);
+    let second_id = provider.unique_id(&Headers::parse(second.as_bytes()), 200);
+
+    assert_ne!(first_id, second_id);
+    assert_eq!(first_id.value().unwrap().len(), 32);
+    // The size is part of the id.
+    assert_ne!(
+        provider.unique_id(&Headers::parse(first.as_bytes()), 121),
+        first_id
+    );
+
+    let previous: Snapshot = [first_id.to_string()].into_iter().collect();
+
+    let mut fetched = Vec::new();
+
+    let outcome = sync::run_with(
+        &mut client,
+        &provider,
+        &previous,
+        |info, message| {
+            fetched.push((info.number(), message));
+
+            Ok(())
+        },
+        |_info| false,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(fetched, vec![(MessageNumber::new(2), Bytes::from("New"))]);
+    assert_eq!(outcome.changes().retained.len(), 1);
+    assert_eq!(
+        outcome.snapshot(),
+        &[first_id.to_string(), second_id.to_string()]
+            .into_iter()
+            .collect()
+    );
+
+    assert!(sent_commands(client).ends_with("LIST\r\nTOP 1 0\r\nTOP 2 0\r\nRETR 2\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_fetch_unseen() {
+    let mut store: Snapshot = ["abc"].into_iter().collect();
+
+    let mut client = create_logged_in_mock_client(
+        "+OK 3 messages\r\n1 120\r\n2 200\r\n3 300\r\n.\r\n+OK\r\n1 abc\r\n2 def\r\n3 ghi\r\n.\r\n+OK\r\nSecond\r\n.\r\n+OK\r\nThird\r\n.\r\n",
+    )
+    .await;
+
+    let messages = client.message_infos().await.unwrap();
+
+    // Handling the third message fails, as if the process crashed.
+    let result = sync::fetch_unseen(&mut client, &messages, &mut store, |info, _message| {
+        if info.number() == 3 {
+            Err(Error::new(ErrorKind::Rejected, "Disk full"))
+        } else {
+            Ok(())
+        }
+    })
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(store, ["abc", "def"].into_iter().collect());
+
+    // The next session numbers the remaining messages differently.
+    let mut client = create_logged_in_mock_client(
+        "+OK 2 messages\r\n1 200\r\n2 300\r\n.\r\n+OK\r\n1 def\r\n2 ghi\r\n.\r\n+OK\r\nThird\r\n.\r\n",
+    )
+    .await;
+
+    let messages = client.message_infos().await.unwrap();
+
+    let mut fetched = Vec::new();
+
+    let infos = sync::fetch_unseen(&mut client, &messages, &mut store, |info, message| {
+        fetched.push((info.number(), message));
+
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(fetched, vec![(MessageNumber::new(2), Bytes::from("Third"))]);
+    assert_eq!(infos.len(), 1);
+    assert!(store.contains("ghi"));
+
+    assert!(sent_commands(client).ends_with("LIST\r\nUIDL\r\nRETR 2\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_fetch_if() {
+    use crate::sync::Fetched;
+
+    let mut client = create_logged_in_mock_client(
+        "+OK 2 messages\r\n1 120\r\n2 5000\r\n.\r\n+OK\r\n1 abc\r\n2 def\r\n.\r\n+OK\r\nSmall\r\n.\r\n+OK\r\nSubject: Large\r\n\r\n.\r\n",
+    )
+    .await;
+
+    let messages = client.message_infos().await.unwrap();
+
+    let mut fetched = Vec::new();
+
+    sync::fetch_if(
+        &mut client,
+        &messages,
+        |info| info.size() < 1000,
+        |info, message| {
+            fetched.push((info.number(), message));
+
+            Ok(())
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        fetched,
+        vec![
+            (
+                MessageNumber::new(1),
+                Fetched::Message(Bytes::from("Small"))
+            ),
+            (
+                MessageNumber::new(2),
+                Fetched::Headers(Bytes::from("Subject: Large\r\n"))
+            ),
+        ]
+    );
+
+    assert!(sent_commands(client).ends_with("RETR 1\r\nTOP 2 0\r\n"));
+}
+
+#[cfg(feature = "spill")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_spill() {
+    use crate::{runtime::io::ReadExt, spill::SpilledMessage};
+
+    let mut client = create_logged_in_mock_client(
+        "+OK\r\nSmall\r\n.\r\n+OK\r\nA rather large message\r\nspanning two lines\r\n.\r\n",
+    )
+    .await;
+
+    match client.retr_spill(1, 16).await.unwrap() {
+        SpilledMessage::InMemory(bytes) => assert_eq!(bytes.as_ref(), b"Small"),
+        SpilledMessage::OnDisk(_) => unreachable!(),
+    }
+
+    match client.retr_spill(2, 16).await.unwrap() {
+        SpilledMessage::OnDisk(mut file) => {
+            let mut content = String::new();
+
+            file.read_to_string(&mut content).await.unwrap();
+
+            assert_eq!(content, "A rather large message\r\nspanning two lines");
+        }
+        SpilledMessage::InMemory(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_max_response_size() {
+    let message = "x".repeat(10_000);
+
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n{message}\r\n.\r\n+OK\r\n{message}\r\n.\r\n",
+        capa = MOCK_CAPABILITIES,
+        message = message
+    );
+
+    let mut client = ClientBuilder::new()
+        .max_response_size(4096)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    match client.retr(1).await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::ResponseTooLarge)),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_last_raw_response() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK 2 320\r\n+OK\r\n1 120\r\n2 200\r\n.\r\n+OK two\r\n",
+        capa = MOCK_CAPABILITIES,
+    );
+
+    let mut client = ClientBuilder::new()
+        .retain_raw_response(16)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    client.stat().await.unwrap();
+
+    assert_eq!(client.last_raw_response(), Some(&b"+OK 2 320\r\n"[..]));
+
+    client.list(None::<MessageNumber>).await.unwrap();
+
+    assert_eq!(
+        client.last_raw_response(),
+        Some(&b"+OK\r\n1 120\r\n2 20"[..])
+    );
+
+    // The response to STAT is missing the size, so it can not be parsed.
+    assert!(client.stat().await.is_err());
+
+    assert_eq!(client.last_raw_response(), Some(&b"+OK two\r\n"[..]));
+
+    let mut client = create_mock_client("+OK 2 320\r\n").await;
+
+    client.stat().await.unwrap();
+
+    assert_eq!(client.last_raw_response(), None);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_set_transcript() {
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut client = create_mock_client(format!(
+        "+OK\r\n+OK\r\n+OK\r\n{}.\r\n+OK 2 320\r\n+OK 2 320\r\n",
+        MOCK_CAPABILITIES
+    ))
+    .await;
+
+    let output = Output::default();
+
+    client
+        .set_transcript(Some(Box::new(output.clone())))
+        .unwrap();
+
+    client.login("user", "hunter2").await.unwrap();
+    client.stat().await.unwrap();
+
+    client.set_transcript(None).unwrap();
+
+    client.stat().await.unwrap();
+
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+
+    // The mock stream hands over all of its data at once, so the lines of both sides are checked separately.
+    let lines = |prefix: &str| -> Vec<String> {
+        output
+            .lines()
+            .filter(|line| line.starts_with(prefix))
+            .map(|line| line.trim_end_matches("\\r\\n").to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        lines("C: "),
+        ["C: USER user", "C: PASS <redacted>", "C: CAPA", "C: STAT"]
+    );
+    assert_eq!(
+        lines("S: "),
+        [
+            "S: +OK",
+            "S: +OK",
+            "S: +OK",
+            "S: USER",
+            "S: TOP",
+            "S: UIDL",
+            "S: .",
+            "S: +OK 2 320"
+        ]
+    );
+    assert!(!output.contains("hunter2"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_bare_line_feed_terminator() {
+    let mut client = create_mock_client("+OK\r\nfirst\n.\nsecond\r\n.\r\n+OK\r\n").await;
+
+    let message = client.retr(1).await.unwrap();
+
+    // The bare line feeds do not end the message in strict mode, the period after one is still taken as byte-stuffing.
+    assert_eq!(&message[..], b"first\n\nsecond");
+
+    client.noop().await.unwrap();
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_with_limit() {
+    let message = "x".repeat(10_000);
+
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n{message}\r\n.\r\n",
+        capa = MOCK_CAPABILITIES,
+        message = message
+    );
+
+    let mut client = ClientBuilder::new()
+        .max_response_size(4096)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    let retrieved = client.retr_with_limit(1, usize::MAX).await.unwrap();
+
+    assert_eq!(retrieved.len(), 10_000);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_retr_with_limit_cancelled() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n{message}\r\n.\r\n",
+        capa = MOCK_CAPABILITIES,
+        message = "x".repeat(100)
+    );
+
+    let mut client = ClientBuilder::new()
+        .max_response_size(4096)
+        .build(MockStream::stuttering(input))
+        .await
+        .unwrap();
+
+    {
+        let mut retr = Box::pin(client.retr_with_limit(1, usize::MAX));
+
+        assert!(futures::poll!(&mut retr).is_pending());
+    }
+
+    assert_eq!(client.inner_mut().unwrap().max_response_size(), 4096);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_listing_streams() {
+    let listing: String = (1..=100).map(|n| format!("{} {}\r\n", n, n * 10)).collect();
+
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK 100 messages\r\n{listing}.\r\n+OK\r\n1 whqtswO00WBw418f9t5JxYwZ\r\n\r\n2 QhdPYR:00WBw1Ph7x7\r\n.\r\n",
+        capa = MOCK_CAPABILITIES,
+        listing = listing
+    );
+
+    let mut client = ClientBuilder::new()
+        .read_buffer_size(16)
+        .parse_mode(ParseMode::Lenient)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    // The listing is far larger than this, but only a single line has to fit.
+    client.set_max_response_size(64).unwrap();
+
+    let stats: Vec<_> = client
+        .list_stream()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(stats.len(), 100);
+    assert_eq!(stats[99].counter().value().unwrap(), 100);
+    assert_eq!(stats[99].size().value().unwrap(), 1000);
+
+    let unique_ids: Vec<_> = client
+        .uidl_stream()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(unique_ids.len(), 2);
+    assert_eq!(unique_ids[1].id().as_str().unwrap(), "QhdPYR:00WBw1Ph7x7");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_read_rate_limit() {
+    let message = "x".repeat(8192);
+
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n{message}\r\n.\r\n",
+        capa = MOCK_CAPABILITIES,
+        message = message
+    );
+
+    let mut client = ClientBuilder::new()
+        .read_rate_limit(4096)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    let start = Instant::now();
+
+    assert_eq!(client.retr(1).await.unwrap().len(), 8192);
+
+    // The first second worth of data is allowed as a burst, the rest has to wait.
+    assert!(start.elapsed() >= Duration::from_millis(800));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_metrics() {
+    let greeting = "+OK POP3 server ready\r\n";
+    let capa = format!("+OK\r\n{}.\r\n", MOCK_CAPABILITIES);
+    let responses = "+OK 2 320\r\n+OK 1 120\r\n+OK 2 320\r\n";
+
+    let input = format!("{}{}{}", greeting, capa, responses);
+
+    let mut client = ClientBuilder::new()
+        .build(MockStream::new(&input))
+        .await
+        .unwrap();
+
+    client.stat().await.unwrap();
+    client.list(Some(1)).await.unwrap();
+    client.stat().await.unwrap();
+
+    let metrics = client.metrics().unwrap();
+
+    assert_eq!(metrics.bytes_received, input.len() as u64);
+    assert_eq!(
+        metrics.bytes_sent,
+        "CAPA\r\nSTAT\r\nLIST 1\r\nSTAT\r\n".len() as u64
+    );
+
+    assert_eq!(metrics.commands["STAT"].count, 2);
+    assert_eq!(metrics.commands["LIST"].count, 1);
+    assert_eq!(metrics.commands["CAPA"].count, 1);
+    assert!(!metrics.commands.contains_key("GREET"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_argument_injection() {
+    let input = format!("+OK POP3 server ready\r\n+OK\r\n{}.\r\n", MOCK_CAPABILITIES);
+
+    let mut client = ClientBuilder::new()
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    let err = client
+        .login("user\r\nDELE 1", "password")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::InvalidArgument));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_message_number_validation() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{}.\r\n+OK\r\n+OK 2 320\r\n",
+        MOCK_CAPABILITIES
+    );
+
+    let mut client = ClientBuilder::new()
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    let err = client.retr(0).await.unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::InvalidArgument));
+
+    client.dele(1).await.unwrap();
+
+    // The deleted message is left out of the count, but still takes up a message number.
+    client.stat().await.unwrap();
+
+    let err = client.top(4, 0).await.unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::InvalidArgument));
+
+    let err = client.top(2, usize::MAX).await.unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::InvalidArgument));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_bare_line_endings() {
+    let input =
+        "+OK POP3 server ready\n+OK\nUSER\nTOP\n.\n+OK 2 320\r+OK\rSubject: Hi\r\rHello\r.\r";
+
+    let mut client = ClientBuilder::new()
+        .parse_mode(ParseMode::Lenient)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    assert!(client.has_capability([Capability::Top]));
+
+    assert_eq!(client.stat().await.unwrap().counter().value().unwrap(), 2);
+
+    assert_eq!(
+        client.retr(1).await.unwrap().as_ref(),
+        b"Subject: Hi\r\n\r\nHello"
+    );
+}
+
+#[derive(Default)]
+struct Recorder {
+    seen: Mutex<Vec<String>>,
+}
+
+impl Interceptor for Arc<Recorder> {
+    fn on_request(&self, request: &Request) -> crate::error::Result<()> {
+        if request.command() == &Command::Dele {
+            return Err(crate::error::Error::new(
+                ErrorKind::Rejected,
+                "Deleting is not allowed",
+            ));
+        }
+
+        self.seen
+            .lock()
+            .unwrap()
+            .push(request.redacted().to_string());
+
+        Ok(())
+    }
+
+    fn on_response(&self, command: &Command, response: &Response) {
+        let outcome = match response {
+            Response::Err(_) => "-ERR",
+            _ => "+OK",
+        };
+
+        self.seen
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", command.name(), outcome));
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_interceptor() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{}.\r\n+OK 2 320\r\n-ERR no such message\r\n",
+        MOCK_CAPABILITIES
+    );
+
+    let recorder = Arc::new(Recorder::default());
+
+    let mut client = ClientBuilder::new()
+        .interceptor(recorder.clone())
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    client.stat().await.unwrap();
+    client.retr(2).await.unwrap_err();
+
+    let err = client.dele(1).await.unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::Rejected));
+
+    assert_eq!(
+        *recorder.seen.lock().unwrap(),
+        vec![
+            "GREET +OK",
+            "CAPA",
+            "CAPA +OK",
+            "STAT",
+            "STAT +OK",
+            "RETR 2",
+            "RETR -ERR"
+        ]
+    );
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_buffer_growth() {
+    let message = "x".repeat(100_000);
+
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n{message}\r\n.\r\n+OK 1 10\r\n",
+        capa = MOCK_CAPABILITIES,
+        message = message
+    );
+
+    let mut client = ClientBuilder::new()
+        .read_buffer_size(64 * 1024)
+        .buffer_growth(BufferGrowth::Exponential)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    assert_eq!(client.retr(1).await.unwrap().len(), 100_000);
+    assert_eq!(client.stat().await.unwrap().size().value().unwrap(), 10);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_greeting_timeout() {
+    let result = ClientBuilder::new()
+        .greeting_timeout(Duration::from_millis(50))
+        .build(MockStream::hanging("+OK POP3"))
+        .await;
+
+    match result {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::Timeout { command: None })),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_idle_time() {
+    let mut client = create_mock_client("+OK keeping session alive\r\n+OK Bye\r\n").await;
+
+    // Works in the authentication state, before any command was sent.
+    assert!(client.idle_time().is_some());
+
+    assert_eq!(
+        client.noop().await.unwrap().value().unwrap(),
+        "keeping session alive"
+    );
+
+    assert!(client.idle_time().unwrap() < Duration::from_secs(60));
+
+    client.quit().await.unwrap();
+
+    assert!(client.last_activity().is_none());
+    assert!(client.idle_time().is_none());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_ping() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{}.\r\n+OK\r\n-ERR not now\r\n",
+        MOCK_CAPABILITIES
+    );
+
+    let mut client = super::new(MockStream::hanging(input)).await.unwrap();
+
+    let health = client.ping(Duration::from_secs(60)).await;
+
+    assert!(matches!(health, Health::Healthy(_)));
+    assert!(health.latency().is_some());
+
+    // A negative response still means the server is there.
+    assert!(client.ping(Duration::from_secs(60)).await.is_alive());
+
+    match client.ping(Duration::from_millis(50)).await {
+        Health::Dead(err) => assert!(matches!(err.kind(), ErrorKind::Timeout { .. })),
+        _ => unreachable!(),
+    }
+
+    assert!(!client.is_usable());
+    assert!(!client.ping(Duration::from_secs(60)).await.is_alive());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_custom_timer() {
+    use crate::timer::{NeverTimer, Sleep, Timer};
+
+    /// A timer that fires right away, no matter the duration.
+    struct ImmediateTimer;
+
+    impl Timer for ImmediateTimer {
+        fn sleep(&self, _duration: Duration) -> Sleep {
+            Box::pin(futures::future::ready(()))
+        }
+    }
+
+    let result = ClientBuilder::new()
+        .timer(Arc::new(ImmediateTimer))
+        .greeting_timeout(Duration::from_secs(3600))
+        .build(MockStream::hanging("+OK POP3"))
+        .await;
+
+    match result {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::Timeout { command: None })),
+        Ok(_) => unreachable!(),
+    }
+
+    let mut client = ClientBuilder::new()
+        .timer(Arc::new(NeverTimer))
+        .build(MockStream::new(format!(
+            "+OK POP3 server ready\r\n+OK\r\n{}.\r\n+OK Bye\r\n",
+            MOCK_CAPABILITIES
+        )))
+        .await
+        .unwrap();
+
+    // The timer never fires, so even a timeout of zero leaves the server time to respond.
+    assert!(client.close(Duration::ZERO).await.is_some());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_login_delay() {
+    let capabilities = "USER\r\nLOGIN-DELAY 900\r\n";
+
+    let builder = ClientBuilder::new().server_name("login-delay.example.com");
+
+    let connect = || {
+        let input = format!(
+            "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK\r\n+OK\r\n{capa}.\r\n",
+            capa = capabilities
+        );
+
+        builder.build(MockStream::new(input))
+    };
+
+    let mut client = connect().await.unwrap();
+
+    client.login("user", "password").await.unwrap();
+
+    let mut client = connect().await.unwrap();
+
+    match client.login("user", "password").await {
+        Err(err) => match err.kind() {
+            ErrorKind::LoginDelay(remaining) => assert!(remaining.as_secs() > 800),
+            _ => unreachable!(),
+        },
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_login_retry() {
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n{}+OK\r\n{capa}.\r\n",
+        [
+            // Rejected.
+            "+OK\r\n-ERR [AUTH] invalid password\r\n",
+            // Cancelled after USER, which is then cancelled on the server as well.
+            "+OK\r\n-ERR\r\n-ERR not logged in\r\n",
+            "+OK\r\n+OK\r\n",
+        ]
+        .concat(),
+        capa = MOCK_CAPABILITIES
+    );
+
+    let mut client = super::new(MockStream::stuttering(input)).await.unwrap();
+
+    match client.login("user", "wrong").await {
+        Err(err) => assert_eq!(err.response_code(), Some(&ResponseCode::Auth)),
+        Ok(_) => unreachable!(),
+    }
+
+    // Nothing is left to clean up after a rejected attempt.
+    client.reset_auth().await.unwrap();
+
+    {
+        let mut login = Box::pin(client.login("user", "dropped"));
+
+        assert!(futures::poll!(&mut login).is_pending());
+    }
+
+    client.login("user", "right").await.unwrap();
+
+    assert_eq!(client.get_state(), &ClientState::Transaction);
+    assert!(matches!(
+        client.reset_auth().await.unwrap_err().kind(),
+        ErrorKind::IncorrectStateForCommand
+    ));
+
+    assert!(sent_commands(client).starts_with(
+        "USER user\r\nPASS wrong\r\nUSER user\r\n*\r\nNOOP\r\nUSER user\r\nPASS right\r\n"
+    ));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_login_delay_rejection() {
+    let input = "+OK POP3 server ready\r\n+OK\r\nUSER\r\nLOGIN-DELAY 900\r\n.\r\n+OK\r\n-ERR [LOGIN-DELAY] wait a bit\r\n";
+
+    let mut client = ClientBuilder::new()
+        .server_name("login-delay-rejection.example.com")
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    assert!(client.login("user", "password").await.is_err());
+
+    match client.login("user", "password").await {
+        Err(err) => match err.kind() {
+            ErrorKind::LoginDelay(remaining) => assert!(remaining.as_secs() > 800),
+            _ => unreachable!(),
+        },
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_keep_alive() {
+    let client = create_logged_in_mock_client("+OK\r\n").await;
+
+    let client = client.keep_alive(Duration::from_millis(20));
+
+    crate::timer::default_timer()
+        .sleep(Duration::from_millis(60))
+        .await;
+
+    let client = client.into_inner().await;
+
+    assert!(sent_commands(client).contains("CAPA\r\nNOOP\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_resilient_reconnect() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let connections = AtomicUsize::new(0);
+
+    let mut client = ResilientClient::new(
+        || {
+            // The first connection drops right after logging in.
+            let responses = match connections.fetch_add(1, Ordering::SeqCst) {
+                0 => "",
+                _ => "+OK 2 320\r\n",
+            };
+
+            let input = format!(
+                "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK\r\n+OK\r\n{capa}.\r\n{}",
+                responses,
+                capa = MOCK_CAPABILITIES
+            );
+
+            super::new(MockStream::new(input))
+        },
+        Credentials::login("user", "password"),
+    );
+
+    let stat = client.stat().await.unwrap();
+
+    assert_eq!(stat.counter().value().unwrap(), 2);
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_resilient_no_replay() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let connections = AtomicUsize::new(0);
+
+    let mut client = ResilientClient::new(
+        || {
+            // The first connection drops right after logging in.
+            let responses = match connections.fetch_add(1, Ordering::SeqCst) {
+                0 => "",
+                _ => "+OK\r\nHello\r\n.\r\n",
+            };
+
+            let input = format!(
+                "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK\r\n+OK\r\n{capa}.\r\n{}",
+                responses,
+                capa = MOCK_CAPABILITIES
+            );
+
+            super::new(MockStream::new(input))
+        },
+        Credentials::login("user", "password"),
+    );
+
+    client.set_retry_policy(ExponentialBackoff::new().initial_delay(Duration::from_millis(1)));
+
+    // Message 1 may be a different message in the next session, so it is not fetched from there.
+    let err = client.retr(1).await.unwrap_err();
+
+    assert!(err.is_transient());
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    assert_eq!(client.retr(1).await.unwrap(), "Hello");
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_resilient_retry() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let connections = AtomicUsize::new(0);
+
+    let mut client = ResilientClient::new(
+        || {
+            connections.fetch_add(1, Ordering::SeqCst);
+
+            let input = format!(
+                "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK\r\n+OK\r\n{capa}.\r\n-ERR [SYS/TEMP] busy\r\n-ERR [SYS/TEMP] busy\r\n+OK 2 320\r\n",
+                capa = MOCK_CAPABILITIES
+            );
+
+            super::new(MockStream::new(input))
+        },
+        Credentials::login("user", "password"),
+    );
+
+    client.set_retry_policy(ExponentialBackoff::new().initial_delay(Duration::from_millis(1)));
+
+    let stat = client.stat().await.unwrap();
+
+    assert_eq!(stat.counter().value().unwrap(), 2);
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    client.set_retry_policy(NoRetry);
+
+    assert!(client.stat().await.unwrap_err().is_transient());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_pool_reuse() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let connections = AtomicUsize::new(0);
+
+    let pool = Pool::new(
+        || {
+            connections.fetch_add(1, Ordering::SeqCst);
+
+            let input = format!(
+                "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK\r\n+OK\r\n{capa}.\r\n+OK 2 320\r\n+OK\r\n+OK 2 320\r\n",
+                capa = MOCK_CAPABILITIES
+            );
+
+            super::new(MockStream::new(input))
+        },
+        Credentials::login("user", "password"),
+    );
+
+    {
+        let mut client = pool.get().await.unwrap();
+
+        client.stat().await.unwrap();
+    }
+
+    assert_eq!(pool.idle_count(), 1);
+
+    let mut client = pool.get().await.unwrap();
+
+    client.stat().await.unwrap();
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_shared_client() {
+    let client = create_logged_in_mock_client("+OK 1 120\r\n+OK 2 320\r\n+OK\r\n+OK 1 320\r\n")
+        .await
+        .shared();
+
+    let other = client.clone();
+
+    let guard = client.lock().await;
+
+    let mut first = Box::pin(other.list(Some(1)));
+    let mut second = Box::pin(client.stat());
+    let mut cancelled = Box::pin(client.noop());
+
+    assert!(futures::poll!(&mut first).is_pending());
+    assert!(futures::poll!(&mut second).is_pending());
+    assert!(futures::poll!(&mut cancelled).is_pending());
+    assert_eq!(client.waiting(), 3);
+
+    drop(cancelled);
+
+    assert_eq!(client.waiting(), 2);
+
+    drop(guard);
+
+    // The turns are handed out in the order they were asked for, skipping the task that stopped waiting.
+    let (second, first) = futures::join!(second, first);
+
+    match first.unwrap() {
+        ListResponse::Single(stat) => assert_eq!(stat.size().value().unwrap(), 120),
+        ListResponse::Multiple(_) => unreachable!(),
+    }
+    assert_eq!(second.unwrap().counter().value().unwrap(), 2);
+
+    {
+        let mut session = client.lock().await;
+
+        session.noop().await.unwrap();
+
+        assert_eq!(session.stat().await.unwrap().size().value().unwrap(), 320);
+    }
+
+    let client = match client.into_inner() {
+        Ok(_) => unreachable!(),
+        Err(client) => client,
+    };
+
+    drop(other);
+
+    assert_eq!(
+        client.into_inner().ok().unwrap().get_state(),
+        &ClientState::Transaction
+    );
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_response_code() {
+    let mut client = create_mock_client("+OK\r\n-ERR [IN-USE] mailbox locked\r\n").await;
+
+    let err = client.login("user", "password").await.unwrap_err();
+
+    assert_eq!(err.response_code(), Some(&ResponseCode::InUse));
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::ServerError { code: Some(ResponseCode::InUse), text, command: Some(Command::Pass) }
+            if text.raw() == b"mailbox locked"
+    ));
+    assert_eq!(err.to_string(), "server error: [IN-USE] mailbox locked");
+
+    assert!(err.is_in_use());
+    assert!(err.is_transient());
+    assert!(!err.is_auth_failure());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_error_classification() {
+    let mut client = create_mock_client("+OK\r\n-ERR [AUTH] invalid password\r\n").await;
+
+    let err = client.login("user", "password").await.unwrap_err();
+
+    assert!(err.is_auth_failure());
+    assert!(!err.is_transient());
+
+    // The connection is closed once the scripted responses run out.
+    let err = client.capa().await.unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::ConnectionClosed));
+    assert!(err.is_transient());
+    assert!(!err.is_auth_failure());
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_apop_login() {
+    let mut client = create_mock_client(format!("+OK\r\n+OK\r\n{}.\r\n", MOCK_CAPABILITIES)).await;
+
+    client.apop_login("mrose", "tanstaaf").await.unwrap();
+
+    assert_eq!(client.get_state(), &ClientState::Transaction);
+
+    assert!(sent_commands(client).starts_with("APOP mrose c4c9334bac560ecc979e58001b3e22fb\r\n"));
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_apop_login_without_timestamp() {
+    let stream = MockStream::new(format!(
+        "+OK POP3 server ready\r\n+OK\r\n{}.\r\n",
+        MOCK_CAPABILITIES
+    ));
+
+    let mut client = super::new(stream).await.unwrap();
+
+    match client.apop_login("mrose", "tanstaaf").await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::FeatureUnsupported)),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg(feature = "sasl")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_auth_strategy() {
+    let capabilities = "SASL PLAIN\r\nUSER\r\n";
+
+    let mut client = create_mock_client_with(
+        capabilities,
+        format!(
+            "-ERR [AUTH] APOP disabled\r\n+OK\r\n+OK\r\n+OK\r\n{}.\r\n",
+            capabilities
+        ),
+    )
+    .await;
+
+    let strategy = AuthStrategy::new("mrose", "tanstaaf");
+
+    assert_eq!(
+        client.authenticate(&strategy).await.unwrap(),
+        AuthMechanism::UserPass
+    );
+    assert_eq!(client.get_state(), &ClientState::Transaction);
+
+    // SASL PLAIN is skipped on a plain connection, so the password is only sent once.
+    assert!(sent_commands(client).starts_with(
+        "APOP mrose c4c9334bac560ecc979e58001b3e22fb\r\nUSER mrose\r\nPASS tanstaaf\r\n"
+    ));
+
+    // Without a timestamp in the greeting APOP is skipped, and a rejection of the last mechanism is returned as is.
+    let stream = MockStream::new(format!(
+        "+OK POP3 server ready\r\n+OK\r\n{}.\r\n-ERR [AUTH] nope\r\n",
+        capabilities
+    ));
+
+    let mut client = super::new(stream).await.unwrap();
+
+    let strategy = strategy.with_preference([AuthMechanism::Apop, AuthMechanism::UserPass]);
+
+    match client.authenticate(&strategy).await {
+        Err(err) => assert_eq!(err.response_code(), Some(&ResponseCode::Auth)),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg(feature = "sasl")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_login_policy() {
+    let capabilities = "SASL CRAM-MD5 PLAIN\r\nUSER\r\n";
+
+    let input = format!(
+        "+OK POP3 server ready <1896.697170952@dbc.mtview.ca.us>\r\n+OK\r\n{capa}.\r\n{}+OK\r\n{capa}.\r\n",
+        [
+            "+ PDE4OTYuNjk3MTcwOTUyQHBvc3RvZmZpY2UucmVzdG9uLm1jaS5uZXQ+\r\n",
+            "-ERR [AUTH] nope\r\n",
+            "+OK maildrop locked and ready\r\n",
+        ]
+        .concat(),
+        capa = capabilities
+    );
+
+    let mut client = ClientBuilder::new()
+        .login_policy(LoginPolicy::Strongest)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
+
+    let (message, _) = client.login("mrose", "tanstaaf").await.unwrap();
+
+    assert_eq!(message.value().unwrap(), "maildrop locked and ready");
+
+    assert!(sent_commands(client).starts_with(
+        "AUTH CRAM-MD5\r\nbXJvc2UgODVhMmRlYWViNTIwOWIwZjU0ZDQwNzU5ZGFjYzI0YzQ=\r\nAPOP mrose c4c9334bac560ecc979e58001b3e22fb\r\n"
+    ));
+}
+
+#[cfg(feature = "sasl")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_login_policy_plain_connection() {
+    let capabilities = "SASL PLAIN\r\nUSER\r\n";
 
-use crate::runtime::net::TcpStream;
-use dotenv::dotenv;
-use log::info;
+    let input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{capa}.\r\n+OK\r\n+OK maildrop locked and ready\r\n+OK\r\n{capa}.\r\n",
+        capa = capabilities
+    );
 
-use crate::{
-    response::{capability::Capability, list::ListResponse, types::DataType, uidl::UidlResponse},
-    ClientState,
-};
+    let mut client = ClientBuilder::new()
+        .login_policy(LoginPolicy::Plain)
+        .build(MockStream::new(input))
+        .await
+        .unwrap();
 
-use super::Client;
+    client.login("mrose", "tanstaaf").await.unwrap();
 
-#[derive(Debug)]
-struct ClientInfo {
-    server: String,
-    port: u16,
-    username: String,
-    password: String,
+    assert!(sent_commands(client).starts_with("USER mrose\r\nPASS tanstaaf\r\n"));
 }
 
-fn create_client_info() -> ClientInfo {
-    dotenv().ok();
+/// A mechanism that answers "ping" with "pong" and gives up on any other challenge.
+#[cfg(feature = "sasl")]
+struct PingAuthenticator;
 
-    ClientInfo {
-        server: env::var("SERVER").unwrap().to_owned(),
-        port: env::var("PORT").unwrap().parse().unwrap(),
-        username: env::var("USERNAME").unwrap().to_owned(),
-        password: env::var("PASSWORD").unwrap().to_owned(),
+#[cfg(feature = "sasl")]
+impl crate::sasl::Authenticator for PingAuthenticator {
+    fn mechanism(&self) -> &str {
+        "X-PING"
     }
-}
 
-async fn create_logged_in_client() -> Client<TcpStream> {
-    let client_info = create_client_info();
-    let server = client_info.server.as_ref();
-    let port = client_info.port;
+    fn process(&mut self, challenge: &[u8]) -> crate::error::Result<Vec<u8>> {
+        match challenge {
+            b"ping" => Ok(b"pong".to_vec()),
+            _ => Err(crate::error::Error::new(
+                ErrorKind::UnexpectedResponse,
+                "Not a ping",
+            )),
+        }
+    }
+}
 
-    let username = client_info.username;
-    let password = client_info.password;
+#[cfg(feature = "sasl")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_auth_continuations() {
+    let mut client = create_mock_client(format!(
+        "+ cGluZw==\r\n+ cGluZw==\r\n+OK\r\n+OK\r\n{}.\r\n",
+        MOCK_CAPABILITIES
+    ))
+    .await;
 
-    let mut client = super::connect_plain((server, port)).await.unwrap();
+    client.auth(PingAuthenticator).await.unwrap();
 
-    client.login(username, password).await.unwrap();
+    assert_eq!(client.get_state(), &ClientState::Transaction);
 
-    client
+    assert_eq!(
+        sent_commands(client),
+        "AUTH X-PING\r\ncG9uZw==\r\ncG9uZw==\r\nCAPA\r\n"
+    );
 }
 
-// async fn create_logged_in_client_tls() -> Client<impl crate::tls::TlsStream<TcpStream>> {
-//     let client_info = create_client_info();
-//     let server = client_info.server.as_ref();
-//     let port = client_info.port;
+#[cfg(feature = "sasl")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_auth_cancel() {
+    let mut client = create_mock_client("+ cG9uZw==\r\n-ERR cancelled\r\n").await;
 
-//     let username = client_info.username;
-//     let password = client_info.password;
+    match client.auth(PingAuthenticator).await {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::UnexpectedResponse)),
+        Ok(_) => unreachable!(),
+    }
 
-//     let tls = async_native_tls::TlsConnector::new();
+    assert_eq!(client.get_state(), &ClientState::Authentication);
 
-//     let mut client = super::connect((server, port), server, &tls).await.unwrap();
+    assert_eq!(sent_commands(client), "AUTH X-PING\r\n*\r\n");
+}
 
-//     client.login(username, password).await.unwrap();
+#[cfg(feature = "sasl")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_external_requires_tls() {
+    let mut client = create_mock_client("").await;
 
-//     client
-// }
+    let result = client.auth(crate::sasl::ExternalAuthenticator::new()).await;
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+    match result {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::FeatureUnsupported)),
+        Ok(_) => unreachable!(),
+    }
+
+    assert!(!client.is_secure());
+    assert_eq!(sent_commands(client), "");
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_connect() {
     let client_info = create_client_info();
@@ -81,7 +2546,10 @@ async fn e2e_connect() {
     client.quit().await.unwrap();
 }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_login() {
     let mut client = create_logged_in_client().await;
@@ -91,7 +2559,10 @@ async fn e2e_login() {
     client.quit().await.unwrap();
 }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 #[cfg(feature = "sasl")]
 async fn e2e_auth() {
@@ -112,17 +2583,23 @@ async fn e2e_auth() {
     client.quit().await.unwrap();
 }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_noop() {
     let mut client = create_logged_in_client().await;
 
-    assert_eq!(client.noop().await.unwrap(), ());
+    client.noop().await.unwrap();
 
     client.quit().await.unwrap();
 }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_stat() {
     let mut client = create_logged_in_client().await;
@@ -134,14 +2611,17 @@ async fn e2e_stat() {
     client.quit().await.unwrap();
 }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_list() {
     let mut client = create_logged_in_client().await;
 
     // let list = client.list(Some(1)).await.unwrap();
 
-    let response = client.list(None).await.unwrap();
+    let response = client.list(None::<MessageNumber>).await.unwrap();
 
     match response {
         ListResponse::Multiple(list) => {
@@ -155,7 +2635,10 @@ async fn e2e_list() {
     client.quit().await.unwrap();
 }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_capa() {
     let mut client = create_logged_in_client().await;
@@ -163,11 +2646,8 @@ async fn e2e_capa() {
     let capas = client.capa().await.unwrap();
 
     for capa in capas {
-        match capa {
-            Capability::LoginDelay(time) => {
-                println!("{}", time.value().unwrap().as_secs())
-            }
-            _ => {}
+        if let Capability::LoginDelay(time) = capa {
+            println!("{}", time.value().unwrap().as_secs())
         }
     }
 
@@ -199,7 +2679,10 @@ async fn e2e_capa() {
 //     client.quit().await.unwrap();
 // }
 
-#[cfg_attr(feature = "runtime-tokio", tokio::test)]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
 #[cfg_attr(feature = "runtime-async-std", async_std::test)]
 async fn e2e_uidl() {
     let mut client = create_logged_in_client().await;
@@ -213,7 +2696,7 @@ async fn e2e_uidl() {
     //     _ => {}
     // };
 
-    let uidl = client.uidl(None).await.unwrap();
+    let uidl = client.uidl(None::<MessageNumber>).await.unwrap();
 
     match uidl {
         UidlResponse::Multiple(list) => {
@@ -226,3 +2709,636 @@ async fn e2e_uidl() {
 
     client.quit().await.unwrap();
 }
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_list_summary_line() {
+    let mut client = create_logged_in_mock_client(
+        "+OK 2 320\r\n1 120\r\n2 200\r\n.\r\n+OK scan listing follows\r\n1 120\r\n2 200\r\n.\r\n+OK 2 200 octets\r\n",
+    )
+    .await;
+
+    for _ in 0..2 {
+        match client.list(None::<MessageNumber>).await.unwrap() {
+            ListResponse::Multiple(list) => assert_eq!(list.items().len(), 2),
+            _ => unreachable!(),
+        }
+    }
+
+    match client.list(Some(2)).await.unwrap() {
+        ListResponse::Single(stat) => assert_eq!(stat.size().value().unwrap(), 200),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_server() {
+    use crate::mock::MockServer;
+
+    let server = MockServer::builder()
+        .credentials("user", "password")
+        .message("Subject: Hello\r\n\r\nHi!\r\n.hidden\r\n")
+        .message_with_uid("second", "Subject: Bye\n\nBye!\nSecond line\n")
+        .start()
+        .await
+        .unwrap();
+
+    let mut client = super::connect_plain(server.addr()).await.unwrap();
+
+    let err = client.login("user", "wrong").await.unwrap_err();
+
+    assert!(err.is_auth_failure());
+
+    client.login("user", "password").await.unwrap();
+
+    let stats = client.stat().await.unwrap();
+
+    assert_eq!(stats.counter().value().unwrap(), 2);
+
+    let message = client.retr(1).await.unwrap();
+
+    assert_eq!(message.as_ref(), b"Subject: Hello\r\n\r\nHi!\r\n.hidden");
+
+    let top = client.top(2, 1).await.unwrap();
+
+    assert_eq!(top.as_ref(), b"Subject: Bye\r\n\r\nBye!");
+
+    match client.uidl(None::<MessageNumber>).await.unwrap() {
+        UidlResponse::Multiple(list) => {
+            assert_eq!(list.items()[1].id().as_str().unwrap(), "second")
+        }
+        _ => unreachable!(),
+    }
+
+    client.dele(1).await.unwrap();
+    client.quit().await.unwrap();
+
+    assert_eq!(server.message_count(), 1);
+
+    server.assert_received(&[
+        "CAPA",
+        "USER user",
+        "PASS wrong",
+        "USER user",
+        "PASS password",
+        "CAPA",
+        "STAT",
+        "RETR 1",
+        "TOP 2 1",
+        "UIDL",
+        "DELE 1",
+        "QUIT",
+    ]);
+}
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_transcript() {
+    use crate::transcript::{Recorder, Transcript};
+
+    let mut input = format!(
+        "+OK POP3 server ready\r\n+OK\r\n{}.\r\n+OK\r\n+OK\r\n+OK\r\n{}.\r\n",
+        MOCK_CAPABILITIES, MOCK_CAPABILITIES
+    )
+    .into_bytes();
+
+    // Not valid UTF-8, which has to survive the round trip through the transcript.
+    input.extend_from_slice(b"+OK\r\n1 \xe9t\xe9\r\n.\r\n");
+
+    let recorder = Recorder::new(MockStream::new(input));
+    let recording = recorder.recording();
+
+    let mut client = super::new(recorder).await.unwrap();
+
+    client.login("user", "hunter2").await.unwrap();
+
+    let recorded = client.retr(1).await.unwrap();
+
+    let transcript = recording.transcript().to_string();
+
+    assert!(transcript.contains("C: PASS <redacted>\\r\\n"));
+    assert!(!transcript.contains("hunter2"));
+
+    let transcript = Transcript::parse(&transcript).unwrap();
+
+    let mut client = super::new(transcript.replay()).await.unwrap();
+
+    client.login("user", "another password").await.unwrap();
+
+    assert_eq!(client.retr(1).await.unwrap(), recorded);
+
+    // The client sends something else than it did during the recording.
+    let mut client = super::new(transcript.replay()).await.unwrap();
+
+    assert!(client.login("someone else", "hunter2").await.is_err());
+}
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_session() {
+    use crate::mock::MockSession;
+
+    let greeting = || {
+        MockSession::new()
+            .respond("+OK ready\r\n")
+            .expect("CAPA")
+            .respond(format!("+OK\r\n{}.\r\n", MOCK_CAPABILITIES))
+    };
+
+    let session = greeting()
+        .expect("UIDL")
+        .respond("+OK\r\n1 abc\r\n.\r\n")
+        .expect("STAT")
+        .respond("+OK many\r\n");
+
+    let mut client = super::new(session).await.unwrap();
+
+    match client.uidl(None::<MessageNumber>).await.unwrap() {
+        UidlResponse::Multiple(list) => assert_eq!(list.items().len(), 1),
+        _ => unreachable!(),
+    }
+
+    assert!(matches!(
+        client.stat().await.unwrap_err().kind(),
+        ErrorKind::InvalidResponse
+    ));
+
+    let session = greeting().expect("NOOP").respond("+OK\r\n1 ab");
+
+    let mut client = super::new(session).await.unwrap();
+
+    assert!(client.uidl(None::<MessageNumber>).await.is_err());
+
+    let session = greeting().expect("STAT").close();
+
+    let mut client = super::new(session).await.unwrap();
+
+    assert!(client.stat().await.is_err());
+}
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_session_malformed_listing() {
+    use crate::mock::MockSession;
+
+    let session = MockSession::new()
+        .respond("+OK ready\r\n")
+        .expect("CAPA")
+        .respond(format!("+OK\r\n{}.\r\n", MOCK_CAPABILITIES))
+        .expect("USER user")
+        .respond("+OK\r\n")
+        .expect("PASS pass")
+        .respond("+OK\r\n")
+        .expect("CAPA")
+        .respond(format!("+OK\r\n{}.\r\n", MOCK_CAPABILITIES))
+        .expect("LIST")
+        .respond("+OK\r\nfoo bar\r\n.\r\n")
+        .expect("NOOP")
+        .respond("+OK\r\n");
+
+    let mut client = super::new(session).await.unwrap();
+
+    client.login("user", "pass").await.unwrap();
+
+    assert!(matches!(
+        client.list(None::<MessageNumber>).await.unwrap_err().kind(),
+        ErrorKind::InvalidResponse
+    ));
+
+    assert!(!client.is_usable());
+
+    assert!(matches!(
+        client.noop().await.unwrap_err().kind(),
+        ErrorKind::Poisoned
+    ));
+}
+
+#[cfg(feature = "server")]
+struct TestMaildrop {
+    messages: Vec<&'static str>,
+    deleted: Arc<Mutex<Vec<usize>>>,
+}
+
+#[cfg(feature = "server")]
+impl crate::server::Maildrop for TestMaildrop {
+    fn list(&mut self) -> crate::error::Result<Vec<usize>> {
+        Ok(self.messages.iter().map(|message| message.len()).collect())
+    }
+
+    fn uidl(&mut self, msg_number: usize) -> crate::error::Result<String> {
+        Ok(format!("uid-{}", msg_number))
+    }
+
+    fn retr(&mut self, msg_number: usize) -> crate::error::Result<Bytes> {
+        Ok(Bytes::from_static(self.messages[msg_number - 1].as_bytes()))
+    }
+
+    fn dele(&mut self, msg_numbers: &[usize]) -> crate::error::Result<()> {
+        self.deleted.lock().unwrap().extend_from_slice(msg_numbers);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "server")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_server_session() {
+    use crate::{
+        response::{code::ResponseCode, ErrorResponse},
+        server::Server,
+    };
+
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+
+    let maildrop = Arc::clone(&deleted);
+
+    let mut server = Server::new(move |user: &str, password: &str| {
+        if user == "locked" {
+            return Err(ErrorResponse::new(Some(ResponseCode::InUse), "Locked".into()).into());
+        }
+
+        if user != "john" || password != "correct horse" {
+            return Err(ErrorResponse::new(None, "Invalid credentials".into()).into());
+        }
+
+        Ok(TestMaildrop {
+            messages: vec![
+                "Subject: One\r\n\r\n.Hi\r\n",
+                "Subject: Two\r\n\r\nA\r\nB\r\n",
+            ],
+            deleted: Arc::clone(&maildrop),
+        })
+    })
+    .greeting("Test server ready");
+
+    let input = "STAT\r\nUSER locked\r\nPASS x\r\nUSER john\r\nPASS wrong\r\nUSER john\r\nPASS correct horse\r\nSTAT\r\nLIST\r\nUIDL 2\r\nRETR 1\r\nTOP 2 1\r\nDELE 1\r\nRETR 1\r\nLIST 1\r\nXYZZY\r\nQUIT\r\nNOOP\r\n";
+
+    let mut stream = MockStream::new(input);
+
+    server.serve(&mut stream).await.unwrap();
+
+    let expected = [
+        "+OK Test server ready",
+        "-ERR Not authenticated",
+        "+OK",
+        "-ERR [IN-USE] Locked",
+        "+OK",
+        "-ERR [AUTH] Invalid credentials",
+        "+OK",
+        "+OK Maildrop ready",
+        "+OK 2 43",
+        "+OK\r\n1 21\r\n2 22\r\n.",
+        "+OK 2 uid-2",
+        "+OK\r\nSubject: One\r\n\r\n..Hi\r\n.",
+        "+OK\r\nSubject: Two\r\n\r\nA\r\n.",
+        "+OK Message deleted",
+        "-ERR No such message",
+        "-ERR No such message",
+        "-ERR Unknown command",
+        "+OK Bye",
+    ]
+    .map(|response| format!("{}\r\n", response))
+    .concat();
+
+    assert_eq!(String::from_utf8(stream.output).unwrap(), expected);
+    assert_eq!(*deleted.lock().unwrap(), [1]);
+}
+
+#[cfg(feature = "server")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_server_client() {
+    use crate::{
+        runtime::{net::TcpListener, spawn},
+        server::Server,
+    };
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+
+    let maildrop = Arc::clone(&deleted);
+
+    spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        let mut server = Server::new(move |_: &str, _: &str| {
+            Ok(TestMaildrop {
+                messages: vec!["Subject: One\r\n\r\n.Hi\r\n"],
+                deleted: Arc::clone(&maildrop),
+            })
+        });
+
+        server.serve(stream).await.unwrap();
+    });
+
+    let mut client = super::connect_plain(addr).await.unwrap();
+
+    client.login("john", "secret").await.unwrap();
+
+    match client.list(None::<MessageNumber>).await.unwrap() {
+        ListResponse::Multiple(list) => assert_eq!(list.items().len(), 1),
+        _ => unreachable!(),
+    }
+
+    assert_eq!(
+        client.retr(1).await.unwrap().as_ref(),
+        b"Subject: One\r\n\r\n.Hi"
+    );
+
+    client.dele(1).await.unwrap();
+    client.quit().await.unwrap();
+
+    assert_eq!(*deleted.lock().unwrap(), [1]);
+}
+
+#[cfg(feature = "tunnel")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_tunnel() {
+    use std::{collections::VecDeque, convert::Infallible};
+
+    use futures::{Sink, Stream};
+
+    use crate::tunnel::Tunnel;
+
+    /// A connection that hands out a fixed list of messages and keeps the ones that were sent.
+    struct MessageTunnel {
+        incoming: VecDeque<Vec<u8>>,
+        sent: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl Stream for MessageTunnel {
+        type Item = std::result::Result<Vec<u8>, Infallible>;
+
+        fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.get_mut().incoming.pop_front().map(Ok))
+        }
+    }
+
+    impl Sink<Vec<u8>> for MessageTunnel {
+        type Error = Infallible;
+
+        fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, message: Vec<u8>) -> Result<(), Infallible> {
+            self.sent.lock().unwrap().push(message);
+
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    // Message boundaries do not line up with the responses.
+    let incoming = [
+        "+OK POP3 ser",
+        "ver ready\r\n+OK\r\nUSER\r\n",
+        ".\r\n+OK\r\n+OK\r",
+        "\n+OK\r\nUSER\r\n.\r\n+OK 2 3",
+        "20\r\n",
+        "",
+        "+OK Bye\r\n",
+    ]
+    .iter()
+    .map(|message| message.as_bytes().to_vec())
+    .collect();
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+
+    let tunnel = Tunnel::new(MessageTunnel {
+        incoming,
+        sent: sent.clone(),
+    })
+    .secure(true);
+
+    let mut client = ClientBuilder::new().connect_tunnel(tunnel).await.unwrap();
+
+    assert!(client.is_secure());
+
+    client.login("user", "password").await.unwrap();
+
+    let stat = client.stat().await.unwrap();
+
+    assert_eq!(stat.counter().value().unwrap(), 2);
+
+    client.quit().await.unwrap();
+
+    // Every request is sent as a single message.
+    assert_eq!(
+        *sent.lock().unwrap(),
+        [
+            &b"CAPA\r\n"[..],
+            b"USER user\r\n",
+            b"PASS password\r\n",
+            b"CAPA\r\n",
+            b"STAT\r\n",
+            b"QUIT\r\n"
+        ]
+    );
+}
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_connect_fallback() {
+    use std::net::SocketAddr;
+
+    use crate::{happy_eyeballs::AddressFamily, mock::MockServer, runtime::net::TcpListener};
+
+    let server = MockServer::builder().start().await.unwrap();
+
+    // A port that nothing listens on anymore, so connecting to it is refused.
+    let closed = {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+
+        listener.local_addr().unwrap()
+    };
+
+    let addrs: [SocketAddr; 2] = [closed, server.addr()];
+
+    let mut client = ClientBuilder::new()
+        .connect_attempt_delay(Duration::from_secs(3600))
+        .connect_plain(&addrs[..])
+        .await
+        .unwrap();
+
+    client.quit().await.unwrap();
+
+    let result = ClientBuilder::new()
+        .address_family(AddressFamily::Ipv6Only)
+        .connect_plain(&addrs[..])
+        .await;
+
+    match result {
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::Io(_))),
+        Ok(_) => unreachable!(),
+    }
+}
+
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn connect_local_address() {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::runtime::net::{connect_with, SocketOptions, TcpListener};
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+
+    let options = SocketOptions {
+        local_address: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))),
+        ..Default::default()
+    };
+
+    let (connected, accepted) = futures::join!(
+        connect_with(listener.local_addr().unwrap(), &options),
+        listener.accept()
+    );
+
+    connected.unwrap();
+
+    let (_, peer) = accepted.unwrap();
+
+    assert_eq!(peer.ip(), options.local_address.unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn connect_socket_options() {
+    use crate::runtime::net::{connect_with, SocketOptions, TcpListener};
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+
+    let options = SocketOptions {
+        nodelay: Some(true),
+        keepalive_time: Some(Duration::from_secs(120)),
+        keepalive_interval: Some(Duration::from_secs(30)),
+        ..Default::default()
+    };
+
+    let (connected, accepted) = futures::join!(
+        connect_with(listener.local_addr().unwrap(), &options),
+        listener.accept()
+    );
+
+    let stream = connected.unwrap();
+
+    accepted.unwrap();
+
+    #[cfg(feature = "runtime-async-std")]
+    let fd = std::os::fd::AsRawFd::as_raw_fd(&stream);
+
+    #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+    let fd = std::os::fd::AsRawFd::as_raw_fd(stream.get_ref());
+
+    // The stream outlives the borrowed descriptor.
+    let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+
+    let socket = socket2::SockRef::from(&fd);
+
+    assert!(socket.nodelay().unwrap());
+    assert!(socket.keepalive().unwrap());
+    assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(120));
+    assert_eq!(
+        socket.keepalive_interval().unwrap(),
+        Duration::from_secs(30)
+    );
+}
+
+#[cfg(feature = "async-native-tls")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn connect_danger_options_given_connector() {
+    use crate::runtime::net::TcpListener;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+
+    let tls = async_native_tls::TlsConnector::new();
+
+    let builder = ClientBuilder::new().danger_accept_invalid_certs(true);
+
+    let (connected, accepted) = futures::join!(
+        builder.connect(listener.local_addr().unwrap(), "localhost", &tls),
+        listener.accept()
+    );
+
+    accepted.unwrap();
+
+    match connected {
+        Ok(_) => panic!("Connecting with a given connector should not disable verification"),
+        Err(err) => assert!(matches!(err.kind(), ErrorKind::InvalidArgument)),
+    }
+}
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(
+    all(feature = "runtime-tokio", not(feature = "runtime-async-std")),
+    tokio::test
+)]
+#[cfg_attr(feature = "runtime-async-std", async_std::test)]
+async fn mock_connect_to() {
+    use crate::mock::MockServer;
+
+    let server = MockServer::builder().start().await.unwrap();
+
+    // The reserved .invalid domain never resolves, so the connection can only succeed when it is not looked up.
+    let mut client = ClientBuilder::new()
+        .connect_to(server.addr())
+        .connect_plain(("pop.example.invalid", 110))
+        .await
+        .unwrap();
+
+    client.quit().await.unwrap();
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::ready;
+
+use crate::{
+    runtime::{Duration, Instant},
+    timer::{Sleep, Timer},
+};
+
+/// The amount of bytes that has to be available before a throttled transfer continues, so that a slow limit does not wake up for every single byte.
+const MIN_TRANSFER: u64 = 1024;
+
+/// Limits the throughput in one direction of a connection using a token bucket, which allows bursts of up to a second worth of data.
+pub(crate) struct RateLimit {
+    bytes_per_second: u64,
+    available: f64,
+    refilled_at: Instant,
+    timer: Arc<dyn Timer>,
+    delay: Option<Sleep>,
+}
+
+impl RateLimit {
+    pub fn new(bytes_per_second: u64, timer: Arc<dyn Timer>) -> Self {
+        let bytes_per_second = bytes_per_second.max(1);
+
+        Self {
+            bytes_per_second,
+            available: bytes_per_second as f64,
+            refilled_at: Instant::now(),
+            timer,
+            delay: None,
+        }
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+
+    /// Waits until data may be transferred again, returning how many bytes may be transferred right now.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<usize> {
+        loop {
+            if let Some(delay) = self.delay.as_mut() {
+                ready!(delay.as_mut().poll(cx));
+
+                self.delay = None;
+            }
+
+            self.refill();
+
+            let threshold = self.bytes_per_second.min(MIN_TRANSFER) as f64;
+
+            if self.available >= threshold {
+                return Poll::Ready(self.available as usize);
+            }
+
+            let missing = threshold - self.available;
+
+            let wait = Duration::from_secs_f64(missing / self.bytes_per_second as f64);
+
+            self.delay = Some(self.timer.sleep(wait));
+        }
+    }
+
+    /// Records that the given amount of bytes was transferred.
+    pub fn consume(&mut self, amount: usize) {
+        self.available -= amount as f64;
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+
+        self.refilled_at = now;
+
+        self.available = (self.available + elapsed * self.bytes_per_second as f64)
+            .min(self.bytes_per_second as f64);
+    }
+}
@@ -0,0 +1,84 @@
+//! The only part of an async runtime the client needs besides its io traits: a way to wait.
+//!
+//! The client talks to its connection through the runtime independent `futures::io` traits, and uses a [Timer] for timeouts, rate limits and keep alives.
+//! A timer for the enabled runtime is used by default, a different one can be set using [crate::ClientBuilder::timer], e.g. to run on a runtime this crate has no feature for.
+//! # Examples
+//! ```rust,ignore
+//! use async_pop::timer::{Sleep, Timer};
+//!
+//! struct SmolTimer;
+//!
+//! impl Timer for SmolTimer {
+//!     fn sleep(&self, duration: Duration) -> Sleep {
+//!         Box::pin(async move {
+//!             smol::Timer::after(duration).await;
+//!         })
+//!     }
+//! }
+//!
+//! let client = ClientBuilder::new().timer(Arc::new(SmolTimer)).build(stream).await?;
+//! ```
+
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+/// A future that completes once a [Timer::sleep] has passed.
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
+
+pub trait Timer: Send + Sync {
+    /// Creates a future that completes after the given duration.
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+impl std::fmt::Debug for dyn Timer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Timer")
+    }
+}
+
+/// A [Timer] that uses the async-std runtime.
+#[cfg(feature = "runtime-async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdTimer;
+
+#[cfg(feature = "runtime-async-std")]
+impl Timer for AsyncStdTimer {
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// A [Timer] that uses the tokio runtime.
+#[cfg(feature = "runtime-tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+#[cfg(feature = "runtime-tokio")]
+impl Timer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A [Timer] that never fires, used when no runtime feature is enabled and no other timer was set.
+///
+/// Timeouts never expire and rate limited transfers stall once their budget is used up.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NeverTimer;
+
+impl Timer for NeverTimer {
+    fn sleep(&self, _duration: Duration) -> Sleep {
+        Box::pin(futures::future::pending())
+    }
+}
+
+/// The timer of the enabled runtime, preferring async-std when both runtimes are enabled.
+pub fn default_timer() -> Arc<dyn Timer> {
+    #[cfg(feature = "runtime-async-std")]
+    return Arc::new(AsyncStdTimer);
+
+    #[cfg(all(feature = "runtime-tokio", not(feature = "runtime-async-std")))]
+    return Arc::new(TokioTimer);
+
+    #[cfg(not(any(feature = "runtime-async-std", feature = "runtime-tokio")))]
+    return Arc::new(NeverTimer);
+}
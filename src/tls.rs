@@ -1,13 +1,36 @@
+//! Tls support for the client, abstracted over the tls implementations that can be enabled using feature flags.
+//!
+//! Both `async-native-tls` and `async-rustls` connectors can be converted into a [TlsConnector], which means either can be passed to [crate::connect] or [crate::Client::stls].
+//! To use the certificate store of the system without depending on a tls implementation directly, pass [TlsConnector::System] instead.
+
+use std::{fmt, str::FromStr};
+
+use sha2::{Digest, Sha256};
+
 use crate::{
-    error::Result,
+    error::{err, Error, ErrorKind, Result},
+    macros::warn,
     runtime::io::{Read, Write},
 };
 
+/// A reference to a tls connector from one of the supported tls implementations.
 pub enum TlsConnector<'a> {
     #[cfg(feature = "async-native-tls")]
     NativeTls(&'a async_native_tls::TlsConnector),
     #[cfg(feature = "async-rustls")]
     RustTls(&'a async_rustls::TlsConnector),
+    /// A native-tls connector created by the client itself, which trusts the certificate store of the system.
+    ///
+    /// When connecting through a [crate::ClientBuilder], this is the only connector its tls options apply to, such as [crate::ClientBuilder::danger_accept_invalid_certs].
+    #[cfg(feature = "async-native-tls")]
+    System,
+}
+
+/// The options of a [crate::ClientBuilder] that configure the [TlsConnector::System] connector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct TlsOptions {
+    pub accept_invalid_certs: bool,
+    pub accept_invalid_hostnames: bool,
 }
 
 #[cfg(feature = "async-native-tls")]
@@ -25,23 +48,69 @@ impl<'a> From<&'a async_rustls::TlsConnector> for TlsConnector<'a> {
 }
 
 impl TlsConnector<'_> {
+    /// Perform a tls handshake over the given stream, verifying the server against the given domain.
     pub async fn connect<S: Read + Write + Unpin + Send, D: AsRef<str>>(
         &self,
         domain: D,
         tcp_stream: S,
     ) -> Result<impl TlsStream<S>> {
+        self.connect_with(domain, tcp_stream, &TlsOptions::default())
+            .await
+    }
+
+    /// Perform a tls handshake, applying the given options to the [TlsConnector::System] connector.
+    ///
+    /// The options can not be applied to a connector that was passed in, so disabling verification fails with [ErrorKind::InvalidArgument] for those.
+    pub(crate) async fn connect_with<S: Read + Write + Unpin + Send, D: AsRef<str>>(
+        &self,
+        domain: D,
+        tcp_stream: S,
+        options: &TlsOptions,
+    ) -> Result<impl TlsStream<S>> {
+        if options.accept_invalid_certs || options.accept_invalid_hostnames {
+            #[cfg(feature = "async-native-tls")]
+            let is_system = matches!(self, Self::System);
+            #[cfg(not(feature = "async-native-tls"))]
+            let is_system = false;
+
+            if !is_system {
+                err!(
+                    ErrorKind::InvalidArgument,
+                    "Verification can only be disabled for TlsConnector::System, configure the given connector instead"
+                )
+            }
+
+            warn!(
+                "Connecting to {} without verifying its {}, the connection is not protected against impersonation",
+                domain.as_ref(),
+                if options.accept_invalid_certs {
+                    "certificate"
+                } else {
+                    "hostname"
+                }
+            );
+        }
+
         match self {
             #[cfg(feature = "async-native-tls")]
             Self::NativeTls(connector) => {
                 Ok(connector.connect(domain.as_ref(), tcp_stream).await?)
             }
+            #[cfg(feature = "async-native-tls")]
+            Self::System => {
+                let connector = async_native_tls::TlsConnector::new()
+                    .danger_accept_invalid_certs(options.accept_invalid_certs)
+                    .danger_accept_invalid_hostnames(options.accept_invalid_hostnames);
+
+                Ok(connector.connect(domain.as_ref(), tcp_stream).await?)
+            }
             #[cfg(feature = "async-rustls")]
             Self::RustTls(connector) => {
                 let server_name: async_rustls::rustls::ServerName = match domain.as_ref().try_into()
                 {
                     Ok(domain) => domain,
-                    Err(_err) => crate::err!(
-                        crate::ErrorKind::InvalidDnsName,
+                    Err(_err) => crate::error::err!(
+                        crate::error::ErrorKind::InvalidDnsName,
                         "Given domain name '{}' was invalid",
                         domain.as_ref()
                     ),
@@ -53,10 +122,150 @@ impl TlsConnector<'_> {
     }
 }
 
-pub trait TlsStream<S: Read + Write + Unpin + Send>: Read + Write + Unpin + Send {}
+/// A secure stream, as returned by one of the supported tls implementations.
+pub trait TlsStream<S: Read + Write + Unpin + Send>: Read + Write + Unpin + Send {
+    /// The DER encoded certificate the server presented during the handshake.
+    fn peer_certificate(&self) -> Result<Option<Vec<u8>>>;
+}
 
 #[cfg(feature = "async-native-tls")]
-impl<S: Read + Write + Unpin + Send> TlsStream<S> for async_native_tls::TlsStream<S> {}
+impl<S: Read + Write + Unpin + Send> TlsStream<S> for async_native_tls::TlsStream<S> {
+    fn peer_certificate(&self) -> Result<Option<Vec<u8>>> {
+        match async_native_tls::TlsStream::peer_certificate(self)? {
+            Some(certificate) => Ok(Some(certificate.to_der()?)),
+            None => Ok(None),
+        }
+    }
+}
 
 #[cfg(feature = "async-rustls")]
-impl<S: Read + Write + Unpin + Send> TlsStream<S> for async_rustls::client::TlsStream<S> {}
+impl<S: Read + Write + Unpin + Send> TlsStream<S> for async_rustls::client::TlsStream<S> {
+    fn peer_certificate(&self) -> Result<Option<Vec<u8>>> {
+        let (_, connection) = self.get_ref();
+
+        Ok(connection
+            .peer_certificates()
+            .and_then(|certificates| certificates.first())
+            .map(|certificate| certificate.0.clone()))
+    }
+}
+
+/// The SHA-256 fingerprint of a certificate, used to pin the certificate of a server using [crate::ClientBuilder::pin_certificate].
+///
+/// Can be parsed from the hexadecimal notation used by most tools, with or without colons, e.g. the output of `openssl x509 -noout -fingerprint -sha256`.
+/// # Examples
+/// ```rust,ignore
+/// let fingerprint: Fingerprint = "5E:FF:56:A2:AF:15:88:25:35:D0:78:F1:38:93:79:A9:22:7F:31:18:7B:4C:35:67:40:9E:F0:56:1A:8D:B5:2F".parse()?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// The fingerprint of the given DER encoded certificate.
+    pub fn of(certificate: &[u8]) -> Self {
+        Self(Sha256::digest(certificate).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Fingerprint {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let digits: Vec<u8> = s.bytes().filter(|byte| *byte != b':').collect();
+
+        let mut bytes = [0; 32];
+
+        if digits.len() != bytes.len() * 2 || !digits.iter().all(u8::is_ascii_hexdigit) {
+            err!(
+                ErrorKind::InvalidArgument,
+                "'{}' is not a hexadecimal SHA-256 fingerprint",
+                s
+            )
+        }
+
+        for (byte, pair) in bytes.iter_mut().zip(digits.chunks_exact(2)) {
+            // Only contains ascii hex digits, so this can not fail.
+            *byte = u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap();
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(":")?;
+            }
+
+            write!(f, "{:02X}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Make sure the server presented a certificate matching one of the pinned fingerprints, if any were pinned.
+pub(crate) fn verify_pins<S, T>(stream: &T, pins: &[Fingerprint]) -> Result<()>
+where
+    S: Read + Write + Unpin + Send,
+    T: TlsStream<S>,
+{
+    if pins.is_empty() {
+        return Ok(());
+    }
+
+    let fingerprint = match stream.peer_certificate()? {
+        Some(certificate) => Fingerprint::of(&certificate),
+        None => err!(
+            ErrorKind::CertificateMismatch,
+            "The server did not present a certificate to compare to the pinned fingerprints"
+        ),
+    };
+
+    if !pins.contains(&fingerprint) {
+        err!(
+            ErrorKind::CertificateMismatch,
+            "The certificate of the server has fingerprint {}, which is not pinned",
+            fingerprint
+        )
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Fingerprint;
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = Fingerprint::of(b"abc");
+
+        let text = "BA:78:16:BF:8F:01:CF:EA:41:41:40:DE:5D:AE:22:23:B0:03:61:A3:96:17:7A:9C:B4:10:FF:61:F2:00:15:AD";
+
+        assert_eq!(fingerprint.to_string(), text);
+        assert_eq!(text.parse::<Fingerprint>().unwrap(), fingerprint);
+        assert_eq!(
+            text.replace(':', "")
+                .to_lowercase()
+                .parse::<Fingerprint>()
+                .unwrap(),
+            fingerprint
+        );
+
+        assert!("BA:78".parse::<Fingerprint>().is_err());
+        assert!(text.replace("BA", "ZZ").parse::<Fingerprint>().is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! Recording the dialogue of a session and replaying it later, to reproduce bugs with a specific server in an offline test.
+//!
+//! A [Recorder] wraps the connection to a real server and captures every byte that is sent and received.
+//! The resulting [Transcript] can be saved to a file, which is a readable list of lines prefixed with `C:` for the client and `S:` for the server.
+//! The credentials sent using PASS, APOP and AUTH are left out of the recording.
+//!
+//! A [Replay] plays the server side of a transcript back to a client, and fails if the client sends something else than it did during the recording.
+//! # Examples
+//! ```rust,ignore
+//! use async_pop::transcript::{Recorder, Transcript};
+//!
+//! // Record a session with a real server.
+//! let stream = TcpStream::connect(("pop.example.com", 110)).await?;
+//!
+//! let recorder = Recorder::new(stream);
+//! let recording = recorder.recording();
+//!
+//! let mut client = async_pop::new(recorder).await?;
+//!
+//! client.login("user", "password").await?;
+//! client.uidl(None::<MessageNumber>).await?;
+//! client.quit().await?;
+//!
+//! recording.transcript().save("tests/transcripts/uidl.txt")?;
+//!
+//! // Later on, in a test.
+//! let replay = Transcript::load("tests/transcripts/uidl.txt")?.replay();
+//!
+//! let mut client = async_pop::new(replay).await?;
+//!
+//! client.login("user", "password").await?;
+//! client.uidl(None::<MessageNumber>).await?;
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use crate::{
+    error::{err, ErrorKind, Result},
+    macros::escaped_all,
+    runtime::io::{Read, Write},
+    wire_log::{redact, REDACTED},
+};
+
+/// Which side of the connection sent a part of a [Transcript].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Client,
+    Server,
+}
+
+impl Direction {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::Client => "C: ",
+            Self::Server => "S: ",
+        }
+    }
+}
+
+/// The bytes that were exchanged during a session, in the order they were sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    entries: Vec<(Direction, Vec<u8>)>,
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends data sent by one side of the connection.
+    pub fn push<D: AsRef<[u8]>>(&mut self, direction: Direction, data: D) {
+        let data = data.as_ref();
+
+        match self.entries.last_mut() {
+            Some((last, bytes)) if *last == direction => bytes.extend_from_slice(data),
+            _ => self.entries.push((direction, data.to_vec())),
+        }
+    }
+
+    /// All of the data that was sent by one side of the connection.
+    pub fn data(&self, direction: Direction) -> Vec<u8> {
+        self.entries
+            .iter()
+            .filter(|(from, _)| *from == direction)
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect()
+    }
+
+    /// Parses a transcript in the format written by its [Display] implementation.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut transcript = Self::new();
+
+        for line in input.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let direction = if line.starts_with(Direction::Client.prefix()) {
+                Direction::Client
+            } else if line.starts_with(Direction::Server.prefix()) {
+                Direction::Server
+            } else {
+                err!(
+                    ErrorKind::InvalidTranscript,
+                    "Transcript lines must start with 'C: ' or 'S: ', found '{}'",
+                    line
+                )
+            };
+
+            transcript.push(direction, unescape(&line[direction.prefix().len()..])?);
+        }
+
+        Ok(transcript)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_string())?;
+
+        Ok(())
+    }
+
+    /// Creates a stream that plays the server side of this transcript back to a client.
+    pub fn replay(&self) -> Replay {
+        Replay::new(self)
+    }
+}
+
+impl Display for Transcript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (direction, data) in &self.entries {
+            for line in data.split_inclusive(|byte| *byte == b'\n') {
+                writeln!(f, "{}{}", direction.prefix(), escaped_all(line))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reverses the escaping of a line in a transcript.
+fn unescape(line: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(line.len());
+
+    let mut chars = line.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            let mut buf = [0; 4];
+
+            bytes.extend_from_slice(char.encode_utf8(&mut buf).as_bytes());
+
+            continue;
+        }
+
+        match chars.next() {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => bytes.push(byte),
+                    _ => err!(
+                        ErrorKind::InvalidTranscript,
+                        "Invalid escape sequence '\\x{}' in transcript",
+                        hex
+                    ),
+                }
+            }
+            other => err!(
+                ErrorKind::InvalidTranscript,
+                "Invalid escape sequence '\\{}' in transcript",
+                other.map(String::from).unwrap_or_default()
+            ),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// A shared handle to the [Transcript] that is being captured by a [Recorder].
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    transcript: Arc<Mutex<Transcript>>,
+}
+
+impl Recording {
+    /// A copy of everything that was recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        self.transcript.lock().unwrap().clone()
+    }
+}
+
+/// Wraps a connection and records everything that is sent over it, see the [module docs](self).
+#[derive(Debug)]
+pub struct Recorder<S> {
+    inner: S,
+    recording: Recording,
+    /// The part of the current line from the client that has not been recorded yet, so it can be redacted as a whole.
+    line: Vec<u8>,
+}
+
+impl<S> Recorder<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            recording: Recording::default(),
+            line: Vec::new(),
+        }
+    }
+
+    /// A handle to the transcript, which stays usable after the recorder has been handed to a client.
+    pub fn recording(&self) -> Recording {
+        self.recording.clone()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn record_read(&mut self, data: &[u8]) {
+        self.recording
+            .transcript
+            .lock()
+            .unwrap()
+            .push(Direction::Server, data);
+    }
+
+    fn record_write(&mut self, data: &[u8]) {
+        self.line.extend_from_slice(data);
+
+        while let Some(end) = self.line.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+
+            let mut transcript = self.recording.transcript.lock().unwrap();
+
+            let challenge = transcript
+                .entries
+                .last()
+                .filter(|(direction, _)| *direction == Direction::Server)
+                .map(|(_, data)| matches!(last_line(data), [b'+', b' ' | b'\r' | b'\n', ..]))
+                .unwrap_or(false);
+
+            transcript.push(Direction::Client, redact(&line, challenge));
+        }
+    }
+}
+
+/// The last complete line in the given data.
+fn last_line(data: &[u8]) -> &[u8] {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+
+    match data.iter().rposition(|byte| *byte == b'\n') {
+        Some(start) => &data[start + 1..],
+        None => data,
+    }
+}
+
+/// A stream that plays the server side of a [Transcript] back to a client, created using [Transcript::replay].
+///
+/// Every line the client writes is compared to the recorded one, writing anything else fails with an [std::io::ErrorKind::InvalidData] error.
+/// Credentials that were left out of the recording match any value.
+#[derive(Debug)]
+pub struct Replay {
+    server: Vec<u8>,
+    position: usize,
+    expected: VecDeque<Vec<u8>>,
+    line: Vec<u8>,
+}
+
+impl Replay {
+    fn new(transcript: &Transcript) -> Self {
+        let expected = transcript
+            .data(Direction::Client)
+            .split_inclusive(|byte| *byte == b'\n')
+            .map(|line| line.to_vec())
+            .collect();
+
+        Self {
+            server: transcript.data(Direction::Server),
+            position: 0,
+            expected,
+            line: Vec::new(),
+        }
+    }
+
+    /// Whether the client sent everything it sent during the recording.
+    pub fn is_finished(&self) -> bool {
+        self.expected.is_empty() && self.line.is_empty()
+    }
+
+    fn read_into(&mut self, buf: &mut [u8]) -> usize {
+        let remaining = &self.server[self.position..];
+
+        let amount = remaining.len().min(buf.len());
+
+        buf[..amount].copy_from_slice(&remaining[..amount]);
+
+        self.position += amount;
+
+        amount
+    }
+
+    fn write_from(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.line.extend_from_slice(buf);
+
+        while let Some(end) = self.line.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+
+            let expected = self.expected.pop_front().unwrap_or_default();
+
+            if !replay_matches(&expected, &line) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "The client sent '{}', but the transcript expected '{}'",
+                        escaped_all(&line),
+                        escaped_all(&expected)
+                    ),
+                ));
+            }
+        }
+
+        Ok(buf.len())
+    }
+}
+
+/// Compares a line sent by the client to the recorded one, where anything after a redacted marker matches.
+fn replay_matches(expected: &[u8], actual: &[u8]) -> bool {
+    match expected
+        .windows(REDACTED.len())
+        .position(|window| window == REDACTED)
+    {
+        Some(start) => actual.starts_with(&expected[..start]),
+        None => expected == actual,
+    }
+}
+
+impl<S: Read + Unpin> Read for Recorder<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+
+        let amount = futures::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        this.record_read(&buf[..amount]);
+
+        Poll::Ready(Ok(amount))
+    }
+}
+
+impl<S: Write + Unpin> Write for Recorder<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+
+        let amount = futures::ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+
+        this.record_write(&buf[..amount]);
+
+        Poll::Ready(Ok(amount))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+impl Read for Replay {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Ok(self.get_mut().read_into(buf)))
+    }
+}
+
+impl Write for Replay {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(self.get_mut().write_from(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Direction, Transcript};
+
+    #[test]
+    fn test_transcript_format() {
+        let mut transcript = Transcript::new();
+
+        transcript.push(Direction::Server, b"+OK ready\r\n");
+        transcript.push(Direction::Client, b"RETR 1\r\n");
+        transcript.push(Direction::Server, b"+OK\r\n\xe9t\xe9 \\o/\r\n.\r\n");
+
+        let written = transcript.to_string();
+
+        assert_eq!(
+            written,
+            "S: +OK ready\\r\\nC: RETR 1\\r\\nS: +OK\\r\\nS: \\xe9t\\xe9 \\\\o/\\r\\nS: .\\r\\n"
+                .replace("\\r\\n", "\\r\\n\n")
+        );
+
+        assert_eq!(Transcript::parse(&written).unwrap(), transcript);
+
+        assert!(Transcript::parse("X: hello").is_err());
+        assert!(Transcript::parse("S: \\xzz").is_err());
+    }
+}
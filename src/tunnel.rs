@@ -0,0 +1,161 @@
+//! Running the client over a message based tunnel, such as a WebSocket, instead of a tcp connection.
+//!
+//! Browsers and edge workers can not open tcp connections, but they can reach a POP server through a WebSocket to tcp proxy such as [websockify](https://github.com/novnc/websockify).
+//! A [Tunnel] turns any connection that sends and receives binary messages into a byte stream the client can use, it does not depend on an async runtime and builds for wasm32 targets.
+//!
+//! Most WebSocket crates yield their own message type, which can be mapped to bytes using the `futures` combinators before creating the tunnel.
+//! # Examples
+//! ```rust,ignore
+//! use futures::{SinkExt, StreamExt, TryStreamExt};
+//! use gloo_net::websocket::{futures::WebSocket, Message};
+//!
+//! let socket = WebSocket::open("wss://proxy.example.com/pop3")?
+//!     .try_filter_map(|message| async move {
+//!         Ok(match message {
+//!             Message::Bytes(bytes) => Some(bytes),
+//!             Message::Text(_) => None,
+//!         })
+//!     })
+//!     .with(|bytes: Vec<u8>| async move { Ok(Message::Bytes(bytes)) });
+//!
+//! let mut client = ClientBuilder::new()
+//!     .timer(Arc::new(BrowserTimer))
+//!     .connect_tunnel(Tunnel::new(Box::pin(socket)).secure(true))
+//!     .await?;
+//! ```
+
+use std::{
+    error::Error,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{ready, Sink, Stream};
+
+use crate::runtime::io::{Read, Write};
+
+/// Adapts a connection that sends and receives binary messages to a byte stream, see the [module docs](self).
+///
+/// Every flush of the client is sent as a single message, and the messages that are received are read back to back, so the message boundaries do not have to line up with the lines of the protocol.
+/// The tunnel is closed once the connection ends its stream of messages.
+#[derive(Debug)]
+pub struct Tunnel<T> {
+    inner: T,
+    /// The message that is currently being read.
+    incoming: Vec<u8>,
+    position: usize,
+    /// What was written since the last flush.
+    outgoing: Vec<u8>,
+    secure: bool,
+}
+
+impl<T> Tunnel<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            incoming: Vec::new(),
+            position: 0,
+            outgoing: Vec::new(),
+            secure: false,
+        }
+    }
+
+    /// Mark the tunnel as secure, e.g. because it uses a `wss://` url, which allows mechanisms that require tls to be used.
+    ///
+    /// Only do this when the whole path to the POP server is encrypted, including the hop from the proxy to the server.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+fn io_error<E: Into<Box<dyn Error + Send + Sync>>>(err: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, err)
+}
+
+impl<T, E> Read for Tunnel<T>
+where
+    T: Stream<Item = std::result::Result<Vec<u8>, E>> + Unpin,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+
+        while this.position == this.incoming.len() {
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(message)) => {
+                    this.incoming = message;
+                    this.position = 0;
+                }
+                Some(Err(err)) => return Poll::Ready(Err(io_error(err))),
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+
+        let remaining = &this.incoming[this.position..];
+
+        let amount = remaining.len().min(buf.len());
+
+        buf[..amount].copy_from_slice(&remaining[..amount]);
+
+        this.position += amount;
+
+        Poll::Ready(Ok(amount))
+    }
+}
+
+impl<T, E> Write for Tunnel<T>
+where
+    T: Sink<Vec<u8>, Error = E> + Unpin,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().outgoing.extend_from_slice(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        if !this.outgoing.is_empty() {
+            ready!(Pin::new(&mut this.inner).poll_ready(cx)).map_err(io_error)?;
+
+            let message = std::mem::take(&mut this.outgoing);
+
+            Pin::new(&mut this.inner)
+                .start_send(message)
+                .map_err(io_error)?;
+        }
+
+        Pin::new(&mut this.inner).poll_flush(cx).map_err(io_error)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        Pin::new(&mut self.get_mut().inner)
+            .poll_close(cx)
+            .map_err(io_error)
+    }
+}
@@ -0,0 +1,145 @@
+//! Ways to tell messages apart across sessions, which is what [crate::sync] relies on.
+//!
+//! Message numbers change between sessions, so every message needs an id that does not. Servers that support UIDL hand those out themselves, see [Uidl].
+//! For servers that do not, an id can be derived from the headers of the message instead, see [HeaderHash].
+//! # Examples
+//! ```rust,ignore
+//! use async_pop::uid::HeaderHash;
+//!
+//! let outcome = async_pop::sync::run_with(
+//!     &mut client,
+//!     &HeaderHash::new(),
+//!     &previous,
+//!     |info, message| save_message(info.uid(), message),
+//!     |_info| false,
+//! )
+//! .await?;
+//! ```
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use md5::{Digest, Md5};
+
+use crate::{
+    error::Result,
+    headers::Headers,
+    response::{
+        info::MessageInfo,
+        types::{message::Text, number::MessageNumber, DataType},
+    },
+    runtime::io::{Read, Write},
+    Client,
+};
+
+/// A future that resolves to the unique ids found by a [UidProvider], by message number.
+pub type UniqueIds<'a> =
+    Pin<Box<dyn Future<Output = Result<HashMap<MessageNumber, Text>>> + Send + 'a>>;
+
+/// A way to find an id for every message in the maildrop that stays the same across sessions.
+pub trait UidProvider {
+    /// The unique ids of the given messages by message number, a message that is left out has no unique id.
+    fn unique_ids<'a, S: Read + Write + Unpin + Send>(
+        &'a self,
+        client: &'a mut Client<S>,
+        messages: &'a [MessageInfo],
+    ) -> UniqueIds<'a>;
+}
+
+/// The unique ids the server hands out using UIDL, see [Client::uidl].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uidl;
+
+impl UidProvider for Uidl {
+    fn unique_ids<'a, S: Read + Write + Unpin + Send>(
+        &'a self,
+        client: &'a mut Client<S>,
+        _messages: &'a [MessageInfo],
+    ) -> UniqueIds<'a> {
+        Box::pin(async move {
+            let uidl = client.uidl(None::<MessageNumber>).await?;
+
+            let uids = uidl
+                .into_iter()
+                .map(|unique_id| Ok((unique_id.message_number()?, unique_id.id().clone())))
+                .collect::<Result<_>>()?;
+
+            Ok(uids)
+        })
+    }
+}
+
+/// Derives the unique id of a message by hashing some of its header fields along with its size, for servers that do not support UIDL.
+///
+/// The header block of every message is fetched using [Client::preview], so this takes a command per message.
+/// Copies of the same message, with the same values for the fields, get the same id and are treated as a single message when synchronising.
+/// The id is the hexadecimal MD5 digest of the fields, which fits the requirements for a unique id in [RFC 1939](https://www.rfc-editor.org/rfc/rfc1939#page-12).
+#[derive(Debug, Clone)]
+pub struct HeaderHash {
+    fields: Vec<String>,
+}
+
+impl HeaderHash {
+    /// Hashes the Message-ID, Date and From fields.
+    pub fn new() -> Self {
+        Self::with_fields(["Message-ID", "Date", "From"])
+    }
+
+    /// Hashes the given fields instead of the default ones, a field that occurs more than once is hashed in full.
+    pub fn with_fields<I: IntoIterator<Item = F>, F: Into<String>>(fields: I) -> Self {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The unique id of a message with the given headers and size.
+    pub fn unique_id(&self, headers: &Headers, size: u64) -> Text {
+        let mut hasher = Md5::new();
+
+        for field in &self.fields {
+            hasher.update(field.to_ascii_lowercase());
+            hasher.update(b":");
+
+            for value in headers.get_all(field) {
+                hasher.update(value.raw());
+                hasher.update(b"\n");
+            }
+        }
+
+        hasher.update(size.to_string());
+
+        let id: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Text::from(id.as_str())
+    }
+}
+
+impl Default for HeaderHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UidProvider for HeaderHash {
+    fn unique_ids<'a, S: Read + Write + Unpin + Send>(
+        &'a self,
+        client: &'a mut Client<S>,
+        messages: &'a [MessageInfo],
+    ) -> UniqueIds<'a> {
+        Box::pin(async move {
+            let mut uids = HashMap::with_capacity(messages.len());
+
+            for info in messages {
+                let header = client.preview(info.number(), 0).await?;
+
+                let headers = Headers::parse(&header);
+
+                uids.insert(info.number(), self.unique_id(&headers, info.size()));
+            }
+
+            Ok(uids)
+        })
+    }
+}
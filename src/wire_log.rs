@@ -0,0 +1,186 @@
+//! Copying the bytes that are exchanged with the server to a writer, see [crate::Client::set_transcript].
+
+use std::io::Write;
+
+use crate::{
+    command::Command,
+    macros::{escaped_all, warn},
+    request::Request,
+};
+
+/// Writes every line that is sent or received to a writer, in the format that is read by `Transcript::parse`.
+///
+/// Lines are only written once they are complete, so the credentials in a line from the client can be hidden as a whole.
+pub(crate) struct WireLog {
+    writer: Box<dyn Write + Send>,
+    client: Vec<u8>,
+    server: Vec<u8>,
+    /// Whether the last line from the server is a SASL challenge, which means the next line from the client is an encoded response.
+    challenge: bool,
+}
+
+impl WireLog {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer,
+            client: Vec::new(),
+            server: Vec::new(),
+            challenge: false,
+        }
+    }
+
+    /// Records data that was sent to the server.
+    ///
+    /// Returns false if the writer failed, after which it should no longer be used.
+    pub fn sent(&mut self, data: &[u8]) -> bool {
+        self.client.extend_from_slice(data);
+
+        while let Some(end) = self.client.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.client.drain(..=end).collect();
+
+            let line = redact(&line, self.challenge);
+
+            if !self.write_line("C: ", &line) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Records data that was received from the server.
+    ///
+    /// Returns false if the writer failed, after which it should no longer be used.
+    pub fn received(&mut self, data: &[u8]) -> bool {
+        self.server.extend_from_slice(data);
+
+        while let Some(end) = self.server.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.server.drain(..=end).collect();
+
+            self.challenge = matches!(line.as_slice(), [b'+', b' ' | b'\r' | b'\n', ..]);
+
+            if !self.write_line("S: ", &line) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn write_line(&mut self, prefix: &str, line: &[u8]) -> bool {
+        let result = writeln!(self.writer, "{}{}", prefix, escaped_all(line))
+            .and_then(|_| self.writer.flush());
+
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(
+                    "Failed to write to the transcript, no longer writing to it: {}",
+                    err
+                );
+
+                false
+            }
+        }
+    }
+}
+
+/// Hides the credentials in a line sent by the client.
+///
+/// Lines sent in response to a SASL challenge are hidden completely, as they contain the encoded credentials.
+pub(crate) fn redact(line: &[u8], challenge: bool) -> Vec<u8> {
+    let ending = line.len()
+        - line
+            .iter()
+            .rev()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+
+    let (content, ending) = line.split_at(ending);
+
+    if challenge {
+        return [REDACTED, ending].concat();
+    }
+
+    let content = String::from_utf8_lossy(content);
+
+    let mut parts = content.split(' ');
+
+    let command: Option<Command> = parts.next().and_then(|name| name.parse().ok());
+
+    match command {
+        Some(command @ (Command::Pass | Command::Apop | Command::Auth)) => {
+            let args: Vec<&str> = parts.collect();
+
+            let request = Request::new(command, &args);
+
+            [request.redacted().to_string().as_bytes(), ending].concat()
+        }
+        _ => line.to_vec(),
+    }
+}
+
+pub(crate) const REDACTED: &[u8] = b"<redacted>";
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::{redact, WireLog};
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact(b"PASS hunter2\r\n", false), b"PASS <redacted>\r\n");
+        assert_eq!(
+            redact(b"AUTH PLAIN AHVzZXIAaHVudGVyMg==\r\n", false),
+            b"AUTH PLAIN <redacted>\r\n"
+        );
+        assert_eq!(redact(b"AHVzZXIAaHVudGVyMg==\r\n", true), b"<redacted>\r\n");
+        assert_eq!(redact(b"USER john\r\n", false), b"USER john\r\n");
+    }
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_wire_log() {
+        let output = Shared::default();
+
+        let mut log = WireLog::new(Box::new(output.clone()));
+
+        assert!(log.received(b"+OK ready\r\n+OK\r\nSASL PL"));
+        assert!(log.received(b"AIN\r\n.\r\n"));
+        assert!(log.sent(b"AUTH PLAIN\r\n"));
+        assert!(log.received(b"+ \r\n"));
+        assert!(log.sent(b"AHVzZXIAaHVudGVyMg==\r\n"));
+        assert!(log.received(b"+OK\r\n"));
+        assert!(log.sent(b"STAT\r\n"));
+
+        assert_eq!(
+            String::from_utf8(output.0.lock().unwrap().clone()).unwrap(),
+            [
+                "S: +OK ready\\r\\n",
+                "S: +OK\\r\\n",
+                "S: SASL PLAIN\\r\\n",
+                "S: .\\r\\n",
+                "C: AUTH PLAIN\\r\\n",
+                "S: + \\r\\n",
+                "C: <redacted>\\r\\n",
+                "S: +OK\\r\\n",
+                "C: STAT\\r\\n",
+                ""
+            ]
+            .join("\n")
+        );
+    }
+}